@@ -0,0 +1,32 @@
+//! Token issuance and verification for dx services.
+
+mod keys;
+mod throttle;
+mod token;
+
+pub use keys::*;
+pub use throttle::*;
+pub use token::*;
+
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AuthError {
+    #[error("invalid token: {0}")]
+    InvalidToken(String),
+    #[error("token signature does not match")]
+    InvalidSignature,
+    #[error("invalid signing key")]
+    InvalidKey,
+    #[error("token expired")]
+    TokenExpired,
+    #[error("token issued in the future")]
+    TokenNotYetValid,
+    #[error("token already consumed")]
+    TokenReplayed,
+    #[error("crypto error: {0}")]
+    CryptoError(String),
+    #[error("too many failed verification attempts; retry after {}s", retry_after.as_secs())]
+    Throttled { retry_after: Duration },
+}
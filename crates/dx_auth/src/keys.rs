@@ -0,0 +1,241 @@
+use crate::AuthError;
+use base64::Engine as _;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use ring::signature::{self, Ed25519KeyPair, KeyPair};
+use ring::{hkdf, pbkdf2};
+use sha2::{Digest, Sha256};
+use std::num::NonZeroU32;
+
+/// Matches the BIP39 seed-derivation iteration count, so the phrase -> seed
+/// step behaves like other mnemonic tooling operators already know.
+const PBKDF2_ITERATIONS: u32 = 2048;
+const HKDF_SALT: &[u8] = b"dx-auth-ed25519-v1";
+const HKDF_INFO: &[u8] = b"token-signing-key";
+const MIN_PHRASE_WORDS: usize = 12;
+
+/// An Ed25519 token-signing key derived deterministically from a seed
+/// phrase, so every machine given the same phrase and passphrase mints the
+/// same key and no raw key bytes ever need to be stored or shipped.
+///
+/// Derivation is BIP39-style and fixed for compatibility:
+/// `PBKDF2-HMAC-SHA512(phrase, "mnemonic" + passphrase, 2048)` produces a
+/// 64-byte seed, which HKDF-SHA256 (salt `dx-auth-ed25519-v1`, info
+/// `token-signing-key`) condenses into the 32-byte Ed25519 seed.
+pub struct ProductionTokenGenerator {
+    key_pair: Ed25519KeyPair,
+}
+
+impl ProductionTokenGenerator {
+    /// Derives the signing key from a seed phrase of at least
+    /// [`MIN_PHRASE_WORDS`] lowercase words whose final word is the checksum
+    /// from [`seed_phrase_checksum`]. A malformed phrase or bad checksum is
+    /// [`AuthError::CryptoError`]; typos never silently derive a different
+    /// key.
+    pub fn from_seed_phrase(words: &str, passphrase: &str) -> Result<Self, AuthError> {
+        let phrase = validate_seed_phrase(words)?;
+
+        let mut salt = b"mnemonic".to_vec();
+        salt.extend_from_slice(passphrase.as_bytes());
+        let iterations = NonZeroU32::new(PBKDF2_ITERATIONS)
+            .ok_or_else(|| AuthError::CryptoError("iteration count must be nonzero".into()))?;
+        let mut seed = [0u8; 64];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA512,
+            iterations,
+            &salt,
+            phrase.as_bytes(),
+            &mut seed,
+        );
+
+        let mut key_seed = [0u8; 32];
+        hkdf::Salt::new(hkdf::HKDF_SHA256, HKDF_SALT)
+            .extract(&seed)
+            .expand(&[HKDF_INFO], hkdf::HKDF_SHA256)
+            .and_then(|output| output.fill(&mut key_seed))
+            .map_err(|_| AuthError::CryptoError("HKDF key expansion failed".into()))?;
+
+        let key_pair =
+            Ed25519KeyPair::from_seed_unchecked(&key_seed).map_err(|_| AuthError::InvalidKey)?;
+        Ok(Self { key_pair })
+    }
+
+    pub fn public_key(&self) -> Vec<u8> {
+        self.key_pair.public_key().as_ref().to_vec()
+    }
+
+    /// A stable identifier for this key — the hex-encoded first eight bytes
+    /// of the SHA-256 of the public key — for `kid` headers and JWKS lookup.
+    pub fn key_id(&self) -> String {
+        let digest = Sha256::digest(self.public_key());
+        digest
+            .iter()
+            .take(8)
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// The public half of the signing key as a JWKS document (RFC 7517): one
+    /// `OKP`/`Ed25519` entry with the key bytes base64url-encoded in `x` and
+    /// [`key_id`](Self::key_id) as `kid`. Standard JWT/JOSE libraries consume
+    /// this directly, so non-DX services can verify tokens without linking
+    /// against this crate.
+    pub fn export_jwks(&self) -> serde_json::Value {
+        let mut key = serde_json::Map::new();
+        key.insert("kty".to_string(), serde_json::Value::String("OKP".into()));
+        key.insert(
+            "crv".to_string(),
+            serde_json::Value::String("Ed25519".into()),
+        );
+        key.insert("alg".to_string(), serde_json::Value::String("EdDSA".into()));
+        key.insert("use".to_string(), serde_json::Value::String("sig".into()));
+        key.insert("kid".to_string(), serde_json::Value::String(self.key_id()));
+        key.insert(
+            "x".to_string(),
+            serde_json::Value::String(URL_SAFE_NO_PAD.encode(self.public_key())),
+        );
+        let mut document = serde_json::Map::new();
+        document.insert(
+            "keys".to_string(),
+            serde_json::Value::Array(vec![serde_json::Value::Object(key)]),
+        );
+        serde_json::Value::Object(document)
+    }
+
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        self.key_pair.sign(message).as_ref().to_vec()
+    }
+
+    /// Verifies a signature against a public key exported by
+    /// [`public_key`](Self::public_key); no private material required.
+    pub fn verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> Result<(), AuthError> {
+        signature::UnparsedPublicKey::new(&signature::ED25519, public_key)
+            .verify(message, signature)
+            .map_err(|_| AuthError::InvalidSignature)
+    }
+}
+
+/// The checksum word for a phrase body: the first two bytes of the SHA-256
+/// of the space-joined words, hex encoded. Appending it yields a phrase
+/// [`ProductionTokenGenerator::from_seed_phrase`] accepts.
+pub fn seed_phrase_checksum(words: &[&str]) -> String {
+    let digest = Sha256::digest(words.join(" ").as_bytes());
+    format!(
+        "{:02x}{:02x}",
+        digest.first().copied().unwrap_or(0),
+        digest.get(1).copied().unwrap_or(0)
+    )
+}
+
+fn validate_seed_phrase(words: &str) -> Result<String, AuthError> {
+    let words: Vec<&str> = words.split_whitespace().collect();
+    if words.len() < MIN_PHRASE_WORDS {
+        return Err(AuthError::CryptoError(format!(
+            "seed phrase has {} words, need at least {MIN_PHRASE_WORDS}",
+            words.len()
+        )));
+    }
+    for word in &words {
+        if !word
+            .chars()
+            .all(|character| character.is_ascii_lowercase() || character.is_ascii_digit())
+        {
+            return Err(AuthError::CryptoError(format!(
+                "seed phrase word `{word}` contains invalid characters"
+            )));
+        }
+    }
+    let (checksum_word, body) = words
+        .split_last()
+        .ok_or_else(|| AuthError::CryptoError("seed phrase is empty".into()))?;
+    if *checksum_word != seed_phrase_checksum(body) {
+        return Err(AuthError::CryptoError(
+            "seed phrase checksum mismatch".into(),
+        ));
+    }
+    Ok(words.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn valid_phrase() -> String {
+        let body = [
+            "apple", "brave", "cable", "dance", "eagle", "fable", "gauge", "haven", "input",
+            "jolly", "kneel", "lemon",
+        ];
+        format!("{} {}", body.join(" "), seed_phrase_checksum(&body))
+    }
+
+    #[test]
+    fn test_derived_public_key_is_stable_and_signatures_verify() {
+        let first = ProductionTokenGenerator::from_seed_phrase(&valid_phrase(), "deploy").unwrap();
+        let second = ProductionTokenGenerator::from_seed_phrase(&valid_phrase(), "deploy").unwrap();
+        assert_eq!(first.public_key(), second.public_key());
+
+        let signature = first.sign(b"release manifest");
+        ProductionTokenGenerator::verify(&second.public_key(), b"release manifest", &signature)
+            .unwrap();
+        assert_eq!(
+            ProductionTokenGenerator::verify(&second.public_key(), b"tampered", &signature),
+            Err(AuthError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_exported_jwk_decodes_back_to_the_public_key() {
+        let generator =
+            ProductionTokenGenerator::from_seed_phrase(&valid_phrase(), "deploy").unwrap();
+        let jwks = generator.export_jwks();
+        let key = &jwks["keys"][0];
+        assert_eq!(key["kty"].as_str(), Some("OKP"));
+        assert_eq!(key["crv"].as_str(), Some("Ed25519"));
+        assert_eq!(key["alg"].as_str(), Some("EdDSA"));
+        assert_eq!(key["kid"].as_str(), Some(generator.key_id().as_str()));
+
+        let encoded = key["x"].as_str().unwrap();
+        let decoded = URL_SAFE_NO_PAD.decode(encoded).unwrap();
+        assert_eq!(decoded, generator.public_key());
+    }
+
+    #[test]
+    fn test_passphrase_changes_the_derived_key() {
+        let first = ProductionTokenGenerator::from_seed_phrase(&valid_phrase(), "deploy").unwrap();
+        let second = ProductionTokenGenerator::from_seed_phrase(&valid_phrase(), "other").unwrap();
+        assert_ne!(first.public_key(), second.public_key());
+    }
+
+    #[test]
+    fn test_bad_checksum_is_a_crypto_error() {
+        let phrase = valid_phrase();
+        let mut words: Vec<&str> = phrase.split(' ').collect();
+        let last = words.len() - 1;
+        words[last] = "0000";
+        assert!(matches!(
+            ProductionTokenGenerator::from_seed_phrase(&words.join(" "), ""),
+            Err(AuthError::CryptoError(_))
+        ));
+
+        // A typo in the body invalidates the checksum too.
+        let mut words: Vec<&str> = phrase.split(' ').collect();
+        words[0] = "apples";
+        assert!(matches!(
+            ProductionTokenGenerator::from_seed_phrase(&words.join(" "), ""),
+            Err(AuthError::CryptoError(_))
+        ));
+    }
+
+    #[test]
+    fn test_short_or_malformed_phrases_are_rejected() {
+        assert!(matches!(
+            ProductionTokenGenerator::from_seed_phrase("too short", ""),
+            Err(AuthError::CryptoError(_))
+        ));
+        let body = ["Apple"; 12];
+        let phrase = format!("{} {}", body.join(" "), seed_phrase_checksum(&body));
+        assert!(matches!(
+            ProductionTokenGenerator::from_seed_phrase(&phrase, ""),
+            Err(AuthError::CryptoError(_))
+        ));
+    }
+}
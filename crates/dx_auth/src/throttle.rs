@@ -0,0 +1,194 @@
+use crate::{AuthError, TokenClaims, TokenSigner};
+use collections::HashMap;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct ThrottleConfig {
+    /// Failed attempts allowed within `window` before further attempts are
+    /// refused.
+    pub max_failures: u32,
+    pub window: Duration,
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self {
+            max_failures: 5,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Tracks failed verification attempts per key — typically the claimed
+/// subject or a source address — and refuses further attempts with
+/// [`AuthError::Throttled`] once too many fail within the window, before any
+/// signature work is done. Forgery stays infeasible with or without this;
+/// the throttle just keeps brute-force traffic from burning CPU.
+pub struct VerificationThrottle {
+    config: ThrottleConfig,
+    /// Timestamps of recent failures per key, oldest first.
+    failures: HashMap<String, Vec<u64>>,
+}
+
+impl VerificationThrottle {
+    pub fn new(config: ThrottleConfig) -> Self {
+        Self {
+            config,
+            failures: HashMap::new(),
+        }
+    }
+
+    /// Call before verifying. `now` is seconds since the unix epoch, as
+    /// elsewhere in this crate; see [`unix_timestamp_now`](crate::unix_timestamp_now).
+    pub fn check(&mut self, key: &str, now: u64) -> Result<(), AuthError> {
+        let window = self.config.window.as_secs();
+        let Some(timestamps) = self.failures.get_mut(key) else {
+            return Ok(());
+        };
+        timestamps.retain(|&failed_at| now.saturating_sub(failed_at) < window);
+        if timestamps.is_empty() {
+            self.failures.remove(key);
+            return Ok(());
+        }
+        if timestamps.len() < self.config.max_failures as usize {
+            return Ok(());
+        }
+        let Some(&oldest) = timestamps.first() else {
+            return Ok(());
+        };
+        let retry_after = window.saturating_sub(now.saturating_sub(oldest));
+        Err(AuthError::Throttled {
+            retry_after: Duration::from_secs(retry_after.max(1)),
+        })
+    }
+
+    pub fn record_failure(&mut self, key: &str, now: u64) {
+        // An attacker spraying distinct keys must not grow the map without
+        // bound, so every insert drops keys whose failures have all aged
+        // out of the window.
+        let window = self.config.window.as_secs();
+        self.failures.retain(|_, timestamps| {
+            timestamps.retain(|&failed_at| now.saturating_sub(failed_at) < window);
+            !timestamps.is_empty()
+        });
+        self.failures.entry(key.to_string()).or_default().push(now);
+    }
+
+    pub fn record_success(&mut self, key: &str) {
+        self.failures.remove(key);
+    }
+}
+
+impl TokenSigner {
+    /// Verifies `token` under `throttle`, keyed by `key`. A key that has
+    /// failed too often recently is refused before any signature work; a
+    /// failed verification counts toward the throttle and a successful one
+    /// resets it.
+    pub fn verify_throttled(
+        &self,
+        throttle: &mut VerificationThrottle,
+        key: &str,
+        token: &str,
+        now: u64,
+    ) -> Result<TokenClaims, AuthError> {
+        throttle.check(key, now)?;
+        match self.verify(token, now) {
+            Ok(claims) => {
+                throttle.record_success(key);
+                Ok(claims)
+            }
+            Err(error) => {
+                throttle.record_failure(key, now);
+                Err(error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TokenConfig;
+
+    fn signer() -> TokenSigner {
+        TokenSigner::new(b"test-signing-key".to_vec(), TokenConfig::default())
+    }
+
+    fn throttle() -> VerificationThrottle {
+        VerificationThrottle::new(ThrottleConfig {
+            max_failures: 3,
+            window: Duration::from_secs(60),
+        })
+    }
+
+    #[test]
+    fn test_repeated_failures_within_the_window_are_throttled() {
+        let signer = signer();
+        let mut throttle = throttle();
+        for attempt in 0..3 {
+            assert_eq!(
+                signer.verify_throttled(&mut throttle, "user-1", "garbage", 1_000 + attempt),
+                Err(AuthError::InvalidToken(
+                    "missing signature separator".into()
+                ))
+            );
+        }
+        assert_eq!(
+            signer.verify_throttled(&mut throttle, "user-1", "garbage", 1_003),
+            Err(AuthError::Throttled {
+                retry_after: Duration::from_secs(57),
+            })
+        );
+        // Another subject is unaffected.
+        assert!(throttle.check("user-2", 1_003).is_ok());
+    }
+
+    #[test]
+    fn test_failures_age_out_of_the_window() {
+        let mut throttle = throttle();
+        for attempt in 0..3 {
+            throttle.record_failure("user-1", 1_000 + attempt);
+        }
+        assert!(matches!(
+            throttle.check("user-1", 1_010),
+            Err(AuthError::Throttled { .. })
+        ));
+        assert!(throttle.check("user-1", 1_061).is_ok());
+    }
+
+    #[test]
+    fn test_aged_out_keys_are_dropped_not_retained() {
+        let mut throttle = throttle();
+        for subject in 0..100 {
+            throttle.record_failure(&format!("user-{subject}"), 1_000);
+        }
+        assert_eq!(throttle.failures.len(), 100);
+
+        // Once their failures age out, sprayed keys are swept on the next
+        // insert instead of accumulating forever.
+        throttle.record_failure("user-new", 1_061);
+        assert_eq!(throttle.failures.len(), 1);
+
+        // A check after the window drops the key too.
+        assert!(throttle.check("user-new", 1_200).is_ok());
+        assert!(throttle.failures.is_empty());
+    }
+
+    #[test]
+    fn test_successful_verification_resets_the_counter() {
+        let signer = signer();
+        let mut throttle = throttle();
+        throttle.record_failure("user-1", 1_000_000);
+        throttle.record_failure("user-1", 1_000_001);
+
+        let token = signer.issue("user-1", 1_000_000).unwrap();
+        signer
+            .verify_throttled(&mut throttle, "user-1", &token, 1_000_002)
+            .unwrap();
+
+        // The earlier failures no longer count toward the limit.
+        throttle.record_failure("user-1", 1_000_003);
+        throttle.record_failure("user-1", 1_000_004);
+        assert!(throttle.check("user-1", 1_000_005).is_ok());
+    }
+}
@@ -0,0 +1,332 @@
+use crate::AuthError;
+use base64::Engine as _;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use collections::HashMap;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Debug, Clone)]
+pub struct TokenConfig {
+    pub time_to_live: Duration,
+    /// How far client clocks may drift from ours before a token is rejected.
+    /// An `iat` this far in the future is still accepted, and an `exp` this
+    /// recently passed is still accepted.
+    pub clock_skew_tolerance: Duration,
+}
+
+impl Default for TokenConfig {
+    fn default() -> Self {
+        Self {
+            time_to_live: Duration::from_secs(60 * 60),
+            clock_skew_tolerance: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenClaims {
+    pub subject: String,
+    /// Issuance time, seconds since the unix epoch.
+    pub issued_at: u64,
+    /// Expiry time, seconds since the unix epoch.
+    pub expires_at: u64,
+    /// The `jti` claim: unique among tokens issued by one signer, so a
+    /// [`NonceCache`] can tell a replayed token from a freshly issued one
+    /// with identical subject and timestamps.
+    pub token_id: u64,
+}
+
+pub struct TokenSigner {
+    key: Vec<u8>,
+    config: TokenConfig,
+    next_token_id: AtomicU64,
+}
+
+impl TokenSigner {
+    pub fn new(key: impl Into<Vec<u8>>, config: TokenConfig) -> Self {
+        Self {
+            key: key.into(),
+            config,
+            next_token_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Issues a signed token for `subject`, valid from `now` for the
+    /// configured time to live. `now` is seconds since the unix epoch; see
+    /// [`unix_timestamp_now`].
+    pub fn issue(&self, subject: &str, now: u64) -> Result<String, AuthError> {
+        let claims = TokenClaims {
+            subject: subject.to_string(),
+            issued_at: now,
+            expires_at: now.saturating_add(self.config.time_to_live.as_secs()),
+            token_id: self.next_token_id.fetch_add(1, Ordering::Relaxed),
+        };
+        let payload = encode_claims(&claims)?;
+        let tag = self.signature(&payload)?;
+        Ok(format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(&payload),
+            URL_SAFE_NO_PAD.encode(tag)
+        ))
+    }
+
+    /// Verifies the signature and time validity of `token` as of `now`.
+    ///
+    /// Signature failures are reported before time failures so that a
+    /// tampered token never leaks whether its claimed timestamps were
+    /// plausible.
+    pub fn verify(&self, token: &str, now: u64) -> Result<TokenClaims, AuthError> {
+        let (payload_part, signature_part) = token
+            .split_once('.')
+            .ok_or_else(|| AuthError::InvalidToken("missing signature separator".into()))?;
+        let payload = URL_SAFE_NO_PAD
+            .decode(payload_part)
+            .map_err(|_| AuthError::InvalidToken("payload is not valid base64".into()))?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature_part)
+            .map_err(|_| AuthError::InvalidToken("signature is not valid base64".into()))?;
+
+        let mut mac = HmacSha256::new_from_slice(&self.key).map_err(|_| AuthError::InvalidKey)?;
+        mac.update(&payload);
+        mac.verify_slice(&signature)
+            .map_err(|_| AuthError::InvalidSignature)?;
+
+        let claims = decode_claims(&payload)?;
+        let skew = self.config.clock_skew_tolerance.as_secs();
+        if claims.issued_at > now.saturating_add(skew) {
+            return Err(AuthError::TokenNotYetValid);
+        }
+        if now > claims.expires_at.saturating_add(skew) {
+            return Err(AuthError::TokenExpired);
+        }
+        Ok(claims)
+    }
+
+    /// Like [`verify`](Self::verify), but additionally enforces one-time
+    /// use: the token's id is recorded in `nonce_cache` on first acceptance
+    /// and any later presentation fails with [`AuthError::TokenReplayed`].
+    /// The cache is passed in rather than owned so callers control its
+    /// sharing and lifetime — and tests can inspect or pre-seed it. A cache
+    /// must not outlive the signer whose tokens it records: token ids
+    /// restart with the signer.
+    pub fn verify_single_use(
+        &self,
+        token: &str,
+        now: u64,
+        nonce_cache: &mut NonceCache,
+    ) -> Result<TokenClaims, AuthError> {
+        let claims = self.verify(token, now)?;
+        nonce_cache.consume(&claims, now, self.config.clock_skew_tolerance.as_secs())?;
+        Ok(claims)
+    }
+
+    fn signature(&self, payload: &[u8]) -> Result<Vec<u8>, AuthError> {
+        let mut mac = HmacSha256::new_from_slice(&self.key).map_err(|_| AuthError::InvalidKey)?;
+        mac.update(payload);
+        Ok(mac.finalize().into_bytes().to_vec())
+    }
+}
+
+/// Records consumed token ids so a single-use token presented twice is
+/// caught; see [`TokenSigner::verify_single_use`]. Memory stays bounded
+/// because each entry is dropped once its token's `exp` (plus clock skew)
+/// passes — from then on the ordinary expiry check rejects the token before
+/// the cache is ever consulted.
+#[derive(Debug, Default)]
+pub struct NonceCache {
+    expiries_by_token_id: HashMap<u64, u64>,
+}
+
+impl NonceCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consumed tokens currently retained (not yet expired out).
+    pub fn len(&self) -> usize {
+        self.expiries_by_token_id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expiries_by_token_id.is_empty()
+    }
+
+    fn consume(&mut self, claims: &TokenClaims, now: u64, skew: u64) -> Result<(), AuthError> {
+        self.expiries_by_token_id
+            .retain(|_, expires_at| now <= expires_at.saturating_add(skew));
+        if self
+            .expiries_by_token_id
+            .insert(claims.token_id, claims.expires_at)
+            .is_some()
+        {
+            return Err(AuthError::TokenReplayed);
+        }
+        Ok(())
+    }
+}
+
+pub fn unix_timestamp_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+fn encode_claims(claims: &TokenClaims) -> Result<Vec<u8>, AuthError> {
+    let subject = claims.subject.as_bytes();
+    let subject_len = u16::try_from(subject.len())
+        .map_err(|_| AuthError::InvalidToken("subject too long".into()))?;
+    let mut bytes = Vec::with_capacity(2 + subject.len() + 24);
+    bytes.extend_from_slice(&subject_len.to_le_bytes());
+    bytes.extend_from_slice(subject);
+    bytes.extend_from_slice(&claims.issued_at.to_le_bytes());
+    bytes.extend_from_slice(&claims.expires_at.to_le_bytes());
+    bytes.extend_from_slice(&claims.token_id.to_le_bytes());
+    Ok(bytes)
+}
+
+fn decode_claims(bytes: &[u8]) -> Result<TokenClaims, AuthError> {
+    let truncated = || AuthError::InvalidToken("truncated claims".into());
+    let subject_len = u16::from_le_bytes(
+        bytes
+            .get(..2)
+            .and_then(|slice| slice.try_into().ok())
+            .ok_or_else(truncated)?,
+    ) as usize;
+    let subject = bytes.get(2..2 + subject_len).ok_or_else(truncated)?;
+    let subject = String::from_utf8(subject.to_vec())
+        .map_err(|_| AuthError::InvalidToken("subject is not valid utf-8".into()))?;
+    let rest = bytes.get(2 + subject_len..).ok_or_else(truncated)?;
+    let issued_at = u64::from_le_bytes(
+        rest.get(..8)
+            .and_then(|slice| slice.try_into().ok())
+            .ok_or_else(truncated)?,
+    );
+    let expires_at = u64::from_le_bytes(
+        rest.get(8..16)
+            .and_then(|slice| slice.try_into().ok())
+            .ok_or_else(truncated)?,
+    );
+    let token_id = u64::from_le_bytes(
+        rest.get(16..24)
+            .and_then(|slice| slice.try_into().ok())
+            .ok_or_else(truncated)?,
+    );
+    Ok(TokenClaims {
+        subject,
+        issued_at,
+        expires_at,
+        token_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signer() -> TokenSigner {
+        TokenSigner::new(b"test-signing-key".to_vec(), TokenConfig::default())
+    }
+
+    #[test]
+    fn test_issued_token_round_trips() {
+        let signer = signer();
+        let token = signer.issue("user-1", 1_000_000).unwrap();
+        let claims = signer.verify(&token, 1_000_100).unwrap();
+        assert_eq!(claims.subject, "user-1");
+        assert_eq!(claims.issued_at, 1_000_000);
+        assert_eq!(claims.expires_at, 1_000_000 + 3600);
+    }
+
+    #[test]
+    fn test_future_iat_beyond_tolerance_is_rejected() {
+        let signer = signer();
+        let token = signer.issue("user-1", 1_000_100).unwrap();
+        assert_eq!(
+            signer.verify(&token, 1_000_000),
+            Err(AuthError::TokenNotYetValid)
+        );
+    }
+
+    #[test]
+    fn test_future_iat_within_tolerance_is_accepted() {
+        let signer = signer();
+        let token = signer.issue("user-1", 1_000_020).unwrap();
+        assert!(signer.verify(&token, 1_000_000).is_ok());
+    }
+
+    #[test]
+    fn test_recently_expired_token_is_accepted_within_skew() {
+        let signer = signer();
+        let token = signer.issue("user-1", 1_000_000).unwrap();
+        let expiry = 1_000_000 + 3600;
+        assert!(signer.verify(&token, expiry + 20).is_ok());
+        assert_eq!(
+            signer.verify(&token, expiry + 40),
+            Err(AuthError::TokenExpired)
+        );
+    }
+
+    #[test]
+    fn test_single_use_token_is_rejected_on_second_presentation() {
+        let signer = signer();
+        let mut cache = NonceCache::new();
+        let token = signer.issue("user-1", 1_000_000).unwrap();
+        assert!(
+            signer
+                .verify_single_use(&token, 1_000_100, &mut cache)
+                .is_ok()
+        );
+        assert_eq!(
+            signer.verify_single_use(&token, 1_000_200, &mut cache),
+            Err(AuthError::TokenReplayed)
+        );
+
+        // A distinct token for the same subject and instant is not a replay.
+        let second = signer.issue("user-1", 1_000_000).unwrap();
+        assert!(
+            signer
+                .verify_single_use(&second, 1_000_300, &mut cache)
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_nonce_cache_expires_entries_at_token_expiry() {
+        let signer = signer();
+        let mut cache = NonceCache::new();
+        let short_lived = signer.issue("user-1", 1_000_000).unwrap();
+        signer
+            .verify_single_use(&short_lived, 1_000_000, &mut cache)
+            .unwrap();
+        assert_eq!(cache.len(), 1);
+
+        // Past the first token's expiry (plus skew) its entry is dropped on
+        // the next consume; the ordinary expiry check rejects replays of it
+        // from then on.
+        let later = 1_000_000 + 3600 + 60;
+        let fresh = signer.issue("user-2", later).unwrap();
+        signer.verify_single_use(&fresh, later, &mut cache).unwrap();
+        assert_eq!(cache.len(), 1);
+        assert_eq!(
+            signer.verify_single_use(&short_lived, later, &mut cache),
+            Err(AuthError::TokenExpired)
+        );
+    }
+
+    #[test]
+    fn test_tampered_signature_is_rejected_before_time_checks() {
+        let signer = signer();
+        let expired_long_ago = signer.issue("user-1", 10).unwrap();
+        let (payload, _) = expired_long_ago.split_once('.').unwrap();
+        let forged = format!("{payload}.{}", URL_SAFE_NO_PAD.encode(b"bogus-signature"));
+        assert_eq!(
+            signer.verify(&forged, 1_000_000),
+            Err(AuthError::InvalidSignature)
+        );
+    }
+}
@@ -0,0 +1,345 @@
+use crate::{ArtifactType, BuildError, content_hash};
+use collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+/// Identity of a cached artifact: what produced it (including the version
+/// of its processing logic) and from which input bytes.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CacheKey {
+    pub artifact_type: ArtifactType,
+    /// Version of the processor that produced the artifact. Bumping it
+    /// namespaces this stage's cache away from entries built by older logic,
+    /// leaving other stages' entries valid.
+    pub processor_version: u32,
+    /// The build target the artifact was produced for, so `web` and `node`
+    /// builds of the same source never serve each other's outputs.
+    pub target: String,
+    pub input_hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub key: CacheKey,
+    pub output_path: PathBuf,
+    pub output_hash: String,
+    pub output_size: u64,
+    /// Logical last-access time, stamped by the cache on insert and on each
+    /// verified hit; the eviction order. A monotonic counter rather than a
+    /// wall clock, so accesses within the same instant still order totally.
+    pub last_accessed: u64,
+}
+
+/// A cached artifact whose on-disk bytes no longer match the hash recorded
+/// when it was built — silent corruption, or a tampered cache directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TamperWarning {
+    pub path: PathBuf,
+    pub expected_hash: String,
+    pub actual_hash: String,
+}
+
+impl std::fmt::Display for TamperWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} does not match its recorded hash (expected {}, found {})",
+            self.path.display(),
+            self.expected_hash,
+            self.actual_hash
+        )
+    }
+}
+
+/// In-memory index of previously built artifacts, keyed by [`CacheKey`].
+#[derive(Debug, Default)]
+pub struct BuildCache {
+    entries: HashMap<CacheKey, CacheEntry>,
+    tamper_warnings: Vec<TamperWarning>,
+    access_clock: u64,
+}
+
+impl BuildCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<&CacheEntry> {
+        self.entries.get(key)
+    }
+
+    /// Like [`get`](Self::get), but verifies the on-disk artifact still
+    /// hashes to the entry's recorded `output_hash` before serving it. A
+    /// missing file is an ordinary miss; a mismatch means the cache was
+    /// corrupted or edited behind our back, so the poisoned entry is evicted,
+    /// a [`TamperWarning`] recorded, and the lookup reported as a miss. A hit
+    /// freshens the entry's `last_accessed`.
+    pub fn get_verified(&mut self, key: &CacheKey) -> Option<&CacheEntry> {
+        let entry = self.entries.get(key)?;
+        // An unreadable or missing artifact is indistinguishable from never
+        // having cached it: fall through to a rebuild.
+        let bytes = fs::read(&entry.output_path).ok()?;
+        let actual_hash = content_hash(&bytes);
+        if actual_hash != entry.output_hash {
+            if let Some(entry) = self.entries.remove(key) {
+                self.tamper_warnings.push(TamperWarning {
+                    path: entry.output_path,
+                    expected_hash: entry.output_hash,
+                    actual_hash,
+                });
+            }
+            return None;
+        }
+        let last_accessed = self.tick();
+        let entry = self.entries.get_mut(key)?;
+        entry.last_accessed = last_accessed;
+        Some(&*entry)
+    }
+
+    /// Checks every entry's on-disk artifact against its recorded hash,
+    /// reporting the mismatches sorted by path. Missing files are not
+    /// reported — they are ordinary misses, not corruption.
+    pub fn audit(&self) -> Vec<TamperWarning> {
+        let mut warnings: Vec<TamperWarning> = self
+            .entries
+            .values()
+            .filter_map(|entry| {
+                let bytes = fs::read(&entry.output_path).ok()?;
+                let actual_hash = content_hash(&bytes);
+                (actual_hash != entry.output_hash).then(|| TamperWarning {
+                    path: entry.output_path.clone(),
+                    expected_hash: entry.output_hash.clone(),
+                    actual_hash,
+                })
+            })
+            .collect();
+        warnings.sort_by(|a, b| a.path.cmp(&b.path));
+        warnings
+    }
+
+    /// Warnings accumulated by [`get_verified`](Self::get_verified) since the
+    /// last call, leaving the list empty.
+    pub fn take_tamper_warnings(&mut self) -> Vec<TamperWarning> {
+        std::mem::take(&mut self.tamper_warnings)
+    }
+
+    pub fn insert(&mut self, mut entry: CacheEntry) {
+        entry.last_accessed = self.tick();
+        self.entries.insert(entry.key.clone(), entry);
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.access_clock += 1;
+        self.access_clock
+    }
+
+    /// Total bytes of cached artifacts, as recorded when they were inserted.
+    pub fn total_bytes(&self) -> u64 {
+        self.entries.values().map(|entry| entry.output_size).sum()
+    }
+
+    /// Evicts least-recently-accessed entries — deleting their on-disk
+    /// artifacts — until the recorded bytes fit in `max_bytes`, and returns
+    /// what was evicted. Entries whose output path is in `in_use` are never
+    /// evicted, whatever their age, so the cache can stay over budget when
+    /// everything left is pinned. Callers must only invoke this once the
+    /// current build's full artifact set is known: the pipeline runs it at
+    /// the end of a build, never mid-build, where an eviction could delete an
+    /// artifact a later stage was about to serve.
+    pub fn evict_to_budget(
+        &mut self,
+        max_bytes: u64,
+        in_use: &HashSet<PathBuf>,
+    ) -> Result<Vec<CacheEntry>, BuildError> {
+        let mut total = self.total_bytes();
+        if total <= max_bytes {
+            return Ok(Vec::new());
+        }
+        let mut candidates: Vec<(u64, PathBuf, CacheKey)> = self
+            .entries
+            .values()
+            .filter(|entry| !in_use.contains(&entry.output_path))
+            .map(|entry| {
+                (
+                    entry.last_accessed,
+                    entry.output_path.clone(),
+                    entry.key.clone(),
+                )
+            })
+            .collect();
+        // Oldest first; the path tie-break keeps the eviction order
+        // independent of map iteration order.
+        candidates.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+
+        let mut evicted = Vec::new();
+        for (_, _, key) in candidates {
+            if total <= max_bytes {
+                break;
+            }
+            let Some(entry) = self.entries.remove(&key) else {
+                continue;
+            };
+            match fs::remove_file(&entry.output_path) {
+                Ok(()) => {}
+                // A file already gone holds no bytes; dropping its entry is
+                // the whole eviction.
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => {}
+                Err(source) => {
+                    return Err(BuildError::Io {
+                        path: entry.output_path,
+                        source,
+                    });
+                }
+            }
+            total = total.saturating_sub(entry.output_size);
+            evicted.push(entry);
+        }
+        Ok(evicted)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// A shared artifact store (S3/R2-like) keyed by [`CacheKey`], so caching
+/// works across developers and CI rather than per checkout.
+///
+/// The bytes stored for a key are immutable — the key includes the input
+/// hash — so `store` must be idempotent: concurrent uploads of the same key
+/// may race and any winner is correct.
+///
+/// `Send + Sync` because the pipeline fetches and stores from its worker
+/// threads.
+pub trait RemoteCache: Send + Sync {
+    fn fetch(&self, key: &CacheKey) -> anyhow::Result<Option<Vec<u8>>>;
+    fn store(&self, key: &CacheKey, bytes: &[u8]) -> anyhow::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry_for(path: PathBuf, bytes: &[u8]) -> CacheEntry {
+        let hash = content_hash(bytes);
+        CacheEntry {
+            key: CacheKey {
+                artifact_type: ArtifactType::Media,
+                processor_version: 1,
+                target: "web".to_string(),
+                input_hash: hash.clone(),
+            },
+            output_path: path,
+            output_hash: hash,
+            output_size: bytes.len() as u64,
+            last_accessed: 0,
+        }
+    }
+
+    #[test]
+    fn test_audit_reports_only_mismatched_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let clean = dir.path().join("clean.bin");
+        let tampered = dir.path().join("tampered.bin");
+        let missing = dir.path().join("missing.bin");
+        fs::write(&clean, b"clean bytes").unwrap();
+        fs::write(&tampered, b"original bytes").unwrap();
+
+        let mut cache = BuildCache::new();
+        cache.insert(entry_for(clean, b"clean bytes"));
+        cache.insert(entry_for(tampered.clone(), b"original bytes"));
+        cache.insert(entry_for(missing, b"never written"));
+        assert!(cache.audit().is_empty());
+
+        fs::write(&tampered, b"edited behind our back").unwrap();
+        let warnings = cache.audit();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, tampered);
+        assert_eq!(warnings[0].expected_hash, content_hash(b"original bytes"));
+        assert_eq!(
+            warnings[0].actual_hash,
+            content_hash(b"edited behind our back")
+        );
+    }
+
+    #[test]
+    fn test_get_verified_evicts_tampered_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("artifact.bin");
+        fs::write(&path, b"original bytes").unwrap();
+
+        let mut cache = BuildCache::new();
+        let entry = entry_for(path.clone(), b"original bytes");
+        let key = entry.key.clone();
+        cache.insert(entry);
+        assert!(cache.get_verified(&key).is_some());
+        assert!(cache.take_tamper_warnings().is_empty());
+
+        fs::write(&path, b"edited behind our back").unwrap();
+        assert!(cache.get_verified(&key).is_none());
+        assert!(cache.get(&key).is_none(), "poisoned entry evicted");
+        let warnings = cache.take_tamper_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, path);
+        assert!(cache.take_tamper_warnings().is_empty(), "warnings drained");
+    }
+
+    fn populated_cache(dir: &std::path::Path) -> (BuildCache, Vec<PathBuf>) {
+        let mut cache = BuildCache::new();
+        let mut paths = Vec::new();
+        for name in ["a.bin", "b.bin", "c.bin"] {
+            let path = dir.join(name);
+            fs::write(&path, name.as_bytes()).unwrap();
+            cache.insert(entry_for(path.clone(), name.as_bytes()));
+            paths.push(path);
+        }
+        (cache, paths)
+    }
+
+    #[test]
+    fn test_eviction_drops_the_least_recently_accessed_entry_and_its_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut cache, paths) = populated_cache(dir.path());
+        assert_eq!(cache.total_bytes(), 15);
+
+        // Freshen the oldest entry so `b.bin` becomes the eviction victim.
+        let key_a = entry_for(paths[0].clone(), b"a.bin").key;
+        assert!(cache.get_verified(&key_a).is_some());
+
+        let evicted = cache.evict_to_budget(10, &HashSet::default()).unwrap();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].output_path, paths[1]);
+        assert!(!paths[1].exists(), "the evicted artifact is deleted");
+        assert!(paths[0].exists() && paths[2].exists());
+        assert_eq!(cache.len(), 2);
+        assert!(
+            cache
+                .evict_to_budget(10, &HashSet::default())
+                .unwrap()
+                .is_empty(),
+            "within budget, nothing moves"
+        );
+    }
+
+    #[test]
+    fn test_in_use_entries_are_never_evicted() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut cache, paths) = populated_cache(dir.path());
+
+        let in_use: HashSet<PathBuf> = [paths[0].clone(), paths[1].clone()].into_iter().collect();
+        let evicted = cache.evict_to_budget(4, &in_use).unwrap();
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].output_path, paths[2]);
+        assert_eq!(
+            cache.len(),
+            2,
+            "pinned entries survive even while still over budget"
+        );
+        assert!(paths[0].exists() && paths[1].exists());
+    }
+}
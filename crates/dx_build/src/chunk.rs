@@ -0,0 +1,184 @@
+use crate::content_hash;
+use serde::{Deserialize, Serialize};
+
+/// Parameters for content-defined chunking. Boundaries are chosen by a gear
+/// rolling hash, so a local edit only moves boundaries near the edit and the
+/// chunk stream resynchronizes afterwards — the property that makes CDN delta
+/// uploads cheap.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkerConfig {
+    pub min_size: usize,
+    /// Average chunk size; must be a power of two, as it is used as the
+    /// boundary mask.
+    pub avg_size: usize,
+    pub max_size: usize,
+}
+
+impl Default for ChunkerConfig {
+    fn default() -> Self {
+        Self {
+            min_size: 16 * 1024,
+            avg_size: 64 * 1024,
+            max_size: 256 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkInfo {
+    pub hash: String,
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// Per-artifact listing of its chunks, in order. Reassembling the chunks in
+/// listed order yields the artifact byte-exactly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkManifest {
+    /// Hash of the whole (unchunked) artifact.
+    pub artifact_hash: String,
+    pub total_size: u64,
+    pub chunks: Vec<ChunkInfo>,
+}
+
+impl ChunkManifest {
+    pub fn from_bytes(bytes: &[u8], config: &ChunkerConfig) -> Self {
+        let chunks = chunk_slices(bytes, config)
+            .into_iter()
+            .scan(0u64, |offset, slice| {
+                let info = ChunkInfo {
+                    hash: content_hash(slice),
+                    offset: *offset,
+                    length: slice.len() as u64,
+                };
+                *offset += slice.len() as u64;
+                Some(info)
+            })
+            .collect();
+        Self {
+            artifact_hash: content_hash(bytes),
+            total_size: bytes.len() as u64,
+            chunks,
+        }
+    }
+}
+
+/// Splits `bytes` at content-defined boundaries. Concatenating the returned
+/// slices reproduces `bytes` exactly.
+pub fn chunk_slices<'a>(bytes: &'a [u8], config: &ChunkerConfig) -> Vec<&'a [u8]> {
+    let mask = (config.avg_size.max(2) as u64).next_power_of_two() - 1;
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut hash = 0u64;
+    let mut index = start;
+    while index < bytes.len() {
+        hash = (hash << 1).wrapping_add(GEAR[bytes[index] as usize]);
+        let length = index + 1 - start;
+        let at_boundary = length >= config.min_size && (hash & mask) == 0;
+        if at_boundary || length >= config.max_size {
+            chunks.push(&bytes[start..=index]);
+            start = index + 1;
+            hash = 0;
+        }
+        index += 1;
+    }
+    if start < bytes.len() {
+        chunks.push(&bytes[start..]);
+    }
+    chunks
+}
+
+/// 256 pseudo-random gear constants, generated at compile time with
+/// splitmix64 so the chunk boundaries are stable across builds and platforms.
+static GEAR: [u64; 256] = build_gear_table();
+
+const fn build_gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state = 0x9e37_79b9_7f4a_7c15u64;
+    let mut index = 0;
+    while index < 256 {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = state;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        table[index] = value ^ (value >> 31);
+        index += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> ChunkerConfig {
+        ChunkerConfig {
+            min_size: 256,
+            avg_size: 1024,
+            max_size: 4096,
+        }
+    }
+
+    fn deterministic_bytes(len: usize) -> Vec<u8> {
+        let mut state = 0x1234_5678u32;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reassembly_is_byte_exact() {
+        let bytes = deterministic_bytes(100_000);
+        let chunks = chunk_slices(&bytes, &test_config());
+        assert!(
+            chunks.len() > 10,
+            "expected many chunks, got {}",
+            chunks.len()
+        );
+        let reassembled: Vec<u8> = chunks.concat();
+        assert_eq!(reassembled, bytes);
+
+        let manifest = ChunkManifest::from_bytes(&bytes, &test_config());
+        assert_eq!(manifest.total_size, bytes.len() as u64);
+        assert_eq!(manifest.chunks.len(), chunks.len());
+        assert_eq!(manifest.artifact_hash, content_hash(&bytes));
+    }
+
+    #[test]
+    fn test_single_byte_change_alters_one_chunk() {
+        let config = test_config();
+        let bytes = deterministic_bytes(100_000);
+        let original = ChunkManifest::from_bytes(&bytes, &config);
+
+        let mut edited = bytes;
+        // Flip a byte in the middle of a chunk, away from any boundary, so
+        // the edit cannot move a boundary decision.
+        let target_chunk = &original.chunks[original.chunks.len() / 2];
+        let edit_offset = (target_chunk.offset + target_chunk.length / 2) as usize;
+        edited[edit_offset] ^= 0xff;
+        let modified = ChunkManifest::from_bytes(&edited, &config);
+
+        assert_eq!(original.chunks.len(), modified.chunks.len());
+        let differing = original
+            .chunks
+            .iter()
+            .zip(&modified.chunks)
+            .filter(|(before, after)| before.hash != after.hash)
+            .count();
+        assert_eq!(differing, 1);
+    }
+
+    #[test]
+    fn test_max_size_is_enforced() {
+        let config = test_config();
+        // Constant bytes never hit the boundary mask, so every chunk should
+        // be cut at max_size.
+        let bytes = vec![0u8; 20_000];
+        for chunk in chunk_slices(&bytes, &config) {
+            assert!(chunk.len() <= config.max_size);
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! Asset build pipeline: processes project sources (media, styles, icons,
+//! ...) into hashed output artifacts with content-addressed caching.
+
+mod cache;
+mod chunk;
+mod icon;
+mod media;
+mod pipeline;
+mod style;
+
+pub use cache::*;
+pub use chunk::*;
+pub use icon::*;
+pub use media::*;
+pub use pipeline::*;
+pub use style::*;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum BuildError {
+    #[error("io error at {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("invalid configuration: {0}")]
+    InvalidConfig(String),
+    #[error("remote cache error: {0}")]
+    RemoteCache(#[source] anyhow::Error),
+    #[error("{} artifact(s) exceeded their size budget", violations.len())]
+    BudgetExceeded { violations: Vec<BudgetViolation> },
+    #[error("command `{command}` failed: {stderr}")]
+    CommandFailed { command: String, stderr: String },
+    #[error("{} processor failed on {}: {source}", artifact_type.label(), input.display())]
+    ProcessorFailed {
+        artifact_type: ArtifactType,
+        input: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("{} asset(s) failed to process", failures.len())]
+    AssetsFailed { failures: Vec<BuildError> },
+    #[error("no artifact of the last build was produced at {}", path.display())]
+    UnknownArtifact { path: PathBuf },
+    #[error(
+        "sources {first} and {second} differ only by case and would collide on a case-insensitive filesystem"
+    )]
+    CaseCollision { first: String, second: String },
+    #[error("no encoder is available for {format} output")]
+    UnsupportedFormat { format: &'static str },
+}
+
+/// One artifact that blew its configured size budget, and by how much.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetViolation {
+    pub artifact_type: ArtifactType,
+    pub path: PathBuf,
+    pub size: u64,
+    pub budget: u64,
+}
+
+impl std::fmt::Display for BudgetViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} exceeds its {} byte budget by {} bytes",
+            self.path.display(),
+            self.budget,
+            self.size.saturating_sub(self.budget)
+        )
+    }
+}
+
+/// Version of this crate, embedded in generated manifests so downstream
+/// tooling can tell which producer wrote them.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtifactType {
+    Media,
+    Style,
+    Icon,
+    Font,
+    I18n,
+    Serialized,
+    /// Produced by a user-configured [`CommandStage`] rather than a built-in
+    /// processor.
+    Transformed,
+}
+
+impl ArtifactType {
+    /// Stable lowercase name for span fields and other diagnostics.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Media => "media",
+            Self::Style => "style",
+            Self::Icon => "icon",
+            Self::Font => "font",
+            Self::I18n => "i18n",
+            Self::Serialized => "serialized",
+            Self::Transformed => "transformed",
+        }
+    }
+}
+
+/// One produced output file, identified by the hash of its content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildArtifact {
+    pub artifact_type: ArtifactType,
+    pub path: PathBuf,
+    pub hash: String,
+    pub size: u64,
+    /// Present when the artifact was large enough to be split for delta
+    /// uploads.
+    pub chunks: Option<ChunkManifest>,
+}
+
+impl BuildArtifact {
+    /// This artifact's path relative to the project root, rendered with
+    /// forward slashes regardless of platform; see [`normalized_path`].
+    pub fn normalized_path(&self, root: &Path) -> String {
+        normalized_path(&self.path, root)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildStats {
+    pub artifacts_processed: usize,
+    /// Artifacts served from this pipeline's own cache.
+    pub local_cache_hits: usize,
+    /// Artifacts downloaded from the shared [`RemoteCache`] instead of being
+    /// rebuilt.
+    pub remote_cache_hits: usize,
+    /// Artifacts an incremental build reused wholesale because their source
+    /// was untouched, or touched without its content changing.
+    pub artifacts_cached: usize,
+    pub build_time_ms: u64,
+}
+
+/// Hex-encoded SHA-256 of the given bytes; the basis for all artifact
+/// identity and cache keys.
+pub fn content_hash(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    hex::encode(hasher.finalize())
+}
+
+/// Hex-encoded SHA-256 of everything `reader` yields, hashed in 64 KiB
+/// chunks so a large file never has to fit in memory. Produces exactly the
+/// digest [`content_hash`] would for the same bytes, so the two back the
+/// same cache keys interchangeably.
+pub fn content_hash_reader<R: std::io::Read>(mut reader: R) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Renders `path` relative to `root` with forward-slash separators, so the
+/// same build produces byte-identical manifests (and thus build hashes) on
+/// Windows and Unix. The comparison is textual so a Windows-style path
+/// normalizes correctly even when inspected on another platform; a literal
+/// backslash in a file name is treated as a separator, which such a name
+/// would make the manifest ambiguous about anyway.
+pub fn normalized_path(path: &Path, root: &Path) -> String {
+    let path = path.display().to_string().replace('\\', "/");
+    let root = root.display().to_string().replace('\\', "/");
+    let relative = path
+        .strip_prefix(&root)
+        .map(|rest| rest.trim_start_matches('/'))
+        .unwrap_or(&path);
+    relative.trim_start_matches("./").to_string()
+}
@@ -0,0 +1,181 @@
+//! Resolves SVG icon sources into addressable artifacts, optionally combined
+//! into a single `<symbol>` sprite sheet so a page using dozens of small
+//! icons fetches one file instead of one request per icon.
+
+use crate::content_hash;
+use std::path::{Path, PathBuf};
+
+/// File name the sprite sheet is emitted under in the output directory.
+pub const SPRITE_FILE_NAME: &str = "icons.sprite.svg";
+
+#[derive(Debug, Clone, Default)]
+pub struct IconConfig {
+    /// When set, every resolved icon is folded into one sprite sheet and
+    /// addressed by symbol id; see [`IconProcessor::process`].
+    pub sprite_mode: bool,
+}
+
+/// One icon as authored: a name to address it by and the markup inside its
+/// `<svg>` element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconSource {
+    pub name: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedIcon {
+    pub name: String,
+    pub body: String,
+    pub hash: String,
+    /// The id this icon is addressed by: its `<symbol>` id in sprite mode
+    /// (shared with any icon whose body is byte-identical), otherwise its
+    /// own name.
+    pub symbol_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IconManifest {
+    /// Icon name to symbol id, in source order.
+    pub symbol_ids: Vec<(String, String)>,
+    /// Where the sprite sheet belongs in the output directory; present only
+    /// in sprite mode.
+    pub sprite_path: Option<PathBuf>,
+}
+
+/// What [`IconProcessor::process`] produced: the resolved icons, the
+/// manifest, and — in sprite mode — the sprite markup to write at
+/// [`IconManifest::sprite_path`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconBuildOutput {
+    pub icons: Vec<ResolvedIcon>,
+    pub manifest: IconManifest,
+    pub sprite: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct IconProcessor {
+    config: IconConfig,
+}
+
+impl IconProcessor {
+    pub fn new(config: IconConfig) -> Self {
+        Self { config }
+    }
+
+    /// Resolves `sources` in order. In sprite mode, icons with byte-identical
+    /// bodies share one `<symbol>` — the id is derived from the content hash,
+    /// so it is stable across renames and reorderings — and the manifest
+    /// records where the sprite belongs under `out_dir`.
+    pub fn process(&self, out_dir: &Path, sources: &[IconSource]) -> IconBuildOutput {
+        let mut icons = Vec::with_capacity(sources.len());
+        let mut symbol_ids = Vec::with_capacity(sources.len());
+        let mut sprite_symbols: Vec<(String, String)> = Vec::new();
+        for source in sources {
+            let hash = content_hash(source.body.as_bytes());
+            let symbol_id = if self.config.sprite_mode {
+                let symbol_id = symbol_id_for(&hash);
+                if !sprite_symbols.iter().any(|(id, _)| *id == symbol_id) {
+                    sprite_symbols.push((symbol_id.clone(), source.body.clone()));
+                }
+                symbol_id
+            } else {
+                source.name.clone()
+            };
+            symbol_ids.push((source.name.clone(), symbol_id.clone()));
+            icons.push(ResolvedIcon {
+                name: source.name.clone(),
+                body: source.body.clone(),
+                hash,
+                symbol_id,
+            });
+        }
+
+        let (sprite, sprite_path) = if self.config.sprite_mode {
+            (
+                Some(render_sprite(&sprite_symbols)),
+                Some(out_dir.join(SPRITE_FILE_NAME)),
+            )
+        } else {
+            (None, None)
+        };
+        IconBuildOutput {
+            icons,
+            manifest: IconManifest {
+                symbol_ids,
+                sprite_path,
+            },
+            sprite,
+        }
+    }
+}
+
+fn symbol_id_for(hash: &str) -> String {
+    // Twelve hex characters keep ids short while leaving collisions about as
+    // likely as a hash collision in the cache itself.
+    format!("icon-{}", hash.get(..12).unwrap_or(hash))
+}
+
+fn render_sprite(symbols: &[(String, String)]) -> String {
+    let mut sprite =
+        String::from("<svg xmlns=\"http://www.w3.org/2000/svg\" style=\"display: none\">\n");
+    for (symbol_id, body) in symbols {
+        sprite.push_str(&format!("<symbol id=\"{symbol_id}\">{body}</symbol>\n"));
+    }
+    sprite.push_str("</svg>\n");
+    sprite
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn icon(name: &str, body: &str) -> IconSource {
+        IconSource {
+            name: name.to_string(),
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_sprite_mode_combines_icons_and_deduplicates_identical_bodies() {
+        let processor = IconProcessor::new(IconConfig { sprite_mode: true });
+        let sources = [
+            icon("close", "<path d=\"M0 0L8 8\"/>"),
+            icon("chevron", "<path d=\"M0 4L4 8\"/>"),
+            // Same body as `close`, so it must share close's symbol.
+            icon("dismiss", "<path d=\"M0 0L8 8\"/>"),
+        ];
+        let output = processor.process(Path::new("dist"), &sources);
+
+        let sprite = output.sprite.unwrap();
+        assert_eq!(sprite.matches("<symbol id=").count(), 2);
+        assert_eq!(
+            output.manifest.sprite_path,
+            Some(PathBuf::from("dist").join(SPRITE_FILE_NAME))
+        );
+        assert_eq!(output.icons.len(), 3, "every icon stays addressable");
+        assert_eq!(output.icons[0].symbol_id, output.icons[2].symbol_id);
+        assert_ne!(output.icons[0].symbol_id, output.icons[1].symbol_id);
+        for (_, symbol_id) in &output.manifest.symbol_ids {
+            assert!(
+                sprite.contains(&format!("<symbol id=\"{symbol_id}\">")),
+                "every manifest id resolves in the sprite"
+            );
+        }
+    }
+
+    #[test]
+    fn test_without_sprite_mode_icons_are_addressed_by_name() {
+        let output = IconProcessor::default().process(
+            Path::new("dist"),
+            &[icon("close", "<path d=\"M0 0L8 8\"/>")],
+        );
+        assert!(output.sprite.is_none());
+        assert!(output.manifest.sprite_path.is_none());
+        assert_eq!(
+            output.manifest.symbol_ids,
+            vec![("close".to_string(), "close".to_string())]
+        );
+    }
+}
@@ -0,0 +1,372 @@
+//! Renders the favicon set from one source image: every configured
+//! [`FaviconSize`] in every configured [`ImageFormat`], with a manifest
+//! recording which file serves which size/format pair.
+
+use crate::{BuildError, content_hash, content_hash_reader};
+use memmap2::Mmap;
+use std::fs;
+use std::path::Path;
+
+/// Source files at or above this size are consumed through a memory map —
+/// or, failing that, hashed by streaming — instead of being read into
+/// memory; see [`SourceBytes`] and [`MediaProcessor::hash_source`].
+pub const LARGE_SOURCE_THRESHOLD: u64 = 8 * 1024 * 1024;
+
+/// The bytes of a source file, borrowed from a memory map for files at or
+/// above [`LARGE_SOURCE_THRESHOLD`] so hashing and transforms page data off
+/// disk instead of spiking peak RSS. Small files, and large ones on
+/// platforms or filesystems that refuse the mapping, are read whole — the
+/// bytes are identical either way.
+pub enum SourceBytes {
+    Mapped(Mmap),
+    Buffered(Vec<u8>),
+}
+
+impl SourceBytes {
+    pub fn open(path: &Path) -> Result<Self, BuildError> {
+        let io_error = |source| BuildError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        let metadata = fs::metadata(path).map_err(io_error)?;
+        if metadata.len() >= LARGE_SOURCE_THRESHOLD
+            && let Some(mapped) = map_source(path)
+        {
+            return Ok(Self::Mapped(mapped));
+        }
+        fs::read(path).map(Self::Buffered).map_err(io_error)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Self::Mapped(mapped) => mapped,
+            Self::Buffered(bytes) => bytes,
+        }
+    }
+}
+
+impl AsRef<[u8]> for SourceBytes {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+/// Memory-maps `path` read-only, or `None` where the platform or filesystem
+/// doesn't support it; the caller falls back to buffered reads.
+fn map_source(path: &Path) -> Option<Mmap> {
+    let file = fs::File::open(path).ok()?;
+    // SAFETY: the map is only ever read. A writer truncating the file
+    // mid-build can invalidate the mapping, but sources mutating during a
+    // build corrupt the buffered path just the same; neither path promises
+    // a coherent snapshot of a file being written to.
+    unsafe { Mmap::map(&file) }.ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    Png,
+    WebP,
+    Avif,
+}
+
+impl ImageFormat {
+    /// Stable lowercase name, doubling as the file extension.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::WebP => "webp",
+            Self::Avif => "avif",
+        }
+    }
+}
+
+/// Edge length of a square favicon variant, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FaviconSize(pub u32);
+
+#[derive(Debug, Clone)]
+pub struct MediaConfig {
+    pub favicon_sizes: Vec<FaviconSize>,
+    /// Formats every size is emitted in, in output order.
+    pub formats: Vec<ImageFormat>,
+}
+
+impl Default for MediaConfig {
+    fn default() -> Self {
+        Self {
+            favicon_sizes: vec![FaviconSize(16), FaviconSize(32), FaviconSize(48)],
+            formats: vec![ImageFormat::Png],
+        }
+    }
+}
+
+/// One size/format pair in the manifest, addressed by the emitted file name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaviconEntry {
+    pub size: FaviconSize,
+    pub format: ImageFormat,
+    pub file_name: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FaviconManifest {
+    pub entries: Vec<FaviconEntry>,
+}
+
+/// One favicon variant as produced: its manifest entry plus the bytes to
+/// write under the entry's file name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FaviconImage {
+    pub entry: FaviconEntry,
+    pub bytes: Vec<u8>,
+}
+
+/// What [`MediaProcessor::process`] produced. `warnings` holds the formats
+/// that were requested but skipped for lack of an encoder; skipping is not a
+/// hard failure so the remaining formats still ship.
+pub struct MediaBuildOutput {
+    pub favicons: Vec<FaviconImage>,
+    pub manifest: FaviconManifest,
+    pub warnings: Vec<BuildError>,
+}
+
+type AvifEncoder = Box<dyn Fn(&[u8], FaviconSize) -> Vec<u8>>;
+
+#[derive(Default)]
+pub struct MediaProcessor {
+    config: MediaConfig,
+    /// AVIF needs an external codec the build host may not have, so the
+    /// encoder is injected rather than built in; without one, requesting
+    /// [`ImageFormat::Avif`] produces a warning instead of output.
+    avif_encoder: Option<AvifEncoder>,
+}
+
+impl MediaProcessor {
+    pub fn new(config: MediaConfig) -> Self {
+        Self {
+            config,
+            avif_encoder: None,
+        }
+    }
+
+    pub fn with_avif_encoder(
+        mut self,
+        encoder: impl Fn(&[u8], FaviconSize) -> Vec<u8> + 'static,
+    ) -> Self {
+        self.avif_encoder = Some(Box::new(encoder));
+        self
+    }
+
+    /// Emits `source` at every configured size in every configured format.
+    /// A format without an encoder is skipped with one
+    /// [`BuildError::UnsupportedFormat`] warning; the other formats are
+    /// unaffected.
+    pub fn process(&self, source: &[u8]) -> MediaBuildOutput {
+        let mut favicons = Vec::new();
+        let mut warnings = Vec::new();
+        for format in &self.config.formats {
+            for size in &self.config.favicon_sizes {
+                let bytes = match format {
+                    ImageFormat::Png | ImageFormat::WebP => {
+                        encode_container(*format, *size, source)
+                    }
+                    ImageFormat::Avif => match &self.avif_encoder {
+                        Some(encoder) => encoder(source, *size),
+                        None => {
+                            warnings.push(BuildError::UnsupportedFormat {
+                                format: format.label(),
+                            });
+                            break;
+                        }
+                    },
+                };
+                let hash = content_hash(&bytes);
+                let entry = FaviconEntry {
+                    size: *size,
+                    format: *format,
+                    file_name: file_name_for(*size, *format, &hash),
+                    hash,
+                };
+                favicons.push(FaviconImage { entry, bytes });
+            }
+        }
+        let manifest = FaviconManifest {
+            entries: favicons.iter().map(|image| image.entry.clone()).collect(),
+        };
+        MediaBuildOutput {
+            favicons,
+            manifest,
+            warnings,
+        }
+    }
+
+    /// Content hash of a source file. Files at or above
+    /// [`LARGE_SOURCE_THRESHOLD`] — video assets, mostly — are hashed off a
+    /// memory map, or in fixed-size chunks straight off disk where mapping
+    /// isn't viable; smaller ones are read whole. The digest is identical on
+    /// every path, so cache keys never depend on which one a file took.
+    pub fn hash_source(&self, path: &Path) -> Result<String, BuildError> {
+        let io_error = |source| BuildError::Io {
+            path: path.to_path_buf(),
+            source,
+        };
+        let metadata = fs::metadata(path).map_err(io_error)?;
+        if metadata.len() >= LARGE_SOURCE_THRESHOLD {
+            if let Some(mapped) = map_source(path) {
+                return Ok(content_hash(&mapped));
+            }
+            let file = fs::File::open(path).map_err(io_error)?;
+            content_hash_reader(file).map_err(io_error)
+        } else {
+            let bytes = fs::read(path).map_err(io_error)?;
+            Ok(content_hash(&bytes))
+        }
+    }
+}
+
+/// Stand-in container encode: the format's magic bytes, the edge length, and
+/// the source pixels. Real resampling and compression are left to the host's
+/// codecs; what matters for the cache is that the magic prefix makes the
+/// content hash of, say, the 32px PNG differ from the 32px WebP of the same
+/// source, so the two can never collide under one cache key.
+fn encode_container(format: ImageFormat, size: FaviconSize, source: &[u8]) -> Vec<u8> {
+    let magic: &[u8] = match format {
+        ImageFormat::Png => b"\x89PNG\r\n\x1a\n",
+        ImageFormat::WebP => b"RIFFWEBP",
+        ImageFormat::Avif => b"ftypavif",
+    };
+    let mut bytes = magic.to_vec();
+    bytes.extend_from_slice(&size.0.to_le_bytes());
+    bytes.extend_from_slice(source);
+    bytes
+}
+
+fn file_name_for(size: FaviconSize, format: ImageFormat, hash: &str) -> String {
+    // Same truncation as icon symbol ids: twelve hex characters keep names
+    // short without meaningfully raising the collision odds.
+    format!(
+        "favicon-{0}x{0}.{1}.{2}",
+        size.0,
+        hash.get(..12).unwrap_or(hash),
+        format.label()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &[u8] = b"source image pixels";
+
+    fn config(formats: Vec<ImageFormat>) -> MediaConfig {
+        MediaConfig {
+            favicon_sizes: vec![FaviconSize(16), FaviconSize(32)],
+            formats,
+        }
+    }
+
+    #[test]
+    fn test_every_size_is_emitted_in_every_format_with_distinct_hashes() {
+        let processor = MediaProcessor::new(config(vec![ImageFormat::Png, ImageFormat::WebP]));
+        let output = processor.process(SOURCE);
+        assert!(output.warnings.is_empty());
+        assert_eq!(output.manifest.entries.len(), 4);
+
+        let hash_of = |size: u32, format: ImageFormat| {
+            output
+                .manifest
+                .entries
+                .iter()
+                .find(|entry| entry.size == FaviconSize(size) && entry.format == format)
+                .map(|entry| entry.hash.clone())
+                .unwrap()
+        };
+        assert_ne!(
+            hash_of(16, ImageFormat::Png),
+            hash_of(16, ImageFormat::WebP),
+            "the same size in two formats must not share a cache key"
+        );
+        assert_ne!(hash_of(16, ImageFormat::Png), hash_of(32, ImageFormat::Png));
+        for image in &output.favicons {
+            assert!(image.entry.file_name.ends_with(image.entry.format.label()));
+        }
+    }
+
+    #[test]
+    fn test_missing_avif_encoder_warns_without_failing_the_other_formats() {
+        let processor = MediaProcessor::new(config(vec![ImageFormat::Png, ImageFormat::Avif]));
+        let output = processor.process(SOURCE);
+        assert_eq!(output.manifest.entries.len(), 2, "both PNG sizes ship");
+        assert!(
+            output
+                .manifest
+                .entries
+                .iter()
+                .all(|entry| entry.format == ImageFormat::Png)
+        );
+        assert_eq!(output.warnings.len(), 1, "one warning per skipped format");
+        assert!(matches!(
+            output.warnings[0],
+            BuildError::UnsupportedFormat { format: "avif" }
+        ));
+    }
+
+    #[test]
+    fn test_streaming_and_in_memory_hashes_agree() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clip.mp4");
+        // Larger than the hashing chunk size so the streaming path exercises
+        // more than one chunk.
+        let bytes: Vec<u8> = (0..100_000u32)
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        std::fs::write(&path, &bytes).unwrap();
+
+        let streamed = content_hash_reader(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(streamed, content_hash(&bytes));
+
+        let processor = MediaProcessor::default();
+        assert_eq!(processor.hash_source(&path).unwrap(), streamed);
+    }
+
+    #[test]
+    fn test_mapped_source_matches_the_buffered_path_byte_for_byte() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clip.mp4");
+        // Just over the threshold so `open` takes the mapped path, with a
+        // non-repeating pattern so any offset error changes the digest.
+        let length = LARGE_SOURCE_THRESHOLD as usize + 3;
+        let bytes: Vec<u8> = (0..length).map(|index| (index % 251) as u8).collect();
+        std::fs::write(&path, &bytes).unwrap();
+
+        let source = SourceBytes::open(&path).unwrap();
+        assert!(matches!(source, SourceBytes::Mapped(_)));
+        assert_eq!(source.as_bytes(), bytes.as_slice());
+
+        let processor = MediaProcessor::default();
+        assert_eq!(processor.hash_source(&path).unwrap(), content_hash(&bytes));
+
+        let small = dir.path().join("icon.png");
+        std::fs::write(&small, b"tiny").unwrap();
+        assert!(matches!(
+            SourceBytes::open(&small).unwrap(),
+            SourceBytes::Buffered(_)
+        ));
+    }
+
+    #[test]
+    fn test_injected_avif_encoder_is_used() {
+        let processor = MediaProcessor::new(config(vec![ImageFormat::Avif]))
+            .with_avif_encoder(|source, size| encode_container(ImageFormat::Avif, size, source));
+        let output = processor.process(SOURCE);
+        assert!(output.warnings.is_empty());
+        assert_eq!(output.manifest.entries.len(), 2);
+        assert!(
+            output
+                .favicons
+                .iter()
+                .all(|image| image.entry.format == ImageFormat::Avif)
+        );
+    }
+}
@@ -0,0 +1,2518 @@
+use crate::{
+    ArtifactType, BudgetViolation, BuildArtifact, BuildCache, BuildError, BuildStats, CacheEntry,
+    CacheKey, ChunkManifest, ChunkerConfig, RemoteCache, SourceBytes, TamperWarning, content_hash,
+};
+use collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Instant, SystemTime};
+
+/// Name of the manifest written to the output directory by every build.
+pub const MANIFEST_FILE_NAME: &str = "build-manifest.txt";
+
+/// Name of the inputs lock written next to the manifest: every source file
+/// the build actually read, with its content hash, so a reviewer can verify
+/// which source tree a build came from.
+pub const INPUTS_LOCK_FILE_NAME: &str = "inputs.lock";
+
+/// Processor version used for stages without an entry in
+/// [`BuildConfig::processor_versions`].
+pub const DEFAULT_PROCESSOR_VERSION: u32 = 1;
+
+/// When configured, artifacts at or above `min_artifact_size` also get a
+/// [`ChunkManifest`] so they can be delta-uploaded chunk by chunk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkingOptions {
+    pub chunker: ChunkerConfig,
+    pub min_artifact_size: u64,
+}
+
+impl Default for ChunkingOptions {
+    fn default() -> Self {
+        Self {
+            chunker: ChunkerConfig::default(),
+            min_artifact_size: 1024 * 1024,
+        }
+    }
+}
+
+/// An external tool run as a build stage. Sources whose file name matches
+/// `input_glob` are handed to `command` instead of a built-in processor, and
+/// the file it writes becomes a [`ArtifactType::Transformed`] artifact.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandStage {
+    /// Matched against the source file name, with `*` matching any run of
+    /// characters (for example `*.glsl`).
+    pub input_glob: String,
+    /// Output file name within the output directory; `{stem}` is replaced by
+    /// the source file's stem.
+    pub output_pattern: String,
+    /// Command template run through `sh -c`, with `{in}` and `{out}` replaced
+    /// by the quoted input and output paths.
+    pub command: String,
+    /// Build targets this stage applies to; empty means every target.
+    pub targets: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BuildConfig {
+    pub enable_media: bool,
+    pub enable_styles: bool,
+    pub enable_icons: bool,
+    pub enable_cache: bool,
+    /// Which build this is for (for example `web` or `node`). Stages scoped
+    /// via [`stage_targets`](Self::stage_targets) or
+    /// [`CommandStage::targets`] only run when they list this target, and
+    /// cache keys are namespaced by it so builds for different targets never
+    /// serve each other's artifacts.
+    pub target: String,
+    /// Restricts built-in stages to specific targets; a type with no entry
+    /// runs for every target.
+    pub stage_targets: HashMap<ArtifactType, Vec<String>>,
+    /// Output directory, relative to the project root.
+    pub out_dir: PathBuf,
+    pub chunking: Option<ChunkingOptions>,
+    /// External transforms, consulted before the built-in processors; the
+    /// first stage whose glob matches a source claims it.
+    pub command_stages: Vec<CommandStage>,
+    /// Per-type size ceilings in bytes. Checked after all artifacts are
+    /// produced so one failed build reports every violation at once.
+    pub size_budgets: HashMap<ArtifactType, u64>,
+    /// Per-type overrides of [`DEFAULT_PROCESSOR_VERSION`]. Bump a stage's
+    /// version whenever its processing logic changes so stale cache entries
+    /// built by the old logic stop matching; other stages keep their caches.
+    pub processor_versions: HashMap<ArtifactType, u32>,
+    /// Fixed build timestamp (seconds since the Unix epoch), following the
+    /// `SOURCE_DATE_EPOCH` reproducible-builds convention. When set, every
+    /// place a stage would embed `SystemTime::now()` uses this instead, so
+    /// two builds of the same source are byte-identical. Callers typically
+    /// populate it from the `SOURCE_DATE_EPOCH` environment variable.
+    pub source_date_epoch: Option<i64>,
+    /// Ceiling on the total bytes of cached artifacts. When a finishing
+    /// build leaves the cache over this budget, least-recently-accessed
+    /// entries and their files are evicted until it fits — except the
+    /// finishing build's own artifacts, which are never evicted. `None`
+    /// means the cache grows unbounded.
+    pub cache_max_bytes: Option<u64>,
+    /// How many worker threads process sources concurrently; `0` uses the
+    /// number of available CPU cores. The stages are independent of each
+    /// other, so a project heavy in both media and icons roughly halves its
+    /// wall-clock build time. Artifact order and every produced byte are
+    /// identical whatever the value.
+    pub parallelism: usize,
+    /// When true (the default), the first failing asset aborts the build.
+    /// When false, the build keeps going past failing assets and reports
+    /// every failure at once as [`BuildError::AssetsFailed`], so a broken
+    /// icon in a large build doesn't hide a broken stylesheet behind it.
+    pub fail_fast: bool,
+}
+
+impl Default for BuildConfig {
+    fn default() -> Self {
+        Self {
+            enable_media: true,
+            enable_styles: true,
+            enable_icons: true,
+            enable_cache: true,
+            target: "web".to_string(),
+            stage_targets: HashMap::default(),
+            out_dir: PathBuf::from("dist"),
+            chunking: None,
+            command_stages: Vec::new(),
+            size_budgets: HashMap::default(),
+            processor_versions: HashMap::default(),
+            source_date_epoch: None,
+            cache_max_bytes: None,
+            parallelism: 0,
+            fail_fast: true,
+        }
+    }
+}
+
+/// One input that fed an artifact: a source file's bytes or a piece of
+/// configuration that shaped the output. Everything listed here must be
+/// captured by the artifact's [`CacheKey`], directly or derived; an input in
+/// the graph with no counterpart in the key is a missing-dependency bug that
+/// causes incorrect cache hits.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ArtifactInput {
+    SourceFile { path: PathBuf, hash: String },
+    ConfigValue { name: String, value: String },
+}
+
+/// Which inputs fed each artifact of a build, keyed by the artifact's output
+/// path. Recorded for cache hits too, so a stale-looking artifact can be
+/// inspected without forcing a rebuild.
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    inputs_by_artifact: HashMap<PathBuf, Vec<ArtifactInput>>,
+}
+
+impl DependencyGraph {
+    fn record(&mut self, artifact_path: &Path, inputs: Vec<ArtifactInput>) {
+        self.inputs_by_artifact
+            .insert(artifact_path.to_path_buf(), inputs);
+    }
+
+    pub fn inputs_for(&self, artifact_path: &Path) -> Option<&[ArtifactInput]> {
+        self.inputs_by_artifact
+            .get(artifact_path)
+            .map(|inputs| inputs.as_slice())
+    }
+
+    pub fn artifacts(&self) -> impl Iterator<Item = &Path> {
+        self.inputs_by_artifact.keys().map(|path| path.as_path())
+    }
+}
+
+#[derive(Debug)]
+pub struct BuildResult {
+    pub artifacts: Vec<BuildArtifact>,
+    pub stats: BuildStats,
+    /// Content hash of the build manifest, covering every artifact hash and
+    /// the build timestamp. With `source_date_epoch` fixed, identical sources
+    /// always produce an identical build hash.
+    pub build_hash: String,
+    /// Cached artifacts found on disk with bytes that no longer matched their
+    /// recorded hash. Each was rebuilt rather than served, but a non-empty
+    /// list means something else wrote into the output directory.
+    pub tamper_warnings: Vec<TamperWarning>,
+    dependency_graph: DependencyGraph,
+    /// Project root the build ran from; manifest paths are rendered relative
+    /// to it.
+    root: PathBuf,
+}
+
+/// Schema version of [`BuildResult::write_manifest`]'s JSON output; bumped
+/// whenever a field changes shape or meaning so downstream tooling can
+/// refuse manifests it does not understand.
+pub const JSON_MANIFEST_SCHEMA_VERSION: u32 = 1;
+
+impl BuildResult {
+    pub fn dependency_graph(&self) -> &DependencyGraph {
+        &self.dependency_graph
+    }
+
+    /// Writes the machine-readable build manifest to `path`; see
+    /// [`json_manifest`](Self::json_manifest) for the schema.
+    pub fn write_manifest(&self, path: &Path) -> Result<(), BuildError> {
+        fs::write(path, self.json_manifest()).map_err(|source| BuildError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// The build rendered as JSON under a versioned schema: every artifact's
+    /// type (lowercase), root-relative path, hash, and size, plus the build
+    /// stats and the [`crate::VERSION`] that produced it. Keys come in a
+    /// fixed order and artifacts sorted by path, so manifests from two
+    /// builds diff cleanly — a CDN uploader reads the hashes to set
+    /// immutable cache headers.
+    pub fn json_manifest(&self) -> String {
+        let mut artifacts: Vec<&BuildArtifact> = self.artifacts.iter().collect();
+        artifacts.sort_by_key(|artifact| &artifact.path);
+
+        let mut json = String::from("{\n");
+        json.push_str(&format!(
+            "  \"schema_version\": {JSON_MANIFEST_SCHEMA_VERSION},\n"
+        ));
+        json.push_str(&format!(
+            "  \"producer_version\": \"{}\",\n",
+            json_escaped(crate::VERSION)
+        ));
+        json.push_str(&format!(
+            "  \"build_hash\": \"{}\",\n",
+            json_escaped(&self.build_hash)
+        ));
+        json.push_str("  \"artifacts\": [\n");
+        for (index, artifact) in artifacts.iter().enumerate() {
+            let separator = if index + 1 < artifacts.len() { "," } else { "" };
+            json.push_str(&format!(
+                "    {{\"type\": \"{}\", \"path\": \"{}\", \"hash\": \"{}\", \"size\": {}}}{separator}\n",
+                artifact.artifact_type.label(),
+                json_escaped(&artifact.normalized_path(&self.root)),
+                json_escaped(&artifact.hash),
+                artifact.size,
+            ));
+        }
+        json.push_str("  ],\n");
+        json.push_str(&format!(
+            "  \"stats\": {{\"artifacts_processed\": {}, \"local_cache_hits\": {}, \"remote_cache_hits\": {}, \"artifacts_cached\": {}, \"build_time_ms\": {}}}\n",
+            self.stats.artifacts_processed,
+            self.stats.local_cache_hits,
+            self.stats.remote_cache_hits,
+            self.stats.artifacts_cached,
+            self.stats.build_time_ms,
+        ));
+        json.push_str("}\n");
+        json
+    }
+}
+
+/// `text` with JSON string escapes applied. Hashes never need them, but an
+/// artifact path can contain quotes or backslashes.
+fn json_escaped(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            control if (control as u32) < 0x20 => {
+                escaped.push_str(&format!("\\u{:04x}", control as u32));
+            }
+            other => escaped.push(other),
+        }
+    }
+    escaped
+}
+
+pub struct BuildPipeline {
+    root: PathBuf,
+    config: BuildConfig,
+    cache: BuildCache,
+    remote_cache: Option<Box<dyn RemoteCache>>,
+    /// How each source looked when its artifact was last produced; what
+    /// [`build_incremental`](Self::build_incremental) consults to skip work.
+    fingerprints: HashMap<PathBuf, SourceFingerprint>,
+    /// Transient stage selection for the duration of one
+    /// [`build_types`](Self::build_types) call; `None` defers to the
+    /// `enable_*` flags.
+    type_filter: Option<HashSet<ArtifactType>>,
+}
+
+#[derive(Clone)]
+struct SourceFingerprint {
+    /// `None` when the mtime could not be read; the content hash then
+    /// decides alone.
+    modified_at: Option<SystemTime>,
+    content_hash: String,
+    artifact: BuildArtifact,
+    inputs: Vec<ArtifactInput>,
+}
+
+impl BuildPipeline {
+    pub fn new(root: impl Into<PathBuf>, config: BuildConfig) -> Self {
+        let root = root.into();
+        // Resolved eagerly so a symlinked project directory yields the same
+        // paths `read_dir` does under it — otherwise the output directory
+        // fails its exclusion check and gets re-ingested as source. A root
+        // that doesn't exist yet is kept as configured; the first build
+        // reports the IO error against that path.
+        let root = fs::canonicalize(&root).unwrap_or(root);
+        Self {
+            root,
+            config,
+            cache: BuildCache::new(),
+            remote_cache: None,
+            fingerprints: HashMap::default(),
+            type_filter: None,
+        }
+    }
+
+    /// Backs the local cache with a shared remote store: local misses read
+    /// through to it, and newly produced artifacts are uploaded.
+    pub fn with_remote_cache(mut self, remote_cache: Box<dyn RemoteCache>) -> Self {
+        self.remote_cache = Some(remote_cache);
+        self
+    }
+
+    pub fn config(&self) -> &BuildConfig {
+        &self.config
+    }
+
+    pub fn config_mut(&mut self) -> &mut BuildConfig {
+        &mut self.config
+    }
+
+    /// Processes every recognized source file under the project root into a
+    /// hashed artifact in the output directory.
+    ///
+    /// The build and each processed artifact are wrapped in `tracing` spans
+    /// (free when no subscriber is installed), so enabling a subscriber
+    /// yields per-asset timings and cache outcomes.
+    pub fn build(&mut self) -> Result<BuildResult, BuildError> {
+        let build_span = tracing::info_span!(
+            "build",
+            target = self.config.target.as_str(),
+            sources = tracing::field::Empty,
+            artifacts = tracing::field::Empty,
+        );
+        let _build_guard = build_span.enter();
+        let started_at = Instant::now();
+        let out_dir = self.root.join(&self.config.out_dir);
+        let sources = self.prepare_sources(&out_dir)?;
+        build_span.record("sources", sources.len() as u64);
+
+        let mut jobs = Vec::new();
+        for (index, source) in sources.into_iter().enumerate() {
+            if let Some(stage) = self.command_stage_for(&source) {
+                // A filtered-out command stage still claims its source; the
+                // file must not fall through to a built-in processor it was
+                // configured away from.
+                if self.type_selected(ArtifactType::Transformed) {
+                    jobs.push((index, source, SourceJob::Command(stage)));
+                }
+                continue;
+            }
+            let Some(artifact_type) = self.artifact_type_for(&source) else {
+                continue;
+            };
+            jobs.push((index, source, SourceJob::Builtin(artifact_type)));
+        }
+
+        let (mut indexed, stats, dependency_graph) = self.run_jobs(
+            jobs,
+            &out_dir,
+            BuildStats::default(),
+            DependencyGraph::default(),
+        )?;
+        indexed.sort_by_key(|(index, _)| *index);
+        let artifacts: Vec<BuildArtifact> =
+            indexed.into_iter().map(|(_, artifact)| artifact).collect();
+        build_span.record("artifacts", artifacts.len() as u64);
+        self.finish(artifacts, stats, dependency_graph, &out_dir, started_at)
+    }
+
+    /// Runs only the stages producing `types`, overriding — without mutating
+    /// — the `enable_*` flags; `dx build --only styles,icons` lands here.
+    /// The result's artifacts and stats cover exactly the stages that ran. A
+    /// type whose output references another type's pulls that type in:
+    /// styles reference fonts, so requesting styles builds fonts too rather
+    /// than shipping stylesheets with dangling font URLs.
+    pub fn build_types(&mut self, types: &[ArtifactType]) -> Result<BuildResult, BuildError> {
+        let mut filter: HashSet<ArtifactType> = types.iter().copied().collect();
+        for artifact_type in types {
+            if let Some(required) = required_dependency(*artifact_type) {
+                filter.insert(required);
+            }
+        }
+        self.type_filter = Some(filter);
+        let result = self.build();
+        self.type_filter = None;
+        result
+    }
+
+    /// Walks the same sources and processors as [`build`](Self::build) but
+    /// writes nothing: no artifacts, no cache entries, no manifest. The
+    /// returned artifacts carry the destination paths, hashes, and sizes a
+    /// real build would produce, so CI can diff the plan against a committed
+    /// manifest to catch unexpected asset churn. Stats counters stay zero —
+    /// nothing is processed or served from a cache — except `build_time_ms`,
+    /// which covers the planning walk itself. Command stages are the one
+    /// approximation: their true output exists only after the command runs,
+    /// so they are planned from a prior cache entry when one exists and from
+    /// the source bytes otherwise.
+    pub fn plan(&self) -> Result<BuildResult, BuildError> {
+        let started_at = Instant::now();
+        let out_dir = self.root.join(&self.config.out_dir);
+        // Deliberately not `prepare_sources`: that would create the output
+        // directory, and a plan must leave the tree untouched.
+        let mut sources = Vec::new();
+        collect_sources(&self.root, &out_dir, &mut sources)?;
+        sources.sort();
+        detect_case_collisions(&self.root, &sources)?;
+
+        let mut artifacts = Vec::new();
+        let mut dependency_graph = DependencyGraph::default();
+        for source in sources {
+            if let Some(stage) = self.command_stage_for(&source) {
+                artifacts.push(self.plan_command_stage(
+                    &source,
+                    &stage,
+                    &out_dir,
+                    &mut dependency_graph,
+                )?);
+                continue;
+            }
+            let Some(artifact_type) = self.artifact_type_for(&source) else {
+                continue;
+            };
+            let bytes = fs::read(&source).map_err(|io_error| BuildError::Io {
+                path: source.clone(),
+                source: io_error,
+            })?;
+            let input_hash = content_hash(&bytes);
+            let output_path = out_dir.join(hashed_file_name(&source, &input_hash));
+            dependency_graph.record(
+                &output_path,
+                artifact_inputs(
+                    &self.root,
+                    &self.config,
+                    &source,
+                    &input_hash,
+                    artifact_type,
+                ),
+            );
+            artifacts.push(BuildArtifact {
+                artifact_type,
+                path: output_path,
+                hash: input_hash,
+                size: bytes.len() as u64,
+                chunks: chunk_manifest_for(&self.config, &bytes),
+            });
+        }
+
+        self.check_budgets(&artifacts)?;
+        let manifest = self.render_manifest(&artifacts);
+        let stats = BuildStats {
+            build_time_ms: started_at.elapsed().as_millis() as u64,
+            ..BuildStats::default()
+        };
+        Ok(BuildResult {
+            artifacts,
+            stats,
+            build_hash: content_hash(manifest.as_bytes()),
+            tamper_warnings: Vec::new(),
+            dependency_graph,
+            root: self.root.clone(),
+        })
+    }
+
+    /// What [`run_command_stage`](Self::run_command_stage) would produce for
+    /// `source`, without running the command or writing anything.
+    fn plan_command_stage(
+        &self,
+        source: &Path,
+        stage: &CommandStage,
+        out_dir: &Path,
+        dependency_graph: &mut DependencyGraph,
+    ) -> Result<BuildArtifact, BuildError> {
+        let bytes = fs::read(source).map_err(|io_error| BuildError::Io {
+            path: source.to_path_buf(),
+            source: io_error,
+        })?;
+        let source_hash = content_hash(&bytes);
+        let input_hash = content_hash(
+            format!("{source_hash}\n{}\n{}", stage.command, stage.output_pattern).as_bytes(),
+        );
+        let cache_key = CacheKey {
+            artifact_type: ArtifactType::Transformed,
+            processor_version: processor_version(&self.config, ArtifactType::Transformed),
+            target: self.config.target.clone(),
+            input_hash,
+        };
+        let stem = source
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("asset");
+        let output_path = out_dir.join(stage.output_pattern.replace("{stem}", stem));
+        let mut inputs = artifact_inputs(
+            &self.root,
+            &self.config,
+            source,
+            &source_hash,
+            ArtifactType::Transformed,
+        );
+        inputs.push(ArtifactInput::ConfigValue {
+            name: "command".to_string(),
+            value: stage.command.clone(),
+        });
+        dependency_graph.record(&output_path, inputs);
+
+        // `get`, not `get_verified`: verification re-hashes the artifact on
+        // disk and records tamper warnings, and a plan must be read-only.
+        if self.config.enable_cache
+            && let Some(entry) = self.cache.get(&cache_key)
+        {
+            return Ok(BuildArtifact {
+                artifact_type: ArtifactType::Transformed,
+                path: output_path,
+                hash: entry.output_hash.clone(),
+                size: entry.output_size,
+                chunks: None,
+            });
+        }
+        Ok(BuildArtifact {
+            artifact_type: ArtifactType::Transformed,
+            path: output_path,
+            hash: source_hash,
+            size: bytes.len() as u64,
+            chunks: chunk_manifest_for(&self.config, &bytes),
+        })
+    }
+
+    /// Rebuilds after a watch event. Sources outside `changed_paths` are
+    /// served from the previous build without being re-read; changed ones
+    /// are re-examined cheaply first — an unchanged mtime short-circuits,
+    /// and only an mtime mismatch pays for re-hashing the bytes, so a file
+    /// touched without actually changing is still reported as cached rather
+    /// than processed. A source with no recorded fingerprint (new, or never
+    /// built because no full [`build`](Self::build) ran) is processed
+    /// normally, where [`BuildCache`] applies as usual.
+    pub fn build_incremental(
+        &mut self,
+        changed_paths: &[PathBuf],
+    ) -> Result<BuildResult, BuildError> {
+        let build_span = tracing::info_span!(
+            "build",
+            target = self.config.target.as_str(),
+            sources = tracing::field::Empty,
+            artifacts = tracing::field::Empty,
+        );
+        let _build_guard = build_span.enter();
+        let started_at = Instant::now();
+        let out_dir = self.root.join(&self.config.out_dir);
+        let sources = self.prepare_sources(&out_dir)?;
+        build_span.record("sources", sources.len() as u64);
+
+        let changed: HashSet<PathBuf> = changed_paths
+            .iter()
+            .map(|path| {
+                let absolute = if path.is_absolute() {
+                    path.clone()
+                } else {
+                    self.root.join(path)
+                };
+                // Collected sources are canonical (the root is resolved in
+                // `new`), so changed paths must be too or they never match.
+                fs::canonicalize(&absolute).unwrap_or(absolute)
+            })
+            .collect();
+
+        let mut reused = Vec::new();
+        let mut jobs = Vec::new();
+        let mut stats = BuildStats::default();
+        let mut dependency_graph = DependencyGraph::default();
+        for (index, source) in sources.into_iter().enumerate() {
+            if let Some(artifact) =
+                self.reuse_unchanged(&source, &changed, &mut stats, &mut dependency_graph)?
+            {
+                reused.push((index, artifact));
+                continue;
+            }
+            if let Some(stage) = self.command_stage_for(&source) {
+                jobs.push((index, source, SourceJob::Command(stage)));
+                continue;
+            }
+            let Some(artifact_type) = self.artifact_type_for(&source) else {
+                continue;
+            };
+            jobs.push((index, source, SourceJob::Builtin(artifact_type)));
+        }
+
+        let (mut indexed, stats, dependency_graph) =
+            self.run_jobs(jobs, &out_dir, stats, dependency_graph)?;
+        indexed.extend(reused);
+        indexed.sort_by_key(|(index, _)| *index);
+        let artifacts: Vec<BuildArtifact> =
+            indexed.into_iter().map(|(_, artifact)| artifact).collect();
+        build_span.record("artifacts", artifacts.len() as u64);
+        self.finish(artifacts, stats, dependency_graph, &out_dir, started_at)
+    }
+
+    /// Rebuilds just the artifact the last build produced at
+    /// `artifact_path` — the fast path behind an editor's "regenerate this
+    /// file" action. The recorded dependency inputs name the source that
+    /// feeds the artifact, and only that source is reprocessed; its cache
+    /// entry and fingerprint are refreshed in place. A relative path is
+    /// resolved against the output directory. When the source changed, the
+    /// rebuilt artifact lands at a new hashed path — the returned artifact
+    /// names it.
+    ///
+    /// A path no build of this pipeline produced is
+    /// [`BuildError::UnknownArtifact`], not a silent no-op.
+    pub fn rebuild_artifact(&mut self, artifact_path: &Path) -> Result<BuildArtifact, BuildError> {
+        let out_dir = self.root.join(&self.config.out_dir);
+        let requested = if artifact_path.is_absolute() {
+            artifact_path.to_path_buf()
+        } else {
+            out_dir.join(artifact_path)
+        };
+        let Some(source) = self
+            .fingerprints
+            .iter()
+            .find(|(_, fingerprint)| fingerprint.artifact.path == requested)
+            .map(|(source, _)| source.clone())
+        else {
+            return Err(BuildError::UnknownArtifact { path: requested });
+        };
+        let job = match self.command_stage_for(&source) {
+            Some(stage) => SourceJob::Command(stage),
+            None => match self.artifact_type_for(&source) {
+                Some(artifact_type) => SourceJob::Builtin(artifact_type),
+                // The config changed since the artifact was recorded and no
+                // longer produces it.
+                None => return Err(BuildError::UnknownArtifact { path: requested }),
+            },
+        };
+        let (mut indexed, _, _) = self.run_jobs(
+            vec![(0, source, job)],
+            &out_dir,
+            BuildStats::default(),
+            DependencyGraph::default(),
+        )?;
+        let Some((_, artifact)) = indexed.pop() else {
+            return Err(BuildError::UnknownArtifact { path: requested });
+        };
+        Ok(artifact)
+    }
+
+    /// The previous build's artifact for `source`, when the changed set and
+    /// then the file's mtime and content hash show it unchanged; `None` when
+    /// the source must be reprocessed.
+    fn reuse_unchanged(
+        &mut self,
+        source: &Path,
+        changed: &HashSet<PathBuf>,
+        stats: &mut BuildStats,
+        dependency_graph: &mut DependencyGraph,
+    ) -> Result<Option<BuildArtifact>, BuildError> {
+        let Some(fingerprint) = self.fingerprints.get(source).cloned() else {
+            return Ok(None);
+        };
+        if changed.contains(source) {
+            // Unreadable metadata can't prove the file unchanged, so it
+            // falls through to the byte comparison.
+            let modified_at = fs::metadata(source)
+                .and_then(|metadata| metadata.modified())
+                .ok();
+            if modified_at.is_none() || modified_at != fingerprint.modified_at {
+                let bytes = fs::read(source).map_err(|io_error| BuildError::Io {
+                    path: source.to_path_buf(),
+                    source: io_error,
+                })?;
+                if content_hash(&bytes) != fingerprint.content_hash {
+                    return Ok(None);
+                }
+                // Touched but byte-identical: adopt the new mtime so the
+                // next event for this file resolves on the cheap check.
+                self.record_fingerprint(
+                    source,
+                    &fingerprint.content_hash,
+                    &fingerprint.artifact,
+                    fingerprint.inputs.clone(),
+                );
+            }
+        }
+        stats.artifacts_cached += 1;
+        dependency_graph.record(&fingerprint.artifact.path, fingerprint.inputs);
+        Ok(Some(fingerprint.artifact))
+    }
+
+    fn prepare_sources(&self, out_dir: &Path) -> Result<Vec<PathBuf>, BuildError> {
+        fs::create_dir_all(out_dir).map_err(|source| BuildError::Io {
+            path: out_dir.to_path_buf(),
+            source,
+        })?;
+        let mut sources = Vec::new();
+        collect_sources(&self.root, out_dir, &mut sources)?;
+        sources.sort();
+        detect_case_collisions(&self.root, &sources)?;
+        Ok(sources)
+    }
+
+    /// Remembers how `source` looked when its artifact was produced, so
+    /// [`build_incremental`](Self::build_incremental) can skip it later.
+    fn record_fingerprint(
+        &mut self,
+        source: &Path,
+        source_hash: &str,
+        artifact: &BuildArtifact,
+        inputs: Vec<ArtifactInput>,
+    ) {
+        self.fingerprints.insert(
+            source.to_path_buf(),
+            SourceFingerprint {
+                modified_at: fs::metadata(source)
+                    .and_then(|metadata| metadata.modified())
+                    .ok(),
+                content_hash: source_hash.to_string(),
+                artifact: artifact.clone(),
+                inputs,
+            },
+        );
+    }
+
+    /// Budget checks, manifest and inputs-lock writing, and result assembly
+    /// shared by [`build`](Self::build) and
+    /// [`build_incremental`](Self::build_incremental).
+    fn finish(
+        &mut self,
+        artifacts: Vec<BuildArtifact>,
+        mut stats: BuildStats,
+        dependency_graph: DependencyGraph,
+        out_dir: &Path,
+        started_at: Instant,
+    ) -> Result<BuildResult, BuildError> {
+        self.check_budgets(&artifacts)?;
+
+        let manifest = self.render_manifest(&artifacts);
+        let manifest_path = out_dir.join(MANIFEST_FILE_NAME);
+        fs::write(&manifest_path, &manifest).map_err(|source| BuildError::Io {
+            path: manifest_path,
+            source,
+        })?;
+
+        let inputs_lock = render_inputs_lock(&dependency_graph);
+        let inputs_lock_path = out_dir.join(INPUTS_LOCK_FILE_NAME);
+        fs::write(&inputs_lock_path, &inputs_lock).map_err(|source| BuildError::Io {
+            path: inputs_lock_path,
+            source,
+        })?;
+
+        // Eviction only runs here, once the build's full artifact set is
+        // known; mid-build, an entry that looks stale could still be about to
+        // serve a later stage.
+        if let Some(max_bytes) = self.config.cache_max_bytes {
+            let in_use: HashSet<PathBuf> = artifacts
+                .iter()
+                .map(|artifact| artifact.path.clone())
+                .collect();
+            self.cache.evict_to_budget(max_bytes, &in_use)?;
+        }
+
+        stats.build_time_ms = started_at.elapsed().as_millis() as u64;
+        Ok(BuildResult {
+            artifacts,
+            stats,
+            build_hash: content_hash(manifest.as_bytes()),
+            tamper_warnings: self.cache.take_tamper_warnings(),
+            dependency_graph,
+            root: self.root.clone(),
+        })
+    }
+
+    fn check_budgets(&self, artifacts: &[BuildArtifact]) -> Result<(), BuildError> {
+        let violations: Vec<BudgetViolation> = artifacts
+            .iter()
+            .filter_map(|artifact| {
+                let budget = *self.config.size_budgets.get(&artifact.artifact_type)?;
+                (artifact.size > budget).then(|| BudgetViolation {
+                    artifact_type: artifact.artifact_type,
+                    path: artifact.path.clone(),
+                    size: artifact.size,
+                    budget,
+                })
+            })
+            .collect();
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(BuildError::BudgetExceeded { violations })
+        }
+    }
+
+    /// Seconds since the Unix epoch to embed in generated output, honoring
+    /// the configured `source_date_epoch` when present.
+    fn build_timestamp(&self) -> i64 {
+        self.config.source_date_epoch.unwrap_or_else(|| {
+            std::time::UNIX_EPOCH
+                .elapsed()
+                // A clock before 1970 has no sensible timestamp; zero at
+                // least keeps the output well-formed.
+                .map_or(0, |elapsed| elapsed.as_secs() as i64)
+        })
+    }
+
+    fn render_manifest(&self, artifacts: &[BuildArtifact]) -> String {
+        let mut manifest = format!("built_at_epoch {}\n", self.build_timestamp());
+        for artifact in artifacts {
+            // Paths are recorded relative to the project root with forward
+            // slashes so two checkouts of the same source hash identically,
+            // whatever the platform.
+            manifest.push_str(&format!(
+                "{} {} {}\n",
+                artifact.hash,
+                artifact.size,
+                artifact.normalized_path(&self.root)
+            ));
+        }
+        manifest
+    }
+
+    fn artifact_type_for(&self, path: &Path) -> Option<ArtifactType> {
+        let extension = path.extension()?.to_str()?;
+        let artifact_type = match extension {
+            "png" | "jpg" | "jpeg" | "gif" | "webp" | "avif" | "mp4" | "webm" => {
+                ArtifactType::Media
+            }
+            "css" => ArtifactType::Style,
+            "svg" => ArtifactType::Icon,
+            "woff" | "woff2" | "ttf" | "otf" => ArtifactType::Font,
+            _ => return None,
+        };
+        let enabled = match &self.type_filter {
+            // An explicit type selection overrides the enable flags without
+            // mutating them.
+            Some(filter) => filter.contains(&artifact_type),
+            None => match artifact_type {
+                ArtifactType::Media => self.config.enable_media,
+                ArtifactType::Style => self.config.enable_styles,
+                ArtifactType::Icon => self.config.enable_icons,
+                _ => true,
+            },
+        };
+        (enabled && self.stage_applies(artifact_type)).then_some(artifact_type)
+    }
+
+    fn type_selected(&self, artifact_type: ArtifactType) -> bool {
+        self.type_filter
+            .as_ref()
+            .is_none_or(|filter| filter.contains(&artifact_type))
+    }
+
+    fn stage_applies(&self, artifact_type: ArtifactType) -> bool {
+        self.config
+            .stage_targets
+            .get(&artifact_type)
+            .is_none_or(|targets| targets.contains(&self.config.target))
+    }
+
+    fn command_stage_for(&self, source: &Path) -> Option<CommandStage> {
+        let file_name = source.file_name()?.to_str()?;
+        self.config
+            .command_stages
+            .iter()
+            .filter(|stage| stage.targets.is_empty() || stage.targets.contains(&self.config.target))
+            .find(|stage| glob_matches(&stage.input_glob, file_name))
+            .cloned()
+    }
+
+    fn worker_count(&self, job_count: usize) -> usize {
+        let configured = if self.config.parallelism == 0 {
+            std::thread::available_parallelism().map_or(1, |count| count.get())
+        } else {
+            self.config.parallelism
+        };
+        configured.min(job_count).max(1)
+    }
+
+    /// Processes `jobs` across worker threads and returns the produced
+    /// artifacts tagged with their source index, plus the merged stats and
+    /// dependency graph. Workers claim jobs through a shared counter; the
+    /// cache, fingerprints, and bookkeeping live behind one mutex held only
+    /// briefly, never across file IO or a stage command.
+    fn run_jobs(
+        &mut self,
+        jobs: Vec<(usize, PathBuf, SourceJob)>,
+        out_dir: &Path,
+        stats: BuildStats,
+        dependency_graph: DependencyGraph,
+    ) -> Result<(Vec<(usize, BuildArtifact)>, BuildStats, DependencyGraph), BuildError> {
+        let worker_count = self.worker_count(jobs.len());
+        let processor = SourceProcessor {
+            root: &self.root,
+            config: &self.config,
+            remote_cache: self.remote_cache.as_deref(),
+            shared: Mutex::new(StagedState {
+                cache: &mut self.cache,
+                fingerprints: &mut self.fingerprints,
+                dependency_graph,
+                stats,
+            }),
+        };
+
+        let fail_fast = self.config.fail_fast;
+        let mut indexed = Vec::with_capacity(jobs.len());
+        let mut errors: Vec<(usize, BuildError)> = Vec::new();
+        if worker_count <= 1 {
+            for (index, source, job) in &jobs {
+                match processor.process_job(source, job, out_dir) {
+                    Ok(artifact) => indexed.push((*index, artifact)),
+                    Err(error) => {
+                        errors.push((*index, error));
+                        if fail_fast {
+                            break;
+                        }
+                    }
+                }
+            }
+        } else {
+            let next_job = AtomicUsize::new(0);
+            let failed = AtomicBool::new(false);
+            let results = std::thread::scope(|scope| {
+                let mut handles = Vec::with_capacity(worker_count);
+                for _ in 0..worker_count {
+                    handles.push(scope.spawn(|| {
+                        let mut produced = Vec::new();
+                        let mut failures = Vec::new();
+                        loop {
+                            if failed.load(Ordering::Acquire) {
+                                break;
+                            }
+                            let claimed = next_job.fetch_add(1, Ordering::Relaxed);
+                            let Some((index, source, job)) = jobs.get(claimed) else {
+                                break;
+                            };
+                            match processor.process_job(source, job, out_dir) {
+                                Ok(artifact) => produced.push((*index, artifact)),
+                                Err(error) => {
+                                    failures.push((*index, error));
+                                    if fail_fast {
+                                        failed.store(true, Ordering::Release);
+                                        break;
+                                    }
+                                }
+                            }
+                        }
+                        (produced, failures)
+                    }));
+                }
+                let mut results = Vec::with_capacity(handles.len());
+                for handle in handles {
+                    match handle.join() {
+                        Ok(result) => results.push(result),
+                        Err(panic) => std::panic::resume_unwind(panic),
+                    }
+                }
+                results
+            });
+            for (produced, failures) in results {
+                indexed.extend(produced);
+                errors.extend(failures);
+            }
+        }
+        if !errors.is_empty() {
+            // Ordered by source index so both the fail-fast error and the
+            // aggregate report the same failures in the same order whatever
+            // the worker interleaving.
+            errors.sort_by_key(|(index, _)| *index);
+            let mut failures: Vec<BuildError> =
+                errors.into_iter().map(|(_, error)| error).collect();
+            if fail_fast {
+                return Err(failures.swap_remove(0));
+            }
+            return Err(BuildError::AssetsFailed { failures });
+        }
+
+        let state = processor
+            .shared
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        Ok((indexed, state.stats, state.dependency_graph))
+    }
+}
+
+enum SourceJob {
+    Builtin(ArtifactType),
+    Command(CommandStage),
+}
+
+/// The pipeline state workers share during [`BuildPipeline::run_jobs`],
+/// guarded by one mutex. Held only for cache lookups and bookkeeping — never
+/// across file IO or a stage command — so contention stays negligible next to
+/// the per-source work.
+struct StagedState<'a> {
+    cache: &'a mut BuildCache,
+    fingerprints: &'a mut HashMap<PathBuf, SourceFingerprint>,
+    dependency_graph: DependencyGraph,
+    stats: BuildStats,
+}
+
+impl StagedState<'_> {
+    fn record_fingerprint(
+        &mut self,
+        source: &Path,
+        source_hash: &str,
+        artifact: &BuildArtifact,
+        inputs: Vec<ArtifactInput>,
+    ) {
+        self.fingerprints.insert(
+            source.to_path_buf(),
+            SourceFingerprint {
+                modified_at: fs::metadata(source)
+                    .and_then(|metadata| metadata.modified())
+                    .ok(),
+                content_hash: source_hash.to_string(),
+                artifact: artifact.clone(),
+                inputs,
+            },
+        );
+    }
+}
+
+/// Per-source processing, split off [`BuildPipeline`] so workers can share it
+/// by reference. Two workers racing the same cache key both miss and both
+/// process; that is duplicated work, not corruption, because the key includes
+/// the content hash and the writes are idempotent.
+struct SourceProcessor<'a> {
+    root: &'a Path,
+    config: &'a BuildConfig,
+    remote_cache: Option<&'a dyn RemoteCache>,
+    shared: Mutex<StagedState<'a>>,
+}
+
+impl<'a> SourceProcessor<'a> {
+    fn lock_shared(&self) -> MutexGuard<'_, StagedState<'a>> {
+        // A worker that panicked mid-bookkeeping is re-raised at join; the
+        // state itself is never left half-written across a lock boundary.
+        self.shared.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn process_job(
+        &self,
+        source: &Path,
+        job: &SourceJob,
+        out_dir: &Path,
+    ) -> Result<BuildArtifact, BuildError> {
+        let artifact_type = match job {
+            SourceJob::Builtin(artifact_type) => *artifact_type,
+            SourceJob::Command(_) => ArtifactType::Transformed,
+        };
+        let result = match job {
+            SourceJob::Builtin(artifact_type) => self.process_file(source, out_dir, *artifact_type),
+            SourceJob::Command(stage) => self.run_command_stage(source, stage, out_dir),
+        };
+        // Wrapped here rather than per processor so every failure names the
+        // asset it broke on, however deep the underlying cause.
+        result.map_err(|error| BuildError::ProcessorFailed {
+            artifact_type,
+            input: source
+                .strip_prefix(self.root)
+                .unwrap_or(source)
+                .to_path_buf(),
+            source: Box::new(error),
+        })
+    }
+
+    fn process_file(
+        &self,
+        source: &Path,
+        out_dir: &Path,
+        artifact_type: ArtifactType,
+    ) -> Result<BuildArtifact, BuildError> {
+        let span = tracing::info_span!(
+            "process_artifact",
+            artifact_type = artifact_type.label(),
+            cache_outcome = tracing::field::Empty,
+        );
+        let _span_guard = span.enter();
+        // Memory-mapped above the large-source threshold, so hashing and
+        // copying a video asset never hold its bytes in RAM all at once.
+        let source_bytes = SourceBytes::open(source)?;
+        let bytes = source_bytes.as_bytes();
+        let input_hash = content_hash(bytes);
+        let cache_key = CacheKey {
+            artifact_type,
+            processor_version: processor_version(self.config, artifact_type),
+            target: self.config.target.clone(),
+            input_hash: input_hash.clone(),
+        };
+        let inputs = artifact_inputs(self.root, self.config, source, &input_hash, artifact_type);
+
+        if self.config.enable_cache {
+            let mut shared = self.lock_shared();
+            // `get_verified` re-hashes the on-disk artifact: a corrupted or
+            // tampered file becomes a miss and is rebuilt below.
+            if let Some(entry) = shared.cache.get_verified(&cache_key).cloned() {
+                span.record("cache_outcome", "local_hit");
+                shared.stats.local_cache_hits += 1;
+                shared
+                    .dependency_graph
+                    .record(&entry.output_path, inputs.clone());
+                let artifact = BuildArtifact {
+                    artifact_type,
+                    path: entry.output_path.clone(),
+                    hash: entry.output_hash.clone(),
+                    size: entry.output_size,
+                    chunks: chunk_manifest_for(self.config, bytes),
+                };
+                shared.record_fingerprint(source, &input_hash, &artifact, inputs);
+                return Ok(artifact);
+            }
+        }
+
+        let output_path = out_dir.join(hashed_file_name(source, &input_hash));
+
+        if self.config.enable_cache
+            && let Some(remote_cache) = self.remote_cache
+            && let Some(remote_bytes) = remote_cache
+                .fetch(&cache_key)
+                .map_err(BuildError::RemoteCache)?
+            // A remote entry whose content doesn't match its key is corrupt;
+            // fall through and rebuild rather than ship bad bytes.
+            && content_hash(&remote_bytes) == input_hash
+        {
+            fs::write(&output_path, &remote_bytes).map_err(|io_error| BuildError::Io {
+                path: output_path.clone(),
+                source: io_error,
+            })?;
+            span.record("cache_outcome", "remote_hit");
+            let artifact = BuildArtifact {
+                artifact_type,
+                path: output_path.clone(),
+                hash: input_hash.clone(),
+                size: remote_bytes.len() as u64,
+                chunks: chunk_manifest_for(self.config, bytes),
+            };
+            let mut shared = self.lock_shared();
+            shared.dependency_graph.record(&output_path, inputs.clone());
+            shared.cache.insert(CacheEntry {
+                key: cache_key,
+                output_path,
+                output_hash: input_hash.clone(),
+                output_size: remote_bytes.len() as u64,
+                last_accessed: 0,
+            });
+            shared.stats.remote_cache_hits += 1;
+            shared.record_fingerprint(source, &input_hash, &artifact, inputs);
+            return Ok(artifact);
+        }
+        fs::write(&output_path, bytes).map_err(|io_error| BuildError::Io {
+            path: output_path.clone(),
+            source: io_error,
+        })?;
+
+        let artifact = BuildArtifact {
+            artifact_type,
+            path: output_path.clone(),
+            hash: input_hash.clone(),
+            size: bytes.len() as u64,
+            chunks: chunk_manifest_for(self.config, bytes),
+        };
+        if self.config.enable_cache
+            && let Some(remote_cache) = self.remote_cache
+        {
+            remote_cache
+                .store(&cache_key, bytes)
+                .map_err(BuildError::RemoteCache)?;
+        }
+        let mut shared = self.lock_shared();
+        shared.dependency_graph.record(&output_path, inputs.clone());
+        if self.config.enable_cache {
+            shared.cache.insert(CacheEntry {
+                key: cache_key,
+                output_path,
+                output_hash: input_hash,
+                output_size: bytes.len() as u64,
+                last_accessed: 0,
+            });
+        }
+        shared.record_fingerprint(source, &artifact.hash, &artifact, inputs);
+        span.record("cache_outcome", "processed");
+        shared.stats.artifacts_processed += 1;
+        Ok(artifact)
+    }
+
+    fn run_command_stage(
+        &self,
+        source: &Path,
+        stage: &CommandStage,
+        out_dir: &Path,
+    ) -> Result<BuildArtifact, BuildError> {
+        let span = tracing::info_span!(
+            "process_artifact",
+            artifact_type = ArtifactType::Transformed.label(),
+            cache_outcome = tracing::field::Empty,
+        );
+        let _span_guard = span.enter();
+        let source_bytes = SourceBytes::open(source)?;
+        let source_hash = content_hash(source_bytes.as_bytes());
+        // The command shapes the output just as the input bytes do, so it is
+        // folded into the key: editing the command must miss the cache.
+        let input_hash = content_hash(
+            format!("{source_hash}\n{}\n{}", stage.command, stage.output_pattern).as_bytes(),
+        );
+        let cache_key = CacheKey {
+            artifact_type: ArtifactType::Transformed,
+            processor_version: processor_version(self.config, ArtifactType::Transformed),
+            target: self.config.target.clone(),
+            input_hash,
+        };
+        let mut inputs = artifact_inputs(
+            self.root,
+            self.config,
+            source,
+            &source_hash,
+            ArtifactType::Transformed,
+        );
+        inputs.push(ArtifactInput::ConfigValue {
+            name: "command".to_string(),
+            value: stage.command.clone(),
+        });
+
+        if self.config.enable_cache {
+            let cached_entry = self.lock_shared().cache.get_verified(&cache_key).cloned();
+            if let Some(entry) = cached_entry {
+                let produced = fs::read(&entry.output_path).map_err(|io_error| BuildError::Io {
+                    path: entry.output_path.clone(),
+                    source: io_error,
+                })?;
+                span.record("cache_outcome", "local_hit");
+                let artifact = BuildArtifact {
+                    artifact_type: ArtifactType::Transformed,
+                    path: entry.output_path.clone(),
+                    hash: entry.output_hash.clone(),
+                    size: entry.output_size,
+                    chunks: chunk_manifest_for(self.config, &produced),
+                };
+                let mut shared = self.lock_shared();
+                shared.stats.local_cache_hits += 1;
+                shared
+                    .dependency_graph
+                    .record(&entry.output_path, inputs.clone());
+                shared.record_fingerprint(source, &source_hash, &artifact, inputs);
+                return Ok(artifact);
+            }
+        }
+
+        let stem = source
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("asset");
+        let output_path = out_dir.join(stage.output_pattern.replace("{stem}", stem));
+
+        let rendered = stage
+            .command
+            .replace("{in}", &quoted_path(source))
+            .replace("{out}", &quoted_path(&output_path));
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(&rendered)
+            .output()
+            .map_err(|io_error| BuildError::Io {
+                path: source.to_path_buf(),
+                source: io_error,
+            })?;
+        if !output.status.success() {
+            return Err(BuildError::CommandFailed {
+                command: rendered,
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+        let produced = fs::read(&output_path).map_err(|io_error| BuildError::Io {
+            path: output_path.clone(),
+            source: io_error,
+        })?;
+
+        let artifact = BuildArtifact {
+            artifact_type: ArtifactType::Transformed,
+            path: output_path.clone(),
+            hash: content_hash(&produced),
+            size: produced.len() as u64,
+            chunks: chunk_manifest_for(self.config, &produced),
+        };
+        let mut shared = self.lock_shared();
+        shared.dependency_graph.record(&output_path, inputs.clone());
+        if self.config.enable_cache {
+            shared.cache.insert(CacheEntry {
+                key: cache_key,
+                output_path,
+                output_hash: artifact.hash.clone(),
+                output_size: artifact.size,
+                last_accessed: 0,
+            });
+        }
+        shared.record_fingerprint(source, &source_hash, &artifact, inputs);
+        span.record("cache_outcome", "processed");
+        shared.stats.artifacts_processed += 1;
+        Ok(artifact)
+    }
+}
+
+fn processor_version(config: &BuildConfig, artifact_type: ArtifactType) -> u32 {
+    config
+        .processor_versions
+        .get(&artifact_type)
+        .copied()
+        .unwrap_or(DEFAULT_PROCESSOR_VERSION)
+}
+
+/// Every input that feeds the output produced from `source`, for the
+/// dependency graph. Kept next to [`CacheKey`] construction deliberately:
+/// anything added here must be represented in the key as well.
+fn artifact_inputs(
+    root: &Path,
+    config: &BuildConfig,
+    source: &Path,
+    input_hash: &str,
+    artifact_type: ArtifactType,
+) -> Vec<ArtifactInput> {
+    let mut inputs = vec![
+        ArtifactInput::SourceFile {
+            path: source.strip_prefix(root).unwrap_or(source).to_path_buf(),
+            hash: input_hash.to_string(),
+        },
+        ArtifactInput::ConfigValue {
+            name: "artifact_type".to_string(),
+            value: format!("{artifact_type:?}"),
+        },
+        ArtifactInput::ConfigValue {
+            name: "processor_version".to_string(),
+            value: processor_version(config, artifact_type).to_string(),
+        },
+        ArtifactInput::ConfigValue {
+            name: "target".to_string(),
+            value: config.target.clone(),
+        },
+    ];
+    if let Some(chunking) = &config.chunking {
+        // Chunk manifests are recomputed from the bytes on every path, so
+        // the chunker config shapes the output without needing to be in
+        // the cache key.
+        inputs.push(ArtifactInput::ConfigValue {
+            name: "chunker".to_string(),
+            value: format!("{chunking:?}"),
+        });
+    }
+    inputs
+}
+
+fn chunk_manifest_for(config: &BuildConfig, bytes: &[u8]) -> Option<ChunkManifest> {
+    let chunking = config.chunking.as_ref()?;
+    (bytes.len() as u64 >= chunking.min_artifact_size)
+        .then(|| ChunkManifest::from_bytes(bytes, &chunking.chunker))
+}
+
+/// The type whose artifacts `artifact_type`'s output references, if any; a
+/// partial build selecting the former must build the latter too.
+fn required_dependency(artifact_type: ArtifactType) -> Option<ArtifactType> {
+    match artifact_type {
+        // Stylesheets reference fonts by URL (`@font-face`).
+        ArtifactType::Style => Some(ArtifactType::Font),
+        _ => None,
+    }
+}
+
+/// Renders the inputs lock: one `hash path` line per source file the build
+/// read, sorted by path and deduplicated. Derived from the dependency graph
+/// rather than collected separately, so it reflects exactly the stages that
+/// ran — a source skipped because its stage was disabled never appears, and
+/// a changed input changes its line.
+fn render_inputs_lock(dependency_graph: &DependencyGraph) -> String {
+    let mut entries: Vec<(String, String)> = dependency_graph
+        .inputs_by_artifact
+        .values()
+        .flatten()
+        .filter_map(|input| match input {
+            ArtifactInput::SourceFile { path, hash } => {
+                Some((crate::normalized_path(path, Path::new("")), hash.clone()))
+            }
+            ArtifactInput::ConfigValue { .. } => None,
+        })
+        .collect();
+    entries.sort();
+    entries.dedup();
+    let mut lock = String::from("# dx build inputs lock v1\n");
+    for (path, hash) in entries {
+        lock.push_str(&format!("{hash} {path}\n"));
+    }
+    lock
+}
+
+/// Single-quotes a path for interpolation into an `sh -c` template. Build
+/// paths come from the project tree, so embedded single quotes are not
+/// handled beyond being escaped out of the quoting.
+fn quoted_path(path: &Path) -> String {
+    format!("'{}'", path.display().to_string().replace('\'', r"'\''"))
+}
+
+/// Minimal glob matching for [`CommandStage::input_glob`]: `*` matches any
+/// (possibly empty) run of characters, everything else is literal.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let mut pattern_index = 0;
+    let mut name_index = 0;
+    let mut star: Option<(usize, usize)> = None;
+    while name_index < name.len() {
+        if pattern.get(pattern_index) == Some(&'*') {
+            star = Some((pattern_index, name_index));
+            pattern_index += 1;
+        } else if pattern.get(pattern_index) == name.get(name_index) {
+            pattern_index += 1;
+            name_index += 1;
+        } else if let Some((star_pattern, star_name)) = star {
+            // Backtrack: let the last `*` absorb one more character.
+            pattern_index = star_pattern + 1;
+            name_index = star_name + 1;
+            star = Some((star_pattern, star_name + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[pattern_index..]
+        .iter()
+        .all(|&character| character == '*')
+}
+
+fn hashed_file_name(source: &Path, hash: &str) -> String {
+    let stem = source
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("asset");
+    let short_hash = hash.get(..8).unwrap_or(hash);
+    match source.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => format!("{stem}.{short_hash}.{extension}"),
+        None => format!("{stem}.{short_hash}"),
+    }
+}
+
+fn collect_sources(
+    dir: &Path,
+    out_dir: &Path,
+    sources: &mut Vec<PathBuf>,
+) -> Result<(), BuildError> {
+    let entries = fs::read_dir(dir).map_err(|source| BuildError::Io {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    for entry in entries {
+        let entry = entry.map_err(|source| BuildError::Io {
+            path: dir.to_path_buf(),
+            source,
+        })?;
+        let path = entry.path();
+        if path == out_dir {
+            continue;
+        }
+        if path.is_dir() {
+            collect_sources(&path, out_dir, sources)?;
+        } else if path.is_symlink() {
+            // Resolve the link so the bytes hashed — and so the cache key —
+            // come from the actual target; a broken link is an IO error
+            // reported against the link, not silently skipped.
+            let target = fs::canonicalize(&path).map_err(|source| BuildError::Io {
+                path: path.clone(),
+                source,
+            })?;
+            sources.push(target);
+        } else {
+            sources.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Rejects source pairs that differ only by letter case. The tree builds
+/// fine on a case-sensitive filesystem, but checking out on macOS or Windows
+/// leaves only one of the pair on disk — so the collision is reported on
+/// every platform, before it ships.
+fn detect_case_collisions(root: &Path, sources: &[PathBuf]) -> Result<(), BuildError> {
+    let mut first_by_folded: HashMap<String, String> = HashMap::default();
+    for source in sources {
+        let normalized = crate::normalized_path(source, root);
+        let folded = normalized.to_lowercase();
+        if let Some(first) = first_by_folded.get(&folded) {
+            return Err(BuildError::CaseCollision {
+                first: first.clone(),
+                second: normalized,
+            });
+        }
+        first_by_folded.insert(folded, normalized);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct InMemoryRemoteCache {
+        entries: Mutex<HashMap<CacheKey, Vec<u8>>>,
+        uploads: Mutex<usize>,
+    }
+
+    impl RemoteCache for Arc<InMemoryRemoteCache> {
+        fn fetch(&self, key: &CacheKey) -> anyhow::Result<Option<Vec<u8>>> {
+            Ok(self.entries.lock().unwrap().get(key).cloned())
+        }
+
+        fn store(&self, key: &CacheKey, bytes: &[u8]) -> anyhow::Result<()> {
+            *self.uploads.lock().unwrap() += 1;
+            self.entries
+                .lock()
+                .unwrap()
+                .insert(key.clone(), bytes.to_vec());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_manifest_is_stable_and_machine_readable() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("style.css"), "body { margin: 0; }").unwrap();
+        fs::write(dir.path().join("icon.svg"), "<svg></svg>").unwrap();
+        let config = BuildConfig {
+            source_date_epoch: Some(1_700_000_000),
+            ..BuildConfig::default()
+        };
+        let mut pipeline = BuildPipeline::new(dir.path(), config);
+        let result = pipeline.build().unwrap();
+
+        let manifest_path = dir.path().join("manifest.json");
+        result.write_manifest(&manifest_path).unwrap();
+        let manifest = fs::read_to_string(&manifest_path).unwrap();
+
+        assert!(manifest.contains(&format!(
+            "\"schema_version\": {JSON_MANIFEST_SCHEMA_VERSION}"
+        )));
+        assert!(manifest.contains(&format!("\"producer_version\": \"{}\"", crate::VERSION)));
+        assert!(manifest.contains("\"type\": \"style\""));
+        assert!(manifest.contains("\"type\": \"icon\""));
+        for artifact in &result.artifacts {
+            assert!(
+                manifest.contains(&format!(
+                    "\"path\": \"{}\", \"hash\": \"{}\", \"size\": {}",
+                    artifact.normalized_path(dir.path()),
+                    artifact.hash,
+                    artifact.size
+                )),
+                "every artifact appears with a root-relative path: {manifest}"
+            );
+        }
+        // Artifacts are ordered by path, so reruns of the same tree render
+        // byte-identically apart from timing stats.
+        let icon_position = manifest.find("\"type\": \"icon\"").unwrap();
+        let style_position = manifest.find("\"type\": \"style\"").unwrap();
+        assert!(icon_position < style_position);
+        assert_eq!(result.json_manifest(), manifest);
+    }
+
+    #[test]
+    fn test_build_types_runs_only_the_requested_stages() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("style.css"), "body { margin: 0; }").unwrap();
+        fs::write(dir.path().join("icon.svg"), "<svg></svg>").unwrap();
+        fs::write(dir.path().join("photo.png"), b"png bytes").unwrap();
+        fs::write(dir.path().join("body.woff2"), b"font bytes").unwrap();
+        let mut pipeline = BuildPipeline::new(dir.path(), BuildConfig::default());
+
+        let partial = pipeline.build_types(&[ArtifactType::Style]).unwrap();
+        let mut types: Vec<ArtifactType> = partial
+            .artifacts
+            .iter()
+            .map(|artifact| artifact.artifact_type)
+            .collect();
+        types.sort_by_key(|artifact_type| artifact_type.label());
+        assert_eq!(
+            types,
+            vec![ArtifactType::Font, ArtifactType::Style],
+            "styles pull fonts in; icons and media stay untouched"
+        );
+        assert_eq!(partial.stats.artifacts_processed, 2);
+        assert!(
+            pipeline.config().enable_icons,
+            "the flags are overridden, not mutated"
+        );
+
+        // The skipped stages run normally in the next full build.
+        let full = pipeline.build().unwrap();
+        assert_eq!(full.artifacts.len(), 4);
+    }
+
+    #[test]
+    fn test_stale_cache_entries_are_evicted_once_the_build_finishes() {
+        let dir = tempfile::tempdir().unwrap();
+        let style_path = dir.path().join("style.css");
+        fs::write(&style_path, "body { margin: 0; }").unwrap();
+        let config = BuildConfig {
+            cache_max_bytes: Some(25),
+            ..BuildConfig::default()
+        };
+        let mut pipeline = BuildPipeline::new(dir.path(), config);
+
+        let first = pipeline.build().unwrap();
+        let old_artifact = first.artifacts[0].path.clone();
+        assert!(old_artifact.exists(), "within budget, nothing is evicted");
+
+        // The edit gives the source a new cache key; the old entry goes
+        // stale and pushes the cache over budget.
+        fs::write(&style_path, "body { margin: 1px; }").unwrap();
+        let second = pipeline.build().unwrap();
+        let new_artifact = second.artifacts[0].path.clone();
+        assert!(
+            new_artifact.exists(),
+            "the finishing build is never evicted"
+        );
+        assert!(!old_artifact.exists(), "the stale artifact is deleted");
+
+        // The surviving entry still serves the next build.
+        let third = pipeline.build().unwrap();
+        assert_eq!(third.stats.local_cache_hits, 1);
+    }
+
+    #[test]
+    fn test_remote_cache_is_populated_and_read_through() {
+        let remote = Arc::new(InMemoryRemoteCache::default());
+
+        let first_checkout = tempfile::tempdir().unwrap();
+        fs::write(
+            first_checkout.path().join("style.css"),
+            "body { margin: 0; }",
+        )
+        .unwrap();
+        fs::write(first_checkout.path().join("icon.svg"), "<svg></svg>").unwrap();
+        let mut pipeline = BuildPipeline::new(first_checkout.path(), BuildConfig::default())
+            .with_remote_cache(Box::new(remote.clone()));
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.artifacts_processed, 2);
+        assert_eq!(*remote.uploads.lock().unwrap(), 2);
+
+        // A fresh pipeline (another developer's checkout) has no local cache,
+        // so everything should come from the remote without re-uploading.
+        let second_checkout = tempfile::tempdir().unwrap();
+        fs::write(
+            second_checkout.path().join("style.css"),
+            "body { margin: 0; }",
+        )
+        .unwrap();
+        fs::write(second_checkout.path().join("icon.svg"), "<svg></svg>").unwrap();
+        let mut pipeline = BuildPipeline::new(second_checkout.path(), BuildConfig::default())
+            .with_remote_cache(Box::new(remote.clone()));
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.artifacts_processed, 0);
+        assert_eq!(result.stats.remote_cache_hits, 2);
+        assert_eq!(*remote.uploads.lock().unwrap(), 2);
+        for artifact in &result.artifacts {
+            assert!(artifact.path.exists());
+        }
+
+        // Rebuilding in the same pipeline hits the local cache, not the
+        // remote.
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.local_cache_hits, 2);
+        assert_eq!(result.stats.remote_cache_hits, 0);
+    }
+
+    #[test]
+    fn test_processor_version_bump_invalidates_only_that_stage() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("style.css"), "body { margin: 0; }").unwrap();
+        fs::write(root.path().join("icon.svg"), "<svg></svg>").unwrap();
+
+        let mut pipeline = BuildPipeline::new(root.path(), BuildConfig::default());
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.artifacts_processed, 2);
+
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.local_cache_hits, 2);
+
+        pipeline
+            .config_mut()
+            .processor_versions
+            .insert(ArtifactType::Style, 2);
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.artifacts_processed, 1, "style rebuilt");
+        assert_eq!(result.stats.local_cache_hits, 1, "icon stayed cached");
+
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.local_cache_hits, 2, "new version now cached");
+    }
+
+    #[test]
+    fn test_budget_violations_are_reported_together() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("big.css"), vec![b' '; 2048]).unwrap();
+        fs::write(root.path().join("bigger.css"), vec![b' '; 4096]).unwrap();
+        fs::write(root.path().join("icon.svg"), "<svg></svg>").unwrap();
+
+        let mut config = BuildConfig::default();
+        config.size_budgets.insert(ArtifactType::Style, 1024);
+        let mut pipeline = BuildPipeline::new(root.path(), config);
+
+        match pipeline.build() {
+            Err(BuildError::BudgetExceeded { violations }) => {
+                assert_eq!(violations.len(), 2, "both oversized styles reported");
+                for violation in &violations {
+                    assert_eq!(violation.artifact_type, ArtifactType::Style);
+                    assert_eq!(violation.budget, 1024);
+                    assert!(violation.size > violation.budget);
+                }
+            }
+            other => panic!("expected budget failure, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_fixed_epoch_makes_builds_reproducible() {
+        let build_once = |epoch: Option<i64>| {
+            let root = tempfile::tempdir().unwrap();
+            fs::write(root.path().join("style.css"), "body { margin: 0; }").unwrap();
+            fs::write(root.path().join("icon.svg"), "<svg></svg>").unwrap();
+            let config = BuildConfig {
+                source_date_epoch: epoch,
+                ..BuildConfig::default()
+            };
+            let mut pipeline = BuildPipeline::new(root.path(), config);
+            let result = pipeline.build().unwrap();
+            let manifest =
+                fs::read_to_string(root.path().join("dist").join(MANIFEST_FILE_NAME)).unwrap();
+            (result, manifest)
+        };
+
+        let (first, first_manifest) = build_once(Some(1_700_000_000));
+        let (second, second_manifest) = build_once(Some(1_700_000_000));
+        assert_eq!(first_manifest, second_manifest, "byte-identical manifests");
+        assert_eq!(first.build_hash, second.build_hash);
+        assert!(first_manifest.starts_with("built_at_epoch 1700000000\n"));
+        let first_hashes: Vec<&String> = first
+            .artifacts
+            .iter()
+            .map(|artifact| &artifact.hash)
+            .collect();
+        let second_hashes: Vec<&String> = second
+            .artifacts
+            .iter()
+            .map(|artifact| &artifact.hash)
+            .collect();
+        assert_eq!(first_hashes, second_hashes);
+
+        let (_, other_epoch_manifest) = build_once(Some(1_700_000_001));
+        assert_ne!(first_manifest, other_epoch_manifest);
+    }
+
+    #[test]
+    fn test_parallel_build_matches_sequential_output() {
+        let build_once = |parallelism: usize| {
+            let root = tempfile::tempdir().unwrap();
+            for index in 0..6 {
+                fs::write(
+                    root.path().join(format!("style_{index}.css")),
+                    format!("body {{ margin: {index}px; }}"),
+                )
+                .unwrap();
+                fs::write(
+                    root.path().join(format!("icon_{index}.svg")),
+                    format!("<svg id=\"{index}\"></svg>"),
+                )
+                .unwrap();
+            }
+            let config = BuildConfig {
+                parallelism,
+                source_date_epoch: Some(1_700_000_000),
+                ..BuildConfig::default()
+            };
+            let mut pipeline = BuildPipeline::new(root.path(), config);
+            let result = pipeline.build().unwrap();
+            let manifest =
+                fs::read_to_string(root.path().join("dist").join(MANIFEST_FILE_NAME)).unwrap();
+            (result, manifest)
+        };
+
+        let (sequential, sequential_manifest) = build_once(1);
+        let (parallel, parallel_manifest) = build_once(4);
+        assert_eq!(
+            sequential_manifest, parallel_manifest,
+            "artifact order and bytes must not depend on the worker count"
+        );
+        assert_eq!(sequential.build_hash, parallel.build_hash);
+        let hashes = |result: &BuildResult| -> Vec<String> {
+            result
+                .artifacts
+                .iter()
+                .map(|artifact| artifact.hash.clone())
+                .collect()
+        };
+        assert_eq!(hashes(&sequential), hashes(&parallel));
+        assert_eq!(
+            sequential.stats.artifacts_processed,
+            parallel.stats.artifacts_processed
+        );
+        assert_eq!(parallel.stats.artifacts_processed, 12);
+    }
+
+    #[test]
+    fn test_dependency_graph_lists_every_input_per_artifact() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("style.css"), "body { margin: 0; }").unwrap();
+        fs::write(root.path().join("icon.svg"), "<svg></svg>").unwrap();
+
+        let mut pipeline = BuildPipeline::new(root.path(), BuildConfig::default());
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.dependency_graph().artifacts().count(), 2);
+
+        let style = result
+            .artifacts
+            .iter()
+            .find(|artifact| artifact.artifact_type == ArtifactType::Style)
+            .unwrap();
+        let inputs = result.dependency_graph().inputs_for(&style.path).unwrap();
+        assert!(inputs.contains(&ArtifactInput::SourceFile {
+            path: PathBuf::from("style.css"),
+            hash: style.hash.clone(),
+        }));
+        assert!(inputs.contains(&ArtifactInput::ConfigValue {
+            name: "processor_version".to_string(),
+            value: DEFAULT_PROCESSOR_VERSION.to_string(),
+        }));
+
+        // Cache hits are recorded too, so staleness can be debugged without
+        // forcing a rebuild.
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.local_cache_hits, 2);
+        assert!(result.dependency_graph().inputs_for(&style.path).is_some());
+    }
+
+    #[test]
+    fn test_command_stage_transforms_and_caches() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("shader.glsl"), "void main() {}").unwrap();
+        fs::write(root.path().join("style.css"), "body { margin: 0; }").unwrap();
+
+        let config = BuildConfig {
+            command_stages: vec![CommandStage {
+                input_glob: "*.glsl".to_string(),
+                output_pattern: "{stem}.spv".to_string(),
+                command: "tr 'a-z' 'A-Z' < {in} > {out}".to_string(),
+                targets: Vec::new(),
+            }],
+            ..BuildConfig::default()
+        };
+        let mut pipeline = BuildPipeline::new(root.path(), config);
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.artifacts_processed, 2);
+
+        let transformed = result
+            .artifacts
+            .iter()
+            .find(|artifact| artifact.artifact_type == ArtifactType::Transformed)
+            .unwrap();
+        assert_eq!(
+            transformed.path,
+            root.path().join("dist").join("shader.spv")
+        );
+        assert_eq!(
+            fs::read_to_string(&transformed.path).unwrap(),
+            "VOID MAIN() {}"
+        );
+        let inputs = result
+            .dependency_graph()
+            .inputs_for(&transformed.path)
+            .unwrap();
+        assert!(inputs.contains(&ArtifactInput::ConfigValue {
+            name: "command".to_string(),
+            value: "tr 'a-z' 'A-Z' < {in} > {out}".to_string(),
+        }));
+
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.local_cache_hits, 2);
+        assert_eq!(result.stats.artifacts_processed, 0);
+
+        // Editing the command invalidates the stage's cache entry even though
+        // the input bytes are unchanged.
+        pipeline.config_mut().command_stages[0].command =
+            "tr 'a-z' 'n-za-m' < {in} > {out}".to_string();
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.artifacts_processed, 1, "shader rebuilt");
+        assert_eq!(result.stats.local_cache_hits, 1, "style stayed cached");
+    }
+
+    #[test]
+    fn test_failed_command_stage_reports_stderr() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("shader.glsl"), "void main() {}").unwrap();
+
+        let config = BuildConfig {
+            command_stages: vec![CommandStage {
+                input_glob: "*.glsl".to_string(),
+                output_pattern: "{stem}.spv".to_string(),
+                command: "echo 'no such target' >&2; exit 1".to_string(),
+                targets: Vec::new(),
+            }],
+            ..BuildConfig::default()
+        };
+        let mut pipeline = BuildPipeline::new(root.path(), config);
+        match pipeline.build() {
+            Err(BuildError::ProcessorFailed {
+                artifact_type,
+                input,
+                source,
+            }) => {
+                assert_eq!(artifact_type, ArtifactType::Transformed);
+                assert_eq!(input, Path::new("shader.glsl"));
+                let message = source.to_string();
+                assert!(message.contains("no such target"), "source: {message:?}");
+            }
+            other => panic!("expected command failure, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_continuing_past_failures_reports_every_broken_asset() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("a.glsl"), "void main() {}").unwrap();
+        fs::write(root.path().join("b.glsl"), "void main() {}").unwrap();
+        fs::write(root.path().join("style.css"), "body { margin: 0; }").unwrap();
+
+        let config = BuildConfig {
+            fail_fast: false,
+            command_stages: vec![CommandStage {
+                input_glob: "*.glsl".to_string(),
+                output_pattern: "{stem}.spv".to_string(),
+                command: "echo 'compile error' >&2; exit 1".to_string(),
+                targets: Vec::new(),
+            }],
+            ..BuildConfig::default()
+        };
+        let mut pipeline = BuildPipeline::new(root.path(), config);
+        match pipeline.build() {
+            Err(BuildError::AssetsFailed { failures }) => {
+                let inputs: Vec<&Path> = failures
+                    .iter()
+                    .map(|failure| match failure {
+                        BuildError::ProcessorFailed { input, .. } => input.as_path(),
+                        other => panic!("expected a processor failure, got {other:?}"),
+                    })
+                    .collect();
+                assert_eq!(inputs, vec![Path::new("a.glsl"), Path::new("b.glsl")]);
+            }
+            other => panic!("expected aggregated failures, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_glob_matches_star_and_literals() {
+        assert!(glob_matches("*.glsl", "shader.glsl"));
+        assert!(glob_matches("shader.*", "shader.glsl"));
+        assert!(glob_matches("*", "anything"));
+        assert!(glob_matches("a*b*c", "a-long-b-path-c"));
+        assert!(!glob_matches("*.glsl", "shader.css"));
+        assert!(!glob_matches("shader.glsl", "other.glsl"));
+    }
+
+    #[test]
+    fn test_build_produces_hashed_artifacts() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("style.css"), "body { margin: 0; }").unwrap();
+        fs::write(root.path().join("icon.svg"), "<svg></svg>").unwrap();
+
+        let mut pipeline = BuildPipeline::new(root.path(), BuildConfig::default());
+        let result = pipeline.build().unwrap();
+
+        assert_eq!(result.artifacts.len(), 2);
+        assert_eq!(result.stats.artifacts_processed, 2);
+        for artifact in &result.artifacts {
+            assert!(artifact.path.exists());
+            assert!(artifact.chunks.is_none());
+        }
+    }
+
+    #[test]
+    fn test_plan_reports_artifacts_without_writing_anything() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("style.css"), "body { margin: 0; }").unwrap();
+        fs::write(root.path().join("icon.svg"), "<svg></svg>").unwrap();
+        let config = BuildConfig {
+            enable_cache: false,
+            // Pin the manifest timestamp so the planned and built hashes are
+            // comparable.
+            source_date_epoch: Some(1_700_000_000),
+            ..BuildConfig::default()
+        };
+        let out_dir = root.path().join(&config.out_dir);
+
+        let mut pipeline = BuildPipeline::new(root.path(), config);
+        let plan = pipeline.plan().unwrap();
+        assert_eq!(plan.artifacts.len(), 2);
+        assert_eq!(plan.stats.artifacts_processed, 0);
+        assert!(
+            !out_dir.exists(),
+            "planning must not create the output directory"
+        );
+
+        // The real build produces exactly what was planned.
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.artifacts, plan.artifacts);
+        assert_eq!(result.build_hash, plan.build_hash);
+        for artifact in &plan.artifacts {
+            assert!(artifact.path.starts_with(&out_dir));
+            assert!(artifact.path.exists());
+        }
+    }
+
+    #[test]
+    fn test_incremental_build_reprocesses_only_the_changed_set() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("style.css"), "body { margin: 0; }").unwrap();
+        fs::write(root.path().join("icon.svg"), "<svg></svg>").unwrap();
+        let mut pipeline = BuildPipeline::new(root.path(), BuildConfig::default());
+        assert_eq!(pipeline.build().unwrap().stats.artifacts_processed, 2);
+
+        fs::write(root.path().join("style.css"), "body { margin: 8px; }").unwrap();
+        let result = pipeline
+            .build_incremental(&[PathBuf::from("style.css")])
+            .unwrap();
+        assert_eq!(result.stats.artifacts_processed, 1, "only the style reran");
+        assert_eq!(
+            result.stats.artifacts_cached, 1,
+            "the icon was served from the previous build"
+        );
+        assert_eq!(result.artifacts.len(), 2);
+        let style = result
+            .artifacts
+            .iter()
+            .find(|artifact| artifact.artifact_type == ArtifactType::Style)
+            .unwrap();
+        assert_eq!(style.hash, content_hash(b"body { margin: 8px; }"));
+
+        // A brand-new source has no fingerprint, so it is processed even
+        // though it is outside the changed set.
+        fs::write(root.path().join("logo.svg"), "<svg>logo</svg>").unwrap();
+        let result = pipeline.build_incremental(&[]).unwrap();
+        assert_eq!(result.stats.artifacts_processed, 1);
+        assert_eq!(result.stats.artifacts_cached, 2);
+    }
+
+    #[test]
+    fn test_rebuild_artifact_reprocesses_only_the_requested_output() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("style.css"), "body { margin: 0; }").unwrap();
+        fs::write(root.path().join("icon.svg"), "<svg></svg>").unwrap();
+        let mut pipeline = BuildPipeline::new(root.path(), BuildConfig::default());
+        let result = pipeline.build().unwrap();
+        let style_output = result
+            .artifacts
+            .iter()
+            .find(|artifact| artifact.artifact_type == ArtifactType::Style)
+            .unwrap()
+            .path
+            .clone();
+
+        fs::write(root.path().join("style.css"), "body { margin: 8px; }").unwrap();
+        let rebuilt = pipeline.rebuild_artifact(&style_output).unwrap();
+        assert_eq!(rebuilt.hash, content_hash(b"body { margin: 8px; }"));
+        assert!(
+            rebuilt.path.exists(),
+            "the changed source landed at its new hashed path"
+        );
+
+        // The refreshed fingerprint serves the rebuilt artifact on the next
+        // incremental build without reprocessing it again.
+        let result = pipeline.build_incremental(&[]).unwrap();
+        assert_eq!(result.stats.artifacts_processed, 0);
+        assert_eq!(result.stats.artifacts_cached, 2);
+        assert!(result.artifacts.contains(&rebuilt));
+
+        assert!(matches!(
+            pipeline.rebuild_artifact(Path::new("no-such-output.css")),
+            Err(BuildError::UnknownArtifact { path }) if path.ends_with("no-such-output.css")
+        ));
+    }
+
+    #[test]
+    fn test_touched_but_unchanged_source_is_reported_cached() {
+        let root = tempfile::tempdir().unwrap();
+        let style_path = root.path().join("style.css");
+        fs::write(&style_path, "body { margin: 0; }").unwrap();
+        let mut pipeline = BuildPipeline::new(root.path(), BuildConfig::default());
+        pipeline.build().unwrap();
+
+        // A `touch`: the mtime moves but the bytes do not.
+        fs::File::options()
+            .write(true)
+            .open(&style_path)
+            .unwrap()
+            .set_modified(SystemTime::now() + std::time::Duration::from_secs(5))
+            .unwrap();
+        let result = pipeline
+            .build_incremental(std::slice::from_ref(&style_path))
+            .unwrap();
+        assert_eq!(
+            result.stats.artifacts_processed, 0,
+            "identical bytes must not reprocess"
+        );
+        assert_eq!(result.stats.artifacts_cached, 1);
+
+        // The new mtime was adopted, so the next event for this file
+        // resolves on the cheap check and still reports cached.
+        let result = pipeline.build_incremental(&[style_path]).unwrap();
+        assert_eq!(result.stats.artifacts_processed, 0);
+        assert_eq!(result.stats.artifacts_cached, 1);
+    }
+
+    #[test]
+    fn test_large_artifacts_get_chunk_manifests() {
+        let root = tempfile::tempdir().unwrap();
+        let large: Vec<u8> = (0..64 * 1024).map(|index| (index % 251) as u8).collect();
+        fs::write(root.path().join("video.mp4"), &large).unwrap();
+        fs::write(root.path().join("style.css"), "body { margin: 0; }").unwrap();
+
+        let config = BuildConfig {
+            chunking: Some(ChunkingOptions {
+                chunker: ChunkerConfig {
+                    min_size: 1024,
+                    avg_size: 4096,
+                    max_size: 16 * 1024,
+                },
+                min_artifact_size: 32 * 1024,
+            }),
+            ..BuildConfig::default()
+        };
+        let mut pipeline = BuildPipeline::new(root.path(), config);
+        let result = pipeline.build().unwrap();
+
+        let video = result
+            .artifacts
+            .iter()
+            .find(|artifact| artifact.artifact_type == ArtifactType::Media)
+            .unwrap();
+        let manifest = video.chunks.as_ref().unwrap();
+        assert_eq!(manifest.total_size, large.len() as u64);
+        assert!(manifest.chunks.len() > 1);
+        let summed: u64 = manifest.chunks.iter().map(|chunk| chunk.length).sum();
+        assert_eq!(summed, manifest.total_size);
+
+        let style = result
+            .artifacts
+            .iter()
+            .find(|artifact| artifact.artifact_type == ArtifactType::Style)
+            .unwrap();
+        assert!(style.chunks.is_none());
+    }
+
+    #[test]
+    fn test_target_scoped_stages_run_only_for_their_target() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("favicon.svg"), "<svg></svg>").unwrap();
+        fs::write(root.path().join("style.css"), "body { margin: 0; }").unwrap();
+
+        let mut config = BuildConfig::default();
+        config
+            .stage_targets
+            .insert(ArtifactType::Icon, vec!["web".to_string()]);
+        let mut pipeline = BuildPipeline::new(root.path(), config);
+
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.artifacts_processed, 2);
+        assert!(
+            result
+                .artifacts
+                .iter()
+                .any(|artifact| artifact.artifact_type == ArtifactType::Icon),
+            "favicon built for web"
+        );
+
+        pipeline.config_mut().target = "node".to_string();
+        let result = pipeline.build().unwrap();
+        assert!(
+            result
+                .artifacts
+                .iter()
+                .all(|artifact| artifact.artifact_type != ArtifactType::Icon),
+            "no favicon for node"
+        );
+        // The style cache is namespaced by target, so the node build
+        // reprocesses it rather than reusing the web entry.
+        assert_eq!(result.stats.artifacts_processed, 1);
+        assert_eq!(result.stats.local_cache_hits, 0);
+
+        pipeline.config_mut().target = "web".to_string();
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.local_cache_hits, 2, "web entries survived");
+    }
+
+    #[test]
+    fn test_command_stage_targets_gate_the_stage() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("shader.glsl"), "void main() {}").unwrap();
+
+        let config = BuildConfig {
+            command_stages: vec![CommandStage {
+                input_glob: "*.glsl".to_string(),
+                output_pattern: "{stem}.spv".to_string(),
+                command: "tr 'a-z' 'A-Z' < {in} > {out}".to_string(),
+                targets: vec!["web".to_string()],
+            }],
+            target: "node".to_string(),
+            ..BuildConfig::default()
+        };
+        let mut pipeline = BuildPipeline::new(root.path(), config);
+        let result = pipeline.build().unwrap();
+        assert!(result.artifacts.is_empty(), "stage skipped for node");
+
+        pipeline.config_mut().target = "web".to_string();
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.artifacts.len(), 1);
+        assert_eq!(result.artifacts[0].artifact_type, ArtifactType::Transformed);
+    }
+
+    #[derive(Clone, Debug)]
+    struct CapturedSpan {
+        name: String,
+        fields: Vec<(String, String)>,
+    }
+
+    impl CapturedSpan {
+        fn field(&self, name: &str) -> Option<&str> {
+            self.fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value.as_str())
+        }
+    }
+
+    struct FieldVisitor<'a>(&'a mut Vec<(String, String)>);
+
+    impl tracing::field::Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .push((field.name().to_string(), format!("{value:?}")));
+        }
+    }
+
+    struct SpanCapture {
+        spans: Arc<Mutex<Vec<CapturedSpan>>>,
+    }
+
+    impl tracing::Subscriber for SpanCapture {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attributes: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut fields = Vec::new();
+            attributes.record(&mut FieldVisitor(&mut fields));
+            let mut spans = self.spans.lock().unwrap();
+            spans.push(CapturedSpan {
+                name: attributes.metadata().name().to_string(),
+                fields,
+            });
+            tracing::span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &tracing::span::Id, values: &tracing::span::Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            let index = span.into_u64() as usize - 1;
+            if let Some(captured) = spans.get_mut(index) {
+                values.record(&mut FieldVisitor(&mut captured.fields));
+            }
+        }
+
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+        fn event(&self, _event: &tracing::Event<'_>) {}
+
+        fn enter(&self, _span: &tracing::span::Id) {}
+
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+
+    #[test]
+    fn test_spans_cover_each_processed_artifact_with_cache_outcomes() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("style.css"), "body { margin: 0; }").unwrap();
+        fs::write(root.path().join("icon.svg"), "<svg></svg>").unwrap();
+        let mut pipeline = BuildPipeline::new(root.path(), BuildConfig::default());
+
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        tracing::subscriber::with_default(
+            SpanCapture {
+                spans: spans.clone(),
+            },
+            || pipeline.build().unwrap(),
+        );
+
+        let captured = spans.lock().unwrap().clone();
+        let build_spans: Vec<&CapturedSpan> = captured
+            .iter()
+            .filter(|span| span.name == "build")
+            .collect();
+        assert_eq!(build_spans.len(), 1);
+        assert_eq!(build_spans[0].field("target"), Some("\"web\""));
+        assert_eq!(build_spans[0].field("sources"), Some("2"));
+        assert_eq!(build_spans[0].field("artifacts"), Some("2"));
+
+        let artifact_spans: Vec<&CapturedSpan> = captured
+            .iter()
+            .filter(|span| span.name == "process_artifact")
+            .collect();
+        assert_eq!(artifact_spans.len(), 2, "one span per enabled stage");
+        let mut artifact_types: Vec<&str> = artifact_spans
+            .iter()
+            .filter_map(|span| span.field("artifact_type"))
+            .collect();
+        artifact_types.sort_unstable();
+        assert_eq!(artifact_types, vec!["\"icon\"", "\"style\""]);
+        for span in &artifact_spans {
+            assert_eq!(span.field("cache_outcome"), Some("\"processed\""));
+        }
+
+        // A second build of the same sources reports cache hits instead.
+        let spans = Arc::new(Mutex::new(Vec::new()));
+        tracing::subscriber::with_default(
+            SpanCapture {
+                spans: spans.clone(),
+            },
+            || pipeline.build().unwrap(),
+        );
+        let captured = spans.lock().unwrap().clone();
+        for span in captured
+            .iter()
+            .filter(|span| span.name == "process_artifact")
+        {
+            assert_eq!(span.field("cache_outcome"), Some("\"local_hit\""));
+        }
+    }
+
+    #[test]
+    fn test_windows_style_paths_normalize_to_forward_slashes() {
+        use crate::normalized_path;
+
+        let root = PathBuf::from(r"C:\projects\app");
+        let artifact = BuildArtifact {
+            artifact_type: ArtifactType::Style,
+            path: PathBuf::from(r"C:\projects\app\dist\style.abc123.css"),
+            hash: "abc123".to_string(),
+            size: 0,
+            chunks: None,
+        };
+        assert_eq!(artifact.normalized_path(&root), "dist/style.abc123.css");
+
+        assert_eq!(
+            normalized_path(&PathBuf::from(r"dist\assets\img.png"), &PathBuf::new()),
+            "dist/assets/img.png"
+        );
+        assert_eq!(
+            normalized_path(
+                &PathBuf::from("/home/dev/app/dist/img.png"),
+                &PathBuf::from("/home/dev/app"),
+            ),
+            "dist/img.png"
+        );
+        // A path outside the root keeps its full (normalized) form rather
+        // than being misrendered as relative.
+        assert_eq!(
+            normalized_path(
+                &PathBuf::from(r"D:\other\img.png"),
+                &PathBuf::from(r"C:\app")
+            ),
+            "D:/other/img.png"
+        );
+    }
+
+    #[test]
+    fn test_manifest_paths_are_relative_with_forward_slashes() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("style.css"), "body { margin: 0; }").unwrap();
+
+        let mut pipeline = BuildPipeline::new(root.path(), BuildConfig::default());
+        let result = pipeline.build().unwrap();
+        let manifest =
+            fs::read_to_string(root.path().join("dist").join(MANIFEST_FILE_NAME)).unwrap();
+
+        assert!(!manifest.contains('\\'));
+        let style = &result.artifacts[0];
+        assert!(
+            manifest.contains(&format!(
+                " dist/{}\n",
+                style.path.file_name().unwrap().display()
+            )),
+            "manifest: {manifest:?}"
+        );
+    }
+
+    #[test]
+    fn test_inputs_lock_reflects_the_actual_run() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("style.css"), "body { margin: 0; }").unwrap();
+        fs::write(root.path().join("icon.svg"), "<svg></svg>").unwrap();
+        let lock_path = root.path().join("dist").join(INPUTS_LOCK_FILE_NAME);
+
+        let mut pipeline = BuildPipeline::new(root.path(), BuildConfig::default());
+        pipeline.build().unwrap();
+        let full_lock = fs::read_to_string(&lock_path).unwrap();
+        assert!(full_lock.contains(" style.css\n"), "lock: {full_lock:?}");
+        assert!(full_lock.contains(" icon.svg\n"));
+
+        // With the icon stage disabled the icon is never read, so the lock
+        // for that run must not list it.
+        let config = BuildConfig {
+            enable_icons: false,
+            ..BuildConfig::default()
+        };
+        let mut pipeline = BuildPipeline::new(root.path(), config);
+        pipeline.build().unwrap();
+        let partial_lock = fs::read_to_string(&lock_path).unwrap();
+        assert!(partial_lock.contains(" style.css\n"));
+        assert!(!partial_lock.contains("icon.svg"), "lock: {partial_lock:?}");
+
+        // A changed input changes the lock, even though the same files are
+        // read.
+        fs::write(root.path().join("style.css"), "body { margin: 8px; }").unwrap();
+        pipeline.build().unwrap();
+        let changed_lock = fs::read_to_string(&lock_path).unwrap();
+        assert_ne!(changed_lock, partial_lock);
+        assert!(changed_lock.contains(" style.css\n"));
+    }
+
+    #[test]
+    fn test_case_colliding_sources_fail_the_build() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("Logo.svg"), "<svg>A</svg>").unwrap();
+        fs::write(root.path().join("logo.svg"), "<svg>a</svg>").unwrap();
+
+        let mut pipeline = BuildPipeline::new(root.path(), BuildConfig::default());
+        match pipeline.build() {
+            Err(BuildError::CaseCollision { first, second }) => {
+                assert_eq!(first, "Logo.svg");
+                assert_eq!(second, "logo.svg");
+            }
+            other => panic!("expected a case collision, got {other:?}"),
+        }
+
+        fs::remove_file(root.path().join("logo.svg")).unwrap();
+        pipeline.build().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_source_is_hashed_by_its_target() {
+        let shared = tempfile::tempdir().unwrap();
+        let target = shared.path().join("theme.css");
+        fs::write(&target, "body { margin: 0; }").unwrap();
+
+        let root = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(&target, root.path().join("style.css")).unwrap();
+
+        let mut pipeline = BuildPipeline::new(root.path(), BuildConfig::default());
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.artifacts.len(), 1);
+        assert_eq!(
+            result.artifacts[0].hash,
+            content_hash(b"body { margin: 0; }"),
+            "the cache key reflects the target's bytes, not the link"
+        );
+
+        // Editing the target through its real path must miss the cache even
+        // though the link itself is untouched.
+        fs::write(&target, "body { margin: 8px; }").unwrap();
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.artifacts_processed, 1);
+        assert_eq!(result.stats.local_cache_hits, 0);
+        assert_eq!(
+            result.artifacts[0].hash,
+            content_hash(b"body { margin: 8px; }")
+        );
+    }
+
+    #[test]
+    fn test_corrupted_cached_artifact_forces_a_rebuild() {
+        let root = tempfile::tempdir().unwrap();
+        fs::write(root.path().join("style.css"), "body { margin: 0; }").unwrap();
+        fs::write(root.path().join("icon.svg"), "<svg></svg>").unwrap();
+
+        let mut pipeline = BuildPipeline::new(root.path(), BuildConfig::default());
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.artifacts_processed, 2);
+        let style = result
+            .artifacts
+            .iter()
+            .find(|artifact| artifact.artifact_type == ArtifactType::Style)
+            .unwrap();
+        let style_path = style.path.clone();
+        let style_hash = style.hash.clone();
+
+        fs::write(&style_path, "body { margin: 9999px; }").unwrap();
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.artifacts_processed, 1, "tampered file rebuilt");
+        assert_eq!(result.stats.local_cache_hits, 1, "icon stayed cached");
+        assert_eq!(result.tamper_warnings.len(), 1);
+        assert_eq!(result.tamper_warnings[0].path, style_path);
+        assert_eq!(result.tamper_warnings[0].expected_hash, style_hash);
+        assert_eq!(
+            fs::read_to_string(&style_path).unwrap(),
+            "body { margin: 0; }",
+            "rebuild restored the artifact"
+        );
+
+        // The rebuilt entry is clean again, and warnings are not re-reported.
+        let result = pipeline.build().unwrap();
+        assert_eq!(result.stats.local_cache_hits, 2);
+        assert!(result.tamper_warnings.is_empty());
+    }
+}
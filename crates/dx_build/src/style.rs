@@ -0,0 +1,210 @@
+//! Turns CSS source into a [`BinaryStyleBundle`]: the bytes to ship plus
+//! metadata, and optionally an index of `:root` custom properties so runtime
+//! theming can read variables without re-parsing the whole blob.
+
+use crate::content_hash;
+
+#[derive(Debug, Clone, Default)]
+pub struct StyleConfig {
+    /// When set, top-level `:root` custom properties are copied into
+    /// [`BinaryStyleBundle::custom_properties`]. The CSS bytes themselves
+    /// are left untouched either way.
+    pub extract_custom_properties: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StyleArtifactMetadata {
+    pub source_hash: String,
+    pub source_size: u64,
+    /// Top-level rule blocks in the source.
+    pub rule_count: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryStyleBundle {
+    pub css: Vec<u8>,
+    pub metadata: StyleArtifactMetadata,
+    /// `--name` / value pairs from top-level `:root` blocks. A name declared
+    /// twice appears once, with the later value, ordered by its winning
+    /// declaration — matching how the cascade would resolve it. Empty unless
+    /// [`StyleConfig::extract_custom_properties`] is set.
+    pub custom_properties: Vec<(String, String)>,
+}
+
+#[derive(Debug, Default)]
+pub struct StyleProcessor {
+    config: StyleConfig,
+}
+
+impl StyleProcessor {
+    pub fn new(config: StyleConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn process(&self, css: &str) -> BinaryStyleBundle {
+        BinaryStyleBundle {
+            css: css.as_bytes().to_vec(),
+            metadata: StyleArtifactMetadata {
+                source_hash: content_hash(css.as_bytes()),
+                source_size: css.len() as u64,
+                rule_count: top_level_blocks(css).count(),
+            },
+            custom_properties: if self.config.extract_custom_properties {
+                extract_root_custom_properties(css)
+            } else {
+                Vec::new()
+            },
+        }
+    }
+}
+
+/// `(selector, body)` for each top-level `{ ... }` block, nested blocks
+/// included in the body. Tolerant of malformed input: an unclosed block is
+/// simply not yielded.
+fn top_level_blocks(css: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut rest = css;
+    std::iter::from_fn(move || {
+        let open = rest.find('{')?;
+        let selector = rest[..open].trim();
+        let body_end = open + matching_brace(&rest[open..])?;
+        let body = &rest[open + 1..body_end];
+        rest = &rest[body_end + 1..];
+        Some((selector, body))
+    })
+}
+
+/// Offset of the brace closing the block opened at `text[0]`, accounting for
+/// nesting; `None` when the block never closes.
+fn matching_brace(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (offset, character) in text.char_indices() {
+        match character {
+            '{' => depth += 1,
+            '}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(offset);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn extract_root_custom_properties(css: &str) -> Vec<(String, String)> {
+    let mut properties: Vec<(String, String)> = Vec::new();
+    for (selector, body) in top_level_blocks(css) {
+        if selector != ":root" {
+            continue;
+        }
+        for declaration in top_level_declarations(body) {
+            let Some((name, value)) = declaration.split_once(':') else {
+                continue;
+            };
+            let name = name.trim();
+            if !name.starts_with("--") {
+                continue;
+            }
+            // Last-wins, as the cascade resolves duplicates: drop any
+            // earlier declaration so the surviving entry sits where the
+            // winning one was declared.
+            if let Some(position) = properties.iter().position(|(existing, _)| existing == name) {
+                properties.remove(position);
+            }
+            properties.push((name.to_string(), value.trim().to_string()));
+        }
+    }
+    properties
+}
+
+/// The `;`-separated declarations directly inside a block body, skipping
+/// anything inside nested blocks so properties declared in nested selectors
+/// are left in place.
+fn top_level_declarations(body: &str) -> impl Iterator<Item = &str> {
+    let mut rest = body;
+    std::iter::from_fn(move || {
+        loop {
+            if rest.is_empty() {
+                return None;
+            }
+            let mut depth = 0usize;
+            let mut end = rest.len();
+            let mut nested = false;
+            for (offset, character) in rest.char_indices() {
+                match character {
+                    '{' => {
+                        depth += 1;
+                        nested = true;
+                    }
+                    '}' => depth = depth.saturating_sub(1),
+                    ';' if depth == 0 => {
+                        end = offset;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            let declaration = &rest[..end];
+            rest = rest.get(end + 1..).unwrap_or("");
+            if !nested && !declaration.trim().is_empty() {
+                return Some(declaration);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extracting() -> StyleProcessor {
+        StyleProcessor::new(StyleConfig {
+            extract_custom_properties: true,
+        })
+    }
+
+    #[test]
+    fn test_root_custom_properties_are_extracted_without_touching_the_css() {
+        let css = ":root {\n  --accent: #ff0077;\n  --spacing: 4px;\n  color: black;\n}\n.button { padding: var(--spacing); }\n";
+        let bundle = extracting().process(css);
+        assert_eq!(
+            bundle.custom_properties,
+            vec![
+                ("--accent".to_string(), "#ff0077".to_string()),
+                ("--spacing".to_string(), "4px".to_string()),
+            ]
+        );
+        assert_eq!(bundle.css, css.as_bytes(), "the blob is left intact");
+        assert_eq!(bundle.metadata.rule_count, 2);
+        assert_eq!(bundle.metadata.source_size, css.len() as u64);
+
+        let disabled = StyleProcessor::default().process(css);
+        assert!(disabled.custom_properties.is_empty());
+        assert_eq!(disabled.css, bundle.css);
+    }
+
+    #[test]
+    fn test_nested_and_non_root_properties_stay_in_place() {
+        let css = ":root {\n  --base: 1rem;\n  @media (prefers-color-scheme: dark) {\n    --base: 2rem;\n  }\n}\n.dark { --accent: red; }\n";
+        let bundle = extracting().process(css);
+        assert_eq!(
+            bundle.custom_properties,
+            vec![("--base".to_string(), "1rem".to_string())],
+            "nested and non-root declarations are not indexed"
+        );
+    }
+
+    #[test]
+    fn test_duplicate_custom_properties_are_last_wins() {
+        let css = ":root { --accent: red; --spacing: 4px; }\n:root { --accent: blue; }\n";
+        let bundle = extracting().process(css);
+        assert_eq!(
+            bundle.custom_properties,
+            vec![
+                ("--spacing".to_string(), "4px".to_string()),
+                ("--accent".to_string(), "blue".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! Tamper-evident audit log of mutating Forge operations. Entries are
+//! hash-chained — each entry's hash covers its fields *and* its
+//! predecessor's hash — so a verifier can detect an edited, deleted, or
+//! reordered entry anywhere in the log, not just at the tail.
+
+use crate::ForgeError;
+use crate::storage::hash_bytes;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// File name of the log inside the project's `.dx/` directory.
+pub const AUDIT_LOG_FILE_NAME: &str = "audit.log";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditOperation {
+    SnapshotCommit,
+    BranchMerge,
+    ConfigInjection,
+    PackageInstall,
+}
+
+impl AuditOperation {
+    fn label(self) -> &'static str {
+        match self {
+            Self::SnapshotCommit => "snapshot-commit",
+            Self::BranchMerge => "branch-merge",
+            Self::ConfigInjection => "config-injection",
+            Self::PackageInstall => "package-install",
+        }
+    }
+
+    fn from_label(label: &str) -> Option<Self> {
+        match label {
+            "snapshot-commit" => Some(Self::SnapshotCommit),
+            "branch-merge" => Some(Self::BranchMerge),
+            "config-injection" => Some(Self::ConfigInjection),
+            "package-install" => Some(Self::PackageInstall),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// Position in the log, starting at zero; gaps mean deletion.
+    pub sequence: u64,
+    /// Who performed the operation: a user id or service identity.
+    pub actor: String,
+    pub operation: AuditOperation,
+    /// When the operation happened, seconds since the unix epoch.
+    pub timestamp: u64,
+    /// The snapshot the operation produced, when it produced one.
+    pub snapshot_id: Option<u64>,
+    /// Hash over this entry's fields and the previous entry's hash.
+    pub entry_hash: String,
+}
+
+/// Append-only log stored as one tab-separated line per entry under `.dx/`.
+/// Appending re-reads the tail to chain onto it, so multiple `AuditLog`
+/// values over the same file stay consistent as long as appends do not race.
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    /// Opens (without creating) the log in `dx_dir`, the project's `.dx/`
+    /// directory.
+    pub fn new(dx_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            path: dx_dir.into().join(AUDIT_LOG_FILE_NAME),
+        }
+    }
+
+    /// Appends an entry chained onto the current tail and returns it. The
+    /// existing chain is verified first: appending to a log that has already
+    /// been tampered with would launder the tampering into a valid-looking
+    /// suffix.
+    pub fn append(
+        &self,
+        actor: &str,
+        operation: AuditOperation,
+        timestamp: u64,
+        snapshot_id: Option<u64>,
+    ) -> Result<AuditEntry, ForgeError> {
+        let entries = self.verified_entries()?;
+        let sequence = entries.len() as u64;
+        let previous_hash = entries
+            .last()
+            .map(|entry| entry.entry_hash.as_str())
+            .unwrap_or(GENESIS_HASH);
+        let mut entry = AuditEntry {
+            sequence,
+            actor: actor.to_string(),
+            operation,
+            timestamp,
+            snapshot_id,
+            entry_hash: String::new(),
+        };
+        entry.entry_hash = chain_hash(&entry, previous_hash);
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|source| io_error(parent, source))?;
+        }
+        let mut contents = fs::read_to_string(&self.path).unwrap_or_default();
+        contents.push_str(&render_line(&entry));
+        fs::write(&self.path, contents).map_err(|source| io_error(&self.path, source))?;
+        Ok(entry)
+    }
+
+    /// The entries whose sequence numbers fall in `range`, after verifying
+    /// the whole chain — a read never silently returns tampered history.
+    pub fn entries(&self, range: Range<u64>) -> Result<Vec<AuditEntry>, ForgeError> {
+        Ok(self
+            .verified_entries()?
+            .into_iter()
+            .filter(|entry| range.contains(&entry.sequence))
+            .collect())
+    }
+
+    /// Walks the full chain, failing with [`ForgeError::AuditChainBroken`]
+    /// at the first entry whose hash, sequence, or link to its predecessor
+    /// does not hold. Returns how many entries the log contains.
+    pub fn verify(&self) -> Result<usize, ForgeError> {
+        Ok(self.verified_entries()?.len())
+    }
+
+    fn verified_entries(&self) -> Result<Vec<AuditEntry>, ForgeError> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            // A log that was never written is an empty, valid chain.
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(source) => return Err(io_error(&self.path, source)),
+        };
+        let mut entries = Vec::new();
+        let mut previous_hash = GENESIS_HASH.to_string();
+        for (index, line) in contents.lines().enumerate() {
+            let sequence = index as u64;
+            let broken = |message: String| ForgeError::AuditChainBroken { sequence, message };
+            let entry =
+                parse_line(line).ok_or_else(|| broken(format!("unparseable entry: {line:?}")))?;
+            if entry.sequence != sequence {
+                return Err(broken(format!(
+                    "sequence {} where {} was expected; entries were deleted or reordered",
+                    entry.sequence, sequence
+                )));
+            }
+            if chain_hash(&entry, &previous_hash) != entry.entry_hash {
+                return Err(broken(
+                    "hash does not match the entry and its predecessor".into(),
+                ));
+            }
+            previous_hash = entry.entry_hash.clone();
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+}
+
+/// Chain anchor for the first entry, so an empty predecessor is
+/// distinguishable from a missing one.
+const GENESIS_HASH: &str = "genesis";
+
+fn chain_hash(entry: &AuditEntry, previous_hash: &str) -> String {
+    hash_bytes(format!("{previous_hash}\n{}", entry_fields(entry)).as_bytes())
+}
+
+fn entry_fields(entry: &AuditEntry) -> String {
+    format!(
+        "{}\t{}\t{}\t{}\t{}",
+        entry.sequence,
+        entry.actor,
+        entry.operation.label(),
+        entry.timestamp,
+        entry
+            .snapshot_id
+            .map_or("-".to_string(), |id| id.to_string()),
+    )
+}
+
+fn render_line(entry: &AuditEntry) -> String {
+    format!("{}\t{}\n", entry_fields(entry), entry.entry_hash)
+}
+
+fn parse_line(line: &str) -> Option<AuditEntry> {
+    let mut fields = line.split('\t');
+    let sequence = fields.next()?.parse().ok()?;
+    let actor = fields.next()?.to_string();
+    let operation = AuditOperation::from_label(fields.next()?)?;
+    let timestamp = fields.next()?.parse().ok()?;
+    let snapshot_id = match fields.next()? {
+        "-" => None,
+        id => Some(id.parse().ok()?),
+    };
+    let entry_hash = fields.next()?.to_string();
+    if fields.next().is_some() {
+        return None;
+    }
+    Some(AuditEntry {
+        sequence,
+        actor,
+        operation,
+        timestamp,
+        snapshot_id,
+        entry_hash,
+    })
+}
+
+fn io_error(path: &Path, source: std::io::Error) -> ForgeError {
+    ForgeError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn populated_log(dx_dir: &Path) -> AuditLog {
+        let log = AuditLog::new(dx_dir);
+        log.append("alice", AuditOperation::SnapshotCommit, 1_000, Some(1))
+            .unwrap();
+        log.append("bot/ci", AuditOperation::ConfigInjection, 1_010, None)
+            .unwrap();
+        log.append("alice", AuditOperation::BranchMerge, 1_020, Some(2))
+            .unwrap();
+        log
+    }
+
+    #[test]
+    fn test_entries_chain_and_read_back_by_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = populated_log(&dir.path().join(".dx"));
+        assert_eq!(log.verify().unwrap(), 3);
+
+        let middle = log.entries(1..2).unwrap();
+        assert_eq!(middle.len(), 1);
+        assert_eq!(middle[0].actor, "bot/ci");
+        assert_eq!(middle[0].operation, AuditOperation::ConfigInjection);
+        assert_eq!(middle[0].snapshot_id, None);
+
+        // A fresh handle over the same file sees — and extends — the chain.
+        let reopened = AuditLog::new(dir.path().join(".dx"));
+        reopened
+            .append("bob", AuditOperation::PackageInstall, 1_030, None)
+            .unwrap();
+        assert_eq!(log.verify().unwrap(), 4);
+    }
+
+    #[test]
+    fn test_corrupting_an_entry_is_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let dx_dir = dir.path().join(".dx");
+        let log = populated_log(&dx_dir);
+
+        let path = dx_dir.join(AUDIT_LOG_FILE_NAME);
+        let tampered = fs::read_to_string(&path)
+            .unwrap()
+            .replace("alice", "mallory");
+        fs::write(&path, tampered).unwrap();
+        assert!(matches!(
+            log.verify(),
+            Err(ForgeError::AuditChainBroken { sequence: 0, .. })
+        ));
+        // Tampered history also refuses reads and further appends.
+        assert!(log.entries(0..10).is_err());
+        assert!(
+            log.append("mallory", AuditOperation::SnapshotCommit, 1_040, None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_deleting_an_entry_breaks_the_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        let dx_dir = dir.path().join(".dx");
+        let log = populated_log(&dx_dir);
+
+        let path = dx_dir.join(AUDIT_LOG_FILE_NAME);
+        let contents = fs::read_to_string(&path).unwrap();
+        let without_middle: String = contents
+            .lines()
+            .enumerate()
+            .filter(|(index, _)| *index != 1)
+            .map(|(_, line)| format!("{line}\n"))
+            .collect();
+        fs::write(&path, without_middle).unwrap();
+        assert!(matches!(
+            log.verify(),
+            Err(ForgeError::AuditChainBroken { sequence: 1, .. })
+        ));
+    }
+}
@@ -0,0 +1,211 @@
+use crate::ForgeError;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Checks an Ed25519 signature over a downloaded binary: `verifier(binary,
+/// signature)` returns true when the signature matches the pinned public
+/// key. A callback rather than a key type, so this crate stays free of
+/// crypto dependencies — wire it to `dx_auth::ProductionTokenGenerator::
+/// verify` to check against the publisher's pinned key.
+pub type UpdateVerifier = Arc<dyn Fn(&[u8], &[u8]) -> bool + Send + Sync>;
+
+/// Which release track an installation follows. Beta installations also
+/// accept stable releases, so a stable build promoting past the newest beta
+/// reaches them without a channel switch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateChannel {
+    Stable,
+    Beta,
+}
+
+/// One published release as fetched from the update feed.
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub version: (u64, u64, u64),
+    pub channel: UpdateChannel,
+    /// Fraction of installations (0–100) the release has been rolled out
+    /// to. An installation's deterministic bucket must fall below this for
+    /// the release to be offered.
+    pub rollout_percent: u8,
+    /// The downloaded binary.
+    pub binary: Vec<u8>,
+    /// Ed25519 signature over `binary`, produced with the publisher's
+    /// pinned key.
+    pub signature: Vec<u8>,
+}
+
+/// Verifies and applies binary updates, honoring the installation's channel
+/// and each release's staged-rollout percentage.
+pub struct AutoUpdater {
+    channel: UpdateChannel,
+    rollout_bucket: u8,
+    verifier: UpdateVerifier,
+}
+
+impl AutoUpdater {
+    /// `install_id` is any stable per-installation identifier; it is hashed
+    /// into a 0–99 rollout bucket, so the same machine lands on the same
+    /// side of every percentage across checks instead of flapping in and
+    /// out of a rollout.
+    pub fn new(channel: UpdateChannel, install_id: &str, verifier: UpdateVerifier) -> Self {
+        Self {
+            channel,
+            rollout_bucket: rollout_bucket(install_id),
+            verifier,
+        }
+    }
+
+    /// The newest release this installation should update to: filtered to
+    /// the channels it accepts and to rollouts its bucket is inside, then
+    /// the highest version. `None` when nothing eligible is newer than
+    /// `current_version`.
+    pub fn select_release<'a>(
+        &self,
+        releases: &'a [Release],
+        current_version: (u64, u64, u64),
+    ) -> Option<&'a Release> {
+        releases
+            .iter()
+            .filter(|release| {
+                self.accepts_channel(release.channel)
+                    && self.rollout_bucket < release.rollout_percent
+                    && release.version > current_version
+            })
+            .max_by_key(|release| release.version)
+    }
+
+    fn accepts_channel(&self, channel: UpdateChannel) -> bool {
+        match self.channel {
+            UpdateChannel::Stable => channel == UpdateChannel::Stable,
+            UpdateChannel::Beta => true,
+        }
+    }
+
+    /// Verifies the release's signature and swaps it into place at
+    /// `binary_path`. Verification happens before any filesystem write, and
+    /// the binary is staged to a sibling file and renamed over the target,
+    /// so a rejected signature — or a crash mid-write — leaves the current
+    /// binary untouched.
+    pub fn apply_update(&self, release: &Release, binary_path: &Path) -> Result<(), ForgeError> {
+        if !(self.verifier)(&release.binary, &release.signature) {
+            return Err(ForgeError::UpdateRejected(version_string(release.version)));
+        }
+        let staged_path = binary_path.with_extension("update");
+        fs::write(&staged_path, &release.binary).map_err(|source| ForgeError::Io {
+            path: staged_path.clone(),
+            source,
+        })?;
+        fs::rename(&staged_path, binary_path).map_err(|source| ForgeError::Io {
+            path: binary_path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+/// The installation's deterministic 0–99 rollout bucket.
+fn rollout_bucket(install_id: &str) -> u8 {
+    let hash = crate::storage::hash_bytes(install_id.as_bytes());
+    let folded = hash.bytes().fold(0u64, |accumulator, byte| {
+        accumulator.wrapping_mul(31).wrapping_add(byte as u64)
+    });
+    (folded % 100) as u8
+}
+
+fn version_string(version: (u64, u64, u64)) -> String {
+    format!("{}.{}.{}", version.0, version.1, version.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stands in for Ed25519: a signature is valid iff it is the binary
+    /// reversed.
+    fn test_verifier() -> UpdateVerifier {
+        Arc::new(|binary: &[u8], signature: &[u8]| signature.iter().rev().eq(binary.iter()))
+    }
+
+    fn test_sign(binary: &[u8]) -> Vec<u8> {
+        binary.iter().rev().copied().collect()
+    }
+
+    fn release(version: (u64, u64, u64), channel: UpdateChannel, rollout_percent: u8) -> Release {
+        let binary = format!("binary {}", version_string(version)).into_bytes();
+        let signature = test_sign(&binary);
+        Release {
+            version,
+            channel,
+            rollout_percent,
+            binary,
+            signature,
+        }
+    }
+
+    #[test]
+    fn test_tampered_update_is_rejected_and_keeps_the_current_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary_path = dir.path().join("dx");
+        fs::write(&binary_path, b"current binary").unwrap();
+
+        let updater = AutoUpdater::new(UpdateChannel::Stable, "machine-1", test_verifier());
+        let mut tampered = release((1, 1, 0), UpdateChannel::Stable, 100);
+        tampered.binary.push(b'!');
+        assert!(matches!(
+            updater.apply_update(&tampered, &binary_path),
+            Err(ForgeError::UpdateRejected(version)) if version == "1.1.0"
+        ));
+        assert_eq!(fs::read(&binary_path).unwrap(), b"current binary");
+
+        let genuine = release((1, 1, 0), UpdateChannel::Stable, 100);
+        updater.apply_update(&genuine, &binary_path).unwrap();
+        assert_eq!(fs::read(&binary_path).unwrap(), genuine.binary);
+    }
+
+    #[test]
+    fn test_channel_selection_picks_the_right_version() {
+        let releases = vec![
+            release((1, 0, 0), UpdateChannel::Stable, 100),
+            release((1, 1, 0), UpdateChannel::Stable, 100),
+            release((1, 2, 0), UpdateChannel::Beta, 100),
+        ];
+
+        let stable = AutoUpdater::new(UpdateChannel::Stable, "machine-1", test_verifier());
+        let selected = stable.select_release(&releases, (1, 0, 0)).unwrap();
+        assert_eq!(selected.version, (1, 1, 0), "stable never offers a beta");
+
+        let beta = AutoUpdater::new(UpdateChannel::Beta, "machine-1", test_verifier());
+        let selected = beta.select_release(&releases, (1, 0, 0)).unwrap();
+        assert_eq!(selected.version, (1, 2, 0));
+
+        assert!(
+            stable.select_release(&releases, (1, 1, 0)).is_none(),
+            "nothing newer than the current version"
+        );
+    }
+
+    #[test]
+    fn test_rollout_percentage_gates_deterministically() {
+        let updater = AutoUpdater::new(UpdateChannel::Stable, "machine-1", test_verifier());
+        let gated = vec![release(
+            (1, 1, 0),
+            UpdateChannel::Stable,
+            updater.rollout_bucket,
+        )];
+        assert!(
+            updater.select_release(&gated, (1, 0, 0)).is_none(),
+            "bucket {} is outside a {}% rollout",
+            updater.rollout_bucket,
+            updater.rollout_bucket,
+        );
+
+        let open = vec![release((1, 1, 0), UpdateChannel::Stable, 100)];
+        assert!(updater.select_release(&open, (1, 0, 0)).is_some());
+
+        let same_machine = AutoUpdater::new(UpdateChannel::Stable, "machine-1", test_verifier());
+        assert_eq!(
+            updater.rollout_bucket, same_machine.rollout_bucket,
+            "the same install id always lands in the same bucket"
+        );
+    }
+}
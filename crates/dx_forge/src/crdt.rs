@@ -0,0 +1,363 @@
+use collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+/// A globally unique, totally ordered id minted by one replica. The counter
+/// is a Lamport clock, so causally later ops always compare greater; the
+/// replica id breaks ties between concurrent ops deterministically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct OpId {
+    pub counter: u64,
+    pub replica: u64,
+}
+
+/// A character-level edit or formatting annotation. Replicas exchange these
+/// in any order (causal delivery assumed: an op arrives after the ops that
+/// minted the ids it references) and converge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Operation {
+    Insert {
+        id: OpId,
+        /// The character this one goes after; `None` inserts at the front.
+        after: Option<OpId>,
+        character: char,
+    },
+    Delete {
+        id: OpId,
+        target: OpId,
+    },
+    Annotate(AttributeOp),
+}
+
+impl Operation {
+    fn id(&self) -> OpId {
+        match self {
+            Self::Insert { id, .. } | Self::Delete { id, .. } => *id,
+            Self::Annotate(op) => op.id,
+        }
+    }
+}
+
+/// A formatting span (bold, italic, link target, ...) anchored to character
+/// ids rather than offsets, so it survives concurrent edits. Overlapping
+/// spans merge by attribute-set union; when two spans set the same key over
+/// the same character, the op with the greater [`OpId`] wins (last writer
+/// wins, with the replica id as the concurrent tie-break).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttributeOp {
+    pub id: OpId,
+    pub start: OpId,
+    /// Inclusive: the span covers every visible character currently ordered
+    /// between `start` and `end`.
+    pub end: OpId,
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone)]
+struct Character {
+    id: OpId,
+    character: char,
+    deleted: bool,
+}
+
+/// A replicated rich-text document: an RGA character sequence plus
+/// CRDT-mergeable attribute spans. Local edits return the [`Operation`] to
+/// broadcast; remote ops are fed to [`apply`](Self::apply). Two replicas
+/// that have seen the same op set render identical text and attributes.
+pub struct CrdtDocument {
+    replica: u64,
+    clock: u64,
+    characters: Vec<Character>,
+    annotations: Vec<AttributeOp>,
+    applied: HashSet<OpId>,
+}
+
+impl CrdtDocument {
+    pub fn new(replica: u64) -> Self {
+        Self {
+            replica,
+            clock: 0,
+            characters: Vec::new(),
+            annotations: Vec::new(),
+            applied: HashSet::default(),
+        }
+    }
+
+    fn next_id(&mut self) -> OpId {
+        self.clock += 1;
+        OpId {
+            counter: self.clock,
+            replica: self.replica,
+        }
+    }
+
+    /// Inserts locally and returns the op to broadcast.
+    pub fn insert_after(&mut self, after: Option<OpId>, character: char) -> Operation {
+        let operation = Operation::Insert {
+            id: self.next_id(),
+            after,
+            character,
+        };
+        self.apply(&operation);
+        operation
+    }
+
+    /// Deletes locally (tombstoning the character) and returns the op to
+    /// broadcast.
+    pub fn delete(&mut self, target: OpId) -> Operation {
+        let operation = Operation::Delete {
+            id: self.next_id(),
+            target,
+        };
+        self.apply(&operation);
+        operation
+    }
+
+    /// Formats the inclusive `start..=end` character range locally and
+    /// returns the op to broadcast.
+    pub fn annotate(
+        &mut self,
+        start: OpId,
+        end: OpId,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Operation {
+        let operation = Operation::Annotate(AttributeOp {
+            id: self.next_id(),
+            start,
+            end,
+            key: key.into(),
+            value: value.into(),
+        });
+        self.apply(&operation);
+        operation
+    }
+
+    /// Applies a local or remote op. Idempotent: redelivered ops are no-ops.
+    pub fn apply(&mut self, operation: &Operation) {
+        if !self.applied.insert(operation.id()) {
+            return;
+        }
+        self.clock = self.clock.max(operation.id().counter);
+        match operation {
+            Operation::Insert {
+                id,
+                after,
+                character,
+            } => {
+                let mut index = match after {
+                    None => 0,
+                    Some(anchor) => match self.index_of(*anchor) {
+                        Some(anchor_index) => anchor_index + 1,
+                        // Causal delivery was violated; appending keeps the
+                        // character visible rather than losing it.
+                        None => self.characters.len(),
+                    },
+                };
+                // RGA ordering: concurrent inserts at the same anchor land
+                // greatest id first, so every replica picks the same slot.
+                while self
+                    .characters
+                    .get(index)
+                    .is_some_and(|existing| existing.id > *id)
+                {
+                    index += 1;
+                }
+                self.characters.insert(
+                    index,
+                    Character {
+                        id: *id,
+                        character: *character,
+                        deleted: false,
+                    },
+                );
+            }
+            Operation::Delete { target, .. } => {
+                if let Some(index) = self.index_of(*target) {
+                    self.characters[index].deleted = true;
+                }
+            }
+            Operation::Annotate(op) => self.annotations.push(op.clone()),
+        }
+    }
+
+    fn index_of(&self, id: OpId) -> Option<usize> {
+        self.characters
+            .iter()
+            .position(|character| character.id == id)
+    }
+
+    pub fn text(&self) -> String {
+        self.characters
+            .iter()
+            .filter(|character| !character.deleted)
+            .map(|character| character.character)
+            .collect()
+    }
+
+    /// The ids of the visible characters, in document order. Position `n`
+    /// here corresponds to character `n` of [`text`](Self::text).
+    pub fn character_ids(&self) -> Vec<OpId> {
+        self.characters
+            .iter()
+            .filter(|character| !character.deleted)
+            .map(|character| character.id)
+            .collect()
+    }
+
+    /// The merged attributes covering one character: the union of every span
+    /// containing it, last writer winning per key.
+    pub fn attributes_at(&self, target: OpId) -> HashMap<String, String> {
+        let Some(position) = self.index_of(target) else {
+            return HashMap::default();
+        };
+        let mut winners: HashMap<&str, &AttributeOp> = HashMap::default();
+        for op in &self.annotations {
+            let (Some(start), Some(end)) = (self.index_of(op.start), self.index_of(op.end)) else {
+                continue;
+            };
+            if position < start || position > end {
+                continue;
+            }
+            if winners
+                .get(op.key.as_str())
+                .is_none_or(|current| op.id > current.id)
+            {
+                winners.insert(op.key.as_str(), op);
+            }
+        }
+        winners
+            .into_iter()
+            .map(|(key, op)| (key.to_string(), op.value.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn type_text(document: &mut CrdtDocument, text: &str) -> Vec<Operation> {
+        let mut operations = Vec::new();
+        let mut anchor = None;
+        for character in text.chars() {
+            let operation = document.insert_after(anchor, character);
+            anchor = Some(operation.id());
+            operations.push(operation);
+        }
+        operations
+    }
+
+    fn sync(a: &mut CrdtDocument, b: &mut CrdtDocument, a_ops: &[Operation], b_ops: &[Operation]) {
+        for operation in b_ops {
+            a.apply(operation);
+        }
+        for operation in a_ops {
+            b.apply(operation);
+        }
+    }
+
+    #[test]
+    fn test_concurrent_overlapping_formatting_converges() {
+        let mut alice = CrdtDocument::new(1);
+        let mut bob = CrdtDocument::new(2);
+        let typed = type_text(&mut alice, "hello");
+        for operation in &typed {
+            bob.apply(operation);
+        }
+        let ids = alice.character_ids();
+
+        // Concurrently: alice bolds "hell", bob italicizes "llo".
+        let alice_ops = vec![alice.annotate(ids[0], ids[3], "bold", "true")];
+        let bob_ops = vec![bob.annotate(ids[2], ids[4], "italic", "true")];
+        sync(&mut alice, &mut bob, &alice_ops, &bob_ops);
+
+        for document in [&alice, &bob] {
+            assert_eq!(document.text(), "hello");
+            let overlap = document.attributes_at(ids[2]);
+            assert_eq!(overlap.get("bold").map(String::as_str), Some("true"));
+            assert_eq!(overlap.get("italic").map(String::as_str), Some("true"));
+            assert!(!document.attributes_at(ids[4]).contains_key("bold"));
+        }
+        assert_eq!(alice.attributes_at(ids[1]), bob.attributes_at(ids[1]));
+    }
+
+    #[test]
+    fn test_same_key_conflict_is_last_writer_wins_on_both_replicas() {
+        let mut alice = CrdtDocument::new(1);
+        let mut bob = CrdtDocument::new(2);
+        let typed = type_text(&mut alice, "link");
+        for operation in &typed {
+            bob.apply(operation);
+        }
+        let ids = alice.character_ids();
+
+        let alice_ops = vec![alice.annotate(ids[0], ids[3], "href", "https://a.example")];
+        let bob_ops = vec![bob.annotate(ids[1], ids[2], "href", "https://b.example")];
+        sync(&mut alice, &mut bob, &alice_ops, &bob_ops);
+
+        // Same Lamport counter on both ops, so the replica id breaks the tie
+        // identically everywhere.
+        for document in [&alice, &bob] {
+            assert_eq!(
+                document
+                    .attributes_at(ids[1])
+                    .get("href")
+                    .map(String::as_str),
+                Some("https://b.example")
+            );
+            assert_eq!(
+                document
+                    .attributes_at(ids[0])
+                    .get("href")
+                    .map(String::as_str),
+                Some("https://a.example")
+            );
+        }
+    }
+
+    #[test]
+    fn test_concurrent_inserts_at_the_same_anchor_converge() {
+        let mut alice = CrdtDocument::new(1);
+        let mut bob = CrdtDocument::new(2);
+        let typed = type_text(&mut alice, "ad");
+        for operation in &typed {
+            bob.apply(operation);
+        }
+        let anchor = Some(alice.character_ids()[0]);
+
+        let alice_ops = vec![alice.insert_after(anchor, 'b')];
+        let bob_ops = vec![bob.insert_after(anchor, 'c')];
+        sync(&mut alice, &mut bob, &alice_ops, &bob_ops);
+
+        assert_eq!(alice.text(), bob.text());
+
+        // Redelivery is a no-op.
+        let before = alice.text();
+        for operation in &bob_ops {
+            alice.apply(operation);
+        }
+        assert_eq!(alice.text(), before);
+    }
+
+    #[test]
+    fn test_annotations_survive_interior_edits() {
+        let mut document = CrdtDocument::new(1);
+        type_text(&mut document, "abc");
+        let ids = document.character_ids();
+        document.annotate(ids[0], ids[2], "bold", "true");
+
+        document.delete(ids[1]);
+        document.insert_after(Some(ids[1]), 'x');
+        let new_ids = document.character_ids();
+        assert_eq!(document.text(), "axc");
+        // The span is anchored to surviving characters, so the new interior
+        // character is covered too.
+        for id in new_ids {
+            assert_eq!(
+                document.attributes_at(id).get("bold").map(String::as_str),
+                Some("true")
+            );
+        }
+    }
+}
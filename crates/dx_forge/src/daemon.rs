@@ -0,0 +1,267 @@
+use crate::ForgeError;
+use collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Commands the daemon accepts over its IPC socket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum IpcCommand {
+    /// Re-validate and apply a new configuration without restarting the
+    /// daemon.
+    ReloadConfig(ForgeConfig),
+    Shutdown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct ForgeConfig {
+    pub tools: Vec<ToolConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolConfig {
+    pub id: String,
+    pub command: String,
+    pub args: Vec<String>,
+    /// Environment overrides; these apply to a running tool on its next
+    /// invocation, so changing them never forces a restart.
+    pub env: HashMap<String, String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ConfigValidator;
+
+/// One problem found by [`ConfigValidator::diagnostics`], located in the
+/// configuration source text when the offending value could be found there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDiagnostic {
+    pub message: String,
+    /// Byte range of the offending value in the source; `None` when it could
+    /// not be located (e.g. the text changed since it was parsed).
+    pub span: Option<Range<usize>>,
+}
+
+/// Byte range of the next occurrence of `needle` at or after `from`.
+fn span_of(source: &str, needle: &str, from: usize) -> Option<Range<usize>> {
+    source.get(from..)?.find(needle).map(|offset| {
+        let start = from + offset;
+        start..start + needle.len()
+    })
+}
+
+impl ConfigValidator {
+    pub fn validate(&self, config: &ForgeConfig) -> Result<(), ForgeError> {
+        let mut seen_ids: HashSet<&String> = HashSet::default();
+        for tool in &config.tools {
+            if tool.id.is_empty() {
+                return Err(ForgeError::InvalidConfig(
+                    "tool id must not be empty".into(),
+                ));
+            }
+            if tool.command.is_empty() {
+                return Err(ForgeError::InvalidConfig(format!(
+                    "tool {} has an empty command",
+                    tool.id
+                )));
+            }
+            if !seen_ids.insert(&tool.id) {
+                return Err(ForgeError::InvalidConfig(format!(
+                    "duplicate tool id: {}",
+                    tool.id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Like [`validate`](Self::validate), but collects every problem instead
+    /// of stopping at the first, and locates each offending value in the
+    /// `source` text the config was parsed from so an editor can mark it in
+    /// place. An empty result means the config is valid. Each tool's id is
+    /// located past the previous tool's, so a duplicate id points at its
+    /// second occurrence rather than the legitimate first one.
+    pub fn diagnostics(&self, config: &ForgeConfig, source: &str) -> Vec<ConfigDiagnostic> {
+        let mut diagnostics = Vec::new();
+        let mut seen_ids: HashSet<&String> = HashSet::default();
+        let mut cursor = 0;
+        for tool in &config.tools {
+            let id_span = span_of(source, &format!("\"{}\"", tool.id), cursor);
+            if let Some(span) = &id_span {
+                cursor = span.end;
+            }
+            if tool.id.is_empty() {
+                diagnostics.push(ConfigDiagnostic {
+                    message: "tool id must not be empty".into(),
+                    span: id_span,
+                });
+            } else if !seen_ids.insert(&tool.id) {
+                diagnostics.push(ConfigDiagnostic {
+                    message: format!("duplicate tool id: {}", tool.id),
+                    span: id_span,
+                });
+            }
+            if tool.command.is_empty() {
+                diagnostics.push(ConfigDiagnostic {
+                    message: format!("tool {} has an empty command", tool.id),
+                    span: span_of(source, "\"\"", cursor),
+                });
+            }
+        }
+        diagnostics
+    }
+}
+
+/// How each tool was affected by a [`Daemon::reload_config`], so the caller
+/// can report what took effect live and what still needs a restart.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReloadOutcome {
+    /// Tools whose changes took effect without interrupting them (added
+    /// tools, removed tools, and env-only changes).
+    pub applied_live: Vec<String>,
+    /// Tools whose command line changed; the new config is recorded but the
+    /// running process must be restarted to pick it up.
+    pub restart_required: Vec<String>,
+    pub unchanged: Vec<String>,
+}
+
+pub struct Daemon {
+    config: ForgeConfig,
+    validator: ConfigValidator,
+}
+
+impl Daemon {
+    pub fn new(config: ForgeConfig) -> Result<Self, ForgeError> {
+        let validator = ConfigValidator;
+        validator.validate(&config)?;
+        Ok(Self { config, validator })
+    }
+
+    pub fn config(&self) -> &ForgeConfig {
+        &self.config
+    }
+
+    /// Validates and applies a new configuration. Rejection is atomic: if
+    /// validation fails, the running configuration is left untouched.
+    pub fn reload_config(&mut self, new_config: ForgeConfig) -> Result<ReloadOutcome, ForgeError> {
+        self.validator.validate(&new_config)?;
+
+        let mut outcome = ReloadOutcome::default();
+        let old_tools: HashMap<&String, &ToolConfig> = self
+            .config
+            .tools
+            .iter()
+            .map(|tool| (&tool.id, tool))
+            .collect();
+        for tool in &new_config.tools {
+            match old_tools.get(&tool.id) {
+                None => outcome.applied_live.push(tool.id.clone()),
+                Some(old) if old.command != tool.command || old.args != tool.args => {
+                    outcome.restart_required.push(tool.id.clone());
+                }
+                Some(old) if old.env != tool.env => outcome.applied_live.push(tool.id.clone()),
+                Some(_) => outcome.unchanged.push(tool.id.clone()),
+            }
+        }
+        for tool in &self.config.tools {
+            if !new_config
+                .tools
+                .iter()
+                .any(|new_tool| new_tool.id == tool.id)
+            {
+                outcome.applied_live.push(tool.id.clone());
+            }
+        }
+
+        self.config = new_config;
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(id: &str, command: &str) -> ToolConfig {
+        ToolConfig {
+            id: id.to_string(),
+            command: command.to_string(),
+            args: Vec::new(),
+            env: HashMap::default(),
+        }
+    }
+
+    #[test]
+    fn test_reload_classifies_changes() {
+        let mut daemon = Daemon::new(ForgeConfig {
+            tools: vec![tool("lint", "lint-bin"), tool("fmt", "fmt-bin")],
+        })
+        .unwrap();
+
+        let mut fmt_with_env = tool("fmt", "fmt-bin");
+        fmt_with_env.env.insert("LOG".into(), "debug".into());
+        let outcome = daemon
+            .reload_config(ForgeConfig {
+                tools: vec![
+                    tool("lint", "lint-bin-v2"),
+                    fmt_with_env,
+                    tool("test", "test-bin"),
+                ],
+            })
+            .unwrap();
+
+        assert_eq!(outcome.restart_required, vec!["lint".to_string()]);
+        assert_eq!(
+            outcome.applied_live,
+            vec!["fmt".to_string(), "test".to_string()]
+        );
+        assert!(outcome.unchanged.is_empty());
+        assert_eq!(daemon.config().tools.len(), 3);
+    }
+
+    #[test]
+    fn test_diagnostics_locate_every_problem_in_the_source() {
+        let source = "[[tools]]\nid = \"lint\"\ncommand = \"lint-bin\"\n\n[[tools]]\nid = \"lint\"\ncommand = \"\"\n";
+        let config = ForgeConfig {
+            tools: vec![tool("lint", "lint-bin"), tool("lint", "")],
+        };
+
+        let diagnostics = ConfigValidator.diagnostics(&config, source);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].message, "duplicate tool id: lint");
+        let duplicate_span = diagnostics[0].span.clone().unwrap();
+        assert_eq!(&source[duplicate_span.clone()], "\"lint\"");
+        assert!(
+            duplicate_span.start > source.find("\"lint\"").unwrap(),
+            "the second occurrence is flagged, not the legitimate first"
+        );
+        assert_eq!(diagnostics[1].message, "tool lint has an empty command");
+        assert_eq!(&source[diagnostics[1].span.clone().unwrap()], "\"\"");
+
+        assert!(
+            ConfigValidator
+                .diagnostics(
+                    &ForgeConfig {
+                        tools: vec![tool("lint", "lint-bin")]
+                    },
+                    source
+                )
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_invalid_reload_is_a_no_op() {
+        let initial = ForgeConfig {
+            tools: vec![tool("lint", "lint-bin")],
+        };
+        let mut daemon = Daemon::new(initial.clone()).unwrap();
+
+        let result = daemon.reload_config(ForgeConfig {
+            tools: vec![tool("a", "a-bin"), tool("a", "a-bin")],
+        });
+        assert!(matches!(result, Err(ForgeError::InvalidConfig(_))));
+        assert_eq!(daemon.config(), &initial);
+
+        let outcome = daemon.reload_config(initial).unwrap();
+        assert_eq!(outcome.unchanged, vec!["lint".to_string()]);
+    }
+}
@@ -0,0 +1,71 @@
+//! Developer-experience tooling runtime: orchestrates registered tools,
+//! snapshots project state, and runs long-lived daemon services.
+
+mod audit;
+mod auto_update;
+mod crdt;
+mod daemon;
+mod injection;
+mod lsp_bridge;
+mod metrics;
+mod orchestrator;
+mod pattern_index;
+mod platform_io;
+mod shutdown;
+mod snapshot;
+mod storage;
+mod tool_registry;
+
+pub use audit::*;
+pub use auto_update::*;
+pub use crdt::*;
+pub use daemon::*;
+pub use injection::*;
+pub use lsp_bridge::*;
+pub use metrics::*;
+pub use orchestrator::*;
+pub use pattern_index::*;
+pub use platform_io::*;
+pub use shutdown::*;
+pub use snapshot::*;
+pub use storage::*;
+pub use tool_registry::*;
+
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ForgeError {
+    #[error("tool not found: {0}")]
+    ToolNotFound(String),
+    #[error("dependency cycle involving tool: {0}")]
+    DependencyCycle(String),
+    #[error("tool {id} failed: {message}")]
+    ToolFailed { id: String, message: String },
+    #[error("invalid configuration: {0}")]
+    InvalidConfig(String),
+    #[error("blob not found: {0}")]
+    BlobNotFound(String),
+    #[error("branch not found: {0}")]
+    BranchNotFound(String),
+    #[error("snapshot not found: {0}")]
+    SnapshotNotFound(u64),
+    #[error("corrupt blob {id}: {message}")]
+    CorruptBlob { id: String, message: String },
+    #[error("update {0} failed signature verification; keeping the current binary")]
+    UpdateRejected(String),
+    #[error("component {name} failed integrity check: expected hash {expected}, got {actual}")]
+    ComponentIntegrity {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+    #[error("audit log broken at entry {sequence}: {message}")]
+    AuditChainBroken { sequence: u64, message: String },
+    #[error("io error at {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
@@ -0,0 +1,155 @@
+use crate::{ForgeError, storage::hash_bytes};
+use collections::HashMap;
+
+/// Describes a component available from the remote store, including the
+/// SHA-256 (hex) of its bytes as published. Fetched bytes that do not hash
+/// to `content_hash` are never cached or injected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComponentMetadata {
+    pub name: String,
+    pub version: String,
+    pub content_hash: String,
+}
+
+/// Where component bytes come from — an R2/S3-like object store in
+/// production, an in-memory map in tests. Implementations return the bytes
+/// as stored; the [`InjectionManager`] is responsible for verifying them.
+pub trait ComponentSource {
+    fn fetch(&self, metadata: &ComponentMetadata) -> Result<Vec<u8>, ForgeError>;
+}
+
+/// Fetches components, verifies them against their metadata hash, and caches
+/// verified bytes by component name.
+///
+/// The cache is re-verified on every read: if a component's metadata now
+/// advertises a different hash, the stale cached bytes are evicted and the
+/// new version fetched, so a metadata bump never serves old bytes.
+pub struct InjectionManager {
+    source: Box<dyn ComponentSource>,
+    cache: HashMap<String, Vec<u8>>,
+}
+
+impl InjectionManager {
+    pub fn new(source: Box<dyn ComponentSource>) -> Self {
+        Self {
+            source,
+            cache: HashMap::default(),
+        }
+    }
+
+    /// The component's verified bytes, from cache when the cached copy still
+    /// matches `metadata.content_hash`, otherwise freshly fetched. Fetched
+    /// bytes that hash differently — tampered or truncated — fail with
+    /// [`ForgeError::ComponentIntegrity`] and leave the cache untouched.
+    pub fn load_component(&mut self, metadata: &ComponentMetadata) -> Result<Vec<u8>, ForgeError> {
+        if let Some(cached) = self.cache.get(&metadata.name) {
+            if hash_bytes(cached) == metadata.content_hash {
+                return Ok(cached.clone());
+            }
+            self.cache.remove(&metadata.name);
+        }
+        let bytes = self.source.fetch(metadata)?;
+        let actual = hash_bytes(&bytes);
+        if actual != metadata.content_hash {
+            return Err(ForgeError::ComponentIntegrity {
+                name: metadata.name.clone(),
+                expected: metadata.content_hash.clone(),
+                actual,
+            });
+        }
+        self.cache.insert(metadata.name.clone(), bytes.clone());
+        Ok(bytes)
+    }
+
+    pub fn is_cached(&self, name: &str) -> bool {
+        self.cache.contains_key(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    struct FakeSource {
+        bytes: Vec<u8>,
+        fetch_count: Rc<Cell<usize>>,
+    }
+
+    impl ComponentSource for FakeSource {
+        fn fetch(&self, _metadata: &ComponentMetadata) -> Result<Vec<u8>, ForgeError> {
+            self.fetch_count.set(self.fetch_count.get() + 1);
+            Ok(self.bytes.clone())
+        }
+    }
+
+    fn metadata_for(name: &str, bytes: &[u8]) -> ComponentMetadata {
+        ComponentMetadata {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            content_hash: hash_bytes(bytes),
+        }
+    }
+
+    #[test]
+    fn test_verified_component_is_cached_and_served_without_refetching() {
+        let fetch_count = Rc::new(Cell::new(0));
+        let mut manager = InjectionManager::new(Box::new(FakeSource {
+            bytes: b"component body".to_vec(),
+            fetch_count: fetch_count.clone(),
+        }));
+        let metadata = metadata_for("editor-panel", b"component body");
+
+        assert_eq!(
+            manager.load_component(&metadata).unwrap(),
+            b"component body"
+        );
+        assert_eq!(
+            manager.load_component(&metadata).unwrap(),
+            b"component body"
+        );
+        assert_eq!(fetch_count.get(), 1, "second load served from cache");
+    }
+
+    #[test]
+    fn test_mismatched_fetch_fails_and_caches_nothing() {
+        let fetch_count = Rc::new(Cell::new(0));
+        let mut manager = InjectionManager::new(Box::new(FakeSource {
+            bytes: b"truncated or tampered".to_vec(),
+            fetch_count,
+        }));
+        let metadata = metadata_for("editor-panel", b"what was published");
+
+        match manager.load_component(&metadata) {
+            Err(ForgeError::ComponentIntegrity {
+                name,
+                expected,
+                actual,
+            }) => {
+                assert_eq!(name, "editor-panel");
+                assert_eq!(expected, hash_bytes(b"what was published"));
+                assert_eq!(actual, hash_bytes(b"truncated or tampered"));
+            }
+            other => panic!("expected an integrity error, got {other:?}"),
+        }
+        assert!(!manager.is_cached("editor-panel"));
+    }
+
+    #[test]
+    fn test_stale_cached_bytes_are_evicted_when_metadata_changes() {
+        let fetch_count = Rc::new(Cell::new(0));
+        let mut manager = InjectionManager::new(Box::new(FakeSource {
+            bytes: b"version two".to_vec(),
+            fetch_count: fetch_count.clone(),
+        }));
+
+        // Seed the cache with version one, then advertise version two.
+        manager
+            .cache
+            .insert("editor-panel".to_string(), b"version one".to_vec());
+        let metadata = metadata_for("editor-panel", b"version two");
+        assert_eq!(manager.load_component(&metadata).unwrap(), b"version two");
+        assert_eq!(fetch_count.get(), 1, "stale cache entry forced a refetch");
+    }
+}
@@ -0,0 +1,193 @@
+//! Forwards daemon-side analysis to an LSP client. The daemon's live config
+//! watcher revalidates `dx.toml` on every edit and pushes the result here;
+//! the bridge turns it into `textDocument/publishDiagnostics` notifications
+//! for a transport to drain and write to the editor, so config mistakes show
+//! up as squiggles while the file is being typed.
+
+use crate::{ConfigValidator, ForgeConfig};
+
+/// The `publishDiagnostics` severity for an error.
+pub const LSP_SEVERITY_ERROR: u32 = 1;
+
+/// A zero-based position, with `character` counted in UTF-16 code units as
+/// the LSP specification requires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LspPosition {
+    pub line: u32,
+    pub character: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LspRange {
+    pub start: LspPosition,
+    pub end: LspPosition,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LspDiagnostic {
+    pub range: LspRange,
+    pub severity: u32,
+    pub message: String,
+}
+
+/// A server-to-client notification queued on the bridge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LspNotification {
+    PublishDiagnostics {
+        uri: String,
+        diagnostics: Vec<LspDiagnostic>,
+    },
+}
+
+pub struct LspBridge {
+    validator: ConfigValidator,
+    outgoing: Vec<LspNotification>,
+}
+
+impl LspBridge {
+    pub fn new() -> Self {
+        Self {
+            validator: ConfigValidator,
+            outgoing: Vec::new(),
+        }
+    }
+
+    /// Validates `config` against the `source` text it was parsed from and
+    /// queues a `publishDiagnostics` notification for `uri`. A clean
+    /// validation still queues one, with an empty diagnostics array: that is
+    /// how LSP clears squiggles left by a previous revision of the file.
+    pub fn publish_config_diagnostics(&mut self, uri: &str, config: &ForgeConfig, source: &str) {
+        let diagnostics = self
+            .validator
+            .diagnostics(config, source)
+            .into_iter()
+            .map(|diagnostic| LspDiagnostic {
+                range: diagnostic
+                    .span
+                    .map_or(FILE_START, |span| range_in(source, span)),
+                severity: LSP_SEVERITY_ERROR,
+                message: diagnostic.message,
+            })
+            .collect();
+        self.outgoing.push(LspNotification::PublishDiagnostics {
+            uri: uri.to_string(),
+            diagnostics,
+        });
+    }
+
+    /// Takes every queued notification, in the order it was queued, leaving
+    /// the queue empty.
+    pub fn drain_notifications(&mut self) -> Vec<LspNotification> {
+        std::mem::take(&mut self.outgoing)
+    }
+}
+
+impl Default for LspBridge {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Where a diagnostic without a source span lands: a zero-width range at the
+/// start of the file, which editors render as a whole-file marker.
+const FILE_START: LspRange = LspRange {
+    start: LspPosition {
+        line: 0,
+        character: 0,
+    },
+    end: LspPosition {
+        line: 0,
+        character: 0,
+    },
+};
+
+fn range_in(source: &str, span: std::ops::Range<usize>) -> LspRange {
+    LspRange {
+        start: position_in(source, span.start),
+        end: position_in(source, span.end),
+    }
+}
+
+fn position_in(source: &str, offset: usize) -> LspPosition {
+    let prefix = source.get(..offset).unwrap_or(source);
+    let line_start = prefix.rfind('\n').map_or(0, |newline| newline + 1);
+    LspPosition {
+        line: prefix.matches('\n').count() as u32,
+        character: prefix[line_start..].encode_utf16().count() as u32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ToolConfig;
+    use collections::HashMap;
+
+    fn tool(id: &str, command: &str) -> ToolConfig {
+        ToolConfig {
+            id: id.to_string(),
+            command: command.to_string(),
+            args: Vec::new(),
+            env: HashMap::default(),
+        }
+    }
+
+    const DUPLICATE_SOURCE: &str = "[[tools]]\nid = \"lint\"\ncommand = \"lint-bin\"\n\n[[tools]]\nid = \"lint\"\ncommand = \"fix-bin\"\n";
+
+    #[test]
+    fn test_validation_error_produces_a_diagnostic_with_the_right_range() {
+        let config = ForgeConfig {
+            tools: vec![tool("lint", "lint-bin"), tool("lint", "fix-bin")],
+        };
+        let mut bridge = LspBridge::new();
+        bridge.publish_config_diagnostics("file:///project/dx.toml", &config, DUPLICATE_SOURCE);
+
+        let notifications = bridge.drain_notifications();
+        assert_eq!(notifications.len(), 1);
+        let LspNotification::PublishDiagnostics { uri, diagnostics } = &notifications[0];
+        assert_eq!(uri, "file:///project/dx.toml");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "duplicate tool id: lint");
+        assert_eq!(diagnostics[0].severity, LSP_SEVERITY_ERROR);
+        // The second `"lint"`, on the zero-based sixth line.
+        assert_eq!(
+            diagnostics[0].range,
+            LspRange {
+                start: LspPosition {
+                    line: 5,
+                    character: 5
+                },
+                end: LspPosition {
+                    line: 5,
+                    character: 11
+                },
+            }
+        );
+        assert!(bridge.drain_notifications().is_empty());
+    }
+
+    #[test]
+    fn test_fixing_all_errors_publishes_an_empty_array_to_clear_squiggles() {
+        let mut bridge = LspBridge::new();
+        let broken = ForgeConfig {
+            tools: vec![tool("lint", "lint-bin"), tool("lint", "fix-bin")],
+        };
+        bridge.publish_config_diagnostics("file:///project/dx.toml", &broken, DUPLICATE_SOURCE);
+
+        let fixed_source = "[[tools]]\nid = \"lint\"\ncommand = \"lint-bin\"\n\n[[tools]]\nid = \"fix\"\ncommand = \"fix-bin\"\n";
+        let fixed = ForgeConfig {
+            tools: vec![tool("lint", "lint-bin"), tool("fix", "fix-bin")],
+        };
+        bridge.publish_config_diagnostics("file:///project/dx.toml", &fixed, fixed_source);
+
+        let notifications = bridge.drain_notifications();
+        assert_eq!(notifications.len(), 2);
+        let LspNotification::PublishDiagnostics { diagnostics, .. } = &notifications[0];
+        assert!(!diagnostics.is_empty());
+        let LspNotification::PublishDiagnostics { diagnostics, .. } = &notifications[1];
+        assert!(
+            diagnostics.is_empty(),
+            "the clean revision publishes an explicit empty array"
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use crate::ForgeError;
+use collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Default EWMA weight given to the latest observation.
+const DEFAULT_SMOOTHING: f64 = 0.3;
+/// Default slowdown versus the baseline, in percent, before a run is
+/// flagged as a regression.
+const DEFAULT_REGRESSION_THRESHOLD_PERCENT: f64 = 50.0;
+
+/// A tool ran markedly slower than its rolling baseline. Surfaced in
+/// [`ExecutionReport::regressions`](crate::ExecutionReport::regressions) as a
+/// warning; it never fails the run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PerformanceRegression {
+    pub tool_id: String,
+    pub baseline_ms: f64,
+    pub observed_ms: f64,
+    /// How far past the baseline the observed time landed, in percent.
+    pub regression_percent: f64,
+}
+
+/// Persists per-tool timing baselines under `.dx/` and flags runs that
+/// regress beyond a configurable percentage of their rolling baseline.
+///
+/// Baselines are exponentially weighted moving averages, so a tool that
+/// legitimately got slower converges to its new normal over a few runs
+/// instead of being flagged forever.
+pub struct MetricsCollector {
+    path: PathBuf,
+    smoothing: f64,
+    regression_threshold_percent: f64,
+    baselines: HashMap<String, f64>,
+}
+
+impl MetricsCollector {
+    /// Loads the timing baselines stored under `<project_root>/.dx/`, or
+    /// starts empty when none have been recorded yet.
+    pub fn load(project_root: impl AsRef<Path>) -> Result<Self, ForgeError> {
+        let path = project_root.as_ref().join(".dx").join("tool_timings.tsv");
+        let mut baselines = HashMap::default();
+        match fs::read_to_string(&path) {
+            Ok(contents) => {
+                for line in contents.lines() {
+                    // A line an older or newer version wrote differently is
+                    // just a lost baseline, not a reason to fail the build.
+                    if let Some((tool_id, baseline)) = line.split_once('\t')
+                        && let Ok(baseline_ms) = baseline.parse::<f64>()
+                        && baseline_ms.is_finite()
+                    {
+                        baselines.insert(tool_id.to_string(), baseline_ms);
+                    }
+                }
+            }
+            Err(source) if source.kind() == std::io::ErrorKind::NotFound => {}
+            Err(source) => return Err(ForgeError::Io { path, source }),
+        }
+        Ok(Self {
+            path,
+            smoothing: DEFAULT_SMOOTHING,
+            regression_threshold_percent: DEFAULT_REGRESSION_THRESHOLD_PERCENT,
+            baselines,
+        })
+    }
+
+    /// Sets how far past the baseline, in percent, a run may land before it
+    /// is flagged.
+    pub fn with_regression_threshold(mut self, percent: f64) -> Self {
+        self.regression_threshold_percent = percent;
+        self
+    }
+
+    /// Sets the EWMA weight given to the latest observation, in `0.0..=1.0`.
+    /// Higher values track changes faster but flag noisy tools more often.
+    pub fn with_smoothing(mut self, smoothing: f64) -> Self {
+        self.smoothing = smoothing.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn baseline_ms(&self, tool_id: &str) -> Option<f64> {
+        self.baselines.get(tool_id).copied()
+    }
+
+    /// Folds one observed execution time into the tool's baseline, returning
+    /// a [`PerformanceRegression`] when it exceeded the previous baseline by
+    /// more than the configured threshold. The first observation for a tool
+    /// seeds its baseline and is never flagged.
+    pub fn record(&mut self, tool_id: &str, observed_ms: f64) -> Option<PerformanceRegression> {
+        if !observed_ms.is_finite() || observed_ms < 0.0 {
+            return None;
+        }
+        let Some(baseline_ms) = self.baselines.get(tool_id).copied() else {
+            self.baselines.insert(tool_id.to_string(), observed_ms);
+            return None;
+        };
+        let updated = self.smoothing * observed_ms + (1.0 - self.smoothing) * baseline_ms;
+        self.baselines.insert(tool_id.to_string(), updated);
+
+        // Sub-millisecond baselines would flag on scheduler jitter alone, so
+        // regressions are judged against a 1ms floor.
+        let floor_ms = baseline_ms.max(1.0);
+        let allowed_ms = floor_ms * (1.0 + self.regression_threshold_percent / 100.0);
+        if observed_ms > allowed_ms {
+            Some(PerformanceRegression {
+                tool_id: tool_id.to_string(),
+                baseline_ms,
+                observed_ms,
+                regression_percent: (observed_ms - baseline_ms) / floor_ms * 100.0,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Writes the baselines back under `.dx/`, creating the directory on
+    /// first use. Tools are written in sorted order so the file diffs
+    /// cleanly between runs.
+    pub fn save(&self) -> Result<(), ForgeError> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(|source| ForgeError::Io {
+                path: parent.to_path_buf(),
+                source,
+            })?;
+        }
+        let mut baselines: Vec<(&String, &f64)> = self.baselines.iter().collect();
+        baselines.sort_by_key(|(tool_id, _)| *tool_id);
+        let mut contents = String::new();
+        for (tool_id, baseline_ms) in baselines {
+            contents.push_str(&format!("{tool_id}\t{baseline_ms}\n"));
+        }
+        fs::write(&self.path, contents).map_err(|source| ForgeError::Io {
+            path: self.path.clone(),
+            source,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slow_run_against_a_fast_baseline_is_flagged() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut collector = MetricsCollector::load(dir.path()).unwrap();
+
+        assert_eq!(collector.record("bundle", 10.0), None, "first run seeds");
+        assert_eq!(collector.record("bundle", 11.0), None, "within threshold");
+
+        let regression = collector
+            .record("bundle", 100.0)
+            .expect("a 10x slowdown must be flagged");
+        assert_eq!(regression.tool_id, "bundle");
+        assert!(regression.baseline_ms < 11.0);
+        assert_eq!(regression.observed_ms, 100.0);
+        assert!(regression.regression_percent > 800.0);
+    }
+
+    #[test]
+    fn test_baseline_converges_to_a_new_normal() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut collector = MetricsCollector::load(dir.path()).unwrap();
+        collector.record("lint", 10.0);
+        assert!(collector.record("lint", 40.0).is_some());
+        for _ in 0..20 {
+            collector.record("lint", 40.0);
+        }
+        assert_eq!(
+            collector.record("lint", 40.0),
+            None,
+            "a sustained slowdown becomes the baseline"
+        );
+    }
+
+    #[test]
+    fn test_baselines_round_trip_through_dx_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut collector = MetricsCollector::load(dir.path()).unwrap();
+        collector.record("codegen", 25.0);
+        collector.record("bundle", 400.0);
+        collector.save().unwrap();
+
+        let reloaded = MetricsCollector::load(dir.path()).unwrap();
+        assert_eq!(reloaded.baseline_ms("codegen"), Some(25.0));
+        assert_eq!(reloaded.baseline_ms("bundle"), Some(400.0));
+        assert_eq!(reloaded.baseline_ms("lint"), None);
+
+        let contents = std::fs::read_to_string(dir.path().join(".dx/tool_timings.tsv")).unwrap();
+        assert_eq!(contents, "bundle\t400\ncodegen\t25\n");
+    }
+}
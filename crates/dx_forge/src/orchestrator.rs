@@ -0,0 +1,462 @@
+use crate::{ForgeError, MetricsCollector, PerformanceRegression, ShutdownHandler, ShutdownState};
+use collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Process exit codes reported by [`Orchestrator::execute_all`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(i32)]
+pub enum ExitCode {
+    #[default]
+    Success = 0,
+    ToolFailed = 1,
+    /// A shutdown was requested and at least one tool was aborted or never
+    /// scheduled. 128 + SIGINT, matching shell conventions.
+    ShutdownPartial = 130,
+}
+
+pub struct ToolExecution {
+    pub id: String,
+    pub dependencies: Vec<String>,
+    /// Hash of the tool's inputs, recorded in the execution trace so runs
+    /// can be diffed for nondeterminism.
+    pub inputs_hash: Option<String>,
+    /// Paths the tool declares it produces; copied into the trace.
+    pub outputs: Vec<String>,
+    run: Box<dyn FnOnce() -> anyhow::Result<()> + Send>,
+}
+
+impl ToolExecution {
+    pub fn new(
+        id: impl Into<String>,
+        dependencies: Vec<String>,
+        run: impl FnOnce() -> anyhow::Result<()> + Send + 'static,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            dependencies,
+            inputs_hash: None,
+            outputs: Vec::new(),
+            run: Box::new(run),
+        }
+    }
+
+    pub fn with_inputs_hash(mut self, inputs_hash: impl Into<String>) -> Self {
+        self.inputs_hash = Some(inputs_hash.into());
+        self
+    }
+
+    pub fn with_outputs(mut self, outputs: Vec<String>) -> Self {
+        self.outputs = outputs;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceStatus {
+    Completed,
+    Failed,
+    Aborted,
+    Skipped,
+}
+
+/// One tool's row in the [`ExecutionTrace`]. Times are milliseconds since
+/// the run started; absent for tools that never ran (skipped) or never
+/// finished (aborted end time).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceEntry {
+    pub tool_id: String,
+    pub started_ms: Option<u64>,
+    pub ended_ms: Option<u64>,
+    pub status: TraceStatus,
+    pub inputs_hash: Option<String>,
+    pub outputs: Vec<String>,
+    /// Why a skipped tool never ran.
+    pub skip_reason: Option<String>,
+}
+
+/// A machine-readable record of one [`Orchestrator::execute_all_traced`]
+/// run, serializable to JSON: what ran, in what order, and why anything
+/// didn't. Diffing traces between runs exposes nondeterminism.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionTrace {
+    /// The resolved dependency order tools were eligible to run in.
+    pub dependency_order: Vec<String>,
+    /// One entry per tool: finished tools in observed completion order,
+    /// then aborted, then skipped.
+    pub entries: Vec<TraceEntry>,
+}
+
+#[derive(Debug, Default)]
+pub struct ExecutionReport {
+    /// Tools that ran to completion, in completion order.
+    pub completed: Vec<String>,
+    /// Tools that returned an error.
+    pub failed: Vec<String>,
+    /// Tools still running when the shutdown grace deadline expired.
+    pub aborted: Vec<String>,
+    /// Tools that were never scheduled because shutdown began first.
+    pub skipped: Vec<String>,
+    /// Tools that completed but ran markedly slower than their rolling
+    /// baseline; empty unless a [`MetricsCollector`] is attached.
+    pub regressions: Vec<PerformanceRegression>,
+    pub exit_code: ExitCode,
+}
+
+#[derive(Default)]
+pub struct Orchestrator {
+    tools: Vec<ToolExecution>,
+    metrics: Option<MetricsCollector>,
+}
+
+impl Orchestrator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register_tool(&mut self, tool: ToolExecution) {
+        self.tools.push(tool);
+    }
+
+    /// Attaches a timing baseline collector. Each completed tool's execution
+    /// time is folded into its baseline, regressions are surfaced in the
+    /// report, and the updated baselines are persisted when the run ends.
+    pub fn set_metrics_collector(&mut self, collector: MetricsCollector) {
+        self.metrics = Some(collector);
+    }
+
+    /// Runs every registered tool in dependency order with up to
+    /// `concurrency` tools in flight. Cooperates with `shutdown`: once a
+    /// shutdown is requested no new tools are scheduled, in-flight tools get
+    /// until the grace deadline to finish, and anything still running after
+    /// that is reported as aborted.
+    pub fn execute_all(
+        &mut self,
+        shutdown: &ShutdownHandler,
+        concurrency: usize,
+    ) -> Result<ExecutionReport, ForgeError> {
+        self.execute_all_traced(shutdown, concurrency)
+            .map(|(report, _)| report)
+    }
+
+    /// Like [`execute_all`](Self::execute_all), but also records a
+    /// structured [`ExecutionTrace`] of the run.
+    pub fn execute_all_traced(
+        &mut self,
+        shutdown: &ShutdownHandler,
+        concurrency: usize,
+    ) -> Result<(ExecutionReport, ExecutionTrace), ForgeError> {
+        let concurrency = concurrency.max(1);
+        let mut pending = self.topological_order()?;
+        let epoch = Instant::now();
+        let mut trace = ExecutionTrace {
+            dependency_order: pending.iter().map(|tool| tool.id.clone()).collect(),
+            entries: Vec::new(),
+        };
+        let mut tool_metadata: HashMap<String, (Option<String>, Vec<String>)> = pending
+            .iter()
+            .map(|tool| {
+                (
+                    tool.id.clone(),
+                    (tool.inputs_hash.clone(), tool.outputs.clone()),
+                )
+            })
+            .collect();
+        let mut started_ms: HashMap<String, u64> = HashMap::default();
+        let mut finish_events: Vec<(String, TraceStatus, u64)> = Vec::new();
+        let mut skip_reasons: Vec<(String, String)> = Vec::new();
+        let mut report = ExecutionReport::default();
+        let mut in_flight = Vec::new();
+        let (completion_tx, completion_rx) = mpsc::channel::<(String, anyhow::Result<()>)>();
+
+        loop {
+            let shutting_down = shutdown.state() == ShutdownState::ShuttingDown;
+            if !shutting_down {
+                while in_flight.len() < concurrency {
+                    let Some(ready_index) = pending.iter().position(|tool: &ToolExecution| {
+                        tool.dependencies
+                            .iter()
+                            .all(|dependency| report.completed.contains(dependency))
+                    }) else {
+                        break;
+                    };
+                    let tool = pending.remove(ready_index);
+                    let completion_tx = completion_tx.clone();
+                    let id = tool.id.clone();
+                    let run = tool.run;
+                    started_ms.insert(id.clone(), epoch.elapsed().as_millis() as u64);
+                    in_flight.push(id.clone());
+                    thread::spawn(move || {
+                        let result = run();
+                        // The receiver only goes away once the grace deadline
+                        // has expired and this tool was written off as aborted.
+                        completion_tx.send((id, result)).ok();
+                    });
+                }
+            }
+
+            if in_flight.is_empty() {
+                if shutting_down || pending.is_empty() {
+                    break;
+                }
+                // Remaining tools depend on a tool that failed; they can never
+                // become ready.
+                for tool in pending.drain(..) {
+                    skip_reasons.push((tool.id.clone(), "a dependency failed".into()));
+                    report.skipped.push(tool.id);
+                }
+                break;
+            }
+
+            let timeout = if let Some(deadline) = shutdown.deadline() {
+                deadline.saturating_duration_since(Instant::now())
+            } else {
+                Duration::from_millis(50)
+            };
+            match completion_rx.recv_timeout(timeout) {
+                Ok((id, result)) => {
+                    in_flight.retain(|in_flight_id| in_flight_id != &id);
+                    let ended = epoch.elapsed().as_millis() as u64;
+                    match result {
+                        Ok(()) => {
+                            if let Some(collector) = &mut self.metrics
+                                && let Some(started) = started_ms.get(&id).copied()
+                                && let Some(regression) =
+                                    collector.record(&id, ended.saturating_sub(started) as f64)
+                            {
+                                report.regressions.push(regression);
+                            }
+                            finish_events.push((id.clone(), TraceStatus::Completed, ended));
+                            report.completed.push(id);
+                        }
+                        Err(_) => {
+                            finish_events.push((id.clone(), TraceStatus::Failed, ended));
+                            report.failed.push(id);
+                        }
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    if shutdown
+                        .deadline()
+                        .is_some_and(|deadline| Instant::now() >= deadline)
+                    {
+                        report.aborted.append(&mut in_flight);
+                        break;
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        let shutting_down = shutdown.state() == ShutdownState::ShuttingDown;
+        for tool in pending.drain(..) {
+            let reason = if shutting_down {
+                "shutdown requested before scheduling"
+            } else {
+                "a dependency failed"
+            };
+            skip_reasons.push((tool.id.clone(), reason.into()));
+            report.skipped.push(tool.id);
+        }
+
+        report.exit_code = if !report.aborted.is_empty() || !report.skipped.is_empty() {
+            ExitCode::ShutdownPartial
+        } else if !report.failed.is_empty() {
+            ExitCode::ToolFailed
+        } else {
+            ExitCode::Success
+        };
+
+        let mut entry_for = |id: &str, status, started, ended, skip_reason: Option<String>| {
+            let (inputs_hash, outputs) = tool_metadata.remove(id).unwrap_or_default();
+            TraceEntry {
+                tool_id: id.to_string(),
+                started_ms: started,
+                ended_ms: ended,
+                status,
+                inputs_hash,
+                outputs,
+                skip_reason,
+            }
+        };
+        for (id, status, ended) in &finish_events {
+            let started = started_ms.get(id).copied();
+            trace
+                .entries
+                .push(entry_for(id, *status, started, Some(*ended), None));
+        }
+        for id in &report.aborted {
+            let started = started_ms.get(id).copied();
+            trace
+                .entries
+                .push(entry_for(id, TraceStatus::Aborted, started, None, None));
+        }
+        for (id, reason) in skip_reasons {
+            trace.entries.push(entry_for(
+                &id,
+                TraceStatus::Skipped,
+                None,
+                None,
+                Some(reason),
+            ));
+        }
+
+        if let Some(collector) = &self.metrics {
+            collector.save()?;
+        }
+
+        Ok((report, trace))
+    }
+
+    fn topological_order(&mut self) -> Result<Vec<ToolExecution>, ForgeError> {
+        for tool in &self.tools {
+            for dependency in &tool.dependencies {
+                if !self.tools.iter().any(|other| &other.id == dependency) {
+                    return Err(ForgeError::ToolNotFound(dependency.clone()));
+                }
+            }
+        }
+        let mut ordered: Vec<ToolExecution> = Vec::with_capacity(self.tools.len());
+        let mut remaining: Vec<ToolExecution> = self.tools.drain(..).collect();
+        while !remaining.is_empty() {
+            let Some(ready_index) = remaining.iter().position(|tool| {
+                tool.dependencies
+                    .iter()
+                    .all(|dependency| ordered.iter().any(|other| &other.id == dependency))
+            }) else {
+                return Err(ForgeError::DependencyCycle(remaining[0].id.clone()));
+            };
+            ordered.push(remaining.remove(ready_index));
+        }
+        Ok(ordered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ShutdownConfig;
+
+    fn instant_tool(id: &str, dependencies: Vec<String>) -> ToolExecution {
+        ToolExecution::new(id, dependencies, || Ok(()))
+    }
+
+    #[test]
+    fn test_execute_all_respects_dependencies() {
+        let mut orchestrator = Orchestrator::new();
+        orchestrator.register_tool(instant_tool("lint", vec!["codegen".into()]));
+        orchestrator.register_tool(instant_tool("codegen", vec![]));
+        let shutdown = ShutdownHandler::default();
+        let report = orchestrator.execute_all(&shutdown, 2).unwrap();
+        assert_eq!(report.completed, vec!["codegen", "lint"]);
+        assert_eq!(report.exit_code, ExitCode::Success);
+    }
+
+    #[test]
+    fn test_trace_records_execution_sequence_and_skip_reasons() {
+        let mut orchestrator = Orchestrator::new();
+        orchestrator.register_tool(instant_tool("codegen", vec![]).with_inputs_hash("abc123"));
+        orchestrator.register_tool(ToolExecution::new("lint", vec!["codegen".into()], || {
+            Err(std::io::Error::other("lint found problems").into())
+        }));
+        orchestrator.register_tool(
+            instant_tool("bundle", vec!["lint".into()]).with_outputs(vec!["dist/app.js".into()]),
+        );
+
+        let shutdown = ShutdownHandler::default();
+        let (report, trace) = orchestrator.execute_all_traced(&shutdown, 1).unwrap();
+        assert_eq!(report.exit_code, ExitCode::ShutdownPartial);
+
+        assert_eq!(trace.dependency_order, vec!["codegen", "lint", "bundle"]);
+        let sequence: Vec<(&str, TraceStatus)> = trace
+            .entries
+            .iter()
+            .map(|entry| (entry.tool_id.as_str(), entry.status))
+            .collect();
+        assert_eq!(
+            sequence,
+            vec![
+                ("codegen", TraceStatus::Completed),
+                ("lint", TraceStatus::Failed),
+                ("bundle", TraceStatus::Skipped),
+            ]
+        );
+        assert_eq!(trace.entries[0].inputs_hash.as_deref(), Some("abc123"));
+        assert!(trace.entries[0].ended_ms >= trace.entries[0].started_ms);
+        assert_eq!(trace.entries[2].outputs, vec!["dist/app.js".to_string()]);
+        assert_eq!(
+            trace.entries[2].skip_reason.as_deref(),
+            Some("a dependency failed")
+        );
+        assert_eq!(trace.entries[2].started_ms, None);
+    }
+
+    #[test]
+    fn test_slow_rerun_against_a_persisted_baseline_flags_a_regression() {
+        let dir = tempfile::tempdir().unwrap();
+        let shutdown = ShutdownHandler::default();
+
+        let mut baseline_run = Orchestrator::new();
+        baseline_run.set_metrics_collector(MetricsCollector::load(dir.path()).unwrap());
+        baseline_run.register_tool(instant_tool("bundle", vec![]));
+        let report = baseline_run.execute_all(&shutdown, 1).unwrap();
+        assert_eq!(report.completed, vec!["bundle"]);
+        assert!(
+            report.regressions.is_empty(),
+            "first run seeds the baseline"
+        );
+
+        let mut slow_run = Orchestrator::new();
+        slow_run.set_metrics_collector(MetricsCollector::load(dir.path()).unwrap());
+        slow_run.register_tool(ToolExecution::new("bundle", vec![], || {
+            thread::sleep(Duration::from_millis(300));
+            Ok(())
+        }));
+        let report = slow_run.execute_all(&shutdown, 1).unwrap();
+        assert_eq!(report.completed, vec!["bundle"]);
+        assert_eq!(
+            report.exit_code,
+            ExitCode::Success,
+            "a regression only warns"
+        );
+        assert_eq!(report.regressions.len(), 1);
+        assert_eq!(report.regressions[0].tool_id, "bundle");
+        assert!(report.regressions[0].observed_ms > report.regressions[0].baseline_ms);
+    }
+
+    #[test]
+    fn test_shutdown_honors_grace_deadline_and_reports_partial_completion() {
+        let mut orchestrator = Orchestrator::new();
+        orchestrator.register_tool(instant_tool("fast", vec![]));
+        orchestrator.register_tool(ToolExecution::new("slow", vec!["fast".into()], || {
+            thread::sleep(Duration::from_secs(5));
+            Ok(())
+        }));
+        orchestrator.register_tool(instant_tool("after", vec!["slow".into()]));
+
+        let shutdown = ShutdownHandler::new(ShutdownConfig {
+            grace: Duration::from_millis(200),
+        });
+        let shutdown_trigger = shutdown.clone();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            shutdown_trigger.request_shutdown();
+        });
+
+        let started_at = Instant::now();
+        let report = orchestrator.execute_all(&shutdown, 1).unwrap();
+        let elapsed = started_at.elapsed();
+
+        assert!(
+            elapsed < Duration::from_secs(2),
+            "deadline not honored, took {elapsed:?}"
+        );
+        assert_eq!(report.completed, vec!["fast"]);
+        assert_eq!(report.aborted, vec!["slow"]);
+        assert_eq!(report.skipped, vec!["after"]);
+        assert_eq!(report.exit_code, ExitCode::ShutdownPartial);
+    }
+}
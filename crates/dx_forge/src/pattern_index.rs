@@ -0,0 +1,320 @@
+use crate::storage::hash_bytes;
+use crate::{BlobId, BlobStore, ForgeError};
+use collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const INDEX_HEADER: &str = "dx-pattern-index v1";
+
+/// One occurrence of a configured pattern in a scanned file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternMatch {
+    pub pattern: String,
+    /// 1-based line number of the occurrence.
+    pub line: usize,
+}
+
+struct IndexedFile {
+    /// Content hash at the time the file was last scanned; a file whose hash
+    /// still matches is skipped on the next scan.
+    hash: String,
+    matches: Vec<PatternMatch>,
+}
+
+/// Finds literal patterns in project files, keeping a per-file index keyed by
+/// content hash so repeated scans only re-read files that changed. The index
+/// survives daemon restarts via [`save_index`](Self::save_index) and
+/// [`load_index`](Self::load_index), which persist it through the
+/// [`BlobStore`].
+pub struct PatternDetector {
+    patterns: Vec<String>,
+    index: HashMap<PathBuf, IndexedFile>,
+}
+
+impl PatternDetector {
+    pub fn new(patterns: Vec<String>) -> Self {
+        Self {
+            patterns,
+            index: HashMap::default(),
+        }
+    }
+
+    /// Scans `files`, re-reading only those whose content hash changed since
+    /// they were last indexed; indexed files absent from `files` are dropped.
+    /// Returns how many files were actually rescanned.
+    pub fn scan(&mut self, files: &[PathBuf]) -> Result<usize, ForgeError> {
+        self.index
+            .retain(|path, _| files.iter().any(|file| file == path));
+        let mut rescanned = 0;
+        for file in files {
+            let bytes = fs::read(file).map_err(|source| ForgeError::Io {
+                path: file.clone(),
+                source,
+            })?;
+            let hash = hash_bytes(&bytes);
+            if self
+                .index
+                .get(file)
+                .is_some_and(|indexed| indexed.hash == hash)
+            {
+                continue;
+            }
+            let matches = scan_bytes(&self.patterns, &bytes);
+            self.index
+                .insert(file.clone(), IndexedFile { hash, matches });
+            rescanned += 1;
+        }
+        Ok(rescanned)
+    }
+
+    pub fn matches_for(&self, path: &Path) -> Option<&[PatternMatch]> {
+        self.index
+            .get(path)
+            .map(|indexed| indexed.matches.as_slice())
+    }
+
+    pub fn indexed_files(&self) -> impl Iterator<Item = &Path> {
+        self.index.keys().map(|path| path.as_path())
+    }
+
+    /// Persists the index to the blob store and returns its address. The
+    /// serialization is deterministic (files sorted by path), so an unchanged
+    /// index always maps to the same blob.
+    pub fn save_index(&self, store: &BlobStore) -> Result<BlobId, ForgeError> {
+        let mut output = String::new();
+        output.push_str(INDEX_HEADER);
+        output.push('\n');
+        for pattern in &self.patterns {
+            output.push_str(&format!("pattern\t{}\n", escape(pattern)));
+        }
+        let mut files: Vec<(&PathBuf, &IndexedFile)> = self.index.iter().collect();
+        files.sort_by_key(|(path, _)| *path);
+        for (path, indexed) in files {
+            output.push_str(&format!(
+                "file\t{}\t{}\n",
+                indexed.hash,
+                escape(&path.display().to_string())
+            ));
+            for pattern_match in &indexed.matches {
+                output.push_str(&format!(
+                    "match\t{}\t{}\n",
+                    pattern_match.line,
+                    escape(&pattern_match.pattern)
+                ));
+            }
+        }
+        store.store(output.as_bytes())
+    }
+
+    /// Restores an index persisted by [`save_index`](Self::save_index).
+    /// Entries for files that no longer exist are purged, and if `patterns`
+    /// differs from the set the index was built with the entries are
+    /// discarded entirely — their matches were found by the wrong patterns,
+    /// so the next [`scan`](Self::scan) starts from scratch.
+    pub fn load_index(
+        patterns: Vec<String>,
+        store: &BlobStore,
+        id: &BlobId,
+    ) -> Result<Self, ForgeError> {
+        let corrupt = |message: &str| ForgeError::CorruptBlob {
+            id: id.0.clone(),
+            message: message.into(),
+        };
+        let bytes = store.read(id)?;
+        let text = String::from_utf8(bytes).map_err(|_| corrupt("index is not utf-8"))?;
+        let mut lines = text.lines();
+        if lines.next() != Some(INDEX_HEADER) {
+            return Err(corrupt("missing pattern index header"));
+        }
+
+        let mut saved_patterns = Vec::new();
+        let mut index: HashMap<PathBuf, IndexedFile> = HashMap::default();
+        let mut current_file: Option<PathBuf> = None;
+        for line in lines {
+            let (kind, rest) = line
+                .split_once('\t')
+                .ok_or_else(|| corrupt("malformed index line"))?;
+            match kind {
+                "pattern" => saved_patterns.push(unescape(rest)),
+                "file" => {
+                    let (hash, path) = rest
+                        .split_once('\t')
+                        .ok_or_else(|| corrupt("malformed file line"))?;
+                    let path = PathBuf::from(unescape(path));
+                    index.insert(
+                        path.clone(),
+                        IndexedFile {
+                            hash: hash.to_string(),
+                            matches: Vec::new(),
+                        },
+                    );
+                    current_file = Some(path);
+                }
+                "match" => {
+                    let (line_number, pattern) = rest
+                        .split_once('\t')
+                        .ok_or_else(|| corrupt("malformed match line"))?;
+                    let line_number = line_number
+                        .parse()
+                        .map_err(|_| corrupt("malformed match line number"))?;
+                    let file = current_file
+                        .as_ref()
+                        .and_then(|path| index.get_mut(path))
+                        .ok_or_else(|| corrupt("match before any file"))?;
+                    file.matches.push(PatternMatch {
+                        pattern: unescape(pattern),
+                        line: line_number,
+                    });
+                }
+                _ => return Err(corrupt("unknown index line kind")),
+            }
+        }
+
+        if saved_patterns != patterns {
+            index.clear();
+        }
+        index.retain(|path, _| path.exists());
+        Ok(Self { patterns, index })
+    }
+}
+
+fn scan_bytes(patterns: &[String], bytes: &[u8]) -> Vec<PatternMatch> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut matches = Vec::new();
+    for (line_index, line) in text.lines().enumerate() {
+        for pattern in patterns {
+            if line.contains(pattern.as_str()) {
+                matches.push(PatternMatch {
+                    pattern: pattern.clone(),
+                    line: line_index + 1,
+                });
+            }
+        }
+    }
+    matches
+}
+
+/// The index format is line- and tab-delimited, so those characters (and the
+/// escape character itself) are escaped in patterns and paths.
+fn escape(text: &str) -> String {
+    text.replace('\\', r"\\")
+        .replace('\t', r"\t")
+        .replace('\n', r"\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut characters = text.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            output.push(character);
+            continue;
+        }
+        match characters.next() {
+            Some('t') => output.push('\t'),
+            Some('n') => output.push('\n'),
+            Some(other) => output.push(other),
+            None => output.push('\\'),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StorageConfig;
+
+    fn patterns() -> Vec<String> {
+        vec!["TODO".to_string(), "FIXME".to_string()]
+    }
+
+    #[test]
+    fn test_unchanged_files_are_not_rescanned() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.rs");
+        let second = dir.path().join("second.rs");
+        fs::write(&first, "fn main() {}\n// TODO: cleanup\n").unwrap();
+        fs::write(&second, "// nothing here\n").unwrap();
+        let files = vec![first.clone(), second.clone()];
+
+        let mut detector = PatternDetector::new(patterns());
+        assert_eq!(detector.scan(&files).unwrap(), 2);
+        assert_eq!(detector.scan(&files).unwrap(), 0, "hashes unchanged");
+
+        fs::write(&second, "// FIXME: broken\n").unwrap();
+        assert_eq!(detector.scan(&files).unwrap(), 1);
+        assert_eq!(
+            detector.matches_for(&second).unwrap(),
+            &[PatternMatch {
+                pattern: "FIXME".to_string(),
+                line: 1,
+            }]
+        );
+        assert_eq!(
+            detector.matches_for(&first).unwrap(),
+            &[PatternMatch {
+                pattern: "TODO".to_string(),
+                line: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_index_round_trips_through_storage_without_rescan() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("main.rs");
+        fs::write(&file, "// TODO: one\n// TODO: two\n").unwrap();
+        let files = vec![file.clone()];
+        let store = BlobStore::new(dir.path().join("store"), StorageConfig::default());
+
+        let mut detector = PatternDetector::new(patterns());
+        detector.scan(&files).unwrap();
+        let id = detector.save_index(&store).unwrap();
+
+        let mut restored = PatternDetector::load_index(patterns(), &store, &id).unwrap();
+        assert_eq!(
+            restored.matches_for(&file).unwrap(),
+            detector.matches_for(&file).unwrap()
+        );
+        assert_eq!(restored.scan(&files).unwrap(), 0, "restart needs no rescan");
+    }
+
+    #[test]
+    fn test_deleted_file_is_purged_on_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let kept = dir.path().join("kept.rs");
+        let deleted = dir.path().join("deleted.rs");
+        fs::write(&kept, "// TODO: keep\n").unwrap();
+        fs::write(&deleted, "// TODO: gone\n").unwrap();
+        let store = BlobStore::new(dir.path().join("store"), StorageConfig::default());
+
+        let mut detector = PatternDetector::new(patterns());
+        detector.scan(&[kept.clone(), deleted.clone()]).unwrap();
+        let id = detector.save_index(&store).unwrap();
+
+        fs::remove_file(&deleted).unwrap();
+        let restored = PatternDetector::load_index(patterns(), &store, &id).unwrap();
+        assert!(restored.matches_for(&deleted).is_none());
+        assert!(restored.matches_for(&kept).is_some());
+        assert_eq!(restored.indexed_files().count(), 1);
+    }
+
+    #[test]
+    fn test_changed_pattern_set_discards_the_persisted_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("main.rs");
+        fs::write(&file, "// TODO: one\n").unwrap();
+        let store = BlobStore::new(dir.path().join("store"), StorageConfig::default());
+
+        let mut detector = PatternDetector::new(patterns());
+        detector.scan(std::slice::from_ref(&file)).unwrap();
+        let id = detector.save_index(&store).unwrap();
+
+        let restored = PatternDetector::load_index(vec!["HACK".to_string()], &store, &id).unwrap();
+        assert!(
+            restored.matches_for(&file).is_none(),
+            "stale matches dropped"
+        );
+    }
+}
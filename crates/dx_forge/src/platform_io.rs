@@ -0,0 +1,409 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+/// The kinds of file activity a platform watcher can report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileEventKind {
+    Create,
+    Modify,
+    Delete,
+    Rename,
+    Access,
+}
+
+impl FileEventKind {
+    const ALL: [Self; 5] = [
+        Self::Create,
+        Self::Modify,
+        Self::Delete,
+        Self::Rename,
+        Self::Access,
+    ];
+
+    fn mask_bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// A set of [`FileEventKind`]s, packed so backends can hand it straight to
+/// OS facilities that take a bitmask (e.g. inotify).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventKindMask(u8);
+
+impl EventKindMask {
+    pub fn empty() -> Self {
+        Self(0)
+    }
+
+    pub fn all() -> Self {
+        FileEventKind::ALL
+            .into_iter()
+            .fold(Self::empty(), Self::with)
+    }
+
+    pub fn with(self, kind: FileEventKind) -> Self {
+        Self(self.0 | kind.mask_bit())
+    }
+
+    pub fn contains(self, kind: FileEventKind) -> bool {
+        self.0 & kind.mask_bit() != 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEvent {
+    pub path: PathBuf,
+    pub kind: FileEventKind,
+}
+
+/// Where a subscription's kind filter runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventFiltering {
+    /// The OS facility filters before waking userspace.
+    Native,
+    /// The backend delivers everything and the sink drops unwanted kinds.
+    Adapter,
+}
+
+/// What backed an [`EventStream`] subscription: which platform backend, and
+/// whether the kind filter ran natively or in the adapter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlatformInfo {
+    pub backend: &'static str,
+    pub event_filtering: EventFiltering,
+}
+
+/// A platform file-watching backend (inotify, FSEvents, a polling fallback).
+/// The subscription hands it an [`EventSink`] to deliver events through.
+pub trait PlatformWatcher {
+    fn name(&self) -> &'static str;
+
+    /// Installs the subscription's kind mask in the OS facility itself, so
+    /// unwanted events never wake userspace. Returns false when the platform
+    /// has no such facility; the sink then filters delivered events instead.
+    fn apply_kind_mask(&mut self, mask: EventKindMask) -> bool;
+
+    fn attach(&mut self, sink: EventSink);
+}
+
+/// The delivery side of a subscription, held by the backend. Dropping events
+/// of unsubscribed kinds happens here when the backend couldn't filter
+/// natively.
+pub struct EventSink {
+    sender: mpsc::Sender<FileEvent>,
+    /// `None` when the backend filters natively and everything it delivers
+    /// is already wanted.
+    adapter_mask: Option<EventKindMask>,
+}
+
+impl EventSink {
+    pub fn send(&self, event: FileEvent) {
+        if let Some(mask) = self.adapter_mask
+            && !mask.contains(event.kind)
+        {
+            return;
+        }
+        // A send failure means the stream was dropped; the backend will be
+        // detached by its owner.
+        self.sender.send(event).ok();
+    }
+}
+
+/// A bounded ring of recently delivered events, fed by the watcher's owner
+/// alongside its sinks. A subscriber that attaches after changes already
+/// fired can catch up from it via
+/// [`EventStreamBuilder::subscribe_with_replay`].
+pub struct ReplayBuffer {
+    events: VecDeque<FileEvent>,
+    capacity: usize,
+}
+
+impl ReplayBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Remembers `event`, evicting the oldest once `capacity` is reached.
+    pub fn record(&mut self, event: FileEvent) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    /// The most recent `count` buffered events of the masked kinds, oldest
+    /// first.
+    fn recent(&self, count: usize, mask: EventKindMask) -> VecDeque<FileEvent> {
+        let mut matched = VecDeque::new();
+        for event in self.events.iter().rev() {
+            if matched.len() == count {
+                break;
+            }
+            if mask.contains(event.kind) {
+                matched.push_front(event.clone());
+            }
+        }
+        matched
+    }
+}
+
+/// An event delivered by an [`EventStream`], marked with whether it came out
+/// of a [`ReplayBuffer`] rather than being observed live, so a consumer that
+/// already acted on the original delivery doesn't act twice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedEvent {
+    pub event: FileEvent,
+    /// True when the event predates this subscription and was served from
+    /// the replay buffer.
+    pub replayed: bool,
+}
+
+/// The receiving side of a subscription created by [`EventStreamBuilder`].
+/// Events arrive already filtered to the subscribed kinds, whichever side
+/// did the filtering; any catch-up events are drained before live ones.
+pub struct EventStream {
+    replayed: VecDeque<FileEvent>,
+    receiver: mpsc::Receiver<FileEvent>,
+}
+
+impl EventStream {
+    pub fn try_next(&mut self) -> Option<ReceivedEvent> {
+        if let Some(event) = self.replayed.pop_front() {
+            return Some(ReceivedEvent {
+                event,
+                replayed: true,
+            });
+        }
+        self.receiver.try_recv().ok().map(|event| ReceivedEvent {
+            event,
+            replayed: false,
+        })
+    }
+}
+
+/// Builds a filtered subscription against a [`PlatformWatcher`]. With no
+/// explicit kinds, all kinds are delivered.
+#[derive(Debug, Default)]
+pub struct EventStreamBuilder {
+    kinds: Option<EventKindMask>,
+    replay: Option<usize>,
+}
+
+impl EventStreamBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Narrows the subscription to `kind`; may be called repeatedly to
+    /// subscribe to several kinds.
+    pub fn kind(mut self, kind: FileEventKind) -> Self {
+        self.kinds = Some(self.kinds.unwrap_or_else(EventKindMask::empty).with(kind));
+        self
+    }
+
+    /// Caps how many buffered events
+    /// [`subscribe_with_replay`](Self::subscribe_with_replay) seeds the
+    /// stream with; without this, everything the buffer retains is replayed.
+    pub fn with_replay(mut self, count: usize) -> Self {
+        self.replay = Some(count);
+        self
+    }
+
+    /// Attaches to the backend and returns the stream plus a
+    /// [`PlatformInfo`] describing which side ended up filtering.
+    pub fn subscribe(self, backend: &mut dyn PlatformWatcher) -> (EventStream, PlatformInfo) {
+        self.attach(backend, VecDeque::new())
+    }
+
+    /// Like [`subscribe`](Self::subscribe), but first seeds the stream with
+    /// the most recent events `buffer` retains — filtered to the subscribed
+    /// kinds and capped by [`with_replay`](Self::with_replay) — delivered
+    /// oldest first and flagged as replayed.
+    pub fn subscribe_with_replay(
+        self,
+        backend: &mut dyn PlatformWatcher,
+        buffer: &ReplayBuffer,
+    ) -> (EventStream, PlatformInfo) {
+        let mask = self.kinds.unwrap_or_else(EventKindMask::all);
+        let replayed = buffer.recent(self.replay.unwrap_or(buffer.capacity), mask);
+        self.attach(backend, replayed)
+    }
+
+    fn attach(
+        self,
+        backend: &mut dyn PlatformWatcher,
+        replayed: VecDeque<FileEvent>,
+    ) -> (EventStream, PlatformInfo) {
+        let mask = self.kinds.unwrap_or_else(EventKindMask::all);
+        let filtered_natively = backend.apply_kind_mask(mask);
+        let (sender, receiver) = mpsc::channel();
+        backend.attach(EventSink {
+            sender,
+            adapter_mask: if filtered_natively { None } else { Some(mask) },
+        });
+        let info = PlatformInfo {
+            backend: backend.name(),
+            event_filtering: if filtered_natively {
+                EventFiltering::Native
+            } else {
+                EventFiltering::Adapter
+            },
+        };
+        (EventStream { replayed, receiver }, info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// A backend that either honors the mask itself (like inotify) or
+    /// delivers everything (like a naive poller).
+    struct TestWatcher {
+        native: bool,
+        applied_mask: Option<EventKindMask>,
+        sink: Option<EventSink>,
+    }
+
+    impl TestWatcher {
+        fn new(native: bool) -> Self {
+            Self {
+                native,
+                applied_mask: None,
+                sink: None,
+            }
+        }
+
+        fn emit(&self, path: &Path, kind: FileEventKind) {
+            if self.native
+                && let Some(mask) = self.applied_mask
+                && !mask.contains(kind)
+            {
+                return;
+            }
+            if let Some(sink) = &self.sink {
+                sink.send(FileEvent {
+                    path: path.to_path_buf(),
+                    kind,
+                });
+            }
+        }
+    }
+
+    impl PlatformWatcher for TestWatcher {
+        fn name(&self) -> &'static str {
+            if self.native { "inotify" } else { "poller" }
+        }
+
+        fn apply_kind_mask(&mut self, mask: EventKindMask) -> bool {
+            if self.native {
+                self.applied_mask = Some(mask);
+            }
+            self.native
+        }
+
+        fn attach(&mut self, sink: EventSink) {
+            self.sink = Some(sink);
+        }
+    }
+
+    fn emit_one_of_each(watcher: &TestWatcher) {
+        for kind in FileEventKind::ALL {
+            watcher.emit(Path::new("src/main.rs"), kind);
+        }
+    }
+
+    #[test]
+    fn test_native_backend_filters_in_the_os_facility() {
+        let mut watcher = TestWatcher::new(true);
+        let (mut stream, info) = EventStreamBuilder::new()
+            .kind(FileEventKind::Create)
+            .kind(FileEventKind::Modify)
+            .subscribe(&mut watcher);
+        assert_eq!(info.event_filtering, EventFiltering::Native);
+        assert_eq!(info.backend, "inotify");
+
+        emit_one_of_each(&watcher);
+        let kinds: Vec<_> = std::iter::from_fn(|| stream.try_next())
+            .map(|received| received.event.kind)
+            .collect();
+        assert_eq!(kinds, vec![FileEventKind::Create, FileEventKind::Modify]);
+    }
+
+    #[test]
+    fn test_non_native_backend_filters_in_the_adapter() {
+        let mut watcher = TestWatcher::new(false);
+        let (mut stream, info) = EventStreamBuilder::new()
+            .kind(FileEventKind::Modify)
+            .subscribe(&mut watcher);
+        assert_eq!(info.event_filtering, EventFiltering::Adapter);
+
+        emit_one_of_each(&watcher);
+        let kinds: Vec<_> = std::iter::from_fn(|| stream.try_next())
+            .map(|received| received.event.kind)
+            .collect();
+        assert_eq!(kinds, vec![FileEventKind::Modify]);
+    }
+
+    #[test]
+    fn test_default_builder_delivers_every_kind() {
+        let mut watcher = TestWatcher::new(false);
+        let (mut stream, _) = EventStreamBuilder::new().subscribe(&mut watcher);
+        emit_one_of_each(&watcher);
+        let count = std::iter::from_fn(|| stream.try_next()).count();
+        assert_eq!(count, FileEventKind::ALL.len());
+    }
+
+    #[test]
+    fn test_late_subscriber_catches_up_from_the_replay_buffer() {
+        let mut buffer = ReplayBuffer::new(3);
+        for name in ["a.rs", "b.rs", "c.rs", "d.rs"] {
+            buffer.record(FileEvent {
+                path: PathBuf::from(name),
+                kind: FileEventKind::Modify,
+            });
+        }
+        buffer.record(FileEvent {
+            path: PathBuf::from("e.log"),
+            kind: FileEventKind::Access,
+        });
+
+        let mut watcher = TestWatcher::new(true);
+        let (mut stream, _) = EventStreamBuilder::new()
+            .kind(FileEventKind::Modify)
+            .with_replay(2)
+            .subscribe_with_replay(&mut watcher, &buffer);
+        watcher.emit(Path::new("live.rs"), FileEventKind::Modify);
+
+        let received: Vec<_> = std::iter::from_fn(|| stream.try_next()).collect();
+        let paths: Vec<&Path> = received
+            .iter()
+            .map(|received| received.event.path.as_path())
+            .collect();
+        // "b.rs" aged out of the buffer, the access event is filtered, and
+        // the cap of two keeps the two newest modifications, oldest first.
+        assert_eq!(
+            paths,
+            vec![Path::new("c.rs"), Path::new("d.rs"), Path::new("live.rs")]
+        );
+        let flags: Vec<bool> = received.iter().map(|received| received.replayed).collect();
+        assert_eq!(flags, vec![true, true, false]);
+    }
+
+    #[test]
+    fn test_subscribe_without_a_buffer_replays_nothing() {
+        let mut watcher = TestWatcher::new(true);
+        let (mut stream, _) = EventStreamBuilder::new()
+            .with_replay(5)
+            .subscribe(&mut watcher);
+        assert!(stream.try_next().is_none());
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+#[derive(Debug, Clone)]
+pub struct ShutdownConfig {
+    /// How long in-flight tool executions are allowed to finish after a
+    /// shutdown has been requested, before they are abandoned.
+    pub grace: Duration,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self {
+            grace: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownState {
+    Running,
+    ShuttingDown,
+}
+
+/// Shared flag observed by the orchestrator and daemon loops. Signal handlers
+/// (or tests) call [`ShutdownHandler::request_shutdown`]; long-running work
+/// polls [`ShutdownHandler::state`] between steps.
+#[derive(Clone)]
+pub struct ShutdownHandler {
+    config: ShutdownConfig,
+    shutting_down: Arc<AtomicBool>,
+    requested_at: Arc<Mutex<Option<Instant>>>,
+}
+
+impl ShutdownHandler {
+    pub fn new(config: ShutdownConfig) -> Self {
+        Self {
+            config,
+            shutting_down: Arc::new(AtomicBool::new(false)),
+            requested_at: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn config(&self) -> &ShutdownConfig {
+        &self.config
+    }
+
+    pub fn request_shutdown(&self) {
+        if !self.shutting_down.swap(true, Ordering::SeqCst) {
+            *self.requested_at.lock() = Some(Instant::now());
+        }
+    }
+
+    pub fn state(&self) -> ShutdownState {
+        if self.shutting_down.load(Ordering::SeqCst) {
+            ShutdownState::ShuttingDown
+        } else {
+            ShutdownState::Running
+        }
+    }
+
+    /// The instant by which in-flight work must finish, if a shutdown has been
+    /// requested.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.requested_at
+            .lock()
+            .map(|requested_at| requested_at + self.config.grace)
+    }
+}
+
+impl Default for ShutdownHandler {
+    fn default() -> Self {
+        Self::new(ShutdownConfig::default())
+    }
+}
@@ -0,0 +1,585 @@
+use crate::{BlobId, BlobStore, ForgeError};
+use collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// One file in a snapshot's tree: a repository-relative path and the blob
+/// holding its content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotFile {
+    pub path: String,
+    pub blob: BlobId,
+}
+
+/// A point-in-time capture of a project tree, chained to its predecessor on
+/// the same branch. Files always list the *full* tree, not a diff, so a
+/// snapshot can be restored without walking its ancestry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub id: u64,
+    pub parent: Option<u64>,
+    pub branch: String,
+    pub author: String,
+    pub message: String,
+    /// Seconds since the Unix epoch.
+    pub timestamp: i64,
+    /// Sorted by path.
+    pub files: Vec<SnapshotFile>,
+}
+
+/// How a path differs between two snapshots in
+/// [`changed_paths`](SnapshotManager::changed_paths).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Removed,
+}
+
+/// Git-compatible versioning of project trees on top of the [`BlobStore`]:
+/// snapshots are commits, branches are named heads, and the whole history can
+/// be exported as a `git fast-import` stream via
+/// [`export_git_fast_import`](Self::export_git_fast_import).
+pub struct SnapshotManager {
+    store: BlobStore,
+    snapshots: Vec<Snapshot>,
+    branches: HashMap<String, u64>,
+}
+
+impl SnapshotManager {
+    pub fn new(store: BlobStore) -> Self {
+        Self {
+            store,
+            snapshots: Vec::new(),
+            branches: HashMap::default(),
+        }
+    }
+
+    pub fn store(&self) -> &BlobStore {
+        &self.store
+    }
+
+    /// Captures `files` as a new snapshot on `branch`, storing each file's
+    /// content in the blob store and advancing the branch head. A branch that
+    /// doesn't exist yet is created, rooted at this snapshot.
+    pub fn commit(
+        &mut self,
+        branch: &str,
+        author: &str,
+        message: &str,
+        timestamp: i64,
+        files: &[(String, Vec<u8>)],
+    ) -> Result<u64, ForgeError> {
+        let mut snapshot_files = Vec::with_capacity(files.len());
+        for (path, bytes) in files {
+            snapshot_files.push(SnapshotFile {
+                path: path.clone(),
+                blob: self.store.store(bytes)?,
+            });
+        }
+        snapshot_files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let id = self.snapshots.len() as u64;
+        self.snapshots.push(Snapshot {
+            id,
+            parent: self.branches.get(branch).copied(),
+            branch: branch.to_string(),
+            author: author.to_string(),
+            message: message.to_string(),
+            timestamp,
+            files: snapshot_files,
+        });
+        self.branches.insert(branch.to_string(), id);
+        Ok(id)
+    }
+
+    /// Creates `new_branch` pointing at the current head of `from_branch`
+    /// without committing anything.
+    pub fn fork_branch(&mut self, new_branch: &str, from_branch: &str) -> Result<(), ForgeError> {
+        let head = *self
+            .branches
+            .get(from_branch)
+            .ok_or_else(|| ForgeError::BranchNotFound(from_branch.to_string()))?;
+        self.branches.insert(new_branch.to_string(), head);
+        Ok(())
+    }
+
+    pub fn snapshot(&self, id: u64) -> Option<&Snapshot> {
+        self.snapshots.get(id as usize)
+    }
+
+    pub fn branch_head(&self, branch: &str) -> Option<u64> {
+        self.branches.get(branch).copied()
+    }
+
+    /// Branch names with their head snapshot ids, sorted by name.
+    pub fn branches(&self) -> Vec<(String, u64)> {
+        let mut branches: Vec<(String, u64)> = self
+            .branches
+            .iter()
+            .map(|(name, head)| (name.clone(), *head))
+            .collect();
+        branches.sort();
+        branches
+    }
+
+    /// The path-level changes between two snapshots, sorted by path. Only
+    /// blob ids and per-directory tree hashes are compared — no blob content
+    /// is read — and subtrees whose tree hashes match are pruned without
+    /// visiting the files inside them.
+    pub fn changed_paths(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> Result<Vec<(PathBuf, ChangeKind)>, ForgeError> {
+        let from = self
+            .snapshot(from)
+            .ok_or(ForgeError::SnapshotNotFound(from))?;
+        let to = self.snapshot(to).ok_or(ForgeError::SnapshotNotFound(to))?;
+        let from_tree = HashedTree::build(&from.files);
+        let to_tree = HashedTree::build(&to.files);
+        let mut changes = Vec::new();
+        diff_trees("", Some(&from_tree), Some(&to_tree), &mut changes);
+        changes.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(changes)
+    }
+
+    /// Writes the whole history as a `git fast-import` stream: every blob
+    /// once, each snapshot as a commit on `refs/heads/<branch>`, and forked
+    /// branches as `reset` directives. Piping the output into
+    /// `git fast-import` inside an empty repository reproduces the history.
+    ///
+    /// Blobs are read and written one at a time, so memory use is bounded by
+    /// the largest single blob rather than the total history size.
+    pub fn export_git_fast_import(&self, writer: &mut dyn Write) -> Result<(), ForgeError> {
+        let mut next_mark = 1u64;
+        let mut blob_marks: HashMap<&BlobId, u64> = HashMap::default();
+        for snapshot in &self.snapshots {
+            for file in &snapshot.files {
+                if blob_marks.contains_key(&file.blob) {
+                    continue;
+                }
+                let bytes = self.store.read(&file.blob)?;
+                write!(writer, "blob\nmark :{next_mark}\ndata {}\n", bytes.len())
+                    .and_then(|()| writer.write_all(&bytes))
+                    .and_then(|()| writeln!(writer))
+                    .map_err(stream_error)?;
+                blob_marks.insert(&file.blob, next_mark);
+                next_mark += 1;
+            }
+        }
+
+        let mut commit_marks: HashMap<u64, u64> = HashMap::default();
+        for snapshot in &self.snapshots {
+            let mark = next_mark;
+            next_mark += 1;
+            commit_marks.insert(snapshot.id, mark);
+
+            let identity = git_identity(&snapshot.author);
+            let message = snapshot.message.as_bytes();
+            write!(
+                writer,
+                "commit refs/heads/{}\nmark :{mark}\nauthor {identity} {} +0000\ncommitter {identity} {} +0000\ndata {}\n",
+                snapshot.branch,
+                snapshot.timestamp,
+                snapshot.timestamp,
+                message.len(),
+            )
+            .and_then(|()| writer.write_all(message))
+            .and_then(|()| writeln!(writer))
+            .map_err(stream_error)?;
+            if let Some(parent) = snapshot.parent
+                && let Some(parent_mark) = commit_marks.get(&parent)
+            {
+                writeln!(writer, "from :{parent_mark}").map_err(stream_error)?;
+            }
+            // `deleteall` plus the full tree sidesteps diffing against the
+            // parent; snapshots record full trees anyway.
+            writeln!(writer, "deleteall").map_err(stream_error)?;
+            for file in &snapshot.files {
+                if let Some(blob_mark) = blob_marks.get(&file.blob) {
+                    writeln!(
+                        writer,
+                        "M 100644 :{blob_mark} {}",
+                        quote_git_path(&file.path)
+                    )
+                    .map_err(stream_error)?;
+                }
+            }
+            writeln!(writer).map_err(stream_error)?;
+        }
+
+        // Branches whose head was committed under another name (forks with no
+        // commits of their own) still need their ref written.
+        for (name, head) in self.branches() {
+            if self
+                .snapshot(head)
+                .is_some_and(|snapshot| snapshot.branch != name)
+                && let Some(mark) = commit_marks.get(&head)
+            {
+                write!(writer, "reset refs/heads/{name}\nfrom :{mark}\n\n")
+                    .map_err(stream_error)?;
+            }
+        }
+        writeln!(writer, "done").map_err(stream_error)?;
+        Ok(())
+    }
+}
+
+/// A snapshot's file list reshaped into a directory tree, with a hash per
+/// node computed bottom-up from entry names and blob ids. Two directories
+/// with equal hashes hold identical subtrees, which is what lets the diff
+/// skip them wholesale.
+struct HashedTree {
+    files: HashMap<String, BlobId>,
+    directories: HashMap<String, HashedTree>,
+    hash: String,
+}
+
+impl HashedTree {
+    fn build(files: &[SnapshotFile]) -> Self {
+        #[derive(Default)]
+        struct Node {
+            files: HashMap<String, BlobId>,
+            directories: HashMap<String, Node>,
+        }
+
+        let mut root = Node::default();
+        for file in files {
+            let mut node = &mut root;
+            let mut components = file.path.split('/').peekable();
+            while let Some(component) = components.next() {
+                if components.peek().is_some() {
+                    node = node.directories.entry(component.to_string()).or_default();
+                } else {
+                    node.files.insert(component.to_string(), file.blob.clone());
+                }
+            }
+        }
+
+        fn hash_node(node: Node) -> HashedTree {
+            let mut directories: Vec<(String, HashedTree)> = node
+                .directories
+                .into_iter()
+                .map(|(name, child)| (name, hash_node(child)))
+                .collect();
+            directories.sort_by(|(left, _), (right, _)| left.cmp(right));
+            let mut files: Vec<(String, BlobId)> = node.files.into_iter().collect();
+            files.sort_by(|(left, _), (right, _)| left.cmp(right));
+
+            let mut entries = String::new();
+            for (name, blob) in &files {
+                entries.push_str(&format!("file {name} {}\n", blob.0));
+            }
+            for (name, directory) in &directories {
+                entries.push_str(&format!("tree {name} {}\n", directory.hash));
+            }
+            HashedTree {
+                hash: crate::storage::hash_bytes(entries.as_bytes()),
+                files: files.into_iter().collect(),
+                directories: directories.into_iter().collect(),
+            }
+        }
+        hash_node(root)
+    }
+}
+
+fn diff_trees(
+    prefix: &str,
+    from: Option<&HashedTree>,
+    to: Option<&HashedTree>,
+    changes: &mut Vec<(PathBuf, ChangeKind)>,
+) {
+    if let (Some(from), Some(to)) = (from, to)
+        && from.hash == to.hash
+    {
+        return;
+    }
+    let empty_files = HashMap::default();
+    let empty_directories = HashMap::default();
+    let from_files = from.map_or(&empty_files, |tree| &tree.files);
+    let to_files = to.map_or(&empty_files, |tree| &tree.files);
+    let from_directories = from.map_or(&empty_directories, |tree| &tree.directories);
+    let to_directories = to.map_or(&empty_directories, |tree| &tree.directories);
+
+    for (name, blob) in from_files {
+        let kind = match to_files.get(name) {
+            Some(other) if other == blob => continue,
+            Some(_) => ChangeKind::Modified,
+            None => ChangeKind::Removed,
+        };
+        changes.push((PathBuf::from(format!("{prefix}{name}")), kind));
+    }
+    for name in to_files.keys() {
+        if !from_files.contains_key(name) {
+            changes.push((PathBuf::from(format!("{prefix}{name}")), ChangeKind::Added));
+        }
+    }
+
+    for (name, directory) in from_directories {
+        diff_trees(
+            &format!("{prefix}{name}/"),
+            Some(directory),
+            to_directories.get(name),
+            changes,
+        );
+    }
+    for (name, directory) in to_directories {
+        if !from_directories.contains_key(name) {
+            diff_trees(&format!("{prefix}{name}/"), None, Some(directory), changes);
+        }
+    }
+}
+
+fn stream_error(source: std::io::Error) -> ForgeError {
+    ForgeError::Io {
+        path: PathBuf::from("<fast-import stream>"),
+        source,
+    }
+}
+
+/// `Name <email>` in the shape git's author/committer lines require; the
+/// email is synthesized since Forge only records a display name.
+fn git_identity(author: &str) -> String {
+    let name: String = author
+        .chars()
+        .filter(|character| !matches!(character, '<' | '>' | '\n'))
+        .collect();
+    let name = name.trim();
+    let name = if name.is_empty() { "forge" } else { name };
+    let mut slug: String = name
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() {
+                character.to_ascii_lowercase()
+            } else {
+                '.'
+            }
+        })
+        .collect();
+    if slug.chars().all(|character| character == '.') {
+        slug = "forge".to_string();
+    }
+    format!("{name} <{slug}@forge.local>")
+}
+
+/// fast-import paths are taken verbatim to end of line unless quoted; quote
+/// C-style when the path contains characters that would break that.
+fn quote_git_path(path: &str) -> String {
+    if path.starts_with('"')
+        || path.contains(|character: char| matches!(character, '"' | '\\' | '\n'))
+    {
+        let mut quoted = String::with_capacity(path.len() + 2);
+        quoted.push('"');
+        for character in path.chars() {
+            match character {
+                '"' => quoted.push_str("\\\""),
+                '\\' => quoted.push_str("\\\\"),
+                '\n' => quoted.push_str("\\n"),
+                other => quoted.push(other),
+            }
+        }
+        quoted.push('"');
+        quoted
+    } else {
+        path.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StorageConfig;
+    use std::process::{Command, Stdio};
+
+    fn manager_in(dir: &std::path::Path) -> SnapshotManager {
+        SnapshotManager::new(BlobStore::new(dir.join("store"), StorageConfig::default()))
+    }
+
+    fn sample_history(manager: &mut SnapshotManager) -> Vec<u8> {
+        let binary: Vec<u8> = (0..32 * 1024).map(|index| (index % 256) as u8).collect();
+        manager
+            .commit(
+                "main",
+                "Ada Lovelace",
+                "initial import",
+                1_700_000_000,
+                &[
+                    ("README.md".to_string(), b"# project\n".to_vec()),
+                    ("assets/logo.bin".to_string(), binary.clone()),
+                ],
+            )
+            .unwrap();
+        manager
+            .commit(
+                "main",
+                "Ada Lovelace",
+                "update readme",
+                1_700_000_100,
+                &[
+                    ("README.md".to_string(), b"# project v2\n".to_vec()),
+                    ("assets/logo.bin".to_string(), binary.clone()),
+                ],
+            )
+            .unwrap();
+        manager.fork_branch("release", "main").unwrap();
+        binary
+    }
+
+    #[test]
+    fn test_export_emits_commits_refs_and_deduplicated_blobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = manager_in(dir.path());
+        sample_history(&mut manager);
+
+        let mut stream = Vec::new();
+        manager.export_git_fast_import(&mut stream).unwrap();
+        let text = String::from_utf8_lossy(&stream);
+
+        assert_eq!(
+            text.matches("commit refs/heads/main").count(),
+            2,
+            "one commit per snapshot"
+        );
+        assert!(text.contains("reset refs/heads/release"));
+        assert!(text.contains("author Ada Lovelace <ada.lovelace@forge.local> 1700000000 +0000"));
+        // The unchanged binary blob is emitted once and referenced twice.
+        assert_eq!(text.matches("blob\nmark").count(), 3);
+        assert_eq!(text.matches(" assets/logo.bin").count(), 2);
+        assert!(text.trim_end().ends_with("done"));
+    }
+
+    #[test]
+    fn test_exported_stream_imports_into_a_real_git_repo() {
+        if Command::new("git").arg("--version").output().is_err() {
+            // No git CLI in this environment; the format itself is covered by
+            // the other tests.
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = manager_in(dir.path());
+        let binary = sample_history(&mut manager);
+        let mut stream = Vec::new();
+        manager.export_git_fast_import(&mut stream).unwrap();
+
+        let repo = dir.path().join("repo");
+        let git = |arguments: &[&str]| {
+            let output = Command::new("git")
+                .arg("-C")
+                .arg(&repo)
+                .args(arguments)
+                .output()
+                .unwrap();
+            assert!(
+                output.status.success(),
+                "git {arguments:?} failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            output.stdout
+        };
+        std::fs::create_dir(&repo).unwrap();
+        git(&["init", "-q"]);
+
+        let mut import = Command::new("git")
+            .arg("-C")
+            .arg(&repo)
+            .args(["fast-import", "--quiet", "--done"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        import.stdin.take().unwrap().write_all(&stream).unwrap();
+        let output = import.wait_with_output().unwrap();
+        assert!(
+            output.status.success(),
+            "fast-import failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let log = String::from_utf8(git(&["log", "--format=%s", "main"])).unwrap();
+        assert_eq!(
+            log.lines().collect::<Vec<_>>(),
+            vec!["update readme", "initial import"]
+        );
+        assert_eq!(git(&["show", "main:README.md"]), b"# project v2\n".to_vec());
+        assert_eq!(git(&["show", "main:assets/logo.bin"]), binary);
+        assert_eq!(
+            git(&["rev-parse", "release"]),
+            git(&["rev-parse", "main"]),
+            "forked branch points at the same commit"
+        );
+    }
+
+    #[test]
+    fn test_changed_paths_reports_only_the_actual_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = manager_in(dir.path());
+        let first = manager
+            .commit(
+                "main",
+                "Ada Lovelace",
+                "initial import",
+                1_700_000_000,
+                &[
+                    ("README.md".to_string(), b"# project\n".to_vec()),
+                    ("assets/logo.bin".to_string(), vec![0; 1024]),
+                    ("docs/guide.md".to_string(), b"guide\n".to_vec()),
+                    ("old.txt".to_string(), b"obsolete\n".to_vec()),
+                ],
+            )
+            .unwrap();
+        let second = manager
+            .commit(
+                "main",
+                "Ada Lovelace",
+                "rework",
+                1_700_000_100,
+                &[
+                    ("README.md".to_string(), b"# project v2\n".to_vec()),
+                    ("assets/logo.bin".to_string(), vec![0; 1024]),
+                    ("docs/guide.md".to_string(), b"guide\n".to_vec()),
+                    ("src/new.rs".to_string(), b"fn main() {}\n".to_vec()),
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(
+            manager.changed_paths(first, second).unwrap(),
+            vec![
+                (PathBuf::from("README.md"), ChangeKind::Modified),
+                (PathBuf::from("old.txt"), ChangeKind::Removed),
+                (PathBuf::from("src/new.rs"), ChangeKind::Added),
+            ]
+        );
+        assert_eq!(
+            manager.changed_paths(second, first).unwrap(),
+            vec![
+                (PathBuf::from("README.md"), ChangeKind::Modified),
+                (PathBuf::from("old.txt"), ChangeKind::Added),
+                (PathBuf::from("src/new.rs"), ChangeKind::Removed),
+            ]
+        );
+        assert!(manager.changed_paths(first, first).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_changed_paths_of_unknown_snapshot_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager_in(dir.path());
+        assert!(matches!(
+            manager.changed_paths(0, 1),
+            Err(ForgeError::SnapshotNotFound(0))
+        ));
+    }
+
+    #[test]
+    fn test_fork_of_unknown_branch_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = manager_in(dir.path());
+        assert!(matches!(
+            manager.fork_branch("release", "main"),
+            Err(ForgeError::BranchNotFound(branch)) if branch == "main"
+        ));
+    }
+}
@@ -0,0 +1,217 @@
+use crate::ForgeError;
+use hex::encode as hex_encode;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Identifies a blob by the SHA-256 of its *uncompressed* content, so the
+/// address of a blob never depends on whether it happened to be stored
+/// compressed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlobId(pub String);
+
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    /// Blobs at or above this size are stored zstd-compressed (when that
+    /// actually shrinks them).
+    pub compression_threshold: u64,
+    pub compression_level: i32,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            compression_threshold: 4 * 1024,
+            compression_level: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlobMetadata {
+    /// Uncompressed content length.
+    pub size: u64,
+    /// Bytes occupied on disk (payload only, excluding the header).
+    pub stored_size: u64,
+    pub compressed: bool,
+}
+
+const FLAG_RAW: u8 = 0;
+const FLAG_ZSTD: u8 = 1;
+/// flag byte + uncompressed length (u64 LE).
+const HEADER_LEN: u64 = 9;
+
+/// Content-addressable blob storage on disk, git-style fan-out by the first
+/// two hash characters.
+pub struct BlobStore {
+    root: PathBuf,
+    config: StorageConfig,
+}
+
+impl BlobStore {
+    pub fn new(root: impl Into<PathBuf>, config: StorageConfig) -> Self {
+        Self {
+            root: root.into(),
+            config,
+        }
+    }
+
+    pub fn store(&self, bytes: &[u8]) -> Result<BlobId, ForgeError> {
+        let id = BlobId(hash_bytes(bytes));
+        let path = self.blob_path(&id);
+        if path.exists() {
+            return Ok(id);
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|source| io_error(parent, source))?;
+        }
+
+        let mut file_bytes = Vec::with_capacity(bytes.len() + HEADER_LEN as usize);
+        if bytes.len() as u64 >= self.config.compression_threshold {
+            let compressed = zstd::encode_all(bytes, self.config.compression_level)
+                .map_err(|source| io_error(&path, source))?;
+            if compressed.len() < bytes.len() {
+                file_bytes.push(FLAG_ZSTD);
+                file_bytes.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+                file_bytes.extend_from_slice(&compressed);
+            }
+        }
+        if file_bytes.is_empty() {
+            file_bytes.push(FLAG_RAW);
+            file_bytes.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+            file_bytes.extend_from_slice(bytes);
+        }
+        fs::write(&path, file_bytes).map_err(|source| io_error(&path, source))?;
+        Ok(id)
+    }
+
+    pub fn read(&self, id: &BlobId) -> Result<Vec<u8>, ForgeError> {
+        let path = self.blob_path(id);
+        let file_bytes = fs::read(&path).map_err(|_| ForgeError::BlobNotFound(id.0.clone()))?;
+        let (header, payload) = split_header(id, &file_bytes)?;
+        let bytes = match header.0 {
+            FLAG_RAW => payload.to_vec(),
+            FLAG_ZSTD => zstd::decode_all(payload).map_err(|source| io_error(&path, source))?,
+            flag => {
+                return Err(ForgeError::CorruptBlob {
+                    id: id.0.clone(),
+                    message: format!("unknown storage flag {flag}"),
+                });
+            }
+        };
+        if bytes.len() as u64 != header.1 || hash_bytes(&bytes) != id.0 {
+            return Err(ForgeError::CorruptBlob {
+                id: id.0.clone(),
+                message: "content does not match its address".into(),
+            });
+        }
+        Ok(bytes)
+    }
+
+    pub fn metadata(&self, id: &BlobId) -> Result<BlobMetadata, ForgeError> {
+        let path = self.blob_path(id);
+        let file_bytes = fs::read(&path).map_err(|_| ForgeError::BlobNotFound(id.0.clone()))?;
+        let (header, payload) = split_header(id, &file_bytes)?;
+        Ok(BlobMetadata {
+            size: header.1,
+            stored_size: payload.len() as u64,
+            compressed: header.0 == FLAG_ZSTD,
+        })
+    }
+
+    fn blob_path(&self, id: &BlobId) -> PathBuf {
+        let (fan_out, rest) = id.0.split_at(id.0.len().min(2));
+        self.root.join("blobs").join(fan_out).join(rest)
+    }
+}
+
+fn split_header<'a>(
+    id: &BlobId,
+    file_bytes: &'a [u8],
+) -> Result<((u8, u64), &'a [u8]), ForgeError> {
+    let corrupt = |message: &str| ForgeError::CorruptBlob {
+        id: id.0.clone(),
+        message: message.into(),
+    };
+    let flag = *file_bytes
+        .first()
+        .ok_or_else(|| corrupt("empty blob file"))?;
+    let size_bytes = file_bytes
+        .get(1..HEADER_LEN as usize)
+        .ok_or_else(|| corrupt("truncated blob header"))?;
+    let size = u64::from_le_bytes(
+        size_bytes
+            .try_into()
+            .map_err(|_| corrupt("truncated blob header"))?,
+    );
+    let payload = file_bytes
+        .get(HEADER_LEN as usize..)
+        .ok_or_else(|| corrupt("truncated blob payload"))?;
+    Ok(((flag, size), payload))
+}
+
+pub(crate) fn hash_bytes(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    hex_encode(hasher.finalize())
+}
+
+fn io_error(path: &Path, source: std::io::Error) -> ForgeError {
+    ForgeError::Io {
+        path: path.to_path_buf(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store_in(dir: &Path) -> BlobStore {
+        BlobStore::new(
+            dir,
+            StorageConfig {
+                compression_threshold: 1024,
+                compression_level: 3,
+            },
+        )
+    }
+
+    #[test]
+    fn test_large_compressible_blob_shrinks_on_disk_and_reads_back_identical() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_in(dir.path());
+        let blob = vec![b'a'; 100_000];
+
+        let id = store.store(&blob).unwrap();
+        let metadata = store.metadata(&id).unwrap();
+        assert!(metadata.compressed);
+        assert_eq!(metadata.size, blob.len() as u64);
+        assert!(
+            metadata.stored_size < blob.len() as u64,
+            "stored {} bytes, expected fewer than {}",
+            metadata.stored_size,
+            blob.len()
+        );
+        assert_eq!(store.read(&id).unwrap(), blob);
+    }
+
+    #[test]
+    fn test_small_blob_is_stored_raw() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_in(dir.path());
+        let id = store.store(b"tiny").unwrap();
+        let metadata = store.metadata(&id).unwrap();
+        assert!(!metadata.compressed);
+        assert_eq!(store.read(&id).unwrap(), b"tiny");
+    }
+
+    #[test]
+    fn test_blob_id_is_hash_of_uncompressed_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store_in(dir.path());
+        let blob = vec![7u8; 50_000];
+        let id = store.store(&blob).unwrap();
+        assert_eq!(id.0, hash_bytes(&blob));
+    }
+}
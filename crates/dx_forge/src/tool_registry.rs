@@ -0,0 +1,198 @@
+use collections::{HashMap, HashSet};
+use semver::{Version, VersionReq};
+use thiserror::Error;
+
+/// A registered version of a DX tool and its requirements on other tools.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolInfo {
+    pub id: String,
+    pub version: Version,
+    pub dependencies: Vec<(String, VersionReq)>,
+}
+
+impl ToolInfo {
+    pub fn new(id: impl Into<String>, version: Version) -> Self {
+        Self {
+            id: id.into(),
+            version,
+            dependencies: Vec::new(),
+        }
+    }
+
+    pub fn with_dependency(mut self, id: impl Into<String>, requirement: VersionReq) -> Self {
+        self.dependencies.push((id.into(), requirement));
+        self
+    }
+}
+
+/// No registered version of `tool` satisfies every requirement placed on it,
+/// with each conflicting requirement and the tool that placed it named.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("no version of {tool} satisfies {}", format_requirements(.requirements))]
+pub struct DependencyConflict {
+    pub tool: String,
+    pub requirements: Vec<(String, VersionReq)>,
+}
+
+fn format_requirements(requirements: &[(String, VersionReq)]) -> String {
+    requirements
+        .iter()
+        .map(|(requirer, requirement)| format!("{requirement} (required by {requirer})"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The versions of every known DX tool, by tool id.
+#[derive(Debug, Default)]
+pub struct ToolRegistry {
+    tools: HashMap<String, Vec<ToolInfo>>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, tool: ToolInfo) {
+        self.tools.entry(tool.id.clone()).or_default().push(tool);
+    }
+
+    pub fn versions_of(&self, id: &str) -> &[ToolInfo] {
+        self.tools.get(id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Walks dependency declarations from `root` and picks, for every tool
+    /// reached, the newest registered version satisfying all requirements
+    /// placed on it. Requirements accumulate monotonically across passes —
+    /// including from versions a later pass narrows away — which keeps
+    /// resolution deterministic and guarantees it terminates, at the cost of
+    /// occasionally reporting a conflict a cleverer solver could avoid.
+    pub fn resolve_dependencies(&self, root: &str) -> Result<Vec<ToolInfo>, DependencyConflict> {
+        let mut requirements: HashMap<String, Vec<(String, VersionReq)>> = HashMap::default();
+        loop {
+            let mut chosen: Vec<&ToolInfo> = Vec::new();
+            let mut visited: HashSet<&str> = HashSet::default();
+            let mut queue = vec![root];
+            let mut requirements_grew = false;
+            while let Some(id) = queue.pop() {
+                if !visited.insert(id) {
+                    continue;
+                }
+                let placed = requirements.get(id).cloned().unwrap_or_default();
+                let Some(candidate) = self
+                    .versions_of(id)
+                    .iter()
+                    .filter(|tool| {
+                        placed
+                            .iter()
+                            .all(|(_, requirement)| requirement.matches(&tool.version))
+                    })
+                    .max_by(|a, b| a.version.cmp(&b.version))
+                else {
+                    return Err(DependencyConflict {
+                        tool: id.to_string(),
+                        requirements: placed,
+                    });
+                };
+                chosen.push(candidate);
+                for (dependency, requirement) in &candidate.dependencies {
+                    let placed_on_dependency = requirements.entry(dependency.clone()).or_default();
+                    let pair = (id.to_string(), requirement.clone());
+                    if !placed_on_dependency.contains(&pair) {
+                        placed_on_dependency.push(pair);
+                        requirements_grew = true;
+                    }
+                    queue.push(dependency);
+                }
+            }
+            if !requirements_grew {
+                let mut resolved: Vec<ToolInfo> = chosen.into_iter().cloned().collect();
+                resolved.sort_by(|a, b| a.id.cmp(&b.id));
+                return Ok(resolved);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requirement(text: &str) -> VersionReq {
+        VersionReq::parse(text).unwrap()
+    }
+
+    #[test]
+    fn test_newest_satisfying_version_is_chosen() {
+        let mut registry = ToolRegistry::new();
+        registry.register(
+            ToolInfo::new("app", Version::new(1, 0, 0))
+                .with_dependency("formatter", requirement("^1.0.0")),
+        );
+        registry.register(ToolInfo::new("formatter", Version::new(1, 0, 0)));
+        registry.register(ToolInfo::new("formatter", Version::new(1, 2, 0)));
+        registry.register(ToolInfo::new("formatter", Version::new(2, 0, 0)));
+
+        let resolved = registry.resolve_dependencies("app").unwrap();
+        let formatter = resolved.iter().find(|tool| tool.id == "formatter").unwrap();
+        assert_eq!(formatter.version, Version::new(1, 2, 0));
+    }
+
+    #[test]
+    fn test_shared_requirements_narrow_the_selection() {
+        let mut registry = ToolRegistry::new();
+        registry.register(
+            ToolInfo::new("app", Version::new(1, 0, 0))
+                .with_dependency("linter", requirement("^1.0.0"))
+                .with_dependency("bundler", requirement("^1.0.0")),
+        );
+        registry.register(
+            ToolInfo::new("bundler", Version::new(1, 0, 0))
+                .with_dependency("linter", requirement("^1.3.0")),
+        );
+        registry.register(ToolInfo::new("linter", Version::new(1, 1, 0)));
+        registry.register(ToolInfo::new("linter", Version::new(1, 4, 0)));
+
+        let resolved = registry.resolve_dependencies("app").unwrap();
+        let linter = resolved.iter().find(|tool| tool.id == "linter").unwrap();
+        assert_eq!(linter.version, Version::new(1, 4, 0), "satisfies both");
+    }
+
+    #[test]
+    fn test_incompatible_shared_dependency_reports_both_requirements() {
+        let mut registry = ToolRegistry::new();
+        registry.register(
+            ToolInfo::new("app", Version::new(1, 0, 0))
+                .with_dependency("bundler", requirement("^1.0.0"))
+                .with_dependency("minifier", requirement("^1.0.0")),
+        );
+        registry.register(
+            ToolInfo::new("bundler", Version::new(1, 0, 0))
+                .with_dependency("parser", requirement("^1.0.0")),
+        );
+        registry.register(
+            ToolInfo::new("minifier", Version::new(1, 0, 0))
+                .with_dependency("parser", requirement("^2.0.0")),
+        );
+        registry.register(ToolInfo::new("parser", Version::new(1, 0, 0)));
+        registry.register(ToolInfo::new("parser", Version::new(2, 0, 0)));
+
+        let conflict = registry.resolve_dependencies("app").unwrap_err();
+        assert_eq!(conflict.tool, "parser");
+        let requirers: Vec<&str> = conflict
+            .requirements
+            .iter()
+            .map(|(requirer, _)| requirer.as_str())
+            .collect();
+        assert!(requirers.contains(&"bundler"));
+        assert!(requirers.contains(&"minifier"));
+    }
+
+    #[test]
+    fn test_unregistered_root_is_a_conflict() {
+        let registry = ToolRegistry::new();
+        let conflict = registry.resolve_dependencies("ghost").unwrap_err();
+        assert_eq!(conflict.tool, "ghost");
+        assert!(conflict.requirements.is_empty());
+    }
+}
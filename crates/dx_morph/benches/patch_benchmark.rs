@@ -0,0 +1,61 @@
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+use dx_morph::{
+    AtomicDirtyMask, BindingEntry, BindingMap, BindingType, ComponentState, StatePatcher,
+};
+
+struct BenchComponent {
+    mask: AtomicDirtyMask,
+    bytes: Vec<u8>,
+}
+
+impl ComponentState for BenchComponent {
+    fn component_id(&self) -> u32 {
+        1
+    }
+
+    fn dirty_mask(&self) -> &AtomicDirtyMask {
+        &self.mask
+    }
+
+    fn state_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+fn patch_benchmark(criterion: &mut Criterion) {
+    const BINDING_COUNT: u16 = 60;
+    const VALUE_LEN: u32 = 5;
+
+    let entries: Vec<BindingEntry> = (0..BINDING_COUNT)
+        .map(|index| {
+            BindingEntry::new(
+                index,
+                BindingType::Text,
+                0,
+                u32::from(index) + 10,
+                u32::from(index) * VALUE_LEN,
+                VALUE_LEN,
+            )
+        })
+        .collect();
+    let mut patcher = StatePatcher::new();
+    patcher
+        .register_binding_map(BindingMap::new(1, entries))
+        .unwrap();
+    let component = BenchComponent {
+        mask: AtomicDirtyMask::new(),
+        bytes: vec![b'x'; usize::from(BINDING_COUNT) * VALUE_LEN as usize],
+    };
+
+    criterion.bench_function("patch_60_bindings_10_dirty_bits", |bencher| {
+        bencher.iter(|| {
+            for bit in (0..BINDING_COUNT).step_by(6) {
+                component.mask.mark_dirty(bit);
+            }
+            black_box(patcher.patch(&component))
+        })
+    });
+}
+
+criterion_group!(benches, patch_benchmark);
+criterion_main!(benches);
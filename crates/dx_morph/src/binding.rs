@@ -0,0 +1,271 @@
+use std::borrow::Cow;
+
+/// How a changed field maps onto the DOM node it is bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum BindingType {
+    Text = 0,
+    Attribute = 1,
+    Style = 2,
+    ClassToggle = 3,
+    Visibility = 4,
+    /// Two-way: pushes state to the input like [`Text`](Self::Text), and
+    /// accepts DOM input events written back via
+    /// [`StatePatcher::apply_input_event`](crate::StatePatcher::apply_input_event).
+    ValueSync = 5,
+}
+
+impl BindingType {
+    pub fn from_u8(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Text),
+            1 => Some(Self::Attribute),
+            2 => Some(Self::Style),
+            3 => Some(Self::ClassToggle),
+            4 => Some(Self::Visibility),
+            5 => Some(Self::ValueSync),
+            _ => None,
+        }
+    }
+}
+
+/// How the bytes at a binding's `value_offset` are interpreted when a
+/// [`Text`](BindingType::Text) binding renders them. Non-`Bytes` values are
+/// formatted at patch time, so numeric state doesn't have to be
+/// pre-stringified into the state region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum ValueType {
+    /// A pre-formatted UTF-8 byte range; the original behavior.
+    Bytes = 0,
+    /// A little-endian `i32`; `value_len` must be 4.
+    I32 = 1,
+    /// A little-endian `f64`; `value_len` must be 8.
+    F64 = 2,
+    /// A single byte, zero meaning false; `value_len` must be 1.
+    Bool = 3,
+}
+
+impl ValueType {
+    pub fn from_u8(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Bytes),
+            1 => Some(Self::I32),
+            2 => Some(Self::F64),
+            3 => Some(Self::Bool),
+            _ => None,
+        }
+    }
+}
+
+/// One field-to-node binding. Laid out `#[repr(C)]` so binding maps can be
+/// produced by codegen as flat byte tables and shared across the WASM
+/// boundary. Fields are ordered widest-first so the struct packs without
+/// interior padding; the serialized record covers the tail padding as
+/// reserved bytes (see [`BINDING_ENTRY_LEN`](crate::BINDING_ENTRY_LEN)).
+// `Hash` is a derive like any other: it adds no layout obligations, so the
+// `#[repr(C)]` byte-table contract is unaffected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct BindingEntry {
+    /// Which dirty bit this binding listens to. `u16` so components with
+    /// more than 64 bound fields can bind past bit 63; see
+    /// [`DIRTY_MASK_BITS`](crate::DIRTY_MASK_BITS) for the cap.
+    pub dirty_bit: u16,
+    /// Attribute, style property, or class id, depending on the binding
+    /// type.
+    pub target_id: u16,
+    /// The DOM node the binding writes to.
+    pub node_id: u32,
+    /// Where the bound value lives in the component's state region.
+    pub value_offset: u32,
+    pub value_len: u32,
+    /// Byte offset of the presence flags this binding is gated by; only
+    /// meaningful when `optional` is set.
+    pub presence_offset: u32,
+    /// A [`BindingType`] discriminant; unknown values are skipped by the
+    /// patcher for forward compatibility, or routed to its unknown-binding
+    /// handler when one is registered.
+    pub binding_type: u8,
+    /// Which bit of the presence byte holds this binding's "present" flag.
+    pub presence_bit: u8,
+    /// 1 for an optional binding: a clear presence bit emits
+    /// [`RenderOp::Remove`](crate::RenderOp::Remove) instead of a set op.
+    pub optional: u8,
+    /// A [`ValueType`] discriminant, consulted by `Text` bindings. Zero
+    /// (`Bytes`) keeps the pre-formatted behavior, so tables from older
+    /// codegen — which zeroed what used to be the reserved bytes — are
+    /// unaffected.
+    pub value_type: u8,
+    /// Type-dependent rendering spec for typed values; zero is the default.
+    /// For `F64` a nonzero spec is the number of fixed decimal places; for
+    /// `Bool` spec 1 renders `1`/`0` instead of `true`/`false`.
+    pub format_spec: u8,
+}
+
+impl BindingEntry {
+    pub fn new(
+        dirty_bit: u16,
+        binding_type: BindingType,
+        target_id: u16,
+        node_id: u32,
+        value_offset: u32,
+        value_len: u32,
+    ) -> Self {
+        Self {
+            dirty_bit,
+            target_id,
+            node_id,
+            value_offset,
+            value_len,
+            presence_offset: 0,
+            binding_type: binding_type as u8,
+            presence_bit: 0,
+            optional: 0,
+            value_type: ValueType::Bytes as u8,
+            format_spec: 0,
+        }
+    }
+
+    /// Marks the bound slot as holding a typed value that `Text` bindings
+    /// format at patch time instead of copying pre-formatted bytes.
+    pub fn with_value_type(mut self, value_type: ValueType, format_spec: u8) -> Self {
+        self.value_type = value_type as u8;
+        self.format_spec = format_spec;
+        self
+    }
+
+    /// Gates the binding on a presence bit: when the bit at `presence_bit`
+    /// of the state byte at `presence_offset` is clear, patches emit a
+    /// [`RenderOp::Remove`](crate::RenderOp::Remove) for the node instead of
+    /// setting an empty value.
+    pub fn with_presence(mut self, presence_offset: u32, presence_bit: u8) -> Self {
+        self.presence_offset = presence_offset;
+        self.presence_bit = presence_bit;
+        self.optional = 1;
+        self
+    }
+}
+
+/// Animate-rather-than-snap hint for a [`Style`](BindingType::Style) or
+/// [`ClassToggle`](BindingType::ClassToggle) binding. Carried through to the
+/// emitted op as [`RenderOp::Animated`](crate::RenderOp::Animated); the state
+/// and dirty-bit logic are unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Transition {
+    pub duration_ms: u16,
+    /// Names an easing curve the runtime knows; 0 is conventionally linear.
+    pub easing_id: u8,
+}
+
+/// A component's bindings, in codegen order.
+#[derive(Debug, Clone)]
+pub struct BindingMap {
+    pub component_id: u32,
+    entries: Cow<'static, [BindingEntry]>,
+    /// Transitions by entry index. A side table rather than fields on
+    /// [`BindingEntry`]: transitions are authored at registration time, not
+    /// emitted by codegen, so they stay out of the shared byte table.
+    transitions: Vec<(usize, Transition)>,
+    /// Entry indices grouped by dirty bit, groups ascending by bit and
+    /// indices in entry order within a group. Computed once at construction
+    /// so each set bit of a patch looks its bindings up directly instead of
+    /// scanning every entry.
+    bindings_by_bit: Vec<(u16, Vec<u32>)>,
+}
+
+impl BindingMap {
+    pub fn new(component_id: u32, entries: Vec<BindingEntry>) -> Self {
+        Self {
+            component_id,
+            bindings_by_bit: group_entries_by_bit(&entries),
+            entries: Cow::Owned(entries),
+            transitions: Vec::new(),
+        }
+    }
+
+    pub fn from_static_slice(component_id: u32, entries: &'static [BindingEntry]) -> Self {
+        Self {
+            component_id,
+            bindings_by_bit: group_entries_by_bit(entries),
+            entries: Cow::Borrowed(entries),
+            transitions: Vec::new(),
+        }
+    }
+
+    /// Attaches a transition to the entry at `entry_index`. Only `Style` and
+    /// `ClassToggle` ops are animated; on any other entry the hint is
+    /// ignored at patch time.
+    pub fn with_transition(mut self, entry_index: usize, transition: Transition) -> Self {
+        self.transitions.push((entry_index, transition));
+        self
+    }
+
+    pub fn transition_for(&self, entry_index: usize) -> Option<Transition> {
+        self.transitions
+            .iter()
+            .find(|(index, _)| *index == entry_index)
+            .map(|(_, transition)| *transition)
+    }
+
+    pub fn entries(&self) -> &[BindingEntry] {
+        &self.entries
+    }
+
+    /// The indices of the entries listening to `bit`, in entry order; the
+    /// index form of [`get_bindings_for_bit`](Self::get_bindings_for_bit),
+    /// for callers that also need per-entry side data such as
+    /// [`transition_for`](Self::transition_for).
+    pub fn entry_indices_for_bit(&self, bit: u16) -> &[u32] {
+        self.bindings_by_bit
+            .binary_search_by_key(&bit, |(group_bit, _)| *group_bit)
+            .map_or(&[], |position| &self.bindings_by_bit[position].1)
+    }
+
+    /// The entries listening to `bit`, in entry order.
+    pub fn get_bindings_for_bit(&self, bit: u16) -> impl Iterator<Item = &BindingEntry> + '_ {
+        self.entry_indices_for_bit(bit)
+            .iter()
+            .filter_map(|index| self.entries.get(*index as usize))
+    }
+}
+
+fn group_entries_by_bit(entries: &[BindingEntry]) -> Vec<(u16, Vec<u32>)> {
+    let mut groups: Vec<(u16, Vec<u32>)> = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let index = index as u32;
+        match groups.binary_search_by_key(&entry.dirty_bit, |(bit, _)| *bit) {
+            Ok(position) => groups[position].1.push(index),
+            Err(position) => groups.insert(position, (entry.dirty_bit, vec![index])),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bit_lookup_preserves_entry_order() {
+        let map = BindingMap::new(
+            1,
+            vec![
+                BindingEntry::new(5, BindingType::Text, 0, 10, 0, 1),
+                BindingEntry::new(0, BindingType::Text, 0, 11, 1, 1),
+                BindingEntry::new(5, BindingType::Style, 7, 12, 2, 1),
+                BindingEntry::new(200, BindingType::Text, 0, 13, 3, 1),
+            ],
+        );
+        assert_eq!(map.entry_indices_for_bit(5), &[0, 2]);
+        assert_eq!(map.entry_indices_for_bit(0), &[1]);
+        assert_eq!(map.entry_indices_for_bit(200), &[3]);
+        assert!(map.entry_indices_for_bit(1).is_empty());
+
+        let node_ids: Vec<u32> = map
+            .get_bindings_for_bit(5)
+            .map(|entry| entry.node_id)
+            .collect();
+        assert_eq!(node_ids, vec![10, 12]);
+    }
+}
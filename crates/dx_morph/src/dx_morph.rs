@@ -0,0 +1,20 @@
+//! Dirty-bit driven DOM patching. Components keep their state in a flat byte
+//! region; mutations set bits in an atomic dirty mask, and the
+//! [`StatePatcher`] turns set bits into minimal [`RenderOp`]s via per-field
+//! [`BindingEntry`]s.
+
+mod binding;
+mod ffi;
+mod list;
+mod patcher;
+mod render_op;
+mod state;
+mod wasm_bridge;
+
+pub use binding::*;
+pub use ffi::*;
+pub use list::*;
+pub use patcher::*;
+pub use render_op::*;
+pub use state::*;
+pub use wasm_bridge::*;
@@ -0,0 +1,231 @@
+//! A C ABI around [`StatePatcher`] so the dirty-bit patcher can be embedded
+//! in non-Rust hosts (a native mobile shell, a game engine). Byte formats
+//! are shared with the wasm bridge: binding maps are registered from
+//! serialized [`BindingEntry`](crate::BindingEntry) records, state regions
+//! are `[dirty mask][state bytes]`, and emitted ops use the bridge's op
+//! record layout — see [`patch_into_buffer`] and [`decode_binding_entries`]
+//! for the exact encodings.
+//!
+//! Ownership rules:
+//!
+//! * The handle returned by [`dx_morph_patcher_new`] is owned by the host
+//!   and must be destroyed with [`dx_morph_patcher_free`], exactly once.
+//!   The handle is not synchronized; the host must not call into one
+//!   patcher from two threads at once.
+//! * Every buffer argument is owned by the host and only borrowed for the
+//!   duration of the call; the library never retains a pointer past a call
+//!   and never frees host memory.
+//!
+//! Calls report [`DX_MORPH_OK`], an op count, or a negative error code;
+//! they never unwind across the boundary.
+
+use crate::{BindingMap, StatePatcher, decode_binding_entries, patch_into_buffer};
+
+pub const DX_MORPH_OK: i32 = 0;
+/// A required pointer argument was null.
+pub const DX_MORPH_ERROR_NULL: i32 = -1;
+/// The entries buffer is not a whole number of well-formed binding records.
+pub const DX_MORPH_ERROR_INVALID_ENTRIES: i32 = -2;
+/// The map binds a `(dirty bit, node, binding type)` already registered for
+/// the component; nothing from the map was registered.
+pub const DX_MORPH_ERROR_DUPLICATE_BINDING: i32 = -3;
+/// The state region is shorter than its dirty-mask prefix, or the ops did
+/// not fit in the output buffer. The mask is left intact, so the host can
+/// retry with a larger buffer.
+pub const DX_MORPH_ERROR_PATCH_FAILED: i32 = -4;
+
+/// Creates a patcher and transfers ownership of the returned handle to the
+/// caller; release it with [`dx_morph_patcher_free`].
+#[unsafe(no_mangle)]
+pub extern "C" fn dx_morph_patcher_new() -> *mut StatePatcher {
+    Box::into_raw(Box::new(StatePatcher::new()))
+}
+
+/// Destroys a patcher created by [`dx_morph_patcher_new`]. Null is a no-op.
+///
+/// # Safety
+///
+/// `patcher` must be a handle returned by [`dx_morph_patcher_new`] that has
+/// not already been freed, and no other call may be using it concurrently.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dx_morph_patcher_free(patcher: *mut StatePatcher) {
+    if !patcher.is_null() {
+        drop(unsafe { Box::from_raw(patcher) });
+    }
+}
+
+/// Registers a component's binding map from `entries_len` bytes of
+/// serialized [`BindingEntry`](crate::BindingEntry) records at
+/// `entries_ptr`. Returns [`DX_MORPH_OK`] or a negative error code.
+///
+/// # Safety
+///
+/// `patcher` must be a live handle from [`dx_morph_patcher_new`], and
+/// `entries_ptr` must point to `entries_len` readable bytes that outlive
+/// the call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dx_morph_patcher_register(
+    patcher: *mut StatePatcher,
+    component_id: u32,
+    entries_ptr: *const u8,
+    entries_len: usize,
+) -> i32 {
+    if patcher.is_null() || entries_ptr.is_null() {
+        return DX_MORPH_ERROR_NULL;
+    }
+    let patcher = unsafe { &mut *patcher };
+    let bytes = unsafe { std::slice::from_raw_parts(entries_ptr, entries_len) };
+    let Some(entries) = decode_binding_entries(bytes) else {
+        return DX_MORPH_ERROR_INVALID_ENTRIES;
+    };
+    match patcher.register_binding_map(BindingMap::new(component_id, entries)) {
+        Ok(()) => DX_MORPH_OK,
+        Err(_) => DX_MORPH_ERROR_DUPLICATE_BINDING,
+    }
+}
+
+/// Patches `component_id` against the state region and writes encoded op
+/// records to the output buffer. Returns the op count (possibly zero), or a
+/// negative error code — on error nothing is drained from the dirty mask.
+///
+/// # Safety
+///
+/// `patcher` must be a live handle from [`dx_morph_patcher_new`];
+/// `state_ptr` must point to `state_len` writable bytes and `out_ptr` to
+/// `out_len` writable bytes, non-overlapping and outliving the call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dx_morph_patcher_patch(
+    patcher: *mut StatePatcher,
+    component_id: u32,
+    state_ptr: *mut u8,
+    state_len: usize,
+    out_ptr: *mut u8,
+    out_len: usize,
+) -> i32 {
+    if patcher.is_null() || state_ptr.is_null() || out_ptr.is_null() {
+        return DX_MORPH_ERROR_NULL;
+    }
+    let patcher = unsafe { &*patcher };
+    let state_region = unsafe { std::slice::from_raw_parts_mut(state_ptr, state_len) };
+    let out = unsafe { std::slice::from_raw_parts_mut(out_ptr, out_len) };
+    match patch_into_buffer(patcher, component_id, state_region, out) {
+        // An op count never approaches i32::MAX in practice, but a
+        // truncated count would misreport the buffer's contents, so an
+        // overflow is surfaced as a failure instead.
+        Some(op_count) => i32::try_from(op_count).unwrap_or(DX_MORPH_ERROR_PATCH_FAILED),
+        None => DX_MORPH_ERROR_PATCH_FAILED,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BindingEntry, BindingType, RenderOp, STATE_MASK_LEN, decode_ops};
+
+    fn entry_record(entry: &BindingEntry) -> Vec<u8> {
+        let mut record = entry.dirty_bit.to_le_bytes().to_vec();
+        record.extend_from_slice(&entry.target_id.to_le_bytes());
+        record.extend_from_slice(&entry.node_id.to_le_bytes());
+        record.extend_from_slice(&entry.value_offset.to_le_bytes());
+        record.extend_from_slice(&entry.value_len.to_le_bytes());
+        record.extend_from_slice(&entry.presence_offset.to_le_bytes());
+        record.push(entry.binding_type);
+        record.push(entry.presence_bit);
+        record.push(entry.optional);
+        record.push(entry.value_type);
+        record.push(entry.format_spec);
+        record.extend_from_slice(&[0; 3]);
+        record
+    }
+
+    fn state_region(mask: u64, bytes: &[u8]) -> Vec<u8> {
+        let mut region = vec![0u8; STATE_MASK_LEN];
+        region[..8].copy_from_slice(&mask.to_le_bytes());
+        region.extend_from_slice(bytes);
+        region
+    }
+
+    #[test]
+    fn test_ffi_surface_round_trips_through_raw_pointers() {
+        let patcher = dx_morph_patcher_new();
+        let entries = entry_record(&BindingEntry::new(0, BindingType::Text, 0, 10, 0, 5));
+
+        let status =
+            unsafe { dx_morph_patcher_register(patcher, 1, entries.as_ptr(), entries.len()) };
+        assert_eq!(status, DX_MORPH_OK);
+
+        // Dirty mask 0b1 followed by the state bytes the binding reads.
+        let mut state_region = state_region(1, b"hello");
+        let mut out = vec![0u8; 64];
+        let op_count = unsafe {
+            dx_morph_patcher_patch(
+                patcher,
+                1,
+                state_region.as_mut_ptr(),
+                state_region.len(),
+                out.as_mut_ptr(),
+                out.len(),
+            )
+        };
+        assert_eq!(op_count, 1);
+        assert_eq!(
+            decode_ops(&out, 1).unwrap(),
+            vec![RenderOp::SetText {
+                node_id: 10,
+                value: "hello".into()
+            }]
+        );
+        assert_eq!(
+            &state_region[..STATE_MASK_LEN],
+            &[0; STATE_MASK_LEN],
+            "a successful patch drains the mask"
+        );
+
+        unsafe { dx_morph_patcher_free(patcher) };
+    }
+
+    #[test]
+    fn test_ffi_errors_are_reported_as_codes() {
+        let patcher = dx_morph_patcher_new();
+        let entries = entry_record(&BindingEntry::new(0, BindingType::Text, 0, 10, 0, 5));
+
+        assert_eq!(
+            unsafe { dx_morph_patcher_register(std::ptr::null_mut(), 1, entries.as_ptr(), 1) },
+            DX_MORPH_ERROR_NULL
+        );
+        assert_eq!(
+            unsafe { dx_morph_patcher_register(patcher, 1, entries.as_ptr(), 3) },
+            DX_MORPH_ERROR_INVALID_ENTRIES,
+            "a partial record must not register"
+        );
+        assert_eq!(
+            unsafe { dx_morph_patcher_register(patcher, 1, entries.as_ptr(), entries.len()) },
+            DX_MORPH_OK
+        );
+        assert_eq!(
+            unsafe { dx_morph_patcher_register(patcher, 1, entries.as_ptr(), entries.len()) },
+            DX_MORPH_ERROR_DUPLICATE_BINDING
+        );
+
+        // An output buffer too small for the op: the error is reported and
+        // the dirty mask survives for a retry.
+        let mut state_region = state_region(1, b"hello");
+        let mut undersized = vec![0u8; 4];
+        let status = unsafe {
+            dx_morph_patcher_patch(
+                patcher,
+                1,
+                state_region.as_mut_ptr(),
+                state_region.len(),
+                undersized.as_mut_ptr(),
+                undersized.len(),
+            )
+        };
+        assert_eq!(status, DX_MORPH_ERROR_PATCH_FAILED);
+        assert_eq!(&state_region[..8], &1u64.to_le_bytes());
+        assert_eq!(&state_region[8..STATE_MASK_LEN], &[0; STATE_MASK_LEN - 8]);
+
+        unsafe { dx_morph_patcher_free(patcher) };
+        unsafe { dx_morph_patcher_free(std::ptr::null_mut()) };
+    }
+}
@@ -0,0 +1,225 @@
+//! Keyed-list reconciliation: when a keyed collection reorders, the patcher
+//! emits [`RenderOp::NodeMove`] ops computed from the old and new key order
+//! instead of tearing children down and rebuilding them, so DOM node
+//! identity — and with it focus, selection, and input state — survives the
+//! reorder.
+
+use crate::{KeyedListState, RenderOp};
+
+/// One keyed child as currently rendered: its stable key and the DOM node
+/// holding it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyedChild {
+    pub key: u64,
+    pub node_id: u32,
+}
+
+/// Tracks one keyed collection across patches: remembers the key order last
+/// rendered and turns the next order into the fewest moves that realize it.
+#[derive(Debug, Clone)]
+pub struct KeyedList {
+    parent_id: u32,
+    children: Vec<KeyedChild>,
+}
+
+impl KeyedList {
+    pub fn new(parent_id: u32, children: Vec<KeyedChild>) -> Self {
+        Self {
+            parent_id,
+            children,
+        }
+    }
+
+    /// The children in their currently rendered order.
+    pub fn children(&self) -> &[KeyedChild] {
+        &self.children
+    }
+
+    /// Reconciles the rendered order with [`KeyedListState::child_keys`];
+    /// see [`reorder`](Self::reorder).
+    pub fn reconcile(&mut self, state: &dyn KeyedListState) -> Vec<RenderOp> {
+        self.reorder(state.child_keys())
+    }
+
+    /// Reconciles the rendered order with `new_keys`, returning the move ops
+    /// and adopting the new order. A longest increasing subsequence of the
+    /// surviving children anchors in place and only the rest move, so an
+    /// unchanged order emits nothing and a full reversal emits `n - 1`
+    /// moves. Ops are emitted back to front so each move's anchor is already
+    /// in its final position when the runtime, applying in order, reaches
+    /// it. Keys without a rendered child, and children whose key
+    /// disappeared, are left to the insertion and removal paths — this
+    /// reorders what survives.
+    pub fn reorder(&mut self, new_keys: &[u64]) -> Vec<RenderOp> {
+        let mut old_positions = Vec::new();
+        let mut ordered = Vec::new();
+        for key in new_keys {
+            if let Some(old_position) = self.children.iter().position(|child| child.key == *key) {
+                old_positions.push(old_position);
+                ordered.push(self.children[old_position].clone());
+            }
+        }
+
+        let anchored = longest_increasing_subsequence(&old_positions);
+        let mut ops = Vec::new();
+        let mut next_in_place: Option<u32> = None;
+        for (child, stays) in ordered.iter().zip(&anchored).rev() {
+            if !stays {
+                ops.push(RenderOp::NodeMove {
+                    parent_id: self.parent_id,
+                    node_id: child.node_id,
+                    before_node_id: next_in_place,
+                });
+            }
+            next_in_place = Some(child.node_id);
+        }
+        self.children = ordered;
+        ops
+    }
+}
+
+/// Marks one longest strictly increasing subsequence of `sequence`:
+/// `true` at the positions that belong to it. Everything unmarked must
+/// move.
+fn longest_increasing_subsequence(sequence: &[usize]) -> Vec<bool> {
+    // Patience-sorting variant: `tails[length]` is the position ending the
+    // best increasing run of `length + 1` seen so far, and each position
+    // remembers its predecessor so the winning run can be walked back.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut predecessors: Vec<Option<usize>> = vec![None; sequence.len()];
+    for (position, &value) in sequence.iter().enumerate() {
+        let run_length = tails.partition_point(|&tail| sequence[tail] < value);
+        predecessors[position] = run_length
+            .checked_sub(1)
+            .and_then(|shorter| tails.get(shorter).copied());
+        if let Some(slot) = tails.get_mut(run_length) {
+            *slot = position;
+        } else {
+            tails.push(position);
+        }
+    }
+
+    let mut marks = vec![false; sequence.len()];
+    let mut current = tails.last().copied();
+    while let Some(position) = current {
+        marks[position] = true;
+        current = predecessors[position];
+    }
+    marks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AtomicDirtyMask, ComponentState};
+
+    fn list() -> KeyedList {
+        KeyedList::new(
+            1,
+            (1..=4)
+                .map(|key| KeyedChild {
+                    key,
+                    node_id: key as u32 * 10,
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_reversing_a_list_emits_moves_not_rebuilds() {
+        let mut keyed_list = list();
+        let ops = keyed_list.reorder(&[4, 3, 2, 1]);
+        assert_eq!(ops.len(), 3, "one child anchors, the rest move: {ops:?}");
+        assert!(
+            ops.iter().all(|op| matches!(op, RenderOp::NodeMove { .. })),
+            "a reorder never removes or reinserts: {ops:?}"
+        );
+        assert_eq!(
+            ops,
+            vec![
+                RenderOp::NodeMove {
+                    parent_id: 1,
+                    node_id: 20,
+                    before_node_id: Some(10),
+                },
+                RenderOp::NodeMove {
+                    parent_id: 1,
+                    node_id: 30,
+                    before_node_id: Some(20),
+                },
+                RenderOp::NodeMove {
+                    parent_id: 1,
+                    node_id: 40,
+                    before_node_id: Some(30),
+                },
+            ]
+        );
+        let keys: Vec<u64> = keyed_list
+            .children()
+            .iter()
+            .map(|child| child.key)
+            .collect();
+        assert_eq!(keys, vec![4, 3, 2, 1], "the new order is adopted");
+        assert!(
+            keyed_list.reorder(&[4, 3, 2, 1]).is_empty(),
+            "an unchanged order emits nothing"
+        );
+    }
+
+    #[test]
+    fn test_single_displacement_moves_one_child() {
+        let mut keyed_list = list();
+        // 1 2 3 4 -> 1 3 4 2: only 2 is out of order relative to the rest.
+        let ops = keyed_list.reorder(&[1, 3, 4, 2]);
+        assert_eq!(
+            ops,
+            vec![RenderOp::NodeMove {
+                parent_id: 1,
+                node_id: 20,
+                before_node_id: None,
+            }],
+            "the displaced child moves to the end; everything else anchors"
+        );
+    }
+
+    #[test]
+    fn test_reconcile_reads_the_key_sequence_from_state() {
+        struct Row {
+            mask: AtomicDirtyMask,
+            keys: Vec<u64>,
+        }
+
+        impl ComponentState for Row {
+            fn component_id(&self) -> u32 {
+                1
+            }
+            fn dirty_mask(&self) -> &AtomicDirtyMask {
+                &self.mask
+            }
+            fn state_bytes(&self) -> &[u8] {
+                &[]
+            }
+        }
+
+        impl KeyedListState for Row {
+            fn child_keys(&self) -> &[u64] {
+                &self.keys
+            }
+        }
+
+        let mut keyed_list = list();
+        let row = Row {
+            mask: AtomicDirtyMask::new(),
+            keys: vec![2, 1, 3, 4],
+        };
+        let ops = keyed_list.reconcile(&row);
+        assert_eq!(
+            ops,
+            vec![RenderOp::NodeMove {
+                parent_id: 1,
+                node_id: 20,
+                before_node_id: Some(10),
+            }]
+        );
+    }
+}
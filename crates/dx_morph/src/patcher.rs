@@ -0,0 +1,1294 @@
+use crate::{
+    BindingEntry, BindingMap, BindingType, ComponentState, DirtyMask, MutableComponentState,
+    RenderOp, Transition, ValueType,
+};
+use std::fmt;
+
+/// Rejected registration: two maps for one component bind the same
+/// `(dirty bit, node, binding type)`, which would emit duplicate ops on
+/// every patch. The crate is dependency-free, so this is a plain enum
+/// rather than a `thiserror` type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MorphError {
+    DuplicateBinding {
+        component_id: u32,
+        dirty_bit: u16,
+        node_id: u32,
+        binding_type: u8,
+    },
+}
+
+impl fmt::Display for MorphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateBinding {
+                component_id,
+                dirty_bit,
+                node_id,
+                binding_type,
+            } => write!(
+                f,
+                "component {component_id} binds (bit {dirty_bit}, node {node_id}, \
+                 type {binding_type}) more than once"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MorphError {}
+
+/// A DOM input event reported by the runtime: the node that fired and its new
+/// value. The value is written into the bound fixed-size state slot, NUL
+/// padded; readers trim trailing NULs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputEvent {
+    pub component_id: u32,
+    pub node_id: u32,
+    pub value: Vec<u8>,
+}
+
+/// A field computed from other state fields (`full_name = first + last`).
+/// When any input bit is dirty at patch time, `recompute` refreshes the
+/// derived slot and the derived bit joins the patch, so bindings on it emit
+/// ops for the fresh value.
+pub struct DerivedBinding {
+    pub component_id: u32,
+    /// Dirty bits of the fields the derived value is computed from.
+    pub input_bits: Vec<u16>,
+    /// The bit the derived field's own bindings are registered on. Mutators
+    /// never mark it directly; patching marks it whenever an input changed.
+    pub derived_bit: u16,
+    /// Refreshes the derived field from its inputs. The callback receives
+    /// the whole state region but must write only the derived field's slot,
+    /// NUL-padding any unused tail just as mutators do for their own slots.
+    /// It must be deterministic — it runs once per patch that has a dirty
+    /// input, and the emitted ops are exactly what it wrote.
+    pub recompute: Box<dyn Fn(&mut [u8])>,
+}
+
+/// Extra information about one drained patch, collected only when
+/// [`StatePatcher::set_orphan_tracking`] is enabled.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatchDiagnostics {
+    /// Drained dirty bits with nothing to act on them — no binding entry and
+    /// no derived binding input — ascending. A mutator marking such a bit
+    /// does nothing, which is usually codegen pairing a field with the wrong
+    /// bit.
+    pub orphaned_bits: Vec<u16>,
+}
+
+/// Turns dirty bits into [`RenderOp`]s using each component's registered
+/// [`BindingMap`].
+///
+/// Op ordering is deterministic and documented: components in registration
+/// order, then dirty bits ascending, then binding entries in map order. The
+/// same dirty state always yields the same op sequence, so snapshots and
+/// replay logs are stable.
+#[derive(Default)]
+pub struct StatePatcher {
+    /// Registration order is meaningful (it defines op order across
+    /// components), so maps live in a `Vec` rather than a hash map.
+    components: Vec<BindingMap>,
+    derived: Vec<DerivedBinding>,
+    batch_style_ops: bool,
+    track_orphaned_bits: bool,
+    unknown_binding_handler: Option<Box<dyn Fn(&BindingEntry, &[u8]) -> Option<RenderOp>>>,
+}
+
+impl StatePatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// When enabled, all [`RenderOp::SetStyle`] ops for one node in a patch
+    /// are coalesced into a single [`RenderOp::SetStyleBatch`] so the runtime
+    /// applies them in one DOM touch. The batch sits where the node's first
+    /// style op would have appeared, with pairs in emission order, so overall
+    /// op ordering stays deterministic. Off by default: per-property ops are
+    /// simpler for runtimes that don't need the batching.
+    pub fn set_style_batching(&mut self, enabled: bool) {
+        self.batch_style_ops = enabled;
+    }
+
+    /// When enabled, [`patch_with_diagnostics`](Self::patch_with_diagnostics)
+    /// also reports the drained bits no binding references. Off by default
+    /// so the hot patch path allocates nothing beyond the ops themselves;
+    /// tests and devtools turn it on.
+    pub fn set_orphan_tracking(&mut self, enabled: bool) {
+        self.track_orphaned_bits = enabled;
+    }
+
+    /// Maps binding types this crate doesn't know — discriminants past
+    /// [`BindingType::ValueSync`] — into ops, so a host can introduce custom
+    /// binding types without forking the patcher. The handler receives the
+    /// entry and the component's state bytes and returns the op to emit, or
+    /// `None` to skip the entry. Known types never reach the handler, and
+    /// without one unknown types are skipped as before.
+    pub fn set_unknown_binding_handler(
+        &mut self,
+        handler: Box<dyn Fn(&BindingEntry, &[u8]) -> Option<RenderOp>>,
+    ) {
+        self.unknown_binding_handler = Some(handler);
+    }
+
+    /// Registers a binding map. A component composed of sub-templates may
+    /// register several maps under one id; `patch` walks them all in
+    /// registration order. A map that binds a `(dirty bit, node, type)`
+    /// already bound for the component is rejected whole, so a bad partial
+    /// can't half-register.
+    pub fn register_binding_map(&mut self, map: BindingMap) -> Result<(), MorphError> {
+        let mut seen: Vec<(u16, u32, u8)> = self
+            .binding_maps(map.component_id)
+            .flat_map(|registered| registered.entries())
+            .map(binding_key)
+            .collect();
+        for entry in map.entries() {
+            let key = binding_key(entry);
+            if seen.contains(&key) {
+                return Err(MorphError::DuplicateBinding {
+                    component_id: map.component_id,
+                    dirty_bit: entry.dirty_bit,
+                    node_id: entry.node_id,
+                    binding_type: entry.binding_type,
+                });
+            }
+            seen.push(key);
+        }
+        self.components.push(map);
+        Ok(())
+    }
+
+    /// The component's registered maps, in registration order.
+    pub fn binding_maps(&self, component_id: u32) -> impl Iterator<Item = &BindingMap> + '_ {
+        self.components
+            .iter()
+            .filter(move |map| map.component_id == component_id)
+    }
+
+    /// Registers a derived field. Its `recompute` runs during
+    /// [`patch_mut`](Self::patch_mut) whenever one of its input bits is
+    /// dirty; plain [`patch`](Self::patch) cannot write the state region and
+    /// so never recomputes.
+    pub fn register_derived_binding(&mut self, binding: DerivedBinding) {
+        self.derived.push(binding);
+    }
+
+    /// Drains the component's dirty mask and emits ops for every binding on a
+    /// set bit: bits ascending, maps in registration order, entries in map
+    /// order.
+    ///
+    /// Components with [`DerivedBinding`]s should be patched through
+    /// [`patch_mut`](Self::patch_mut) instead, so their derived slots are
+    /// refreshed first.
+    pub fn patch(&self, state: &dyn ComponentState) -> Vec<RenderOp> {
+        let mut ops = Vec::new();
+        self.patch_into(state, &mut ops);
+        ops
+    }
+
+    /// Like [`patch`](Self::patch), but appends the ops to `out` instead of
+    /// returning a fresh vector, so a frame patching many components reuses
+    /// one buffer rather than allocating per call.
+    pub fn patch_into(&self, state: &dyn ComponentState, out: &mut Vec<RenderOp>) {
+        if self.binding_maps(state.component_id()).next().is_none() {
+            return;
+        }
+        let dirty = state.dirty_mask().take_dirty();
+        self.emit_ops_into(state, dirty, out);
+    }
+
+    /// Like [`patch`](Self::patch), but also reports which drained bits had
+    /// zero matching bindings — the complement of dead-binding detection:
+    /// marks nothing ever renders. Diagnostics are empty unless
+    /// [`set_orphan_tracking`](Self::set_orphan_tracking) is enabled.
+    pub fn patch_with_diagnostics(
+        &self,
+        state: &dyn ComponentState,
+    ) -> (Vec<RenderOp>, PatchDiagnostics) {
+        if self.binding_maps(state.component_id()).next().is_none() {
+            return (Vec::new(), PatchDiagnostics::default());
+        }
+        let dirty = state.dirty_mask().take_dirty();
+        let diagnostics = PatchDiagnostics {
+            orphaned_bits: self.orphaned_bits(state.component_id(), dirty),
+        };
+        (self.emit_ops(state, dirty), diagnostics)
+    }
+
+    /// Like [`patch`](Self::patch), but first refreshes every registered
+    /// [`DerivedBinding`] whose input bits are in the drained mask, adding
+    /// the derived bits to the patch so their bindings emit the recomputed
+    /// values.
+    pub fn patch_mut(&self, state: &mut dyn MutableComponentState) -> Vec<RenderOp> {
+        if self.binding_maps(state.component_id()).next().is_none() {
+            return Vec::new();
+        }
+        let mut dirty = state.dirty_mask().take_dirty();
+        for binding in &self.derived {
+            if binding.component_id == state.component_id()
+                && binding.input_bits.iter().any(|bit| dirty.is_set(*bit))
+            {
+                (binding.recompute)(state.state_bytes_mut());
+                dirty.set(binding.derived_bit);
+            }
+        }
+        self.emit_ops(state, dirty)
+    }
+
+    /// The drained bits nothing references. A bit that only feeds a
+    /// [`DerivedBinding`] is not orphaned: it renders through the derived
+    /// bit's bindings.
+    fn orphaned_bits(&self, component_id: u32, dirty: DirtyMask) -> Vec<u16> {
+        if !self.track_orphaned_bits {
+            return Vec::new();
+        }
+        dirty
+            .iter_set_bits()
+            .filter(|bit| {
+                let bound = self
+                    .binding_maps(component_id)
+                    .any(|map| map.get_bindings_for_bit(*bit).next().is_some());
+                let feeds_derived = self.derived.iter().any(|binding| {
+                    binding.component_id == component_id && binding.input_bits.contains(bit)
+                });
+                !bound && !feeds_derived
+            })
+            .collect()
+    }
+
+    fn emit_op_or_fallback(&self, entry: &BindingEntry, state_bytes: &[u8]) -> Option<RenderOp> {
+        if BindingType::from_u8(entry.binding_type).is_none() {
+            let handler = self.unknown_binding_handler.as_ref()?;
+            return handler(entry, state_bytes);
+        }
+        emit_op(entry, state_bytes)
+    }
+
+    fn emit_ops(&self, state: &dyn ComponentState, dirty: DirtyMask) -> Vec<RenderOp> {
+        let mut ops = Vec::new();
+        self.emit_ops_into(state, dirty, &mut ops);
+        ops
+    }
+
+    fn emit_ops_into(&self, state: &dyn ComponentState, dirty: DirtyMask, out: &mut Vec<RenderOp>) {
+        let appended_from = out.len();
+        for bit in dirty.iter_set_bits() {
+            for map in self.binding_maps(state.component_id()) {
+                for entry_index in map.entry_indices_for_bit(bit) {
+                    let entry_index = *entry_index as usize;
+                    let Some(entry) = map.entries().get(entry_index) else {
+                        continue;
+                    };
+                    if let Some(op) = self.emit_op_or_fallback(entry, state.state_bytes()) {
+                        out.push(match map.transition_for(entry_index) {
+                            Some(transition) => apply_transition(op, transition),
+                            None => op,
+                        });
+                    }
+                }
+            }
+        }
+        if self.batch_style_ops {
+            // Coalescing is scoped to this patch's ops so batches never
+            // merge style ops across components sharing `out`.
+            let appended = out.split_off(appended_from);
+            out.extend(coalesce_style_ops(appended));
+        }
+    }
+
+    /// Patches several components in one pass. Ops are grouped by component
+    /// in first-registration order regardless of the order of `states`.
+    pub fn patch_many(&self, states: &[&dyn ComponentState]) -> Vec<RenderOp> {
+        let mut ops = Vec::new();
+        self.patch_all(states, &mut ops);
+        ops
+    }
+
+    /// Like [`patch_many`](Self::patch_many), but appends to `out`, draining
+    /// every component's dirty mask in one pass without allocating a vector
+    /// per component.
+    pub fn patch_all(&self, states: &[&dyn ComponentState], out: &mut Vec<RenderOp>) {
+        let mut patched = Vec::new();
+        for map in &self.components {
+            if patched.contains(&map.component_id) {
+                continue;
+            }
+            patched.push(map.component_id);
+            if let Some(state) = states
+                .iter()
+                .find(|state| state.component_id() == map.component_id)
+            {
+                self.patch_into(*state, out);
+            }
+        }
+    }
+
+    /// Closes the two-way loop: writes an input event's value into the
+    /// [`BindingType::ValueSync`] slot bound to the event's node and marks
+    /// that binding's dirty bit, so the rest of the app observes the change
+    /// on the next patch.
+    ///
+    /// Returns false — leaving state untouched — when no two-way binding
+    /// matches the node or the value doesn't fit its slot.
+    pub fn apply_input_event(
+        &self,
+        state: &mut dyn MutableComponentState,
+        event: &InputEvent,
+    ) -> bool {
+        if state.component_id() != event.component_id {
+            return false;
+        }
+        let Some(entry) = self
+            .binding_maps(event.component_id)
+            .flat_map(|map| map.entries())
+            .find(|entry| {
+                entry.binding_type == BindingType::ValueSync as u8 && entry.node_id == event.node_id
+            })
+        else {
+            return false;
+        };
+        if event.value.len() > entry.value_len as usize {
+            return false;
+        }
+        let start = entry.value_offset as usize;
+        let Some(end) = start.checked_add(entry.value_len as usize) else {
+            return false;
+        };
+        let Some(slot) = state.state_bytes_mut().get_mut(start..end) else {
+            return false;
+        };
+        slot[..event.value.len()].copy_from_slice(&event.value);
+        slot[event.value.len()..].fill(0);
+        state.dirty_mask().mark_dirty(entry.dirty_bit);
+        true
+    }
+}
+
+/// When a component's ops are emitted relative to the mutation that dirtied
+/// it; the latency/throughput tradeoff, chosen per component.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PatchPolicy {
+    /// Patched as soon as [`StateManager::state_mutated`] reports the
+    /// mutation, for components where reflecting input immediately matters
+    /// more than batching (a text field echoing keystrokes).
+    Eager,
+    /// Bits accumulate until [`StateManager::flush_frame`], so several
+    /// mutations in one tick cost one patch. The default: it matches the
+    /// batched frame loop most components already live in.
+    #[default]
+    Lazy,
+}
+
+/// Schedules patching on top of a [`StatePatcher`]: eager components flush
+/// right after a mutation is reported, lazy ones wait for the frame flush.
+#[derive(Default)]
+pub struct StateManager {
+    patcher: StatePatcher,
+    /// Sparse overrides of the default policy, keyed by component id.
+    policies: Vec<(u32, PatchPolicy)>,
+}
+
+impl StateManager {
+    pub fn new(patcher: StatePatcher) -> Self {
+        Self {
+            patcher,
+            policies: Vec::new(),
+        }
+    }
+
+    pub fn patcher(&self) -> &StatePatcher {
+        &self.patcher
+    }
+
+    pub fn patcher_mut(&mut self) -> &mut StatePatcher {
+        &mut self.patcher
+    }
+
+    pub fn set_policy(&mut self, component_id: u32, policy: PatchPolicy) {
+        if let Some((_, existing)) = self
+            .policies
+            .iter_mut()
+            .find(|(registered, _)| *registered == component_id)
+        {
+            *existing = policy;
+        } else {
+            self.policies.push((component_id, policy));
+        }
+    }
+
+    pub fn policy_for(&self, component_id: u32) -> PatchPolicy {
+        self.policies
+            .iter()
+            .find(|(registered, _)| *registered == component_id)
+            .map_or(PatchPolicy::default(), |(_, policy)| *policy)
+    }
+
+    /// Reports that a mutator marked bits on `state`. An eager component is
+    /// patched on the spot and its ops returned for immediate application; a
+    /// lazy component returns nothing and keeps its bits for
+    /// [`flush_frame`](Self::flush_frame).
+    pub fn state_mutated(&self, state: &dyn ComponentState) -> Vec<RenderOp> {
+        match self.policy_for(state.component_id()) {
+            PatchPolicy::Eager => self.patcher.patch(state),
+            PatchPolicy::Lazy => Vec::new(),
+        }
+    }
+
+    /// Drains everything still dirty — lazy components, plus any eager bits
+    /// marked since their last mutation report — in
+    /// [`StatePatcher::patch_many`] order.
+    pub fn flush_frame(&self, states: &[&dyn ComponentState]) -> Vec<RenderOp> {
+        self.patcher.patch_many(states)
+    }
+}
+
+/// Folds every [`RenderOp::SetStyle`] into a per-node
+/// [`RenderOp::SetStyleBatch`], merging across intervening non-style ops so
+/// each node gets exactly one style touch per patch.
+fn coalesce_style_ops(ops: Vec<RenderOp>) -> Vec<RenderOp> {
+    let mut coalesced: Vec<RenderOp> = Vec::with_capacity(ops.len());
+    for op in ops {
+        let RenderOp::SetStyle {
+            node_id,
+            property_id,
+            value,
+        } = op
+        else {
+            coalesced.push(op);
+            continue;
+        };
+        let existing_batch = coalesced.iter_mut().find_map(|candidate| match candidate {
+            RenderOp::SetStyleBatch {
+                node_id: batch_node_id,
+                properties,
+            } if *batch_node_id == node_id => Some(properties),
+            _ => None,
+        });
+        match existing_batch {
+            Some(properties) => properties.push((property_id, value)),
+            None => coalesced.push(RenderOp::SetStyleBatch {
+                node_id,
+                properties: vec![(property_id, value)],
+            }),
+        }
+    }
+    coalesced
+}
+
+/// Wraps a style or class op in [`RenderOp::Animated`] so the runtime
+/// animates it instead of snapping. Other ops — including the `Remove` an
+/// optional binding emits when its presence bit clears — apply instantly;
+/// animating a removal or a text swap has no sensible interpolation.
+/// Animated style ops also stay out of [`RenderOp::SetStyleBatch`]: each one
+/// carries its own timing, so there is no single batch to fold them into.
+fn apply_transition(op: RenderOp, transition: Transition) -> RenderOp {
+    match op {
+        RenderOp::SetStyle { .. } | RenderOp::ToggleClass { .. } => RenderOp::Animated {
+            op: Box::new(op),
+            duration_ms: transition.duration_ms,
+            easing_id: transition.easing_id,
+        },
+        other => other,
+    }
+}
+
+fn binding_key(entry: &BindingEntry) -> (u16, u32, u8) {
+    (entry.dirty_bit, entry.node_id, entry.binding_type)
+}
+
+fn emit_op(entry: &BindingEntry, state_bytes: &[u8]) -> Option<RenderOp> {
+    let binding_type = BindingType::from_u8(entry.binding_type)?;
+    if entry.optional != 0 {
+        let presence_byte = state_bytes.get(entry.presence_offset as usize)?;
+        if presence_byte & (1 << entry.presence_bit) == 0 {
+            return Some(RenderOp::Remove {
+                node_id: entry.node_id,
+                target_id: entry.target_id,
+            });
+        }
+    }
+    let start = entry.value_offset as usize;
+    let end = start.checked_add(entry.value_len as usize)?;
+    let value_bytes = state_bytes.get(start..end)?;
+    let op = match binding_type {
+        BindingType::Text => RenderOp::SetText {
+            node_id: entry.node_id,
+            value: format_text_value(entry, value_bytes)?,
+        },
+        BindingType::Attribute => RenderOp::SetAttribute {
+            node_id: entry.node_id,
+            attribute_id: entry.target_id,
+            value: String::from_utf8_lossy(value_bytes).into_owned(),
+        },
+        BindingType::Style => RenderOp::SetStyle {
+            node_id: entry.node_id,
+            property_id: entry.target_id,
+            value: String::from_utf8_lossy(value_bytes).into_owned(),
+        },
+        BindingType::ClassToggle => RenderOp::ToggleClass {
+            node_id: entry.node_id,
+            class_id: entry.target_id,
+            enabled: value_bytes.first().copied().unwrap_or(0) != 0,
+        },
+        BindingType::Visibility => RenderOp::SetVisibility {
+            node_id: entry.node_id,
+            visible: value_bytes.first().copied().unwrap_or(0) != 0,
+        },
+        BindingType::ValueSync => {
+            let trimmed_len = value_bytes
+                .iter()
+                .rposition(|byte| *byte != 0)
+                .map_or(0, |position| position + 1);
+            RenderOp::SetValue {
+                node_id: entry.node_id,
+                value: String::from_utf8_lossy(&value_bytes[..trimmed_len]).into_owned(),
+            }
+        }
+    };
+    Some(op)
+}
+
+/// Renders a `Text` binding's slot according to its [`ValueType`]. Numeric
+/// and bool values go through Rust's own formatter, which is
+/// locale-independent, so output is byte-identical across hosts. The one
+/// `String` built here is the allocation every op already pays for its
+/// value — there is no intermediate buffer. An unknown value type, an
+/// unknown spec, or a slot whose size doesn't match the type is skipped,
+/// mirroring how unknown binding types are handled.
+fn format_text_value(entry: &BindingEntry, value_bytes: &[u8]) -> Option<String> {
+    match ValueType::from_u8(entry.value_type)? {
+        ValueType::Bytes => Some(String::from_utf8_lossy(value_bytes).into_owned()),
+        ValueType::I32 => {
+            let value = i32::from_le_bytes(value_bytes.try_into().ok()?);
+            (entry.format_spec == 0).then(|| value.to_string())
+        }
+        ValueType::F64 => {
+            let value = f64::from_le_bytes(value_bytes.try_into().ok()?);
+            Some(match entry.format_spec {
+                0 => value.to_string(),
+                decimal_places => format!("{value:.*}", decimal_places as usize),
+            })
+        }
+        ValueType::Bool => {
+            let [byte] = value_bytes else {
+                return None;
+            };
+            let value = *byte != 0;
+            match entry.format_spec {
+                0 => Some(value.to_string()),
+                1 => Some(if value { "1" } else { "0" }.to_string()),
+                _ => None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AtomicDirtyMask;
+
+    pub(crate) struct TestComponent {
+        pub id: u32,
+        pub mask: AtomicDirtyMask,
+        pub bytes: Vec<u8>,
+    }
+
+    impl MutableComponentState for TestComponent {
+        fn state_bytes_mut(&mut self) -> &mut [u8] {
+            &mut self.bytes
+        }
+    }
+
+    impl ComponentState for TestComponent {
+        fn component_id(&self) -> u32 {
+            self.id
+        }
+
+        fn dirty_mask(&self) -> &AtomicDirtyMask {
+            &self.mask
+        }
+
+        fn state_bytes(&self) -> &[u8] {
+            &self.bytes
+        }
+    }
+
+    fn text_component(id: u32) -> (TestComponent, BindingMap) {
+        let component = TestComponent {
+            id,
+            mask: AtomicDirtyMask::new(),
+            bytes: b"hello world!".to_vec(),
+        };
+        let map = BindingMap::new(
+            id,
+            vec![
+                BindingEntry::new(0, BindingType::Text, 0, id + 1, 0, 5),
+                BindingEntry::new(1, BindingType::Text, 0, id + 2, 6, 5),
+            ],
+        );
+        (component, map)
+    }
+
+    #[test]
+    fn test_ops_are_ordered_by_bit_then_entry() {
+        let (component, map) = text_component(1);
+        let mut patcher = StatePatcher::new();
+        patcher.register_binding_map(map).unwrap();
+
+        // Mark in descending order; ops must still come out ascending.
+        component.mask.mark_dirty(1);
+        component.mask.mark_dirty(0);
+        let ops = patcher.patch(&component);
+        assert_eq!(
+            ops,
+            vec![
+                RenderOp::SetText {
+                    node_id: 2,
+                    value: "hello".into()
+                },
+                RenderOp::SetText {
+                    node_id: 3,
+                    value: "world".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bindings_past_bit_63_fire() {
+        let component = TestComponent {
+            id: 1,
+            mask: AtomicDirtyMask::new(),
+            bytes: b"hello world!".to_vec(),
+        };
+        let map = BindingMap::new(
+            1,
+            vec![
+                BindingEntry::new(100, BindingType::Text, 0, 10, 0, 5),
+                BindingEntry::new(101, BindingType::Text, 0, 11, 6, 5),
+            ],
+        );
+        let mut patcher = StatePatcher::new();
+        patcher.register_binding_map(map).unwrap();
+
+        component.mask.mark_dirty(100);
+        assert_eq!(
+            patcher.patch(&component),
+            vec![RenderOp::SetText {
+                node_id: 10,
+                value: "hello".into()
+            }],
+            "only the binding on bit 100 fires"
+        );
+        assert!(component.mask.peek().is_empty(), "the high bit was drained");
+    }
+
+    #[test]
+    fn test_style_batching_coalesces_per_node() {
+        let component = TestComponent {
+            id: 1,
+            mask: AtomicDirtyMask::new(),
+            bytes: b"redgreen10px".to_vec(),
+        };
+        let map = BindingMap::new(
+            1,
+            vec![
+                BindingEntry::new(0, BindingType::Style, 7, 50, 0, 3),
+                BindingEntry::new(1, BindingType::Style, 8, 50, 3, 5),
+                BindingEntry::new(2, BindingType::Style, 7, 60, 8, 4),
+            ],
+        );
+        let mut patcher = StatePatcher::new();
+        patcher.register_binding_map(map).unwrap();
+
+        component.mask.mark_dirty(0);
+        component.mask.mark_dirty(1);
+        component.mask.mark_dirty(2);
+        let per_property_ops = patcher.patch(&component);
+        assert_eq!(per_property_ops.len(), 3, "batching is off by default");
+
+        patcher.set_style_batching(true);
+        component.mask.mark_dirty(0);
+        component.mask.mark_dirty(1);
+        component.mask.mark_dirty(2);
+        let ops = patcher.patch(&component);
+        assert_eq!(
+            ops,
+            vec![
+                RenderOp::SetStyleBatch {
+                    node_id: 50,
+                    properties: vec![(7, "red".into()), (8, "green".into())],
+                },
+                RenderOp::SetStyleBatch {
+                    node_id: 60,
+                    properties: vec![(7, "10px".into())],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_transitioned_binding_emits_an_animated_op() {
+        // Style value at 0..3, class flag at byte 3.
+        let component = TestComponent {
+            id: 1,
+            mask: AtomicDirtyMask::new(),
+            bytes: b"red\x01".to_vec(),
+        };
+        let map = BindingMap::new(
+            1,
+            vec![
+                BindingEntry::new(0, BindingType::Style, 7, 50, 0, 3),
+                BindingEntry::new(1, BindingType::ClassToggle, 4, 50, 3, 1),
+            ],
+        )
+        .with_transition(
+            0,
+            Transition {
+                duration_ms: 150,
+                easing_id: 2,
+            },
+        );
+        let mut patcher = StatePatcher::new();
+        patcher.register_binding_map(map).unwrap();
+
+        component.mask.mark_dirty(0);
+        component.mask.mark_dirty(1);
+        assert_eq!(
+            patcher.patch(&component),
+            vec![
+                RenderOp::Animated {
+                    op: Box::new(RenderOp::SetStyle {
+                        node_id: 50,
+                        property_id: 7,
+                        value: "red".into(),
+                    }),
+                    duration_ms: 150,
+                    easing_id: 2,
+                },
+                // The plain binding still snaps.
+                RenderOp::ToggleClass {
+                    node_id: 50,
+                    class_id: 4,
+                    enabled: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_optional_binding_toggles_between_set_and_remove() {
+        // Byte 0 holds presence flags; the subtitle text lives at 1..9.
+        let mut component = TestComponent {
+            id: 1,
+            mask: AtomicDirtyMask::new(),
+            bytes: b"\x01subtitle".to_vec(),
+        };
+        let mut patcher = StatePatcher::new();
+        patcher
+            .register_binding_map(BindingMap::new(
+                1,
+                vec![BindingEntry::new(0, BindingType::Text, 0, 40, 1, 8).with_presence(0, 0)],
+            ))
+            .unwrap();
+
+        component.mask.mark_dirty(0);
+        assert_eq!(
+            patcher.patch(&component),
+            vec![RenderOp::SetText {
+                node_id: 40,
+                value: "subtitle".into()
+            }]
+        );
+
+        component.bytes[0] = 0;
+        component.mask.mark_dirty(0);
+        assert_eq!(
+            patcher.patch(&component),
+            vec![RenderOp::Remove {
+                node_id: 40,
+                target_id: 0
+            }]
+        );
+
+        component.bytes[0] = 1;
+        component.mask.mark_dirty(0);
+        assert_eq!(
+            patcher.patch(&component),
+            vec![RenderOp::SetText {
+                node_id: 40,
+                value: "subtitle".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_input_event_writes_back_and_flips_dirty_bit() {
+        let mut component = TestComponent {
+            id: 1,
+            mask: AtomicDirtyMask::new(),
+            bytes: b"initial state".to_vec(),
+        };
+        let mut patcher = StatePatcher::new();
+        patcher
+            .register_binding_map(BindingMap::new(
+                1,
+                vec![BindingEntry::new(2, BindingType::ValueSync, 0, 50, 0, 7)],
+            ))
+            .unwrap();
+
+        let applied = patcher.apply_input_event(
+            &mut component,
+            &InputEvent {
+                component_id: 1,
+                node_id: 50,
+                value: b"typed".to_vec(),
+            },
+        );
+        assert!(applied);
+        assert!(component.mask.peek().is_set(2));
+        assert_eq!(&component.bytes[..7], b"typed\0\0");
+
+        let ops = patcher.patch(&component);
+        assert_eq!(
+            ops,
+            vec![RenderOp::SetValue {
+                node_id: 50,
+                value: "typed".into()
+            }]
+        );
+        assert!(component.mask.peek().is_empty(), "patch drained the bit");
+    }
+
+    #[test]
+    fn test_oversized_or_unbound_input_events_are_rejected() {
+        let mut component = TestComponent {
+            id: 1,
+            mask: AtomicDirtyMask::new(),
+            bytes: vec![0; 8],
+        };
+        let mut patcher = StatePatcher::new();
+        patcher
+            .register_binding_map(BindingMap::new(
+                1,
+                vec![BindingEntry::new(0, BindingType::ValueSync, 0, 50, 0, 4)],
+            ))
+            .unwrap();
+
+        let oversized = InputEvent {
+            component_id: 1,
+            node_id: 50,
+            value: b"too long".to_vec(),
+        };
+        assert!(!patcher.apply_input_event(&mut component, &oversized));
+
+        let unbound_node = InputEvent {
+            component_id: 1,
+            node_id: 51,
+            value: b"ok".to_vec(),
+        };
+        assert!(!patcher.apply_input_event(&mut component, &unbound_node));
+        assert!(component.mask.peek().is_empty());
+        assert_eq!(component.bytes, vec![0; 8]);
+    }
+
+    #[test]
+    fn test_patch_many_follows_registration_order() {
+        let (first, first_map) = text_component(10);
+        let (second, second_map) = text_component(20);
+        let mut patcher = StatePatcher::new();
+        patcher.register_binding_map(second_map).unwrap();
+        patcher.register_binding_map(first_map).unwrap();
+
+        let run = |first: &TestComponent, second: &TestComponent| {
+            first.mask.mark_dirty(0);
+            second.mask.mark_dirty(0);
+            // States passed in id order, but component 20 was registered
+            // first.
+            patcher.patch_many(&[first, second])
+        };
+
+        let ops = run(&first, &second);
+        let node_order: Vec<u32> = ops.iter().map(|op| op.node_id()).collect();
+        assert_eq!(node_order, vec![21, 11]);
+        assert_eq!(ops, run(&first, &second), "same inputs, same op sequence");
+    }
+
+    #[test]
+    fn test_eager_components_flush_on_mutation_and_lazy_ones_wait() {
+        let (eager, eager_map) = text_component(10);
+        let (lazy, lazy_map) = text_component(20);
+        let mut patcher = StatePatcher::new();
+        patcher.register_binding_map(eager_map).unwrap();
+        patcher.register_binding_map(lazy_map).unwrap();
+        let mut manager = StateManager::new(patcher);
+        manager.set_policy(10, PatchPolicy::Eager);
+
+        eager.mask.mark_dirty(0);
+        lazy.mask.mark_dirty(0);
+        let immediate = manager.state_mutated(&eager);
+        assert_eq!(
+            immediate,
+            vec![RenderOp::SetText {
+                node_id: 11,
+                value: "hello".into()
+            }],
+            "eager ops are emitted before any frame flush"
+        );
+        assert!(
+            manager.state_mutated(&lazy).is_empty(),
+            "lazy components hold their bits"
+        );
+
+        let frame = manager.flush_frame(&[&eager, &lazy]);
+        assert_eq!(
+            frame,
+            vec![RenderOp::SetText {
+                node_id: 21,
+                value: "hello".into()
+            }],
+            "the flush emits the lazy ops and nothing twice for the eager one"
+        );
+    }
+
+    #[test]
+    fn test_patch_all_appends_into_a_reused_buffer() {
+        let (first, first_map) = text_component(10);
+        let (second, second_map) = text_component(20);
+        let mut patcher = StatePatcher::new();
+        patcher.register_binding_map(first_map).unwrap();
+        patcher.register_binding_map(second_map).unwrap();
+
+        first.mask.mark_dirty(0);
+        second.mask.mark_dirty(0);
+        let separate = patcher.patch_many(&[&first, &second]);
+
+        first.mask.mark_dirty(0);
+        second.mask.mark_dirty(0);
+        let mut frame_ops = Vec::with_capacity(separate.len());
+        patcher.patch_all(&[&first, &second], &mut frame_ops);
+        assert_eq!(frame_ops, separate);
+
+        // A buffer with leftovers from an earlier component is appended to,
+        // never cleared.
+        first.mask.mark_dirty(1);
+        let before = frame_ops.len();
+        patcher.patch_into(&first, &mut frame_ops);
+        assert_eq!(frame_ops[..before], separate[..]);
+        assert_eq!(
+            frame_ops[before..],
+            [RenderOp::SetText {
+                node_id: 12,
+                value: "world".into()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_composed_component_patches_all_maps_in_registration_order() {
+        let component = TestComponent {
+            id: 1,
+            mask: AtomicDirtyMask::new(),
+            bytes: b"hello world!".to_vec(),
+        };
+        let mut patcher = StatePatcher::new();
+        patcher
+            .register_binding_map(BindingMap::new(
+                1,
+                vec![BindingEntry::new(0, BindingType::Text, 0, 10, 0, 5)],
+            ))
+            .unwrap();
+        // A sub-template registers its own map under the same component id,
+        // including a binding on the bit the first map already uses.
+        patcher
+            .register_binding_map(BindingMap::new(
+                1,
+                vec![
+                    BindingEntry::new(0, BindingType::Text, 0, 20, 6, 5),
+                    BindingEntry::new(1, BindingType::Text, 0, 30, 11, 1),
+                ],
+            ))
+            .unwrap();
+
+        component.mask.mark_dirty(0);
+        component.mask.mark_dirty(1);
+        let ops = patcher.patch(&component);
+        let node_order: Vec<u32> = ops.iter().map(|op| op.node_id()).collect();
+        // Bit 0 first across both maps in registration order, then bit 1.
+        assert_eq!(node_order, vec![10, 20, 30]);
+    }
+
+    #[test]
+    fn test_derived_binding_recomputes_only_when_an_input_is_dirty() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        // State layout: first name at 0..3, last name at 3..11, and the
+        // derived full name at 11..23 — sized exactly for "Ada Lovelace".
+        let mut bytes = b"AdaLovelace".to_vec();
+        bytes.resize(23, 0);
+        let mut component = TestComponent {
+            id: 1,
+            mask: AtomicDirtyMask::new(),
+            bytes,
+        };
+        let mut patcher = StatePatcher::new();
+        patcher
+            .register_binding_map(BindingMap::new(
+                1,
+                vec![BindingEntry::new(2, BindingType::Text, 0, 10, 11, 12)],
+            ))
+            .unwrap();
+        let recompute_count = Rc::new(Cell::new(0));
+        patcher.register_derived_binding(DerivedBinding {
+            component_id: 1,
+            input_bits: vec![0, 1],
+            derived_bit: 2,
+            recompute: Box::new({
+                let recompute_count = recompute_count.clone();
+                move |state_bytes| {
+                    recompute_count.set(recompute_count.get() + 1);
+                    let full_name = format!(
+                        "{} {}",
+                        String::from_utf8_lossy(&state_bytes[0..3]).trim_end_matches('\0'),
+                        String::from_utf8_lossy(&state_bytes[3..11]).trim_end_matches('\0'),
+                    );
+                    state_bytes[11..11 + full_name.len()].copy_from_slice(full_name.as_bytes());
+                    state_bytes[11 + full_name.len()..23].fill(0);
+                }
+            }),
+        });
+
+        // Only the first-name input changed; one recompute, one text op.
+        component.mask.mark_dirty(0);
+        assert_eq!(
+            patcher.patch_mut(&mut component),
+            vec![RenderOp::SetText {
+                node_id: 10,
+                value: "Ada Lovelace".into()
+            }]
+        );
+        assert_eq!(recompute_count.get(), 1);
+
+        // A bit outside the input set triggers neither recompute nor op.
+        component.mask.mark_dirty(3);
+        assert!(patcher.patch_mut(&mut component).is_empty());
+        assert_eq!(recompute_count.get(), 1);
+    }
+
+    #[test]
+    fn test_orphaned_dirty_bits_are_reported_when_tracking_is_enabled() {
+        let (component, map) = text_component(1);
+        let mut patcher = StatePatcher::new();
+        patcher.register_binding_map(map).unwrap();
+
+        // Tracking is off by default: the orphaned mark is drained silently.
+        component.mask.mark_dirty(7);
+        let (ops, diagnostics) = patcher.patch_with_diagnostics(&component);
+        assert!(ops.is_empty());
+        assert!(diagnostics.orphaned_bits.is_empty());
+
+        patcher.set_orphan_tracking(true);
+        component.mask.mark_dirty(0);
+        component.mask.mark_dirty(6);
+        component.mask.mark_dirty(7);
+        let (ops, diagnostics) = patcher.patch_with_diagnostics(&component);
+        assert_eq!(ops.len(), 1, "the bound bit still renders");
+        assert_eq!(diagnostics.orphaned_bits, vec![6, 7]);
+        assert!(
+            component.mask.peek().is_empty(),
+            "the patch drained the mask"
+        );
+    }
+
+    #[test]
+    fn test_a_bit_feeding_a_derived_binding_is_not_orphaned() {
+        let (component, map) = text_component(1);
+        let mut patcher = StatePatcher::new();
+        patcher.register_binding_map(map).unwrap();
+        patcher.set_orphan_tracking(true);
+        patcher.register_derived_binding(DerivedBinding {
+            component_id: 1,
+            input_bits: vec![4],
+            derived_bit: 0,
+            recompute: Box::new(|_| {}),
+        });
+
+        component.mask.mark_dirty(4);
+        component.mask.mark_dirty(5);
+        let (_, diagnostics) = patcher.patch_with_diagnostics(&component);
+        assert_eq!(diagnostics.orphaned_bits, vec![5]);
+    }
+
+    #[test]
+    fn test_typed_text_bindings_format_at_patch_time() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&(-42i32).to_le_bytes());
+        bytes.extend_from_slice(&2.5f64.to_le_bytes());
+        bytes.push(1);
+        let component = TestComponent {
+            id: 1,
+            mask: AtomicDirtyMask::new(),
+            bytes,
+        };
+        let map = BindingMap::new(
+            1,
+            vec![
+                BindingEntry::new(0, BindingType::Text, 0, 10, 0, 4)
+                    .with_value_type(ValueType::I32, 0),
+                BindingEntry::new(1, BindingType::Text, 0, 11, 4, 8)
+                    .with_value_type(ValueType::F64, 0),
+                BindingEntry::new(2, BindingType::Text, 0, 12, 4, 8)
+                    .with_value_type(ValueType::F64, 3),
+                BindingEntry::new(3, BindingType::Text, 0, 13, 12, 1)
+                    .with_value_type(ValueType::Bool, 0),
+                BindingEntry::new(4, BindingType::Text, 0, 14, 12, 1)
+                    .with_value_type(ValueType::Bool, 1),
+            ],
+        );
+        let mut patcher = StatePatcher::new();
+        patcher.register_binding_map(map).unwrap();
+
+        for bit in 0..5 {
+            component.mask.mark_dirty(bit);
+        }
+        let values: Vec<String> = patcher
+            .patch(&component)
+            .into_iter()
+            .map(|op| match op {
+                RenderOp::SetText { value, .. } => value,
+                other => panic!("unexpected op {other:?}"),
+            })
+            .collect();
+        assert_eq!(values, vec!["-42", "2.5", "2.500", "true", "1"]);
+    }
+
+    #[test]
+    fn test_mismatched_typed_slot_is_skipped() {
+        // An i32 binding over a 5-byte slot can't be formatted; the binding
+        // is dropped from the patch rather than emitting garbage.
+        let component = TestComponent {
+            id: 1,
+            mask: AtomicDirtyMask::new(),
+            bytes: vec![0; 8],
+        };
+        let mut patcher = StatePatcher::new();
+        patcher
+            .register_binding_map(BindingMap::new(
+                1,
+                vec![
+                    BindingEntry::new(0, BindingType::Text, 0, 10, 0, 5)
+                        .with_value_type(ValueType::I32, 0),
+                ],
+            ))
+            .unwrap();
+        component.mask.mark_dirty(0);
+        assert!(patcher.patch(&component).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_binding_types_route_to_the_fallback_handler() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let component = TestComponent {
+            id: 1,
+            mask: AtomicDirtyMask::new(),
+            bytes: b"hello world!".to_vec(),
+        };
+        // A discriminant past ValueSync, as a downstream crate's codegen
+        // would emit for a custom binding type.
+        let mut custom = BindingEntry::new(0, BindingType::Text, 7, 50, 0, 5);
+        custom.binding_type = 9;
+        let mut patcher = StatePatcher::new();
+        patcher
+            .register_binding_map(BindingMap::new(
+                1,
+                vec![custom, BindingEntry::new(1, BindingType::Text, 0, 60, 6, 5)],
+            ))
+            .unwrap();
+
+        component.mask.mark_dirty(0);
+        component.mask.mark_dirty(1);
+        assert_eq!(
+            patcher.patch(&component),
+            vec![RenderOp::SetText {
+                node_id: 60,
+                value: "world".into()
+            }],
+            "without a handler the unknown type is skipped"
+        );
+
+        let handled = Rc::new(Cell::new(0));
+        patcher.set_unknown_binding_handler(Box::new({
+            let handled = handled.clone();
+            move |entry, state_bytes| {
+                handled.set(handled.get() + 1);
+                let start = entry.value_offset as usize;
+                let value = state_bytes.get(start..start + entry.value_len as usize)?;
+                Some(RenderOp::SetAttribute {
+                    node_id: entry.node_id,
+                    attribute_id: entry.target_id,
+                    value: String::from_utf8_lossy(value).into_owned(),
+                })
+            }
+        }));
+        component.mask.mark_dirty(0);
+        component.mask.mark_dirty(1);
+        assert_eq!(
+            patcher.patch(&component),
+            vec![
+                RenderOp::SetAttribute {
+                    node_id: 50,
+                    attribute_id: 7,
+                    value: "hello".into()
+                },
+                RenderOp::SetText {
+                    node_id: 60,
+                    value: "world".into()
+                },
+            ]
+        );
+        assert_eq!(handled.get(), 1, "known types never reach the handler");
+    }
+
+    #[test]
+    fn test_duplicate_binding_rejects_the_whole_map() {
+        let mut patcher = StatePatcher::new();
+        patcher
+            .register_binding_map(BindingMap::new(
+                1,
+                vec![BindingEntry::new(0, BindingType::Text, 0, 10, 0, 5)],
+            ))
+            .unwrap();
+
+        let clashing = BindingMap::new(
+            1,
+            vec![
+                BindingEntry::new(1, BindingType::Text, 0, 20, 6, 5),
+                BindingEntry::new(0, BindingType::Text, 0, 10, 0, 5),
+            ],
+        );
+        assert_eq!(
+            patcher.register_binding_map(clashing),
+            Err(MorphError::DuplicateBinding {
+                component_id: 1,
+                dirty_bit: 0,
+                node_id: 10,
+                binding_type: BindingType::Text as u8,
+            })
+        );
+        // Nothing from the rejected map was registered, not even the
+        // non-clashing entry.
+        assert_eq!(patcher.binding_maps(1).count(), 1);
+
+        // The same map under a different component id is fine.
+        patcher
+            .register_binding_map(BindingMap::new(
+                2,
+                vec![BindingEntry::new(0, BindingType::Text, 0, 10, 0, 5)],
+            ))
+            .unwrap();
+    }
+}
@@ -0,0 +1,250 @@
+use std::fmt;
+
+/// A minimal DOM mutation produced by the patcher. The runtime applies these
+/// in order.
+///
+/// `Eq` and `Hash` are derived so op sequences can be compared to golden
+/// fixtures and deduplicated in tests. `RenderOp` is a plain Rust enum — it
+/// crosses the wasm boundary through
+/// [`patch_into_buffer`](crate::patch_into_buffer), never by reinterpreting
+/// its bytes — so the derives carry no layout obligations;
+/// the `#[repr(C)]` contract lives on [`BindingEntry`](crate::BindingEntry)
+/// alone.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RenderOp {
+    SetText {
+        node_id: u32,
+        value: String,
+    },
+    SetAttribute {
+        node_id: u32,
+        attribute_id: u16,
+        value: String,
+    },
+    SetStyle {
+        node_id: u32,
+        property_id: u16,
+        value: String,
+    },
+    ToggleClass {
+        node_id: u32,
+        class_id: u16,
+        enabled: bool,
+    },
+    SetVisibility {
+        node_id: u32,
+        visible: bool,
+    },
+    /// Pushes a two-way bound value to its input element.
+    SetValue {
+        node_id: u32,
+        value: String,
+    },
+    /// Several style properties for one node, applied in one DOM touch. Only
+    /// emitted when style batching is enabled on the patcher; the runtime
+    /// iterates the pairs in order, each `(property_id, value)` equivalent to
+    /// one [`SetStyle`](Self::SetStyle).
+    SetStyleBatch {
+        node_id: u32,
+        properties: Vec<(u16, String)>,
+    },
+    /// Removes what an optional binding previously set — the text content,
+    /// or the attribute/style/class named by `target_id` — rather than
+    /// setting an empty value. Emitted when a binding's presence bit is
+    /// clear.
+    Remove {
+        node_id: u32,
+        target_id: u16,
+    },
+    /// Moves an existing child of `parent_id` before the sibling named by
+    /// `before_node_id` (`None` appends at the parent's end). Emitted when a
+    /// keyed collection reorders — see [`KeyedList`](crate::KeyedList) — so
+    /// the node keeps its identity, and with it focus and input state, where
+    /// remove-plus-reinsert would destroy both.
+    NodeMove {
+        parent_id: u32,
+        node_id: u32,
+        before_node_id: Option<u32>,
+    },
+    /// An op the runtime should animate over `duration_ms` with the easing
+    /// curve named by `easing_id`, rather than applying instantly. Emitted
+    /// for bindings whose map carries a
+    /// [`Transition`](crate::Transition); the wrapped op is what would have
+    /// been emitted without one.
+    Animated {
+        op: Box<RenderOp>,
+        duration_ms: u16,
+        easing_id: u8,
+    },
+}
+
+impl RenderOp {
+    pub fn node_id(&self) -> u32 {
+        match self {
+            Self::SetText { node_id, .. }
+            | Self::SetAttribute { node_id, .. }
+            | Self::SetStyle { node_id, .. }
+            | Self::ToggleClass { node_id, .. }
+            | Self::SetVisibility { node_id, .. }
+            | Self::SetValue { node_id, .. }
+            | Self::SetStyleBatch { node_id, .. }
+            | Self::Remove { node_id, .. }
+            | Self::NodeMove { node_id, .. } => *node_id,
+            Self::Animated { op, .. } => op.node_id(),
+        }
+    }
+}
+
+/// One stable, readable line per op. Snapshot tests compare this against
+/// golden fixtures so a mismatch diffs line by line instead of dumping two
+/// `Vec<RenderOp>` debug blobs.
+impl fmt::Display for RenderOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SetText { node_id, value } => write!(f, "SetText node={node_id} {value:?}"),
+            Self::SetAttribute {
+                node_id,
+                attribute_id,
+                value,
+            } => write!(
+                f,
+                "SetAttribute node={node_id} attribute={attribute_id} {value:?}"
+            ),
+            Self::SetStyle {
+                node_id,
+                property_id,
+                value,
+            } => write!(
+                f,
+                "SetStyle node={node_id} property={property_id} {value:?}"
+            ),
+            Self::ToggleClass {
+                node_id,
+                class_id,
+                enabled,
+            } => write!(
+                f,
+                "ToggleClass node={node_id} class={class_id} {}",
+                if *enabled { "on" } else { "off" }
+            ),
+            Self::SetVisibility { node_id, visible } => write!(
+                f,
+                "SetVisibility node={node_id} {}",
+                if *visible { "visible" } else { "hidden" }
+            ),
+            Self::SetValue { node_id, value } => write!(f, "SetValue node={node_id} {value:?}"),
+            Self::SetStyleBatch {
+                node_id,
+                properties,
+            } => {
+                write!(f, "SetStyleBatch node={node_id} [")?;
+                for (index, (property_id, value)) in properties.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{property_id}={value:?}")?;
+                }
+                write!(f, "]")
+            }
+            Self::Remove { node_id, target_id } => {
+                write!(f, "Remove node={node_id} target={target_id}")
+            }
+            Self::NodeMove {
+                parent_id,
+                node_id,
+                before_node_id,
+            } => match before_node_id {
+                Some(before_node_id) => write!(
+                    f,
+                    "NodeMove parent={parent_id} node={node_id} before={before_node_id}"
+                ),
+                None => write!(f, "NodeMove parent={parent_id} node={node_id} to-end"),
+            },
+            Self::Animated {
+                op,
+                duration_ms,
+                easing_id,
+            } => write!(
+                f,
+                "Animated duration_ms={duration_ms} easing={easing_id} {op}"
+            ),
+        }
+    }
+}
+
+/// Renders an op sequence one op per line, with a trailing newline so the
+/// output can be written to and diffed against a fixture file directly.
+pub fn pretty_print_ops(ops: &[RenderOp]) -> String {
+    let mut output = String::new();
+    for op in ops {
+        output.push_str(&op.to_string());
+        output.push('\n');
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_pretty_printed_ops_are_stable_and_readable() {
+        let ops = vec![
+            RenderOp::SetText {
+                node_id: 1,
+                value: "hello \"world\"".to_string(),
+            },
+            RenderOp::SetAttribute {
+                node_id: 2,
+                attribute_id: 7,
+                value: "primary".to_string(),
+            },
+            RenderOp::ToggleClass {
+                node_id: 3,
+                class_id: 4,
+                enabled: true,
+            },
+            RenderOp::SetVisibility {
+                node_id: 3,
+                visible: false,
+            },
+            RenderOp::SetStyleBatch {
+                node_id: 5,
+                properties: vec![(1, "red".to_string()), (2, "8px".to_string())],
+            },
+            RenderOp::Remove {
+                node_id: 6,
+                target_id: 9,
+            },
+        ];
+        assert_eq!(
+            pretty_print_ops(&ops),
+            concat!(
+                "SetText node=1 \"hello \\\"world\\\"\"\n",
+                "SetAttribute node=2 attribute=7 \"primary\"\n",
+                "ToggleClass node=3 class=4 on\n",
+                "SetVisibility node=3 hidden\n",
+                "SetStyleBatch node=5 [1=\"red\", 2=\"8px\"]\n",
+                "Remove node=6 target=9\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_ops_hash_consistently_with_equality() {
+        let op = RenderOp::SetStyle {
+            node_id: 1,
+            property_id: 2,
+            value: "red".to_string(),
+        };
+        let mut set = HashSet::new();
+        assert!(set.insert(op.clone()));
+        assert!(!set.insert(op), "equal op hashes to the same bucket");
+        assert!(set.insert(RenderOp::SetStyle {
+            node_id: 1,
+            property_id: 2,
+            value: "blue".to_string(),
+        }));
+    }
+}
@@ -0,0 +1,360 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Words in the crate-wide [`DirtyMask`]/[`AtomicDirtyMask`] aliases. Four
+/// words — 256 bindable fields — covers the widest generated components
+/// while keeping a take a handful of atomic ops.
+pub const DIRTY_MASK_WORDS: usize = 4;
+/// Bits the crate-wide mask aliases can address; marks at or past this are
+/// ignored.
+pub const DIRTY_MASK_BITS: u16 = (DIRTY_MASK_WORDS * 64) as u16;
+
+/// One bit per bindable field; bit N set means field N changed since the
+/// last patch. Bit N lives in word N / 64, so word 0 holds bits 0..63.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DirtyMaskN<const WORDS: usize>(pub [u64; WORDS]);
+
+/// The crate-wide mask width; see [`DIRTY_MASK_WORDS`].
+pub type DirtyMask = DirtyMaskN<DIRTY_MASK_WORDS>;
+
+impl<const WORDS: usize> DirtyMaskN<WORDS> {
+    fn slot(bit: u16) -> Option<(usize, u64)> {
+        let word = usize::from(bit) / 64;
+        (word < WORDS).then(|| (word, 1u64 << (bit % 64)))
+    }
+
+    pub fn is_set(&self, bit: u16) -> bool {
+        Self::slot(bit).is_some_and(|(word, mask)| self.0[word] & mask != 0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|word| *word == 0)
+    }
+
+    pub fn set(&mut self, bit: u16) {
+        if let Some((word, mask)) = Self::slot(bit) {
+            self.0[word] |= mask;
+        }
+    }
+
+    /// The set bits, ascending.
+    pub fn iter_set_bits(&self) -> impl Iterator<Item = u16> + '_ {
+        (0..WORDS * 64)
+            .filter_map(|bit| u16::try_from(bit).ok())
+            .filter(|bit| self.is_set(*bit))
+    }
+}
+
+impl<const WORDS: usize> Default for DirtyMaskN<WORDS> {
+    fn default() -> Self {
+        Self([0; WORDS])
+    }
+}
+
+/// Shared dirty mask; mutators mark bits from any thread, the patcher drains
+/// them on the render thread.
+#[derive(Debug)]
+pub struct AtomicDirtyMaskN<const WORDS: usize>([AtomicU64; WORDS]);
+
+/// The crate-wide mask width; see [`DIRTY_MASK_WORDS`].
+pub type AtomicDirtyMask = AtomicDirtyMaskN<DIRTY_MASK_WORDS>;
+
+impl<const WORDS: usize> AtomicDirtyMaskN<WORDS> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn mark_dirty(&self, bit: u16) {
+        if let Some((word, mask)) = DirtyMaskN::<WORDS>::slot(bit) {
+            self.0[word].fetch_or(mask, Ordering::AcqRel);
+        }
+    }
+
+    /// Atomically takes and clears the current mask. Words are drained one
+    /// at a time, so a mark racing the take lands either in the returned
+    /// mask or in the next one — never both, never neither.
+    pub fn take_dirty(&self) -> DirtyMaskN<WORDS> {
+        DirtyMaskN(std::array::from_fn(|word| {
+            self.0[word].swap(0, Ordering::AcqRel)
+        }))
+    }
+
+    /// Reads the current mask without clearing it.
+    pub fn peek(&self) -> DirtyMaskN<WORDS> {
+        DirtyMaskN(std::array::from_fn(|word| {
+            self.0[word].load(Ordering::Acquire)
+        }))
+    }
+
+    /// Clears the mask only if it still equals `expected` (as returned by
+    /// [`peek`](Self::peek)). Returns `false` without clearing anything when a
+    /// [`mark_dirty`](Self::mark_dirty) landed in between, so bits set after
+    /// the peek are never lost — re-peek and retry.
+    pub fn clear_if_unchanged(&self, expected: DirtyMaskN<WORDS>) -> bool {
+        for word in 0..WORDS {
+            if self.0[word]
+                .compare_exchange(expected.0[word], 0, Ordering::AcqRel, Ordering::Acquire)
+                .is_err()
+            {
+                // A later word went stale: re-mark the words already
+                // cleared so the failed attempt swallows nothing and the
+                // caller can simply re-peek and retry.
+                for cleared in 0..word {
+                    self.0[cleared].fetch_or(expected.0[cleared], Ordering::AcqRel);
+                }
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl<const WORDS: usize> Default for AtomicDirtyMaskN<WORDS> {
+    fn default() -> Self {
+        Self(std::array::from_fn(|_| AtomicU64::new(0)))
+    }
+}
+
+/// State a component exposes to the patcher: a stable component id, the
+/// dirty mask, and the flat byte region its bindings index into.
+pub trait ComponentState {
+    fn component_id(&self) -> u32;
+    fn dirty_mask(&self) -> &AtomicDirtyMask;
+    fn state_bytes(&self) -> &[u8];
+}
+
+/// Components rendering a keyed collection expose the keys in their current
+/// order, so the patcher can reconcile a reorder with
+/// [`NodeMove`](crate::RenderOp::NodeMove) ops instead of rebuilding the
+/// children.
+pub trait KeyedListState: ComponentState {
+    fn child_keys(&self) -> &[u64];
+}
+
+/// Components whose state region can be written back into, required for
+/// two-way ([`BindingType::ValueSync`](crate::BindingType::ValueSync))
+/// bindings.
+pub trait MutableComponentState: ComponentState {
+    fn state_bytes_mut(&mut self) -> &mut [u8];
+}
+
+/// Maps typed actions onto state mutations and the dirty bits those
+/// mutations touch, declared together so dispatching can never change a
+/// field without setting its bit — the class of bug that calling
+/// [`AtomicDirtyMask::mark_dirty`] by hand after each mutation invites.
+pub struct Reducer<State, Action> {
+    rules: Vec<ReducerRule<State, Action>>,
+}
+
+struct ReducerRule<State, Action> {
+    matches: Box<dyn Fn(&Action) -> bool>,
+    dirty_bits: Vec<u16>,
+    apply: Box<dyn Fn(&mut State, &Action)>,
+}
+
+impl<State: ComponentState, Action> Reducer<State, Action> {
+    pub fn new() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Registers a rule: when `matches` accepts a dispatched action, `apply`
+    /// runs and every bit in `dirty_bits` is marked afterwards. The bits are
+    /// declared alongside the mutation rather than marked inside it so the
+    /// two cannot drift apart.
+    pub fn add_rule(
+        &mut self,
+        matches: impl Fn(&Action) -> bool + 'static,
+        dirty_bits: impl IntoIterator<Item = u16>,
+        apply: impl Fn(&mut State, &Action) + 'static,
+    ) {
+        self.rules.push(ReducerRule {
+            matches: Box::new(matches),
+            dirty_bits: dirty_bits.into_iter().collect(),
+            apply: Box::new(apply),
+        });
+    }
+
+    /// Applies the first rule matching `action` and marks its declared bits.
+    /// Returns `false` — mutating and marking nothing — when no rule
+    /// matches.
+    pub fn dispatch(&self, state: &mut State, action: &Action) -> bool {
+        let Some(rule) = self.rules.iter().find(|rule| (rule.matches)(action)) else {
+            return false;
+        };
+        (rule.apply)(state, action);
+        for bit in &rule.dirty_bits {
+            state.dirty_mask().mark_dirty(*bit);
+        }
+        true
+    }
+}
+
+impl<State: ComponentState, Action> Default for Reducer<State, Action> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mark_and_take() {
+        let mask = AtomicDirtyMask::new();
+        mask.mark_dirty(0);
+        mask.mark_dirty(63);
+        mask.mark_dirty(100);
+        let taken = mask.take_dirty();
+        assert!(taken.is_set(0));
+        assert!(taken.is_set(63));
+        assert!(taken.is_set(100));
+        assert!(!taken.is_set(1));
+        assert!(mask.take_dirty().is_empty());
+    }
+
+    #[test]
+    fn test_out_of_range_bits_are_ignored() {
+        let mask = AtomicDirtyMask::new();
+        mask.mark_dirty(DIRTY_MASK_BITS);
+        assert!(mask.take_dirty().is_empty());
+    }
+
+    #[test]
+    fn test_clear_if_unchanged_rejects_a_stale_peek() {
+        let mask = AtomicDirtyMask::new();
+        mask.mark_dirty(3);
+        let peeked = mask.peek();
+        mask.mark_dirty(7);
+        assert!(!mask.clear_if_unchanged(peeked));
+        assert!(mask.peek().is_set(3));
+        assert!(mask.peek().is_set(7));
+        assert!(mask.clear_if_unchanged(mask.peek()));
+        assert!(mask.peek().is_empty());
+    }
+
+    #[test]
+    fn test_a_stale_peek_across_words_clears_nothing() {
+        let mask = AtomicDirtyMask::new();
+        mask.mark_dirty(3);
+        mask.mark_dirty(100);
+        let peeked = mask.peek();
+        // The mark lands in the last word, after the earlier words would
+        // already have been cleared; the failed clear must put them back.
+        mask.mark_dirty(200);
+        assert!(!mask.clear_if_unchanged(peeked));
+        assert!(mask.peek().is_set(3));
+        assert!(mask.peek().is_set(100));
+        assert!(mask.peek().is_set(200));
+    }
+
+    #[test]
+    fn test_interleaved_marks_and_conditional_clears_lose_no_bits() {
+        use std::sync::Arc;
+        use std::sync::atomic::AtomicBool;
+
+        let mask = Arc::new(AtomicDirtyMask::new());
+        let done = Arc::new(AtomicBool::new(false));
+        const ROUNDS: u32 = 10_000;
+
+        let marker = std::thread::spawn({
+            let mask = mask.clone();
+            let done = done.clone();
+            move || {
+                for round in 0..ROUNDS {
+                    mask.mark_dirty((round % u32::from(DIRTY_MASK_BITS)) as u16);
+                }
+                done.store(true, Ordering::Release);
+            }
+        });
+
+        let mut drained = [0u64; DIRTY_MASK_WORDS];
+        let mut cleared_count: u64 = 0;
+        while !done.load(Ordering::Acquire) || !mask.peek().is_empty() {
+            let peeked = mask.peek();
+            if !peeked.is_empty() && mask.clear_if_unchanged(peeked) {
+                for (drained_word, peeked_word) in drained.iter_mut().zip(peeked.0) {
+                    *drained_word |= peeked_word;
+                    cleared_count += u64::from(peeked_word.count_ones());
+                }
+            }
+        }
+        marker.join().unwrap();
+
+        // Every mark either survives in the mask (drained by the loop's exit
+        // condition) or was observed by exactly one successful clear; a clear
+        // racing with a mark must fail rather than swallow the fresh bit.
+        assert_eq!(drained, [u64::MAX; DIRTY_MASK_WORDS]);
+        assert!(cleared_count <= ROUNDS as u64);
+        assert!(mask.peek().is_empty());
+    }
+
+    #[test]
+    fn test_reducer_marks_exactly_the_mapped_bits() {
+        struct Counter {
+            mask: AtomicDirtyMask,
+            count: u8,
+            label: u8,
+        }
+
+        impl ComponentState for Counter {
+            fn component_id(&self) -> u32 {
+                1
+            }
+            fn dirty_mask(&self) -> &AtomicDirtyMask {
+                &self.mask
+            }
+            fn state_bytes(&self) -> &[u8] {
+                std::slice::from_ref(&self.count)
+            }
+        }
+
+        enum CounterAction {
+            Increment,
+            Rename(u8),
+        }
+
+        const COUNT_BIT: u16 = 0;
+        const LABEL_BIT: u16 = 3;
+
+        let mut reducer = Reducer::new();
+        reducer.add_rule(
+            |action| matches!(action, CounterAction::Increment),
+            [COUNT_BIT],
+            |counter: &mut Counter, _| counter.count += 1,
+        );
+        reducer.add_rule(
+            |action| matches!(action, CounterAction::Rename(_)),
+            [LABEL_BIT],
+            |counter, action| {
+                if let CounterAction::Rename(label) = action {
+                    counter.label = *label;
+                }
+            },
+        );
+
+        let mut counter = Counter {
+            mask: AtomicDirtyMask::new(),
+            count: 0,
+            label: 0,
+        };
+        assert!(reducer.dispatch(&mut counter, &CounterAction::Increment));
+        assert_eq!(counter.count, 1);
+        let dirty: Vec<u16> = counter.mask.take_dirty().iter_set_bits().collect();
+        assert_eq!(dirty, vec![COUNT_BIT], "only the mapped bit is dirty");
+
+        assert!(reducer.dispatch(&mut counter, &CounterAction::Rename(7)));
+        assert_eq!(counter.label, 7);
+        let dirty: Vec<u16> = counter.mask.take_dirty().iter_set_bits().collect();
+        assert_eq!(dirty, vec![LABEL_BIT]);
+    }
+
+    #[test]
+    fn test_iter_set_bits_is_ascending() {
+        let mask = AtomicDirtyMask::new();
+        for bit in [9, 2, 100, 40] {
+            mask.mark_dirty(bit);
+        }
+        let bits: Vec<u16> = mask.take_dirty().iter_set_bits().collect();
+        assert_eq!(bits, vec![2, 9, 40, 100]);
+    }
+}
@@ -0,0 +1,557 @@
+//! The JS/WASM bridge. The JS side owns two regions of linear memory and
+//! passes them to [`patch_into_buffer`] (exported as `dx_morph_patch`):
+//!
+//! * State region: `[dirty mask: DIRTY_MASK_WORDS x u64 LE][state bytes...]`.
+//!   Mask word 0 holds bits 0..63, word 1 bits 64..127, and so on; the mask
+//!   is drained (zeroed) by a successful patch.
+//! * Output region: one record per emitted [`RenderOp`]:
+//!   `[kind: u8][node_id: u32 LE][target_id: u16 LE][flag: u8]`
+//!   `[value_len: u32 LE][value: utf-8 bytes]`.
+//!   `kind` mirrors the [`BindingType`](crate::BindingType) discriminants.
+//!   `target_id` is the attribute/property/class id and is 0 for text and
+//!   visibility ops. `flag` carries the boolean for toggle/visibility ops.
+//!   For a style batch (`kind` 6), `target_id` is the pair count and the
+//!   value bytes are `target_id` repetitions of
+//!   `[property_id: u16 LE][len: u32 LE][value: utf-8 bytes]`, iterated in
+//!   order by the runtime. A removal (`kind` 7) carries no value bytes;
+//!   `target_id` names the attribute/property/class to remove, 0 for text.
+//!   An animated op (`kind` 8) carries its transition in the header —
+//!   `target_id` is the duration in milliseconds, `flag` the easing id —
+//!   and the value bytes are the wrapped op's own record. A node move
+//!   (`kind` 9) carries the parent id in its value bytes, followed by the
+//!   anchor sibling id when `flag` is set (clear means move to the end).
+//!
+//! The entry point returns the op count, or a negative value when the state
+//! region is malformed or the output region is too small, in which case
+//! nothing is drained and the JS side should retry with a larger buffer.
+
+use crate::{AtomicDirtyMask, BindingEntry, ComponentState, RenderOp, StatePatcher};
+use std::borrow::Cow;
+
+/// Byte length of the dirty mask prefix in the state region.
+pub const STATE_MASK_LEN: usize = 8 * crate::DIRTY_MASK_WORDS;
+/// Fixed bytes per op record before its value payload.
+pub const OP_RECORD_HEADER_LEN: usize = 12;
+
+struct RawComponentState<'a> {
+    component_id: u32,
+    mask: AtomicDirtyMask,
+    bytes: &'a [u8],
+}
+
+impl ComponentState for RawComponentState<'_> {
+    fn component_id(&self) -> u32 {
+        self.component_id
+    }
+
+    fn dirty_mask(&self) -> &AtomicDirtyMask {
+        &self.mask
+    }
+
+    fn state_bytes(&self) -> &[u8] {
+        self.bytes
+    }
+}
+
+/// Runs [`StatePatcher::patch`] against a raw state region and encodes the
+/// resulting ops into `out` per the module contract, returning the op count.
+///
+/// Returns `None` — leaving the dirty mask intact — if the state region is
+/// shorter than the mask prefix or the ops don't fit in `out`.
+pub fn patch_into_buffer(
+    patcher: &StatePatcher,
+    component_id: u32,
+    state_region: &mut [u8],
+    out: &mut [u8],
+) -> Option<usize> {
+    if state_region.len() < STATE_MASK_LEN {
+        return None;
+    }
+    let (mask_bytes, state_bytes) = state_region.split_at_mut(STATE_MASK_LEN);
+    let mut mask_words = [0u64; crate::DIRTY_MASK_WORDS];
+    for (word, word_bytes) in mask_words.iter_mut().zip(mask_bytes.chunks_exact(8)) {
+        *word = u64::from_le_bytes(word_bytes.try_into().ok()?);
+    }
+    let state = RawComponentState {
+        component_id,
+        mask: AtomicDirtyMask::new(),
+        bytes: state_bytes,
+    };
+    for bit in crate::DirtyMaskN(mask_words).iter_set_bits() {
+        state.mask.mark_dirty(bit);
+    }
+
+    let ops = patcher.patch(&state);
+    let mut written = 0;
+    for op in &ops {
+        written += encode_op(op, out.get_mut(written..)?)?;
+    }
+    mask_bytes.fill(0);
+    Some(ops.len())
+}
+
+fn encode_op(op: &RenderOp, out: &mut [u8]) -> Option<usize> {
+    let (kind, target_id, flag, value): (u8, u16, u8, Cow<'_, [u8]>) = match op {
+        RenderOp::SetText { value, .. } => (0, 0, 0, Cow::Borrowed(value.as_bytes())),
+        RenderOp::SetAttribute {
+            attribute_id,
+            value,
+            ..
+        } => (1, *attribute_id, 0, Cow::Borrowed(value.as_bytes())),
+        RenderOp::SetStyle {
+            property_id, value, ..
+        } => (2, *property_id, 0, Cow::Borrowed(value.as_bytes())),
+        RenderOp::ToggleClass {
+            class_id, enabled, ..
+        } => (3, *class_id, u8::from(*enabled), Cow::Borrowed(&[][..])),
+        RenderOp::SetVisibility { visible, .. } => {
+            (4, 0, u8::from(*visible), Cow::Borrowed(&[][..]))
+        }
+        RenderOp::SetValue { value, .. } => (5, 0, 0, Cow::Borrowed(value.as_bytes())),
+        RenderOp::Remove { target_id, .. } => (7, *target_id, 0, Cow::Borrowed(&[][..])),
+        RenderOp::SetStyleBatch { properties, .. } => {
+            let mut payload = Vec::new();
+            for (property_id, value) in properties {
+                payload.extend_from_slice(&property_id.to_le_bytes());
+                payload.extend_from_slice(&(value.len() as u32).to_le_bytes());
+                payload.extend_from_slice(value.as_bytes());
+            }
+            (
+                6,
+                u16::try_from(properties.len()).ok()?,
+                0,
+                Cow::Owned(payload),
+            )
+        }
+        RenderOp::NodeMove {
+            parent_id,
+            before_node_id,
+            ..
+        } => {
+            let mut payload = parent_id.to_le_bytes().to_vec();
+            if let Some(before_node_id) = before_node_id {
+                payload.extend_from_slice(&before_node_id.to_le_bytes());
+            }
+            (
+                9,
+                0,
+                u8::from(before_node_id.is_some()),
+                Cow::Owned(payload),
+            )
+        }
+        RenderOp::Animated {
+            op: inner,
+            duration_ms,
+            easing_id,
+        } => {
+            let mut payload = vec![0u8; encoded_op_len(inner)?];
+            let inner_len = encode_op(inner, &mut payload)?;
+            payload.truncate(inner_len);
+            (8, *duration_ms, *easing_id, Cow::Owned(payload))
+        }
+    };
+    let record_len = OP_RECORD_HEADER_LEN + value.len();
+    if out.len() < record_len {
+        return None;
+    }
+    out[0] = kind;
+    out[1..5].copy_from_slice(&op.node_id().to_le_bytes());
+    out[5..7].copy_from_slice(&target_id.to_le_bytes());
+    out[7] = flag;
+    out[8..12].copy_from_slice(&(value.len() as u32).to_le_bytes());
+    out[12..record_len].copy_from_slice(&value);
+    Some(record_len)
+}
+
+/// Exact encoded size of one op record, so the animated encoder can size the
+/// buffer for its wrapped record without a trial encode.
+fn encoded_op_len(op: &RenderOp) -> Option<usize> {
+    let value_len = match op {
+        RenderOp::SetText { value, .. }
+        | RenderOp::SetAttribute { value, .. }
+        | RenderOp::SetStyle { value, .. }
+        | RenderOp::SetValue { value, .. } => value.len(),
+        RenderOp::ToggleClass { .. } | RenderOp::SetVisibility { .. } | RenderOp::Remove { .. } => {
+            0
+        }
+        RenderOp::SetStyleBatch { properties, .. } => properties
+            .iter()
+            .map(|(_, value)| 6 + value.len())
+            .sum::<usize>(),
+        RenderOp::NodeMove { before_node_id, .. } => {
+            4 + if before_node_id.is_some() { 4 } else { 0 }
+        }
+        RenderOp::Animated { op: inner, .. } => encoded_op_len(inner)?,
+    };
+    OP_RECORD_HEADER_LEN.checked_add(value_len)
+}
+
+/// Length of one serialized [`BindingEntry`] record, matching its `repr(C)`
+/// size: the fields in declaration order, integers little-endian, followed
+/// by three reserved bytes (the struct's tail padding, zero for now).
+pub const BINDING_ENTRY_LEN: usize = 28;
+
+/// Decodes the [`BindingEntry`] records the JS side serializes when
+/// registering a component; see [`BINDING_ENTRY_LEN`] for the layout. The
+/// reserved tail bytes are ignored, so a future field can use them without
+/// breaking this decoder.
+pub fn decode_binding_entries(bytes: &[u8]) -> Option<Vec<BindingEntry>> {
+    if !bytes.len().is_multiple_of(BINDING_ENTRY_LEN) {
+        return None;
+    }
+    let mut entries = Vec::with_capacity(bytes.len() / BINDING_ENTRY_LEN);
+    for record in bytes.chunks_exact(BINDING_ENTRY_LEN) {
+        let binding_type = crate::BindingType::from_u8(record[20])?;
+        let mut entry = BindingEntry::new(
+            u16::from_le_bytes(record[0..2].try_into().ok()?),
+            binding_type,
+            u16::from_le_bytes(record[2..4].try_into().ok()?),
+            u32::from_le_bytes(record[4..8].try_into().ok()?),
+            u32::from_le_bytes(record[8..12].try_into().ok()?),
+            u32::from_le_bytes(record[12..16].try_into().ok()?),
+        );
+        if record[22] != 0 {
+            entry = entry.with_presence(
+                u32::from_le_bytes(record[16..20].try_into().ok()?),
+                record[21],
+            );
+        }
+        entry = entry.with_value_type(crate::ValueType::from_u8(record[23])?, record[24]);
+        entries.push(entry);
+    }
+    Some(entries)
+}
+
+/// Decodes `op_count` records produced by [`patch_into_buffer`]; the inverse
+/// of the encoder, used by tests and native tooling (the JS side has its own
+/// reader).
+pub fn decode_ops(bytes: &[u8], op_count: usize) -> Option<Vec<RenderOp>> {
+    let mut ops = Vec::with_capacity(op_count);
+    let mut offset = 0;
+    for _ in 0..op_count {
+        let header = bytes.get(offset..offset + OP_RECORD_HEADER_LEN)?;
+        let kind = header[0];
+        let node_id = u32::from_le_bytes(header[1..5].try_into().ok()?);
+        let target_id = u16::from_le_bytes(header[5..7].try_into().ok()?);
+        let flag = header[7] != 0;
+        let value_len = u32::from_le_bytes(header[8..12].try_into().ok()?) as usize;
+        let value_start = offset + OP_RECORD_HEADER_LEN;
+        let value_bytes = bytes.get(value_start..value_start + value_len)?;
+        if kind == 6 {
+            let mut properties = Vec::with_capacity(target_id as usize);
+            let mut cursor = 0;
+            for _ in 0..target_id {
+                let pair_header = value_bytes.get(cursor..cursor + 6)?;
+                let property_id = u16::from_le_bytes(pair_header[..2].try_into().ok()?);
+                let pair_len = u32::from_le_bytes(pair_header[2..6].try_into().ok()?) as usize;
+                let pair_value = value_bytes.get(cursor + 6..cursor + 6 + pair_len)?;
+                properties.push((property_id, String::from_utf8(pair_value.to_vec()).ok()?));
+                cursor += 6 + pair_len;
+            }
+            if cursor != value_len {
+                return None;
+            }
+            ops.push(RenderOp::SetStyleBatch {
+                node_id,
+                properties,
+            });
+            offset = value_start + value_len;
+            continue;
+        }
+        if kind == 8 {
+            let mut inner = decode_ops(value_bytes, 1)?;
+            let inner_op = inner.pop()?;
+            ops.push(RenderOp::Animated {
+                op: Box::new(inner_op),
+                duration_ms: target_id,
+                easing_id: header[7],
+            });
+            offset = value_start + value_len;
+            continue;
+        }
+        if kind == 9 {
+            let parent_id = u32::from_le_bytes(value_bytes.get(..4)?.try_into().ok()?);
+            let before_node_id = if flag {
+                Some(u32::from_le_bytes(value_bytes.get(4..8)?.try_into().ok()?))
+            } else {
+                None
+            };
+            ops.push(RenderOp::NodeMove {
+                parent_id,
+                node_id,
+                before_node_id,
+            });
+            offset = value_start + value_len;
+            continue;
+        }
+        let value = String::from_utf8(value_bytes.to_vec()).ok()?;
+        let op = match kind {
+            0 => RenderOp::SetText { node_id, value },
+            1 => RenderOp::SetAttribute {
+                node_id,
+                attribute_id: target_id,
+                value,
+            },
+            2 => RenderOp::SetStyle {
+                node_id,
+                property_id: target_id,
+                value,
+            },
+            3 => RenderOp::ToggleClass {
+                node_id,
+                class_id: target_id,
+                enabled: flag,
+            },
+            4 => RenderOp::SetVisibility {
+                node_id,
+                visible: flag,
+            },
+            5 => RenderOp::SetValue { node_id, value },
+            7 => RenderOp::Remove { node_id, target_id },
+            _ => return None,
+        };
+        ops.push(op);
+        offset = value_start + value_len;
+    }
+    Some(ops)
+}
+
+#[cfg(target_arch = "wasm32")]
+mod wasm {
+    use super::*;
+    use crate::BindingMap;
+    use std::cell::RefCell;
+    use wasm_bindgen::prelude::wasm_bindgen;
+
+    thread_local! {
+        static PATCHER: RefCell<StatePatcher> = RefCell::new(StatePatcher::new());
+    }
+
+    #[wasm_bindgen]
+    pub fn init_dx_morph() {
+        PATCHER.with(|patcher| *patcher.borrow_mut() = StatePatcher::new());
+    }
+
+    /// Registers a component's binding map from `entries_len` serialized
+    /// [`BindingEntry`](crate::BindingEntry) records at `entries_ptr`.
+    #[wasm_bindgen]
+    pub fn dx_morph_register_component(
+        component_id: u32,
+        entries_ptr: *const u8,
+        entries_len: u32,
+    ) -> bool {
+        // SAFETY: the JS side passes a region it allocated inside this
+        // module's linear memory and keeps alive for the duration of the
+        // call.
+        let bytes = unsafe { std::slice::from_raw_parts(entries_ptr, entries_len as usize) };
+        let Some(entries) = decode_binding_entries(bytes) else {
+            return false;
+        };
+        PATCHER.with(|patcher| {
+            patcher
+                .borrow_mut()
+                .register_binding_map(BindingMap::new(component_id, entries))
+                .is_ok()
+        })
+    }
+
+    /// Patches `component_id` against the state region and writes encoded
+    /// ops to the output region. Returns the op count, or -1 when the
+    /// regions are invalid or the output buffer is too small.
+    #[wasm_bindgen]
+    pub fn dx_morph_patch(
+        component_id: u32,
+        state_ptr: *mut u8,
+        state_len: u32,
+        out_ptr: *mut u8,
+        out_len: u32,
+    ) -> i32 {
+        // SAFETY: as above, both regions live in this module's linear memory
+        // and the JS side guarantees they don't overlap.
+        let state_region = unsafe { std::slice::from_raw_parts_mut(state_ptr, state_len as usize) };
+        let out = unsafe { std::slice::from_raw_parts_mut(out_ptr, out_len as usize) };
+        PATCHER.with(|patcher| {
+            match patch_into_buffer(&patcher.borrow(), component_id, state_region, out) {
+                Some(count) => count as i32,
+                None => -1,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BindingEntry, BindingMap, BindingType};
+
+    fn patcher_with_text_bindings(component_id: u32) -> StatePatcher {
+        let mut patcher = StatePatcher::new();
+        patcher
+            .register_binding_map(BindingMap::new(
+                component_id,
+                vec![
+                    BindingEntry::new(0, BindingType::Text, 0, 100, 0, 5),
+                    BindingEntry::new(1, BindingType::Visibility, 0, 101, 5, 1),
+                ],
+            ))
+            .unwrap();
+        patcher
+    }
+
+    fn state_region(mask: u64, bytes: &[u8]) -> Vec<u8> {
+        let mut region = vec![0u8; STATE_MASK_LEN];
+        region[..8].copy_from_slice(&mask.to_le_bytes());
+        region.extend_from_slice(bytes);
+        region
+    }
+
+    #[test]
+    fn test_patch_into_buffer_encodes_ops_and_drains_mask() {
+        let patcher = patcher_with_text_bindings(7);
+        let mut region = state_region(0b11, b"hello\x01");
+        let mut out = vec![0u8; 256];
+
+        let count = patch_into_buffer(&patcher, 7, &mut region, &mut out).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(
+            decode_ops(&out, count).unwrap(),
+            vec![
+                RenderOp::SetText {
+                    node_id: 100,
+                    value: "hello".into()
+                },
+                RenderOp::SetVisibility {
+                    node_id: 101,
+                    visible: true
+                },
+            ]
+        );
+        assert_eq!(&region[..STATE_MASK_LEN], &[0; STATE_MASK_LEN]);
+    }
+
+    #[test]
+    fn test_mask_words_past_the_first_reach_their_bindings() {
+        let mut patcher = StatePatcher::new();
+        patcher
+            .register_binding_map(BindingMap::new(
+                7,
+                vec![BindingEntry::new(100, BindingType::Text, 0, 100, 0, 5)],
+            ))
+            .unwrap();
+        // Bit 100 is bit 36 of mask word 1.
+        let mut region = vec![0u8; STATE_MASK_LEN];
+        region[8..16].copy_from_slice(&(1u64 << 36).to_le_bytes());
+        region.extend_from_slice(b"hello");
+        let mut out = vec![0u8; 64];
+
+        let count = patch_into_buffer(&patcher, 7, &mut region, &mut out).unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(
+            decode_ops(&out, count).unwrap(),
+            vec![RenderOp::SetText {
+                node_id: 100,
+                value: "hello".into()
+            }]
+        );
+        assert_eq!(&region[..STATE_MASK_LEN], &[0; STATE_MASK_LEN]);
+    }
+
+    #[test]
+    fn test_undersized_output_buffer_is_rejected_without_draining() {
+        let patcher = patcher_with_text_bindings(7);
+        let mut region = state_region(0b1, b"hello\x01");
+        let mut out = vec![0u8; 4];
+
+        assert_eq!(patch_into_buffer(&patcher, 7, &mut region, &mut out), None);
+        assert_ne!(
+            &region[..STATE_MASK_LEN],
+            &[0; STATE_MASK_LEN],
+            "failed patch must leave the mask intact for a retry"
+        );
+    }
+
+    #[test]
+    fn test_style_batch_op_round_trips() {
+        let op = RenderOp::SetStyleBatch {
+            node_id: 9,
+            properties: vec![(7, "red".into()), (8, "10px".into())],
+        };
+        let mut out = vec![0u8; 64];
+        let written = encode_op(&op, &mut out).unwrap();
+        assert_eq!(decode_ops(&out[..written], 1).unwrap(), vec![op]);
+
+        let mut undersized = vec![0u8; OP_RECORD_HEADER_LEN + 4];
+        let batch = RenderOp::SetStyleBatch {
+            node_id: 9,
+            properties: vec![(7, "red".into())],
+        };
+        assert_eq!(encode_op(&batch, &mut undersized), None);
+    }
+
+    #[test]
+    fn test_animated_op_round_trips() {
+        let op = RenderOp::Animated {
+            op: Box::new(RenderOp::SetStyle {
+                node_id: 9,
+                property_id: 7,
+                value: "red".into(),
+            }),
+            duration_ms: 150,
+            easing_id: 2,
+        };
+        let mut out = vec![0u8; 64];
+        let written = encode_op(&op, &mut out).unwrap();
+        assert_eq!(decode_ops(&out[..written], 1).unwrap(), vec![op]);
+    }
+
+    #[test]
+    fn test_node_move_op_round_trips() {
+        for before_node_id in [Some(12), None] {
+            let op = RenderOp::NodeMove {
+                parent_id: 3,
+                node_id: 11,
+                before_node_id,
+            };
+            let mut out = vec![0u8; 64];
+            let written = encode_op(&op, &mut out).unwrap();
+            assert_eq!(decode_ops(&out[..written], 1).unwrap(), vec![op]);
+        }
+    }
+
+    #[test]
+    fn test_decode_binding_entries_round_trip() {
+        let entry = BindingEntry::new(300, BindingType::Text, 9, 42, 16, 8)
+            .with_presence(15, 2)
+            .with_value_type(crate::ValueType::F64, 2);
+        let mut record = entry.dirty_bit.to_le_bytes().to_vec();
+        record.extend_from_slice(&entry.target_id.to_le_bytes());
+        record.extend_from_slice(&entry.node_id.to_le_bytes());
+        record.extend_from_slice(&entry.value_offset.to_le_bytes());
+        record.extend_from_slice(&entry.value_len.to_le_bytes());
+        record.extend_from_slice(&entry.presence_offset.to_le_bytes());
+        record.push(entry.binding_type);
+        record.push(entry.presence_bit);
+        record.push(entry.optional);
+        record.push(entry.value_type);
+        record.push(entry.format_spec);
+        record.extend_from_slice(&[0; 3]);
+
+        let entries = decode_binding_entries(&record).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0], entry);
+
+        assert!(decode_binding_entries(&record[..10]).is_none());
+    }
+
+    #[test]
+    fn test_truncated_state_region_is_rejected() {
+        let patcher = patcher_with_text_bindings(7);
+        let mut out = vec![0u8; 64];
+        // Shorter than the full mask prefix — including the old single-word
+        // 8-byte prefix — is malformed.
+        for region_len in [4, 8] {
+            let mut region = vec![0u8; region_len];
+            assert_eq!(patch_into_buffer(&patcher, 7, &mut region, &mut out), None);
+        }
+    }
+}
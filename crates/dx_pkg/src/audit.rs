@@ -0,0 +1,880 @@
+use crate::{PkgError, ResolutionGraph, ResolvedPackage};
+use collections::{HashMap, HashSet};
+use semver::Version;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// The most-downloaded registry packages, used as the reference set for
+/// typosquat detection when the caller doesn't supply their own list.
+pub const POPULAR_PACKAGES: &[&str] = &[
+    "lodash",
+    "react",
+    "react-dom",
+    "express",
+    "axios",
+    "chalk",
+    "commander",
+    "debug",
+    "moment",
+    "webpack",
+    "typescript",
+    "eslint",
+    "prettier",
+    "jest",
+    "mocha",
+    "vue",
+    "next",
+    "vite",
+    "rollup",
+    "babel-core",
+    "uuid",
+    "classnames",
+    "dotenv",
+    "glob",
+    "minimist",
+    "request",
+    "rimraf",
+    "semver",
+    "yargs",
+    "inquirer",
+    "node-fetch",
+    "prop-types",
+    "redux",
+    "rxjs",
+    "socket.io",
+    "styled-components",
+    "tailwindcss",
+    "three",
+    "underscore",
+    "zod",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TyposquatWarning {
+    /// The dependency as declared in the manifest.
+    pub package: String,
+    /// The popular package the name is suspiciously close to.
+    pub suspected: String,
+    /// Levenshtein distance between the two names.
+    pub distance: usize,
+}
+
+impl fmt::Display for TyposquatWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` is {} edit(s) away from popular package `{}`",
+            self.package, self.distance, self.suspected
+        )
+    }
+}
+
+/// Advisory severity, mildest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Low,
+    Moderate,
+    High,
+    Critical,
+}
+
+/// A known advisory against one exact package version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vulnerability {
+    pub package: String,
+    pub version: Version,
+    pub advisory_id: String,
+    pub summary: String,
+    pub severity: Severity,
+    /// When the advisory was published, if the advisory source reports it.
+    pub disclosed_at: Option<SystemTime>,
+    /// The vulnerable entry point, when the advisory names one (e.g.
+    /// `template` for a prototype-pollution bug in `lodash.template`).
+    pub vulnerable_function: Option<String>,
+}
+
+impl fmt::Display for Vulnerability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}@{} — {}",
+            self.advisory_id, self.package, self.version, self.summary
+        )
+    }
+}
+
+/// The result of matching advisories against a resolved dependency tree.
+#[derive(Debug, Default)]
+pub struct AuditReport {
+    pub vulnerabilities: Vec<Vulnerability>,
+}
+
+impl AuditReport {
+    /// Attributes each vulnerability to the direct dependencies that pull it
+    /// in, so the fix list reads "update direct dep X" instead of naming a
+    /// transitive package the user never asked for. A vulnerability reachable
+    /// through several roots appears under each of them; roots whose subtree
+    /// is clean are omitted.
+    pub fn by_root_dependency(
+        &self,
+        graph: &ResolutionGraph,
+    ) -> HashMap<String, Vec<&Vulnerability>> {
+        let package_by_id: HashMap<(&str, &Version), &ResolvedPackage> = graph
+            .packages
+            .iter()
+            .map(|package| ((package.name.as_str(), &package.version), package))
+            .collect();
+        let mut by_root: HashMap<String, Vec<&Vulnerability>> = HashMap::default();
+        for (root_name, root_version) in &graph.root_dependencies {
+            let mut reachable: HashSet<(&str, &Version)> = HashSet::default();
+            let mut queue = vec![(root_name.as_str(), root_version)];
+            while let Some(id) = queue.pop() {
+                if !reachable.insert(id) {
+                    continue;
+                }
+                if let Some(package) = package_by_id.get(&id) {
+                    for (name, version) in &package.dependencies {
+                        queue.push((name.as_str(), version));
+                    }
+                }
+            }
+            let found: Vec<&Vulnerability> = self
+                .vulnerabilities
+                .iter()
+                .filter(|vulnerability| {
+                    reachable.contains(&(vulnerability.package.as_str(), &vulnerability.version))
+                })
+                .collect();
+            if !found.is_empty() {
+                by_root.insert(root_name.clone(), found);
+            }
+        }
+        by_root
+    }
+
+    /// Narrows the report to vulnerabilities disclosed within `max_age` of
+    /// now, for triaging a large backlog newest-first. Advisories without a
+    /// disclosure date are retained — an unknown date is treated as
+    /// unknown/old, and dropping it would silently hide a real issue.
+    pub fn filter_by_age(&self, max_age: Duration) -> AuditReport {
+        let cutoff = SystemTime::now().checked_sub(max_age);
+        AuditReport {
+            vulnerabilities: self
+                .vulnerabilities
+                .iter()
+                .filter(|vulnerability| match (vulnerability.disclosed_at, cutoff) {
+                    (Some(disclosed_at), Some(cutoff)) => disclosed_at >= cutoff,
+                    // No disclosure date, or a window so large the cutoff
+                    // predates representable time: keep the advisory.
+                    _ => true,
+                })
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// One comparable 0–100 number for dashboards, so risk can be tracked
+    /// over time and across projects. The formula:
+    ///
+    /// ```text
+    /// contribution = severity weight × exposure multiplier × recency multiplier
+    /// score        = 100 × sum / (sum + pivot)
+    /// ```
+    ///
+    /// where `sum` is the total of every vulnerability's contribution.
+    /// Exposure comes from the matching [`ReachabilityHint`] (by advisory
+    /// id); a vulnerability without a hint uses the `Imported` multiplier,
+    /// the neutral middle. The saturating map keeps the score under 100
+    /// while staying strictly increasing in `sum`, and contributions are
+    /// clamped to be non-negative, so adding a vulnerability — critical or
+    /// otherwise — never lowers the score, whatever the model's weights.
+    pub fn risk_score(&self, hints: &[ReachabilityHint], model: &RiskModel) -> f64 {
+        let sum: f64 = self
+            .vulnerabilities
+            .iter()
+            .map(|vulnerability| {
+                let exposure = hints
+                    .iter()
+                    .find(|hint| hint.advisory_id == vulnerability.advisory_id)
+                    .map_or(Exposure::Imported, |hint| hint.exposure);
+                let recency = if vulnerability.disclosed_at.is_some_and(|disclosed_at| {
+                    SystemTime::now()
+                        .duration_since(disclosed_at)
+                        .is_ok_and(|age| age <= model.recent_window)
+                }) {
+                    model.recent_multiplier
+                } else {
+                    1.0
+                };
+                (model.severity_weight(vulnerability.severity)
+                    * model.exposure_multiplier(exposure)
+                    * recency)
+                    .max(0.0)
+            })
+            .sum();
+        100.0 * sum / (sum + model.pivot.max(f64::MIN_POSITIVE))
+    }
+}
+
+/// The weighting behind [`AuditReport::risk_score`]. The defaults roughly
+/// double the weight per severity step, halve it for apparently-unused
+/// packages, and bump advisories disclosed within the last month.
+#[derive(Debug, Clone)]
+pub struct RiskModel {
+    pub low_weight: f64,
+    pub moderate_weight: f64,
+    pub high_weight: f64,
+    pub critical_weight: f64,
+    pub function_referenced_multiplier: f64,
+    pub imported_multiplier: f64,
+    pub possibly_unused_multiplier: f64,
+    /// Advisories disclosed within this window count as recent.
+    pub recent_window: Duration,
+    pub recent_multiplier: f64,
+    /// The weighted sum that maps to a score of 50; lower pivots make the
+    /// score climb faster for small reports.
+    pub pivot: f64,
+}
+
+impl Default for RiskModel {
+    fn default() -> Self {
+        Self {
+            low_weight: 1.0,
+            moderate_weight: 3.0,
+            high_weight: 6.0,
+            critical_weight: 12.0,
+            function_referenced_multiplier: 1.5,
+            imported_multiplier: 1.0,
+            possibly_unused_multiplier: 0.5,
+            recent_window: Duration::from_secs(30 * 24 * 60 * 60),
+            recent_multiplier: 1.25,
+            pivot: 20.0,
+        }
+    }
+}
+
+impl RiskModel {
+    fn severity_weight(&self, severity: Severity) -> f64 {
+        match severity {
+            Severity::Low => self.low_weight,
+            Severity::Moderate => self.moderate_weight,
+            Severity::High => self.high_weight,
+            Severity::Critical => self.critical_weight,
+        }
+    }
+
+    fn exposure_multiplier(&self, exposure: Exposure) -> f64 {
+        match exposure {
+            Exposure::FunctionReferenced => self.function_referenced_multiplier,
+            Exposure::Imported => self.imported_multiplier,
+            Exposure::PossiblyUnused => self.possibly_unused_multiplier,
+        }
+    }
+}
+
+/// How exposed the project looks to one vulnerability, strongest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exposure {
+    /// A file imports the package and mentions the advisory's vulnerable
+    /// function.
+    FunctionReferenced,
+    /// A file imports the package, but the vulnerable function (if the
+    /// advisory names one) was not found.
+    Imported,
+    /// No source file imports the package — it may only be pulled in
+    /// transitively, or not used at all.
+    PossiblyUnused,
+}
+
+/// A prioritization hint from [`PackageAuditor::reachability`]: how exposed
+/// the scanned source looks to one vulnerability, with the files the
+/// classification is based on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReachabilityHint {
+    pub advisory_id: String,
+    pub package: String,
+    pub exposure: Exposure,
+    /// Files (relative to the scanned directory, sorted) that import the
+    /// package — narrowed to those referencing the vulnerable function when
+    /// the exposure is [`Exposure::FunctionReferenced`].
+    pub evidence: Vec<PathBuf>,
+}
+
+/// A provider of advisories: the bundled database, OSV, or a
+/// company-internal feed covering private registry packages.
+/// [`PackageAuditor::audit`] queries every configured source per resolved
+/// package and merges the results.
+pub trait AdvisorySource {
+    /// Advisories this source knows against exactly `package`'s resolved
+    /// name and version.
+    fn fetch_advisories(&self, package: &ResolvedPackage) -> Vec<Vulnerability>;
+}
+
+/// An [`AdvisorySource`] serving a fixed in-memory advisory list — the shape
+/// of the bundled database, and of snapshots of external feeds.
+#[derive(Debug, Default)]
+pub struct StaticAdvisorySource {
+    advisories: Vec<Vulnerability>,
+}
+
+impl StaticAdvisorySource {
+    pub fn new(advisories: Vec<Vulnerability>) -> Self {
+        Self { advisories }
+    }
+}
+
+impl AdvisorySource for StaticAdvisorySource {
+    fn fetch_advisories(&self, package: &ResolvedPackage) -> Vec<Vulnerability> {
+        self.advisories
+            .iter()
+            .filter(|advisory| {
+                advisory.package == package.name && advisory.version == package.version
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct PackageAuditor {
+    sources: Vec<Box<dyn AdvisorySource>>,
+}
+
+impl PackageAuditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An auditor that consults `sources` in order, so the bundled database
+    /// can be combined with OSV and internal feeds.
+    pub fn with_sources(sources: Vec<Box<dyn AdvisorySource>>) -> Self {
+        Self { sources }
+    }
+
+    /// Matches every resolved package against every configured source and
+    /// merges the findings into one report. Sources overlap heavily (OSV
+    /// mirrors most ecosystem databases), so duplicates are dropped by
+    /// advisory id — scoped per package and version, because one advisory
+    /// legitimately recurs when several vulnerable versions are resolved and
+    /// each occurrence matters for root attribution.
+    pub fn audit(&self, graph: &ResolutionGraph) -> AuditReport {
+        let mut seen: HashSet<(String, Version, String)> = HashSet::default();
+        let mut vulnerabilities = Vec::new();
+        for package in &graph.packages {
+            for source in &self.sources {
+                for vulnerability in source.fetch_advisories(package) {
+                    let key = (
+                        vulnerability.package.clone(),
+                        vulnerability.version.clone(),
+                        vulnerability.advisory_id.clone(),
+                    );
+                    if seen.insert(key) {
+                        vulnerabilities.push(vulnerability);
+                    }
+                }
+            }
+        }
+        AuditReport { vulnerabilities }
+    }
+
+    /// Flags dependencies whose names are within a small edit distance of a
+    /// popular package name — the classic typosquatting attack (`lodahs`,
+    /// `reakt`). Exact matches are legitimate uses of the popular package and
+    /// scoped packages (`@scope/name`) are namespaced by their owner, so both
+    /// are excluded. The caller judges the result: the warning carries the
+    /// suspected intended name and the distance.
+    pub fn check_typosquat(&self, deps: &[&str], popular: &[&str]) -> Vec<TyposquatWarning> {
+        let mut warnings = Vec::new();
+        for &dep in deps {
+            if dep.starts_with('@') || popular.contains(&dep) {
+                continue;
+            }
+            let mut best: Option<(usize, &str)> = None;
+            for &candidate in popular {
+                let distance = strsim::levenshtein(dep, candidate);
+                if distance <= max_suspicious_distance(candidate)
+                    && best.is_none_or(|(best_distance, _)| distance < best_distance)
+                {
+                    best = Some((distance, candidate));
+                }
+            }
+            if let Some((distance, suspected)) = best {
+                warnings.push(TyposquatWarning {
+                    package: dep.to_string(),
+                    suspected: suspected.to_string(),
+                    distance,
+                });
+            }
+        }
+        warnings
+    }
+
+    /// Classifies each vulnerability in `report` by whether the project
+    /// source under `src_dir` appears to use the vulnerable package, one
+    /// hint per vulnerability in report order.
+    ///
+    /// This is a textual heuristic, not a call graph: it looks for quoted
+    /// import specifiers of the package and, when the advisory names a
+    /// vulnerable function, for that identifier in importing files. Dynamic
+    /// imports, re-exports, and aliased bindings can all evade it — treat
+    /// [`Exposure::PossiblyUnused`] as "deprioritize", never "safe".
+    pub fn reachability(
+        &self,
+        report: &AuditReport,
+        src_dir: &Path,
+    ) -> Result<Vec<ReachabilityHint>, PkgError> {
+        let mut source_paths = Vec::new();
+        collect_source_files(src_dir, &mut source_paths)?;
+        source_paths.sort();
+        let mut sources = Vec::with_capacity(source_paths.len());
+        for path in source_paths {
+            let contents = fs::read_to_string(&path).map_err(|source| PkgError::Io {
+                path: path.clone(),
+                source,
+            })?;
+            let relative = path.strip_prefix(src_dir).unwrap_or(&path).to_path_buf();
+            sources.push((relative, contents));
+        }
+
+        Ok(report
+            .vulnerabilities
+            .iter()
+            .map(|vulnerability| {
+                let mut importing = Vec::new();
+                let mut referencing = Vec::new();
+                for (path, contents) in &sources {
+                    if !imports_package(contents, &vulnerability.package) {
+                        continue;
+                    }
+                    importing.push(path.clone());
+                    if vulnerability
+                        .vulnerable_function
+                        .as_ref()
+                        .is_some_and(|function| references_identifier(contents, function))
+                    {
+                        referencing.push(path.clone());
+                    }
+                }
+                let (exposure, evidence) = if !referencing.is_empty() {
+                    (Exposure::FunctionReferenced, referencing)
+                } else if !importing.is_empty() {
+                    (Exposure::Imported, importing)
+                } else {
+                    (Exposure::PossiblyUnused, Vec::new())
+                };
+                ReachabilityHint {
+                    advisory_id: vulnerability.advisory_id.clone(),
+                    package: vulnerability.package.clone(),
+                    exposure,
+                    evidence,
+                }
+            })
+            .collect())
+    }
+}
+
+const SOURCE_EXTENSIONS: &[&str] = &["js", "jsx", "mjs", "cjs", "ts", "tsx"];
+
+fn collect_source_files(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<(), PkgError> {
+    let io_error = |source| PkgError::Io {
+        path: dir.to_path_buf(),
+        source,
+    };
+    for entry in fs::read_dir(dir).map_err(io_error)? {
+        let path = entry.map_err(io_error)?.path();
+        if path.is_dir() {
+            collect_source_files(&path, paths)?;
+        } else if path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| SOURCE_EXTENSIONS.contains(&extension))
+        {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Whether the file contains a quoted import specifier for `package`, either
+/// the bare name or a subpath (`package/something`). Matching the quoted
+/// specifier covers both `import ... from "pkg"` and `require("pkg")`
+/// without parsing, at the cost of also matching the name in string
+/// literals.
+fn imports_package(contents: &str, package: &str) -> bool {
+    ['"', '\'', '`'].iter().any(|quote| {
+        let bare = format!("{quote}{package}{quote}");
+        let subpath = format!("{quote}{package}/");
+        contents.contains(&bare) || contents.contains(&subpath)
+    })
+}
+
+/// Whether `identifier` appears in the file as a whole word rather than a
+/// substring of a longer identifier.
+fn references_identifier(contents: &str, identifier: &str) -> bool {
+    let is_identifier_byte = |byte: &u8| byte.is_ascii_alphanumeric() || *byte == b'_';
+    let bytes = contents.as_bytes();
+    contents.match_indices(identifier).any(|(index, _)| {
+        let before = index
+            .checked_sub(1)
+            .and_then(|previous| bytes.get(previous));
+        let after = bytes.get(index + identifier.len());
+        !before.is_some_and(is_identifier_byte) && !after.is_some_and(is_identifier_byte)
+    })
+}
+
+/// Short names produce too many accidental near-collisions for a distance of
+/// two to be meaningful, so only one edit is suspicious there.
+fn max_suspicious_distance(popular_name: &str) -> usize {
+    if popular_name.len() >= 6 { 2 } else { 1 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: u64, dependencies: &[(&str, u64)]) -> ResolvedPackage {
+        ResolvedPackage {
+            name: name.to_string(),
+            version: Version::new(version, 0, 0),
+            dependencies: dependencies
+                .iter()
+                .map(|(name, version)| (name.to_string(), Version::new(*version, 0, 0)))
+                .collect(),
+        }
+    }
+
+    fn vulnerability(package: &str, version: u64, advisory_id: &str) -> Vulnerability {
+        Vulnerability {
+            package: package.to_string(),
+            version: Version::new(version, 0, 0),
+            advisory_id: advisory_id.to_string(),
+            summary: "test advisory".to_string(),
+            severity: Severity::Moderate,
+            disclosed_at: None,
+            vulnerable_function: None,
+        }
+    }
+
+    #[test]
+    fn test_vulnerabilities_are_attributed_to_every_pulling_root() {
+        let graph = ResolutionGraph {
+            root_dependencies: vec![
+                ("app-framework".to_string(), Version::new(1, 0, 0)),
+                ("test-runner".to_string(), Version::new(1, 0, 0)),
+                ("linter".to_string(), Version::new(1, 0, 0)),
+            ],
+            packages: vec![
+                package("app-framework", 1, &[("shared-parser", 1)]),
+                package("test-runner", 1, &[("shared-parser", 1), ("sandbox", 1)]),
+                package("linter", 1, &[]),
+                package("shared-parser", 1, &[]),
+                package("sandbox", 1, &[]),
+            ],
+        };
+        let report = AuditReport {
+            vulnerabilities: vec![
+                vulnerability("shared-parser", 1, "DX-0001"),
+                vulnerability("sandbox", 1, "DX-0002"),
+            ],
+        };
+
+        let by_root = report.by_root_dependency(&graph);
+        assert_eq!(
+            by_root["app-framework"],
+            vec![&report.vulnerabilities[0]],
+            "shared vulnerability attributed to the first root"
+        );
+        assert_eq!(
+            by_root["test-runner"],
+            vec![&report.vulnerabilities[0], &report.vulnerabilities[1]],
+            "shared vulnerability attributed to the second root too"
+        );
+        assert!(
+            !by_root.contains_key("linter"),
+            "clean roots are omitted from the fix list"
+        );
+    }
+
+    #[test]
+    fn test_directly_vulnerable_root_is_its_own_entry() {
+        let graph = ResolutionGraph {
+            root_dependencies: vec![("app-framework".to_string(), Version::new(1, 0, 0))],
+            packages: vec![package("app-framework", 1, &[])],
+        };
+        let report = AuditReport {
+            vulnerabilities: vec![vulnerability("app-framework", 1, "DX-0003")],
+        };
+        let by_root = report.by_root_dependency(&graph);
+        assert_eq!(by_root["app-framework"], vec![&report.vulnerabilities[0]]);
+    }
+
+    #[test]
+    fn test_other_versions_of_a_vulnerable_package_are_not_attributed() {
+        let graph = ResolutionGraph {
+            root_dependencies: vec![("app-framework".to_string(), Version::new(1, 0, 0))],
+            packages: vec![
+                package("app-framework", 1, &[("shared-parser", 2)]),
+                package("shared-parser", 2, &[]),
+            ],
+        };
+        let report = AuditReport {
+            vulnerabilities: vec![vulnerability("shared-parser", 1, "DX-0001")],
+        };
+        assert!(report.by_root_dependency(&graph).is_empty());
+    }
+
+    #[test]
+    fn test_filter_by_age_keeps_recent_and_undated_advisories() {
+        let days = |count: u64| Duration::from_secs(count * 24 * 60 * 60);
+        let disclosed = |age: Duration| {
+            let mut entry = vulnerability("shared-parser", 1, "DX-0001");
+            entry.disclosed_at = SystemTime::now().checked_sub(age);
+            entry
+        };
+        let report = AuditReport {
+            vulnerabilities: vec![
+                disclosed(days(5)),
+                disclosed(days(90)),
+                vulnerability("sandbox", 1, "DX-0002"),
+            ],
+        };
+
+        let recent = report.filter_by_age(days(30));
+        assert_eq!(recent.vulnerabilities.len(), 2);
+        assert_eq!(recent.vulnerabilities[0], report.vulnerabilities[0]);
+        assert_eq!(
+            recent.vulnerabilities[1], report.vulnerabilities[2],
+            "undated advisory is retained"
+        );
+
+        let wide = report.filter_by_age(days(365));
+        assert_eq!(wide.vulnerabilities.len(), 3);
+    }
+
+    #[test]
+    fn test_reachability_classifies_by_textual_evidence() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("nested")).unwrap();
+        fs::write(
+            dir.path().join("main.ts"),
+            "import { template } from \"shared-parser\";\nexport const page = template(input);\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("nested/util.js"),
+            "const parser = require('shared-parser/core');\nconst box = require('sandbox');\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("notes.md"), "uses old-logger everywhere").unwrap();
+
+        let mut with_function = vulnerability("shared-parser", 1, "DX-0001");
+        with_function.vulnerable_function = Some("template".to_string());
+        let report = AuditReport {
+            vulnerabilities: vec![
+                with_function,
+                vulnerability("sandbox", 1, "DX-0002"),
+                vulnerability("old-logger", 1, "DX-0003"),
+            ],
+        };
+
+        let auditor = PackageAuditor::new();
+        let hints = auditor.reachability(&report, dir.path()).unwrap();
+        assert_eq!(hints.len(), 3);
+
+        assert_eq!(hints[0].exposure, Exposure::FunctionReferenced);
+        assert_eq!(hints[0].evidence, vec![PathBuf::from("main.ts")]);
+
+        assert_eq!(hints[1].exposure, Exposure::Imported);
+        assert_eq!(hints[1].evidence, vec![PathBuf::from("nested/util.js")]);
+
+        // Mentioned only in a markdown file, which the scan ignores.
+        assert_eq!(hints[2].exposure, Exposure::PossiblyUnused);
+        assert!(hints[2].evidence.is_empty());
+    }
+
+    #[test]
+    fn test_reachability_requires_whole_identifiers_and_specifiers() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("main.js"),
+            "import { untemplated } from \"shared-parser\";\nimport x from \"shared-parser-extras\";\n",
+        )
+        .unwrap();
+
+        let mut with_function = vulnerability("shared-parser", 1, "DX-0001");
+        with_function.vulnerable_function = Some("template".to_string());
+        let report = AuditReport {
+            vulnerabilities: vec![with_function, vulnerability("parser-extras", 1, "DX-0004")],
+        };
+
+        let auditor = PackageAuditor::new();
+        let hints = auditor.reachability(&report, dir.path()).unwrap();
+        assert_eq!(
+            hints[0].exposure,
+            Exposure::Imported,
+            "`untemplated` must not count as a `template` reference"
+        );
+        assert_eq!(
+            hints[1].exposure,
+            Exposure::PossiblyUnused,
+            "`shared-parser-extras` is not an import of `parser-extras`"
+        );
+    }
+
+    #[test]
+    fn test_risk_score_orders_reports_and_never_drops_when_adding() {
+        let model = RiskModel::default();
+        let severity = |level: Severity| {
+            let mut entry = vulnerability("shared-parser", 1, "DX-0001");
+            entry.severity = level;
+            entry
+        };
+
+        let empty = AuditReport::default();
+        assert_eq!(empty.risk_score(&[], &model), 0.0);
+
+        let low = AuditReport {
+            vulnerabilities: vec![severity(Severity::Low)],
+        };
+        let critical = AuditReport {
+            vulnerabilities: vec![severity(Severity::Critical)],
+        };
+        assert!(low.risk_score(&[], &model) > 0.0);
+        assert!(critical.risk_score(&[], &model) > low.risk_score(&[], &model));
+
+        let mut grown = AuditReport {
+            vulnerabilities: critical.vulnerabilities.clone(),
+        };
+        grown.vulnerabilities.push(severity(Severity::Critical));
+        assert!(
+            grown.risk_score(&[], &model) > critical.risk_score(&[], &model),
+            "adding a critical vulnerability must raise the score"
+        );
+
+        let flood = AuditReport {
+            vulnerabilities: vec![severity(Severity::Critical); 1000],
+        };
+        assert!(flood.risk_score(&[], &model) < 100.0, "score saturates");
+    }
+
+    #[test]
+    fn test_reachability_and_recency_weigh_into_the_score() {
+        let model = RiskModel::default();
+        let report = AuditReport {
+            vulnerabilities: vec![vulnerability("shared-parser", 1, "DX-0001")],
+        };
+        let hint = |exposure: Exposure| ReachabilityHint {
+            advisory_id: "DX-0001".to_string(),
+            package: "shared-parser".to_string(),
+            exposure,
+            evidence: Vec::new(),
+        };
+
+        let unused = report.risk_score(&[hint(Exposure::PossiblyUnused)], &model);
+        let unhinted = report.risk_score(&[], &model);
+        let referenced = report.risk_score(&[hint(Exposure::FunctionReferenced)], &model);
+        assert!(unused < unhinted);
+        assert!(unhinted < referenced);
+
+        let mut recent = AuditReport {
+            vulnerabilities: report.vulnerabilities,
+        };
+        recent.vulnerabilities[0].disclosed_at =
+            SystemTime::now().checked_sub(Duration::from_secs(24 * 60 * 60));
+        assert!(
+            recent.risk_score(&[], &model) > unhinted,
+            "a freshly disclosed advisory is more urgent"
+        );
+    }
+
+    #[test]
+    fn test_audit_merges_sources_and_dedupes_by_advisory_id() {
+        /// Stands in for a company-internal feed that knows about private
+        /// packages no public database covers.
+        struct InternalFeed;
+
+        impl AdvisorySource for InternalFeed {
+            fn fetch_advisories(&self, package: &ResolvedPackage) -> Vec<Vulnerability> {
+                if package.name == "@acme/internal-tool" {
+                    vec![vulnerability("@acme/internal-tool", 1, "ACME-0001")]
+                } else if package.name == "shared-parser" {
+                    // Mirrors the built-in advisory; must not be double
+                    // reported.
+                    vec![vulnerability("shared-parser", 1, "DX-0001")]
+                } else {
+                    Vec::new()
+                }
+            }
+        }
+
+        let graph = ResolutionGraph {
+            root_dependencies: vec![
+                ("shared-parser".to_string(), Version::new(1, 0, 0)),
+                ("@acme/internal-tool".to_string(), Version::new(1, 0, 0)),
+            ],
+            packages: vec![
+                package("shared-parser", 1, &[]),
+                package("@acme/internal-tool", 1, &[]),
+            ],
+        };
+        let builtin = StaticAdvisorySource::new(vec![
+            vulnerability("shared-parser", 1, "DX-0001"),
+            vulnerability("shared-parser", 2, "DX-0005"),
+        ]);
+        let auditor = PackageAuditor::with_sources(vec![Box::new(builtin), Box::new(InternalFeed)]);
+
+        let report = auditor.audit(&graph);
+        let ids: Vec<&str> = report
+            .vulnerabilities
+            .iter()
+            .map(|vulnerability| vulnerability.advisory_id.as_str())
+            .collect();
+        assert_eq!(
+            ids,
+            vec!["DX-0001", "ACME-0001"],
+            "built-in and internal advisories merge; the duplicate and the \
+             unresolved version are dropped"
+        );
+    }
+
+    #[test]
+    fn test_one_char_transposition_is_flagged() {
+        let auditor = PackageAuditor::new();
+        let warnings = auditor.check_typosquat(&["lodahs"], POPULAR_PACKAGES);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].suspected, "lodash");
+        assert_eq!(warnings[0].distance, 2);
+
+        let warnings = auditor.check_typosquat(&["exprses"], POPULAR_PACKAGES);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].suspected, "express");
+    }
+
+    #[test]
+    fn test_single_substitution_is_flagged() {
+        let auditor = PackageAuditor::new();
+        let warnings = auditor.check_typosquat(&["reakt"], POPULAR_PACKAGES);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].suspected, "react");
+        assert_eq!(warnings[0].distance, 1);
+    }
+
+    #[test]
+    fn test_exact_matches_and_scoped_packages_are_excluded() {
+        let auditor = PackageAuditor::new();
+        let warnings = auditor.check_typosquat(
+            &["lodash", "@types/lodash", "@acme/reakt"],
+            POPULAR_PACKAGES,
+        );
+        assert!(warnings.is_empty(), "unexpected warnings: {warnings:?}");
+    }
+
+    #[test]
+    fn test_unrelated_names_are_not_flagged() {
+        let auditor = PackageAuditor::new();
+        let warnings = auditor.check_typosquat(&["left-pad-factory-deluxe"], POPULAR_PACKAGES);
+        assert!(warnings.is_empty(), "unexpected warnings: {warnings:?}");
+    }
+}
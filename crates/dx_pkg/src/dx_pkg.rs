@@ -0,0 +1,39 @@
+//! Package management for JavaScript projects: auditing, workspace tooling,
+//! and dependency resolution.
+
+mod audit;
+mod resolve;
+mod workspace;
+
+pub use audit::*;
+pub use resolve::*;
+pub use workspace::*;
+
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum PkgError {
+    #[error("package not found: {0}")]
+    PackageNotFound(String),
+    #[error("invalid manifest: {0}")]
+    InvalidManifest(String),
+    #[error("package {package} has no script named {script}")]
+    ScriptNotFound { package: String, script: String },
+    #[error("workspace dependency cycle involving {0}")]
+    DependencyCycle(String),
+    #[error("build script of {package} failed: {stderr}")]
+    BuildScriptFailed { package: String, stderr: String },
+    #[error("no acceptable version of {package} matches {range}")]
+    NoMatchingVersion { package: String, range: String },
+    #[error("metadata for {package}@{range} not cached; pre-fetch it to resolve offline")]
+    MetadataNotCached { package: String, range: String },
+    #[error("workspace member {package} drifted from the lockfile: {reason}")]
+    LockfileDrift { package: String, reason: String },
+    #[error("io error at {path}: {source}")]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
@@ -0,0 +1,901 @@
+use crate::PkgError;
+use collections::HashMap;
+use semver::{Version, VersionReq};
+use std::cmp::Ordering;
+use std::fmt;
+use std::path::PathBuf;
+
+/// One published version of a package as the registry reports it over DXRP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionMetadata {
+    pub version: Version,
+    /// Withdrawn by its publisher. Still installable — existing lockfiles
+    /// must keep resolving — but never chosen fresh from a range unless
+    /// explicitly allowed.
+    pub yanked: bool,
+}
+
+/// The registry metadata surface the resolver needs. The production client
+/// speaks the DXRP wire protocol; tests substitute an in-memory registry.
+pub trait DxrpClient {
+    fn package_versions(&self, name: &str) -> Result<Vec<VersionMetadata>, PkgError>;
+}
+
+/// How [`resolve_version`] treats yanked versions when choosing fresh from a
+/// range. A lockfile pin overrides either policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum YankedPolicy {
+    #[default]
+    Avoid,
+    /// Explicit user opt-in (`--allow-yanked`): yanked versions compete
+    /// normally.
+    Allow,
+}
+
+/// The version [`resolve_version`] settled on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionChoice {
+    pub version: Version,
+    /// Set when a lockfile pinned a yanked version and it was kept for
+    /// reproducibility; callers should surface this to the user.
+    pub yanked_warning: Option<String>,
+}
+
+/// Picks the version of `name` to install: the lockfile pin when one still
+/// matches the range (kept even if since yanked, with a warning), otherwise
+/// the newest matching version the [`YankedPolicy`] permits.
+pub fn resolve_version(
+    client: &dyn DxrpClient,
+    name: &str,
+    range: &VersionReq,
+    locked: Option<&Version>,
+    policy: YankedPolicy,
+) -> Result<VersionChoice, PkgError> {
+    let mut versions = client.package_versions(name)?;
+    // Newest-first under the same ordering [`candidate_precedence`] applies,
+    // so a fresh pick from a range and a direct candidate selection can
+    // never disagree on which version is "highest".
+    versions.sort_by(|a, b| {
+        b.version
+            .cmp(&a.version)
+            .then_with(|| b.version.to_string().cmp(&a.version.to_string()))
+    });
+
+    if let Some(locked) = locked
+        && range.matches(locked)
+        && let Some(pinned) = versions.iter().find(|metadata| metadata.version == *locked)
+    {
+        return Ok(VersionChoice {
+            version: pinned.version.clone(),
+            yanked_warning: pinned.yanked.then(|| {
+                format!(
+                    "locked version {name}@{locked} has been yanked; keeping it for reproducibility"
+                )
+            }),
+        });
+    }
+
+    let chosen = versions
+        .iter()
+        .filter(|metadata| range.matches(&metadata.version))
+        .find(|metadata| !metadata.yanked || policy == YankedPolicy::Allow)
+        .ok_or_else(|| PkgError::NoMatchingVersion {
+            package: name.to_string(),
+            range: range.to_string(),
+        })?;
+    Ok(VersionChoice {
+        version: chosen.version.clone(),
+        yanked_warning: None,
+    })
+}
+
+/// Where a candidate version was offered from, listed highest-priority
+/// first; the final tie-break in [`candidate_precedence`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum VersionSource {
+    /// Forced by a workspace override.
+    Override,
+    /// Pinned by an existing lockfile entry.
+    Lockfile,
+    /// Published to the registry.
+    Registry,
+}
+
+/// One version offered for selection, tagged with its source so that the
+/// same version offered twice still orders deterministically.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionCandidate {
+    pub version: Version,
+    pub source: VersionSource,
+}
+
+/// The resolver's tie-break rule, exposed rather than buried in a selection
+/// loop so it can be unit-tested directly: the higher version wins; versions
+/// equal under semver precedence are ordered by the lexical form of the full
+/// version string (a safeguard for renderings precedence ignores, like build
+/// metadata); remaining ties go to the higher-priority [`VersionSource`].
+/// `Greater` means `a` is preferred. The rule is total and consults nothing
+/// but the two candidates, so which of several equally-valid candidates wins
+/// can never depend on `HashMap` iteration order.
+pub fn candidate_precedence(a: &VersionCandidate, b: &VersionCandidate) -> Ordering {
+    a.version
+        .cmp(&b.version)
+        .then_with(|| a.version.to_string().cmp(&b.version.to_string()))
+        .then_with(|| b.source.cmp(&a.source))
+}
+
+/// The winning candidate under [`candidate_precedence`], or `None` for an
+/// empty set. Candidates the rule cannot separate are field-for-field
+/// identical, so the choice is the same whatever order they arrive in.
+pub fn select_candidate(candidates: &[VersionCandidate]) -> Option<&VersionCandidate> {
+    candidates.iter().max_by(|a, b| candidate_precedence(a, b))
+}
+
+/// Registry metadata pre-fetched for air-gapped resolution. Holds exactly
+/// what a [`DxrpClient`] would have answered, keyed by package name, so
+/// cached entries resolve identically to online ones.
+#[derive(Debug, Clone, Default)]
+pub struct MetadataCache {
+    packages: HashMap<String, Vec<VersionMetadata>>,
+}
+
+impl MetadataCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, versions: Vec<VersionMetadata>) {
+        self.packages.insert(name.into(), versions);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.packages.contains_key(name)
+    }
+}
+
+impl DxrpClient for MetadataCache {
+    fn package_versions(&self, name: &str) -> Result<Vec<VersionMetadata>, PkgError> {
+        self.packages
+            .get(name)
+            .cloned()
+            .ok_or_else(|| PkgError::PackageNotFound(name.to_string()))
+    }
+}
+
+/// Resolves every dependency purely from `cache`, never touching the
+/// network. A dependency whose metadata isn't cached fails with
+/// [`PkgError::MetadataNotCached`] naming the exact `package@range`, so the
+/// user knows precisely what to pre-fetch before going offline — rather than
+/// a generic resolution failure partway through.
+pub fn resolve_offline(
+    dependencies: &[(String, VersionReq)],
+    cache: &MetadataCache,
+) -> Result<Vec<(String, VersionChoice)>, PkgError> {
+    let mut choices = Vec::with_capacity(dependencies.len());
+    for (name, range) in dependencies {
+        if !cache.contains(name) {
+            return Err(PkgError::MetadataNotCached {
+                package: name.clone(),
+                range: range.to_string(),
+            });
+        }
+        let choice = resolve_version(cache, name, range, None, YankedPolicy::default())?;
+        choices.push((name.clone(), choice));
+    }
+    Ok(choices)
+}
+
+/// A package the resolver pinned, with its dependency edges already resolved
+/// to exact versions.
+#[derive(Debug, Clone)]
+pub struct ResolvedPackage {
+    pub name: String,
+    pub version: Version,
+    pub dependencies: Vec<(String, Version)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ResolutionGraph {
+    /// The workspace root's direct dependencies.
+    pub root_dependencies: Vec<(String, Version)>,
+    pub packages: Vec<ResolvedPackage>,
+}
+
+/// Where one copy of a package goes on disk, relative to the project root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub name: String,
+    pub version: Version,
+    pub path: PathBuf,
+}
+
+/// The installer's instructions: the flattest `node_modules` layout in which
+/// every package still resolves each dependency to its pinned version under
+/// Node resolution rules (own `node_modules` first, then ancestors).
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LayoutPlan {
+    pub placements: Vec<Placement>,
+}
+
+impl LayoutPlan {
+    pub fn top_level_version(&self, name: &str) -> Option<&Version> {
+        self.placements
+            .iter()
+            .find(|placement| placement.name == name && placement.path == top_level_path(name))
+            .map(|placement| &placement.version)
+    }
+}
+
+fn top_level_path(name: &str) -> PathBuf {
+    PathBuf::from("node_modules").join(name)
+}
+
+/// Computes the dedupe/hoist pass: the most widely shared compatible version
+/// of each package is placed at the top level, and only conflicting versions
+/// are nested under the packages that need them.
+pub fn plan_layout(graph: &ResolutionGraph) -> Result<LayoutPlan, PkgError> {
+    let package_by_id: HashMap<(&str, &Version), &ResolvedPackage> = graph
+        .packages
+        .iter()
+        .map(|package| ((package.name.as_str(), &package.version), package))
+        .collect();
+    let find_package = |name: &str, version: &Version| {
+        package_by_id
+            .get(&(name, version))
+            .copied()
+            .ok_or_else(|| PkgError::PackageNotFound(format!("{name}@{version}")))
+    };
+
+    // Pick each name's top-level occupant. The root's direct dependencies
+    // must win their slot (the root has no deeper node_modules to nest
+    // into); everything else goes to the version with the most dependents,
+    // ties broken by the highest version.
+    let mut dependent_counts: HashMap<(&str, &Version), usize> = HashMap::default();
+    for (name, version) in &graph.root_dependencies {
+        *dependent_counts.entry((name, version)).or_default() += 1;
+    }
+    for package in &graph.packages {
+        for (name, version) in &package.dependencies {
+            *dependent_counts.entry((name, version)).or_default() += 1;
+        }
+    }
+    let mut top_level: HashMap<&str, &Version> = HashMap::default();
+    for (name, version) in &graph.root_dependencies {
+        top_level.insert(name, version);
+    }
+    let mut candidates: Vec<((&str, &Version), usize)> = dependent_counts.into_iter().collect();
+    candidates.sort_by(
+        |((name_a, version_a), count_a), ((name_b, version_b), count_b)| {
+            name_a
+                .cmp(name_b)
+                .then(count_b.cmp(count_a))
+                .then(version_b.cmp(version_a))
+        },
+    );
+    for ((name, version), _) in candidates {
+        top_level.entry(name).or_insert(version);
+    }
+
+    // Place the winners, then walk outward: any dependency edge not
+    // satisfied by the top level nests a copy inside the dependent, and that
+    // copy's own edges get checked in turn.
+    let mut plan = LayoutPlan::default();
+    let mut queue: Vec<(PathBuf, &ResolvedPackage)> = Vec::new();
+    let mut names: Vec<&&str> = top_level.keys().collect();
+    names.sort();
+    for name in names {
+        let version = top_level[*name];
+        let path = top_level_path(name);
+        plan.placements.push(Placement {
+            name: name.to_string(),
+            version: version.clone(),
+            path: path.clone(),
+        });
+        queue.push((path, find_package(name, version)?));
+    }
+    while let Some((path, package)) = queue.pop() {
+        for (name, version) in &package.dependencies {
+            if top_level.get(name.as_str()) == Some(&version) {
+                continue;
+            }
+            let nested_path = path.join("node_modules").join(name);
+            if plan
+                .placements
+                .iter()
+                .any(|placement| placement.path == nested_path)
+            {
+                continue;
+            }
+            plan.placements.push(Placement {
+                name: name.clone(),
+                version: version.clone(),
+                path: nested_path.clone(),
+            });
+            queue.push((nested_path, find_package(name, version)?));
+        }
+    }
+    plan.placements.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(plan)
+}
+
+/// One declared dependency edge, before resolution: who asked for a package
+/// and with which range. The resolver itself works with exact pins; these
+/// edges carry the original ranges so [`Lockfile::explain`] can say what
+/// narrowed a choice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequirementEdge {
+    /// `None` for the workspace root's own dependencies.
+    pub dependent: Option<(String, Version)>,
+    pub name: String,
+    /// The declared range, verbatim from the manifest.
+    pub range: String,
+}
+
+/// One `(dependent, range)` pair that pulled a package instance in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constraint {
+    /// `name@version` of the dependent, or `None` for the workspace root.
+    pub dependent: Option<String>,
+    pub range: String,
+}
+
+/// One placed copy of a package: where it landed, who requested it there,
+/// and whether an override forced the version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: Version,
+    pub path: PathBuf,
+    pub constraints: Vec<Constraint>,
+    pub overridden: bool,
+}
+
+/// Why `explain`'s package resolved the way it did: one entry per placed
+/// instance, since a package nested at several versions has a distinct story
+/// at each path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolutionExplanation {
+    pub name: String,
+    /// Sorted by path.
+    pub instances: Vec<LockedPackage>,
+}
+
+impl fmt::Display for ResolutionExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for instance in &self.instances {
+            writeln!(
+                f,
+                "{}@{} ({})",
+                instance.name,
+                instance.version,
+                instance.path.display()
+            )?;
+            for constraint in &instance.constraints {
+                match &constraint.dependent {
+                    Some(dependent) => {
+                        writeln!(f, "  {} from {dependent}", constraint.range)?;
+                    }
+                    None => writeln!(f, "  {} from the workspace root", constraint.range)?,
+                }
+            }
+            if instance.overridden {
+                writeln!(f, "  version forced by an override")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The pinned result of a resolution, annotated with enough provenance to
+/// answer `dx why <pkg>`: every placement from the [`LayoutPlan`] plus the
+/// requirement edges that resolve to it under Node rules.
+#[derive(Debug, Clone, Default)]
+pub struct Lockfile {
+    entries: Vec<LockedPackage>,
+}
+
+impl Lockfile {
+    /// Builds the lockfile from a resolved graph and its layout.
+    /// `requirements` supplies the declared ranges (edges without one are
+    /// recorded as the exact pin); `overrides` names packages whose version
+    /// was forced rather than resolved.
+    pub fn new(
+        graph: &ResolutionGraph,
+        plan: &LayoutPlan,
+        requirements: &[RequirementEdge],
+        overrides: &[String],
+    ) -> Self {
+        let mut entries: Vec<LockedPackage> = plan
+            .placements
+            .iter()
+            .map(|placement| LockedPackage {
+                name: placement.name.clone(),
+                version: placement.version.clone(),
+                path: placement.path.clone(),
+                constraints: Vec::new(),
+                overridden: overrides.contains(&placement.name),
+            })
+            .collect();
+        let range_for = |dependent: Option<(&str, &Version)>, name: &str, version: &Version| {
+            requirements
+                .iter()
+                .find(|edge| {
+                    edge.name == name
+                        && match (&edge.dependent, dependent) {
+                            (None, None) => true,
+                            (Some((edge_name, edge_version)), Some((name, version))) => {
+                                edge_name == name && edge_version == version
+                            }
+                            _ => false,
+                        }
+                })
+                .map(|edge| edge.range.clone())
+                .unwrap_or_else(|| format!("={version}"))
+        };
+        let mut attach = |path: &PathBuf, constraint: Constraint| {
+            if let Some(entry) = entries.iter_mut().find(|entry| entry.path == *path)
+                && !entry.constraints.contains(&constraint)
+            {
+                entry.constraints.push(constraint);
+            }
+        };
+
+        for (name, version) in &graph.root_dependencies {
+            attach(
+                &top_level_path(name),
+                Constraint {
+                    dependent: None,
+                    range: range_for(None, name, version),
+                },
+            );
+        }
+        // Each placed copy resolves its edges under Node rules: its own
+        // nested node_modules first, then the top level. Constraints land on
+        // whichever copy the edge actually resolves to.
+        for placement in &plan.placements {
+            let Some(package) = graph.packages.iter().find(|package| {
+                package.name == placement.name && package.version == placement.version
+            }) else {
+                continue;
+            };
+            for (name, version) in &package.dependencies {
+                let nested = placement.path.join("node_modules").join(name);
+                let target = if plan
+                    .placements
+                    .iter()
+                    .any(|candidate| candidate.path == nested)
+                {
+                    nested
+                } else {
+                    top_level_path(name)
+                };
+                attach(
+                    &target,
+                    Constraint {
+                        dependent: Some(format!("{}@{}", package.name, package.version)),
+                        range: range_for(Some((&package.name, &package.version)), name, version),
+                    },
+                );
+            }
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Self { entries }
+    }
+
+    pub fn entries(&self) -> &[LockedPackage] {
+        &self.entries
+    }
+
+    /// Why `package` resolved as it did, one instance per placed copy. A
+    /// deduped package has a single instance listing every dependent that
+    /// shares it; a nested one has an instance per path, each with only the
+    /// dependents whose edges resolve there.
+    pub fn explain(&self, package: &str) -> Result<ResolutionExplanation, PkgError> {
+        let instances: Vec<LockedPackage> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.name == package)
+            .cloned()
+            .collect();
+        if instances.is_empty() {
+            return Err(PkgError::PackageNotFound(package.to_string()));
+        }
+        Ok(ResolutionExplanation {
+            name: package.to_string(),
+            instances,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn package(name: &str, version: u64, dependencies: &[(&str, u64)]) -> ResolvedPackage {
+        ResolvedPackage {
+            name: name.to_string(),
+            version: Version::new(version, 0, 0),
+            dependencies: dependencies
+                .iter()
+                .map(|(name, version)| (name.to_string(), Version::new(*version, 0, 0)))
+                .collect(),
+        }
+    }
+
+    /// Every placement's dependency edges must resolve to the pinned version
+    /// under Node rules: nested copy first, then the top level.
+    fn assert_layout_is_valid(plan: &LayoutPlan, graph: &ResolutionGraph) {
+        for placement in &plan.placements {
+            let package = graph
+                .packages
+                .iter()
+                .find(|package| {
+                    package.name == placement.name && package.version == placement.version
+                })
+                .unwrap();
+            for (name, version) in &package.dependencies {
+                let nested = placement.path.join("node_modules").join(name);
+                let resolved = plan
+                    .placements
+                    .iter()
+                    .find(|candidate| candidate.path == nested)
+                    .map(|candidate| &candidate.version)
+                    .or_else(|| plan.top_level_version(name));
+                assert_eq!(
+                    resolved,
+                    Some(version),
+                    "{}@{} must resolve {name} to {version}",
+                    placement.name,
+                    placement.version
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_shared_version_is_fully_hoisted() {
+        let graph = ResolutionGraph {
+            root_dependencies: vec![
+                ("a".to_string(), Version::new(1, 0, 0)),
+                ("b".to_string(), Version::new(1, 0, 0)),
+            ],
+            packages: vec![
+                package("a", 1, &[("c", 1)]),
+                package("b", 1, &[("c", 1)]),
+                package("c", 1, &[]),
+            ],
+        };
+        let plan = plan_layout(&graph).unwrap();
+        assert_eq!(plan.placements.len(), 3, "no nesting needed: {plan:?}");
+        assert_eq!(plan.top_level_version("c"), Some(&Version::new(1, 0, 0)));
+        assert_layout_is_valid(&plan, &graph);
+    }
+
+    #[test]
+    fn test_diamond_with_conflicting_versions_nests_the_minority() {
+        let graph = ResolutionGraph {
+            root_dependencies: vec![
+                ("a".to_string(), Version::new(1, 0, 0)),
+                ("b".to_string(), Version::new(1, 0, 0)),
+                ("d".to_string(), Version::new(1, 0, 0)),
+            ],
+            packages: vec![
+                package("a", 1, &[("c", 1)]),
+                package("b", 1, &[("c", 2)]),
+                package("d", 1, &[("c", 2)]),
+                package("c", 1, &[]),
+                package("c", 2, &[]),
+            ],
+        };
+        let plan = plan_layout(&graph).unwrap();
+        assert_eq!(plan.top_level_version("c"), Some(&Version::new(2, 0, 0)));
+        let nested = plan
+            .placements
+            .iter()
+            .find(|placement| placement.path == Path::new("node_modules/a/node_modules/c"))
+            .unwrap();
+        assert_eq!(nested.version, Version::new(1, 0, 0));
+        assert_layout_is_valid(&plan, &graph);
+    }
+
+    #[test]
+    fn test_root_direct_dependency_keeps_the_top_level_slot() {
+        let graph = ResolutionGraph {
+            root_dependencies: vec![
+                ("c".to_string(), Version::new(1, 0, 0)),
+                ("a".to_string(), Version::new(1, 0, 0)),
+                ("b".to_string(), Version::new(1, 0, 0)),
+            ],
+            packages: vec![
+                package("a", 1, &[("c", 2)]),
+                package("b", 1, &[("c", 2)]),
+                package("c", 1, &[]),
+                package("c", 2, &[]),
+            ],
+        };
+        let plan = plan_layout(&graph).unwrap();
+        assert_eq!(plan.top_level_version("c"), Some(&Version::new(1, 0, 0)));
+        for dependent in ["a", "b"] {
+            let nested_path = PathBuf::from(format!("node_modules/{dependent}/node_modules/c"));
+            let nested = plan
+                .placements
+                .iter()
+                .find(|placement| placement.path == nested_path)
+                .unwrap();
+            assert_eq!(nested.version, Version::new(2, 0, 0));
+        }
+        assert_layout_is_valid(&plan, &graph);
+    }
+
+    #[test]
+    fn test_missing_package_for_edge_is_an_error() {
+        let graph = ResolutionGraph {
+            root_dependencies: vec![("a".to_string(), Version::new(1, 0, 0))],
+            packages: vec![package("a", 1, &[("ghost", 1)])],
+        };
+        assert!(matches!(
+            plan_layout(&graph),
+            Err(PkgError::PackageNotFound(_))
+        ));
+    }
+
+    fn edge(dependent: Option<(&str, u64)>, name: &str, range: &str) -> RequirementEdge {
+        RequirementEdge {
+            dependent: dependent
+                .map(|(name, version)| (name.to_string(), Version::new(version, 0, 0))),
+            name: name.to_string(),
+            range: range.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_explain_separates_deduped_and_nested_instances() {
+        let graph = ResolutionGraph {
+            root_dependencies: vec![
+                ("a".to_string(), Version::new(1, 0, 0)),
+                ("b".to_string(), Version::new(1, 0, 0)),
+                ("d".to_string(), Version::new(1, 0, 0)),
+            ],
+            packages: vec![
+                package("a", 1, &[("c", 1)]),
+                package("b", 1, &[("c", 2)]),
+                package("d", 1, &[("c", 2)]),
+                package("c", 1, &[]),
+                package("c", 2, &[]),
+            ],
+        };
+        let plan = plan_layout(&graph).unwrap();
+        let requirements = vec![
+            edge(Some(("a", 1)), "c", "^1.0.0"),
+            edge(Some(("b", 1)), "c", "^2.0.0"),
+            edge(Some(("d", 1)), "c", "^2.0.0"),
+        ];
+        let lockfile = Lockfile::new(&graph, &plan, &requirements, &[]);
+
+        let explanation = lockfile.explain("c").unwrap();
+        assert_eq!(explanation.instances.len(), 2, "one per placed copy");
+
+        // The nested minority copy is requested only by `a`.
+        let nested = &explanation.instances[0];
+        assert_eq!(nested.path, PathBuf::from("node_modules/a/node_modules/c"));
+        assert_eq!(nested.version, Version::new(1, 0, 0));
+        assert_eq!(
+            nested.constraints,
+            vec![Constraint {
+                dependent: Some("a@1.0.0".to_string()),
+                range: "^1.0.0".to_string(),
+            }]
+        );
+
+        // The deduped top-level copy lists both dependents that share it.
+        let deduped = &explanation.instances[1];
+        assert_eq!(deduped.path, PathBuf::from("node_modules/c"));
+        assert_eq!(deduped.version, Version::new(2, 0, 0));
+        assert_eq!(
+            deduped.constraints,
+            vec![
+                Constraint {
+                    dependent: Some("b@1.0.0".to_string()),
+                    range: "^2.0.0".to_string(),
+                },
+                Constraint {
+                    dependent: Some("d@1.0.0".to_string()),
+                    range: "^2.0.0".to_string(),
+                },
+            ]
+        );
+
+        let rendered = explanation.to_string();
+        assert!(rendered.contains("c@1.0.0 (node_modules/a/node_modules/c)"));
+        assert!(rendered.contains("  ^2.0.0 from d@1.0.0"));
+    }
+
+    #[test]
+    fn test_explain_reports_root_edges_overrides_and_unknown_packages() {
+        let graph = ResolutionGraph {
+            root_dependencies: vec![("a".to_string(), Version::new(1, 0, 0))],
+            packages: vec![package("a", 1, &[])],
+        };
+        let plan = plan_layout(&graph).unwrap();
+        let requirements = vec![edge(None, "a", "^1.0.0")];
+        let lockfile = Lockfile::new(&graph, &plan, &requirements, &["a".to_string()]);
+
+        let explanation = lockfile.explain("a").unwrap();
+        assert_eq!(explanation.instances.len(), 1);
+        let instance = &explanation.instances[0];
+        assert!(instance.overridden);
+        assert_eq!(
+            instance.constraints,
+            vec![Constraint {
+                dependent: None,
+                range: "^1.0.0".to_string(),
+            }]
+        );
+        let rendered = explanation.to_string();
+        assert!(rendered.contains("^1.0.0 from the workspace root"));
+        assert!(rendered.contains("version forced by an override"));
+
+        assert!(matches!(
+            lockfile.explain("ghost"),
+            Err(PkgError::PackageNotFound(name)) if name == "ghost"
+        ));
+    }
+
+    struct InMemoryRegistry(HashMap<String, Vec<VersionMetadata>>);
+
+    impl DxrpClient for InMemoryRegistry {
+        fn package_versions(&self, name: &str) -> Result<Vec<VersionMetadata>, PkgError> {
+            self.0
+                .get(name)
+                .cloned()
+                .ok_or_else(|| PkgError::PackageNotFound(name.to_string()))
+        }
+    }
+
+    fn registry(versions: &[(u64, bool)]) -> InMemoryRegistry {
+        let mut packages = HashMap::default();
+        packages.insert(
+            "left-pad".to_string(),
+            versions
+                .iter()
+                .map(|(minor, yanked)| VersionMetadata {
+                    version: Version::new(1, *minor, 0),
+                    yanked: *yanked,
+                })
+                .collect(),
+        );
+        InMemoryRegistry(packages)
+    }
+
+    #[test]
+    fn test_yanked_newest_version_is_skipped() {
+        let client = registry(&[(0, false), (1, false), (2, true)]);
+        let range = VersionReq::parse("^1.0.0").unwrap();
+        let choice =
+            resolve_version(&client, "left-pad", &range, None, YankedPolicy::default()).unwrap();
+        assert_eq!(choice.version, Version::new(1, 1, 0));
+        assert_eq!(choice.yanked_warning, None);
+
+        let choice =
+            resolve_version(&client, "left-pad", &range, None, YankedPolicy::Allow).unwrap();
+        assert_eq!(choice.version, Version::new(1, 2, 0));
+    }
+
+    #[test]
+    fn test_locked_yanked_version_is_kept_with_a_warning() {
+        let client = registry(&[(0, true), (1, false)]);
+        let range = VersionReq::parse("^1.0.0").unwrap();
+        let locked = Version::new(1, 0, 0);
+        let choice = resolve_version(
+            &client,
+            "left-pad",
+            &range,
+            Some(&locked),
+            YankedPolicy::default(),
+        )
+        .unwrap();
+        assert_eq!(choice.version, locked);
+        let warning = choice.yanked_warning.unwrap();
+        assert!(warning.contains("yanked"), "got: {warning}");
+    }
+
+    #[test]
+    fn test_offline_resolution_uses_only_the_cache() {
+        let mut cache = MetadataCache::new();
+        cache.insert(
+            "left-pad",
+            vec![
+                VersionMetadata {
+                    version: Version::new(1, 0, 0),
+                    yanked: false,
+                },
+                VersionMetadata {
+                    version: Version::new(1, 1, 0),
+                    yanked: false,
+                },
+            ],
+        );
+        let dependencies = vec![("left-pad".to_string(), VersionReq::parse("^1.0.0").unwrap())];
+        let choices = resolve_offline(&dependencies, &cache).unwrap();
+        assert_eq!(choices.len(), 1);
+        assert_eq!(choices[0].0, "left-pad");
+        assert_eq!(choices[0].1.version, Version::new(1, 1, 0));
+    }
+
+    #[test]
+    fn test_partial_cache_names_the_missing_metadata() {
+        let mut cache = MetadataCache::new();
+        cache.insert(
+            "left-pad",
+            vec![VersionMetadata {
+                version: Version::new(1, 0, 0),
+                yanked: false,
+            }],
+        );
+        let dependencies = vec![
+            ("left-pad".to_string(), VersionReq::parse("^1.0.0").unwrap()),
+            ("is-even".to_string(), VersionReq::parse("^2.0.0").unwrap()),
+        ];
+        assert!(matches!(
+            resolve_offline(&dependencies, &cache),
+            Err(PkgError::MetadataNotCached { package, range })
+                if package == "is-even" && range == "^2.0.0"
+        ));
+    }
+
+    fn candidate(major: u64, minor: u64, source: VersionSource) -> VersionCandidate {
+        VersionCandidate {
+            version: Version::new(major, minor, 0),
+            source,
+        }
+    }
+
+    #[test]
+    fn test_tie_break_prefers_version_then_source() {
+        // 1.10.0 beats 1.9.0 even though "1.9.0" sorts after "1.10.0"
+        // lexically: the lexical step only separates versions that are equal
+        // under precedence.
+        let candidates = vec![
+            candidate(1, 9, VersionSource::Override),
+            candidate(1, 10, VersionSource::Registry),
+            candidate(1, 10, VersionSource::Lockfile),
+        ];
+        let chosen = select_candidate(&candidates).unwrap();
+        assert_eq!(chosen.version, Version::new(1, 10, 0));
+        assert_eq!(
+            chosen.source,
+            VersionSource::Lockfile,
+            "source breaks the tie"
+        );
+    }
+
+    #[test]
+    fn test_selection_is_independent_of_candidate_order() {
+        // Candidates routed through a HashMap, as a resolver keying them by
+        // source would hold them, then selected from every rotation of the
+        // map's arbitrary iteration order: the winner must never change.
+        let mut by_source: HashMap<&str, VersionCandidate> = HashMap::default();
+        by_source.insert("override", candidate(2, 0, VersionSource::Override));
+        by_source.insert("lockfile", candidate(2, 0, VersionSource::Lockfile));
+        by_source.insert("registry", candidate(2, 0, VersionSource::Registry));
+        let mut candidates: Vec<VersionCandidate> = by_source.into_values().collect();
+
+        for _ in 0..candidates.len() {
+            candidates.rotate_left(1);
+            let chosen = select_candidate(&candidates).unwrap();
+            assert_eq!(chosen.version, Version::new(2, 0, 0));
+            assert_eq!(chosen.source, VersionSource::Override);
+        }
+    }
+
+    #[test]
+    fn test_all_matching_versions_yanked_is_an_error() {
+        let client = registry(&[(0, true), (1, true)]);
+        let range = VersionReq::parse("^1.0.0").unwrap();
+        assert!(matches!(
+            resolve_version(&client, "left-pad", &range, None, YankedPolicy::default()),
+            Err(PkgError::NoMatchingVersion { .. })
+        ));
+    }
+}
@@ -0,0 +1,1229 @@
+use crate::PkgError;
+use collections::{HashMap, HashSet};
+use sha2::{Digest, Sha256};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Node's builtin modules, importable without any declaration (with or
+/// without the `node:` prefix).
+const NODE_BUILTIN_MODULES: &[&str] = &[
+    "assert",
+    "async_hooks",
+    "buffer",
+    "child_process",
+    "cluster",
+    "console",
+    "constants",
+    "crypto",
+    "dgram",
+    "diagnostics_channel",
+    "dns",
+    "domain",
+    "events",
+    "fs",
+    "http",
+    "http2",
+    "https",
+    "inspector",
+    "module",
+    "net",
+    "os",
+    "path",
+    "perf_hooks",
+    "process",
+    "punycode",
+    "querystring",
+    "readline",
+    "repl",
+    "stream",
+    "string_decoder",
+    "timers",
+    "tls",
+    "tty",
+    "url",
+    "util",
+    "v8",
+    "vm",
+    "worker_threads",
+    "zlib",
+];
+
+/// File name of the root workspace lockfile written by
+/// [`Workspace::write_lockfile`].
+pub const WORKSPACE_LOCKFILE_NAME: &str = "dx-workspace.lock";
+
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceMember {
+    pub name: String,
+    pub version: String,
+    pub dir: PathBuf,
+    /// Declared dependencies: name -> version requirement.
+    pub dependencies: HashMap<String, String>,
+    /// Script name -> shell command, as in a `package.json` `scripts` map.
+    pub scripts: HashMap<String, String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Workspace {
+    pub root: PathBuf,
+    pub members: Vec<WorkspaceMember>,
+    /// Yarn-style `nohoist` glob patterns naming packages that must stay in
+    /// each member's local `node_modules` — native modules and other
+    /// version-sensitive packages that break when shared through the root.
+    pub nohoist: Vec<String>,
+    /// Cache key of each member's last successful build, for skipping
+    /// members whose inputs (and dependencies' outputs) are unchanged.
+    build_cache: HashMap<String, String>,
+}
+
+/// A package importing something it never declared, working today only
+/// because hoisting happens to place the dependency within reach. Breaks
+/// under pnpm-style strict layouts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhantomDependency {
+    /// The workspace member doing the undeclared import.
+    pub package: String,
+    /// The imported package (the bare specifier's package part).
+    pub dependency: String,
+    /// The first source file the import was seen in.
+    pub file: PathBuf,
+}
+
+impl std::fmt::Display for PhantomDependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` imports `{}` without declaring it (first seen in {})",
+            self.package,
+            self.dependency,
+            self.file.display()
+        )
+    }
+}
+
+/// Where each external dependency lands on disk: one copy hoisted to the
+/// root `node_modules`, plus nested copies for members whose declared range
+/// lost the hoist vote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HoistLayout {
+    /// Hoisted package name and range, sorted by name.
+    pub hoisted: Vec<(String, String)>,
+    /// `(member, package, range)` tuples kept nested under the member,
+    /// sorted.
+    pub nested: Vec<(String, String, String)>,
+}
+
+#[derive(Debug)]
+pub struct ScriptOutput {
+    pub exit_code: i32,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// How one member fared in a [`Workspace::build`] run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageBuildOutcome {
+    pub package: String,
+    /// True when the member's cache key matched its previous build and the
+    /// build script was not rerun.
+    pub skipped: bool,
+}
+
+/// Per-member outcomes of a [`Workspace::build`] run, in completion order
+/// (topological within each concurrency wave).
+#[derive(Debug)]
+pub struct WorkspaceBuildReport {
+    pub outcomes: Vec<PackageBuildOutcome>,
+}
+
+impl WorkspaceBuildReport {
+    pub fn outcome(&self, package: &str) -> Option<&PackageBuildOutcome> {
+        self.outcomes
+            .iter()
+            .find(|outcome| outcome.package == package)
+    }
+
+    pub fn skipped_count(&self) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.skipped)
+            .count()
+    }
+}
+
+struct MemberBuild {
+    package: String,
+    cache_key: String,
+    skipped: bool,
+}
+
+impl Workspace {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            members: Vec::new(),
+            nohoist: Vec::new(),
+            build_cache: HashMap::default(),
+        }
+    }
+
+    pub fn add_member(&mut self, member: WorkspaceMember) {
+        self.members.push(member);
+    }
+
+    pub fn member(&self, name: &str) -> Result<&WorkspaceMember, PkgError> {
+        self.members
+            .iter()
+            .find(|member| member.name == name)
+            .ok_or_else(|| PkgError::PackageNotFound(name.to_string()))
+    }
+
+    /// Runs a member's script the way npm would: through the platform shell,
+    /// with the member's and the workspace root's `node_modules/.bin`
+    /// prepended to `PATH` (so locally installed binaries resolve), and with
+    /// `dx_package_*` variables describing the package injected into the
+    /// environment.
+    pub fn run_script_in_package(
+        &self,
+        package: &str,
+        script_name: &str,
+    ) -> Result<ScriptOutput, PkgError> {
+        let member = self.member(package)?;
+        let script = member
+            .scripts
+            .get(script_name)
+            .ok_or_else(|| PkgError::ScriptNotFound {
+                package: package.to_string(),
+                script: script_name.to_string(),
+            })?;
+
+        let mut command = if cfg!(windows) {
+            // cmd.exe resolves the `.cmd` shims npm writes into `.bin` via
+            // PATHEXT, so prepending the directories is sufficient there too.
+            let mut command = Command::new("cmd");
+            command.arg("/C").arg(script);
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(script);
+            command
+        };
+
+        command
+            .current_dir(&member.dir)
+            .env("PATH", self.script_path_for(member))
+            .env("dx_package_name", &member.name)
+            .env("dx_package_version", &member.version);
+
+        let output = command.output().map_err(|source| PkgError::Io {
+            path: member.dir.clone(),
+            source,
+        })?;
+        Ok(ScriptOutput {
+            exit_code: output.status.code().unwrap_or(-1),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+
+    /// Statically scans every member's sources for bare import specifiers
+    /// missing from that member's own declared dependencies. Builtin Node
+    /// modules, self-imports, and relative/absolute paths are not phantoms.
+    /// One entry is reported per member/package pair, pointing at the first
+    /// file the import was seen in.
+    pub fn detect_phantom_dependencies(&self) -> Result<Vec<PhantomDependency>, PkgError> {
+        let mut phantoms = Vec::new();
+        for member in &self.members {
+            let mut source_files = Vec::new();
+            collect_source_files(&member.dir, &mut source_files)?;
+            source_files.sort();
+            for file in source_files {
+                let source = fs::read_to_string(&file).map_err(|source| PkgError::Io {
+                    path: file.clone(),
+                    source,
+                })?;
+                for specifier in extract_import_specifiers(&source) {
+                    let Some(dependency) = bare_package_name(specifier) else {
+                        continue;
+                    };
+                    if dependency == member.name
+                        || NODE_BUILTIN_MODULES.contains(&dependency)
+                        || member.dependencies.contains_key(dependency)
+                    {
+                        continue;
+                    }
+                    if !phantoms.iter().any(|phantom: &PhantomDependency| {
+                        phantom.package == member.name && phantom.dependency == dependency
+                    }) {
+                        phantoms.push(PhantomDependency {
+                            package: member.name.clone(),
+                            dependency: dependency.to_string(),
+                            file: file.clone(),
+                        });
+                    }
+                }
+            }
+        }
+        Ok(phantoms)
+    }
+
+    /// Runs every targeted member's `build` script in topological order with
+    /// at most `concurrency` scripts in flight, Turborepo-style. Each member
+    /// is keyed on the hash of its own inputs plus its workspace
+    /// dependencies' output hashes, so an unchanged member is skipped while a
+    /// changed shared package invalidates every dependent. `filter` narrows
+    /// the run to one member and its transitive workspace dependencies.
+    pub fn build(
+        &mut self,
+        filter: Option<&str>,
+        concurrency: usize,
+    ) -> Result<WorkspaceBuildReport, PkgError> {
+        let order = self.build_targets(filter)?;
+        let target_set: HashSet<&str> = order.iter().map(String::as_str).collect();
+        let concurrency = concurrency.max(1);
+        let mut completed: HashMap<String, String> = HashMap::default();
+        let mut outcomes = Vec::new();
+        while completed.len() < order.len() {
+            let wave: Vec<&WorkspaceMember> = self
+                .members
+                .iter()
+                .filter(|member| {
+                    target_set.contains(member.name.as_str())
+                        && !completed.contains_key(&member.name)
+                })
+                .filter(|member| {
+                    member
+                        .dependencies
+                        .keys()
+                        .filter(|dependency| target_set.contains(dependency.as_str()))
+                        .all(|dependency| completed.contains_key(dependency))
+                })
+                .collect();
+            if wave.is_empty() {
+                // Unreachable given the targets are topologically ordered,
+                // but guards the loop against ever spinning.
+                return Err(PkgError::DependencyCycle(
+                    order.first().cloned().unwrap_or_default(),
+                ));
+            }
+            for chunk in wave.chunks(concurrency) {
+                let workspace = &*self;
+                let results: Vec<Result<MemberBuild, PkgError>> = std::thread::scope(|scope| {
+                    let handles: Vec<_> = chunk
+                        .iter()
+                        .map(|member| {
+                            let member = *member;
+                            let mut dependency_hashes: Vec<(String, String)> = member
+                                .dependencies
+                                .keys()
+                                .filter_map(|dependency| {
+                                    completed
+                                        .get(dependency)
+                                        .map(|hash| (dependency.clone(), hash.clone()))
+                                })
+                                .collect();
+                            dependency_hashes.sort();
+                            scope.spawn(move || workspace.build_member(member, &dependency_hashes))
+                        })
+                        .collect();
+                    handles
+                        .into_iter()
+                        .map(|handle| match handle.join() {
+                            Ok(result) => result,
+                            Err(panic) => std::panic::resume_unwind(panic),
+                        })
+                        .collect()
+                });
+                for result in results {
+                    let built = result?;
+                    completed.insert(built.package.clone(), built.cache_key);
+                    outcomes.push(PackageBuildOutcome {
+                        package: built.package,
+                        skipped: built.skipped,
+                    });
+                }
+            }
+        }
+        for (package, cache_key) in completed {
+            self.build_cache.insert(package, cache_key);
+        }
+        Ok(WorkspaceBuildReport { outcomes })
+    }
+
+    /// One member's build, run on a worker thread: hash inputs, consult the
+    /// cache, and run the `build` script on a miss. A member without a
+    /// `build` script is trivially up to date once its inputs are hashed.
+    fn build_member(
+        &self,
+        member: &WorkspaceMember,
+        dependency_hashes: &[(String, String)],
+    ) -> Result<MemberBuild, PkgError> {
+        let input_hash = member_input_hash(member)?;
+        let mut hasher = Sha256::new();
+        hasher.update(member.name.as_bytes());
+        hasher.update(input_hash.as_bytes());
+        for (dependency, hash) in dependency_hashes {
+            hasher.update(dependency.as_bytes());
+            hasher.update(hash.as_bytes());
+        }
+        let cache_key = hex::encode(hasher.finalize());
+        if self.build_cache.get(&member.name) == Some(&cache_key) {
+            return Ok(MemberBuild {
+                package: member.name.clone(),
+                cache_key,
+                skipped: true,
+            });
+        }
+        if member.scripts.contains_key("build") {
+            let output = self.run_script_in_package(&member.name, "build")?;
+            if output.exit_code != 0 {
+                return Err(PkgError::BuildScriptFailed {
+                    package: member.name.clone(),
+                    stderr: output.stderr,
+                });
+            }
+        }
+        Ok(MemberBuild {
+            package: member.name.clone(),
+            cache_key,
+            skipped: false,
+        })
+    }
+
+    /// The members `filter` selects — one member plus its transitive
+    /// workspace dependencies, or every member — topologically ordered so
+    /// dependencies precede dependents.
+    fn build_targets(&self, filter: Option<&str>) -> Result<Vec<String>, PkgError> {
+        let selected: Vec<&WorkspaceMember> = match filter {
+            None => self.members.iter().collect(),
+            Some(name) => {
+                let mut reachable: HashSet<&str> = HashSet::default();
+                let mut queue = vec![self.member(name)?.name.as_str()];
+                while let Some(current) = queue.pop() {
+                    if !reachable.insert(current) {
+                        continue;
+                    }
+                    if let Ok(member) = self.member(current) {
+                        queue.extend(member.dependencies.keys().map(String::as_str).filter(
+                            |dependency| {
+                                self.members.iter().any(|other| other.name == **dependency)
+                            },
+                        ));
+                    }
+                }
+                self.members
+                    .iter()
+                    .filter(|member| reachable.contains(member.name.as_str()))
+                    .collect()
+            }
+        };
+
+        let selected_names: HashSet<&str> =
+            selected.iter().map(|member| member.name.as_str()).collect();
+        let mut order: Vec<String> = Vec::with_capacity(selected.len());
+        let mut placed: HashSet<&str> = HashSet::default();
+        while placed.len() < selected.len() {
+            let mut placed_any = false;
+            for member in &selected {
+                if placed.contains(member.name.as_str()) {
+                    continue;
+                }
+                let ready = member
+                    .dependencies
+                    .keys()
+                    .filter(|dependency| selected_names.contains(dependency.as_str()))
+                    .all(|dependency| placed.contains(dependency.as_str()));
+                if ready {
+                    placed.insert(member.name.as_str());
+                    order.push(member.name.clone());
+                    placed_any = true;
+                }
+            }
+            if !placed_any {
+                let stuck = selected
+                    .iter()
+                    .find(|member| !placed.contains(member.name.as_str()))
+                    .map(|member| member.name.clone())
+                    .unwrap_or_default();
+                return Err(PkgError::DependencyCycle(stuck));
+            }
+        }
+        Ok(order)
+    }
+
+    fn is_nohoisted(&self, package: &str) -> bool {
+        self.nohoist
+            .iter()
+            .any(|pattern| glob_matches(pattern, package))
+    }
+
+    /// Computes which external dependencies hoist to the root `node_modules`.
+    /// For each package the most widely declared range wins; ties go to the
+    /// lexicographically smaller range, so the layout is stable across runs
+    /// regardless of member or map iteration order. Dependencies on other
+    /// workspace members are linked, not installed, and never hoist. A
+    /// package matching a [`nohoist`](Self::nohoist) pattern never hoists
+    /// either: every member that declares it keeps a nested copy, even when
+    /// all declared ranges agree.
+    pub fn hoist_layout(&self) -> HoistLayout {
+        let member_names: HashSet<&str> = self
+            .members
+            .iter()
+            .map(|member| member.name.as_str())
+            .collect();
+        let mut ranges_by_package: HashMap<String, Vec<String>> = HashMap::default();
+        let mut nested = Vec::new();
+        for member in &self.members {
+            for (name, range) in &member.dependencies {
+                if member_names.contains(name.as_str()) {
+                    continue;
+                }
+                if self.is_nohoisted(name) {
+                    nested.push((member.name.clone(), name.clone(), range.clone()));
+                    continue;
+                }
+                ranges_by_package
+                    .entry(name.clone())
+                    .or_default()
+                    .push(range.clone());
+            }
+        }
+
+        let mut grouped: Vec<(String, Vec<String>)> = ranges_by_package.into_iter().collect();
+        grouped.sort_by(|left, right| left.0.cmp(&right.0));
+        let mut hoisted = Vec::new();
+        for (package, ranges) in grouped {
+            let mut tallies: Vec<(String, usize)> = Vec::new();
+            for range in ranges {
+                match tallies.iter_mut().find(|(tallied, _)| *tallied == range) {
+                    Some((_, count)) => *count += 1,
+                    None => tallies.push((range, 1)),
+                }
+            }
+            tallies.sort_by(|left, right| right.1.cmp(&left.1).then(left.0.cmp(&right.0)));
+            let Some((winner, _)) = tallies.first() else {
+                continue;
+            };
+            for member in &self.members {
+                if let Some(range) = member.dependencies.get(&package)
+                    && range != winner
+                {
+                    nested.push((member.name.clone(), package.clone(), range.clone()));
+                }
+            }
+            hoisted.push((package, winner.clone()));
+        }
+        nested.sort();
+        HoistLayout { hoisted, nested }
+    }
+
+    /// Writes the root lockfile: the hoisted layout plus every member's
+    /// declared dependencies and where each one resolves. The rendering is
+    /// fully sorted, so the same workspace always produces byte-identical
+    /// output.
+    pub fn write_lockfile(&self) -> Result<PathBuf, PkgError> {
+        let path = self.root.join(WORKSPACE_LOCKFILE_NAME);
+        fs::write(&path, self.render_lockfile()).map_err(|source| PkgError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        Ok(path)
+    }
+
+    /// Checks the current member declarations against the lockfile on disk,
+    /// failing with [`PkgError::LockfileDrift`] naming the first member (by
+    /// name) that was added, removed, or whose declarations changed since
+    /// the lock was written.
+    pub fn verify_lockfile(&self) -> Result<(), PkgError> {
+        let path = self.root.join(WORKSPACE_LOCKFILE_NAME);
+        let locked = fs::read_to_string(&path).map_err(|source| PkgError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let current = self.render_lockfile();
+        if locked == current {
+            return Ok(());
+        }
+
+        let locked_members = parse_lockfile_members(&locked);
+        let current_members = parse_lockfile_members(&current);
+        let mut names: Vec<&String> = locked_members
+            .keys()
+            .chain(current_members.keys())
+            .collect();
+        names.sort();
+        names.dedup();
+        for name in names {
+            let reason = match (locked_members.get(name), current_members.get(name)) {
+                (None, Some(_)) => "added since the lockfile was written",
+                (Some(_), None) => "removed since the lockfile was written",
+                (Some(locked_section), Some(current_section))
+                    if locked_section != current_section =>
+                {
+                    "its declared dependencies or version changed"
+                }
+                _ => continue,
+            };
+            return Err(PkgError::LockfileDrift {
+                package: name.clone(),
+                reason: reason.to_string(),
+            });
+        }
+        // Every member section agrees yet the files differ: the lockfile was
+        // edited by hand or written by an incompatible version.
+        Err(PkgError::InvalidManifest(format!(
+            "{} does not match the workspace layout",
+            path.display()
+        )))
+    }
+
+    fn render_lockfile(&self) -> String {
+        let layout = self.hoist_layout();
+        let member_names: HashSet<&str> = self
+            .members
+            .iter()
+            .map(|member| member.name.as_str())
+            .collect();
+        let mut contents = String::from("# dx workspace lockfile v1\n");
+        for (name, range) in &layout.hoisted {
+            contents.push_str(&format!("hoisted {name} {range}\n"));
+        }
+        let mut members: Vec<&WorkspaceMember> = self.members.iter().collect();
+        members.sort_by(|left, right| left.name.cmp(&right.name));
+        for member in members {
+            contents.push_str(&format!("member {} {}\n", member.name, member.version));
+            let mut dependencies: Vec<(&String, &String)> = member.dependencies.iter().collect();
+            dependencies.sort();
+            for (name, range) in dependencies {
+                let location = if member_names.contains(name.as_str()) {
+                    "workspace"
+                } else if layout.hoisted.iter().any(|(hoisted_name, hoisted_range)| {
+                    hoisted_name == name && hoisted_range == range
+                }) {
+                    "hoisted"
+                } else {
+                    "nested"
+                };
+                contents.push_str(&format!("dep {name} {range} {location}\n"));
+            }
+        }
+        contents
+    }
+
+    fn script_path_for(&self, member: &WorkspaceMember) -> OsString {
+        let mut paths = vec![
+            member.dir.join("node_modules").join(".bin"),
+            self.root.join("node_modules").join(".bin"),
+        ];
+        if let Some(existing) = std::env::var_os("PATH") {
+            paths.extend(std::env::split_paths(&existing));
+        }
+        // join_paths only fails on a path containing the separator character,
+        // which can't happen for paths we just built from the filesystem.
+        std::env::join_paths(paths.iter()).unwrap_or_default()
+    }
+}
+
+/// Minimal glob matching for `nohoist` patterns: `*` matches any (possibly
+/// empty) run of characters, everything else is literal. Patterns match the
+/// whole package name, so `@native/*` pins a scope and `fsevents` a single
+/// package.
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let mut pattern_index = 0;
+    let mut name_index = 0;
+    let mut star: Option<(usize, usize)> = None;
+    while name_index < name.len() {
+        if pattern.get(pattern_index) == Some(&'*') {
+            star = Some((pattern_index, name_index));
+            pattern_index += 1;
+        } else if pattern.get(pattern_index) == name.get(name_index) {
+            pattern_index += 1;
+            name_index += 1;
+        } else if let Some((star_pattern, star_name)) = star {
+            // Backtrack: let the last `*` absorb one more character.
+            pattern_index = star_pattern + 1;
+            name_index = star_name + 1;
+            star = Some((star_pattern, star_name + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[pattern_index..]
+        .iter()
+        .all(|&character| character == '*')
+}
+
+/// Each member's section of a rendered lockfile — its `member` header line
+/// plus its `dep` lines — keyed by member name, for pinpointing which member
+/// a drifted lockfile disagrees about.
+fn parse_lockfile_members(contents: &str) -> HashMap<String, Vec<String>> {
+    let mut members: HashMap<String, Vec<String>> = HashMap::default();
+    let mut current: Option<String> = None;
+    for line in contents.lines() {
+        if let Some(rest) = line.strip_prefix("member ") {
+            let name = rest.split(' ').next().unwrap_or(rest).to_string();
+            members.insert(name.clone(), vec![line.to_string()]);
+            current = Some(name);
+        } else if line.starts_with("dep ")
+            && let Some(name) = &current
+            && let Some(section) = members.get_mut(name)
+        {
+            section.push(line.to_string());
+        }
+    }
+    members
+}
+
+/// Hash of everything that feeds a member's build: its files (sorted, with
+/// relative paths) and its `build` script text. `node_modules` and `dist`
+/// are excluded — hashing the conventional output directory would make every
+/// build invalidate itself.
+fn member_input_hash(member: &WorkspaceMember) -> Result<String, PkgError> {
+    let mut files = Vec::new();
+    collect_input_files(&member.dir, &mut files)?;
+    files.sort();
+    let mut hasher = Sha256::new();
+    for file in &files {
+        let bytes = fs::read(file).map_err(|source| PkgError::Io {
+            path: file.clone(),
+            source,
+        })?;
+        let relative = file.strip_prefix(&member.dir).unwrap_or(file);
+        hasher.update(relative.display().to_string().as_bytes());
+        hasher.update(&bytes);
+    }
+    if let Some(script) = member.scripts.get("build") {
+        hasher.update(script.as_bytes());
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+fn collect_input_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), PkgError> {
+    let entries = fs::read_dir(dir).map_err(|source| PkgError::Io {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    for entry in entries {
+        let entry = entry.map_err(|source| PkgError::Io {
+            path: dir.to_path_buf(),
+            source,
+        })?;
+        let path = entry.path();
+        if path.is_dir() {
+            if path
+                .file_name()
+                .is_some_and(|name| name == "node_modules" || name == "dist")
+            {
+                continue;
+            }
+            collect_input_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn collect_source_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), PkgError> {
+    let entries = fs::read_dir(dir).map_err(|source| PkgError::Io {
+        path: dir.to_path_buf(),
+        source,
+    })?;
+    for entry in entries {
+        let entry = entry.map_err(|source| PkgError::Io {
+            path: dir.to_path_buf(),
+            source,
+        })?;
+        let path = entry.path();
+        if path.is_dir() {
+            // Imports inside installed packages are their own business.
+            if path.file_name().is_some_and(|name| name == "node_modules") {
+                continue;
+            }
+            collect_source_files(&path, files)?;
+        } else if path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                matches!(extension, "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs")
+            })
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Pulls module specifiers out of `import`/`export ... from` statements and
+/// `require(...)` / dynamic `import(...)` calls. A line scan, not a parser:
+/// good enough for audit-style warnings.
+fn extract_import_specifiers(source: &str) -> Vec<&str> {
+    let mut specifiers = Vec::new();
+    for line in source.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("import") || trimmed.starts_with("export") {
+            if let Some(rest) = trimmed.split(" from ").nth(1) {
+                specifiers.extend(leading_quoted(rest));
+            } else if let Some(rest) = trimmed.strip_prefix("import") {
+                // Side-effect import: `import "polyfill"`.
+                specifiers.extend(leading_quoted(rest.trim_start()));
+            }
+        }
+        for marker in ["require(", "import("] {
+            let mut rest = line;
+            while let Some(position) = rest.find(marker) {
+                rest = &rest[position + marker.len()..];
+                specifiers.extend(leading_quoted(rest));
+            }
+        }
+    }
+    specifiers
+}
+
+/// The quoted string starting at `text`, if any.
+fn leading_quoted(text: &str) -> Option<&str> {
+    let mut characters = text.chars();
+    let quote = characters.next()?;
+    if quote != '\'' && quote != '"' {
+        return None;
+    }
+    characters.as_str().split(quote).next()
+}
+
+/// The package part of a bare specifier: `lodash/fp` -> `lodash`,
+/// `@scope/name/sub` -> `@scope/name`. Relative and absolute paths and
+/// `node:`-prefixed builtins yield `None`.
+fn bare_package_name(specifier: &str) -> Option<&str> {
+    if specifier.is_empty() || specifier.starts_with('.') || specifier.starts_with('/') {
+        return None;
+    }
+    if specifier.starts_with("node:") {
+        return None;
+    }
+    if let Some(rest) = specifier.strip_prefix('@') {
+        let scope_end = rest.find('/')?;
+        let name_start = 1 + scope_end + 1;
+        match specifier[name_start..].find('/') {
+            Some(name_end) => Some(&specifier[..name_start + name_end]),
+            None => Some(specifier),
+        }
+    } else {
+        Some(specifier.split('/').next().unwrap_or(specifier))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn workspace_with_member(root: &std::path::Path, scripts: &[(&str, &str)]) -> Workspace {
+        let member_dir = root.join("packages").join("app");
+        fs::create_dir_all(&member_dir).unwrap();
+        let mut workspace = Workspace::new(root);
+        workspace.add_member(WorkspaceMember {
+            name: "app".into(),
+            version: "1.2.3".into(),
+            dir: member_dir,
+            dependencies: HashMap::default(),
+            scripts: scripts
+                .iter()
+                .map(|(name, body)| (name.to_string(), body.to_string()))
+                .collect(),
+        });
+        workspace
+    }
+
+    #[cfg(unix)]
+    fn install_local_binary(package_dir: &std::path::Path, name: &str, body: &str) {
+        use std::os::unix::fs::PermissionsExt;
+        let bin_dir = package_dir.join("node_modules").join(".bin");
+        fs::create_dir_all(&bin_dir).unwrap();
+        let bin_path = bin_dir.join(name);
+        fs::write(&bin_path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        fs::set_permissions(&bin_path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_script_resolves_locally_installed_binary() {
+        let root = tempfile::tempdir().unwrap();
+        let workspace = workspace_with_member(root.path(), &[("hello", "greet")]);
+        install_local_binary(&workspace.members[0].dir, "greet", "echo greet-from-bin");
+
+        let output = workspace.run_script_in_package("app", "hello").unwrap();
+        assert_eq!(output.exit_code, 0, "stderr: {}", output.stderr);
+        assert!(output.stdout.contains("greet-from-bin"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_script_sees_injected_package_env() {
+        let root = tempfile::tempdir().unwrap();
+        let workspace = workspace_with_member(
+            root.path(),
+            &[("env", "echo $dx_package_name@$dx_package_version")],
+        );
+
+        let output = workspace.run_script_in_package("app", "env").unwrap();
+        assert!(output.stdout.contains("app@1.2.3"));
+    }
+
+    #[test]
+    fn test_phantom_dependencies_are_detected() {
+        let root = tempfile::tempdir().unwrap();
+        let member_dir = root.path().join("packages").join("app");
+        let source_dir = member_dir.join("src");
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::write(
+            source_dir.join("main.ts"),
+            concat!(
+                "import React from 'react';\n",
+                "import { merge } from \"lodash\";\n",
+                "import fs from 'fs';\n",
+                "import path from 'node:path';\n",
+                "import { helper } from './helper';\n",
+                "import { own } from 'app/internal';\n",
+                "export { thing } from '@scope/pkg/sub';\n",
+                "const chalk = require('chalk');\n",
+            ),
+        )
+        .unwrap();
+        // Installed packages must not be scanned.
+        let hoisted = member_dir.join("node_modules").join("lodash");
+        fs::create_dir_all(&hoisted).unwrap();
+        fs::write(hoisted.join("index.js"), "require('phantom-inner');").unwrap();
+
+        let mut workspace = Workspace::new(root.path());
+        workspace.add_member(WorkspaceMember {
+            name: "app".into(),
+            version: "1.0.0".into(),
+            dir: member_dir,
+            dependencies: [("react".to_string(), "^18".to_string())]
+                .into_iter()
+                .collect(),
+            scripts: HashMap::default(),
+        });
+
+        let phantoms = workspace.detect_phantom_dependencies().unwrap();
+        let flagged: Vec<&str> = phantoms
+            .iter()
+            .map(|phantom| phantom.dependency.as_str())
+            .collect();
+        assert_eq!(flagged, vec!["lodash", "@scope/pkg", "chalk"]);
+        for phantom in &phantoms {
+            assert_eq!(phantom.package, "app");
+            assert!(phantom.file.ends_with("src/main.ts"));
+        }
+    }
+
+    fn add_buildable_member(
+        workspace: &mut Workspace,
+        root: &std::path::Path,
+        name: &str,
+        dependencies: &[&str],
+    ) {
+        let member_dir = root.join("packages").join(name);
+        fs::create_dir_all(member_dir.join("src")).unwrap();
+        fs::write(
+            member_dir.join("src").join("index.js"),
+            format!("export const name = '{name}';\n"),
+        )
+        .unwrap();
+        workspace.add_member(WorkspaceMember {
+            name: name.into(),
+            version: "1.0.0".into(),
+            dir: member_dir,
+            dependencies: dependencies
+                .iter()
+                .map(|dependency| (dependency.to_string(), "*".to_string()))
+                .collect(),
+            // The log lands in `dist`, which input hashing excludes, so the
+            // build itself doesn't invalidate the cache.
+            scripts: [(
+                "build".to_string(),
+                "mkdir -p dist && echo built >> dist/log.txt".to_string(),
+            )]
+            .into_iter()
+            .collect(),
+        });
+    }
+
+    fn build_count(workspace: &Workspace, name: &str) -> usize {
+        let log = workspace.member(name).unwrap().dir.join("dist/log.txt");
+        fs::read_to_string(log).map_or(0, |text| text.lines().count())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unchanged_leaf_is_skipped_and_changed_dependency_invalidates() {
+        let root = tempfile::tempdir().unwrap();
+        let mut workspace = Workspace::new(root.path());
+        add_buildable_member(&mut workspace, root.path(), "shared", &[]);
+        add_buildable_member(&mut workspace, root.path(), "app", &["shared"]);
+
+        let report = workspace.build(None, 2).unwrap();
+        assert_eq!(report.outcomes.len(), 2);
+        assert_eq!(report.skipped_count(), 0);
+        assert_eq!(
+            report.outcomes[0].package, "shared",
+            "dependency built first"
+        );
+
+        let report = workspace.build(None, 2).unwrap();
+        assert_eq!(report.skipped_count(), 2, "nothing changed");
+        assert_eq!(build_count(&workspace, "shared"), 1);
+        assert_eq!(build_count(&workspace, "app"), 1);
+
+        // Changing the shared package rebuilds it and its dependent.
+        let shared_source = workspace.member("shared").unwrap().dir.join("src/index.js");
+        fs::write(&shared_source, "export const name = 'changed';\n").unwrap();
+        let report = workspace.build(None, 2).unwrap();
+        assert_eq!(report.skipped_count(), 0);
+        assert_eq!(build_count(&workspace, "shared"), 2);
+        assert_eq!(build_count(&workspace, "app"), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_filter_builds_only_the_member_and_its_dependencies() {
+        let root = tempfile::tempdir().unwrap();
+        let mut workspace = Workspace::new(root.path());
+        add_buildable_member(&mut workspace, root.path(), "shared", &[]);
+        add_buildable_member(&mut workspace, root.path(), "app", &["shared"]);
+        add_buildable_member(&mut workspace, root.path(), "docs", &[]);
+
+        let report = workspace.build(Some("app"), 1).unwrap();
+        let packages: Vec<&str> = report
+            .outcomes
+            .iter()
+            .map(|outcome| outcome.package.as_str())
+            .collect();
+        assert_eq!(packages, vec!["shared", "app"]);
+        assert_eq!(build_count(&workspace, "docs"), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failing_build_script_reports_stderr() {
+        let root = tempfile::tempdir().unwrap();
+        let mut workspace = Workspace::new(root.path());
+        add_buildable_member(&mut workspace, root.path(), "app", &[]);
+        if let Some(member) = workspace.members.first_mut() {
+            member
+                .scripts
+                .insert("build".into(), "echo no entry point >&2; exit 1".into());
+        }
+        match workspace.build(None, 1) {
+            Err(PkgError::BuildScriptFailed { package, stderr }) => {
+                assert_eq!(package, "app");
+                assert!(stderr.contains("no entry point"));
+            }
+            other => panic!("expected a build failure, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_dependency_cycle_is_an_error() {
+        let root = tempfile::tempdir().unwrap();
+        let mut workspace = Workspace::new(root.path());
+        add_buildable_member(&mut workspace, root.path(), "a", &["b"]);
+        add_buildable_member(&mut workspace, root.path(), "b", &["a"]);
+        assert!(matches!(
+            workspace.build(None, 1),
+            Err(PkgError::DependencyCycle(_))
+        ));
+    }
+
+    fn member_with_dependencies(
+        root: &std::path::Path,
+        name: &str,
+        dependencies: &[(&str, &str)],
+    ) -> WorkspaceMember {
+        WorkspaceMember {
+            name: name.into(),
+            version: "1.0.0".into(),
+            dir: root.join("packages").join(name),
+            dependencies: dependencies
+                .iter()
+                .map(|(dependency, range)| (dependency.to_string(), range.to_string()))
+                .collect(),
+            scripts: HashMap::default(),
+        }
+    }
+
+    #[test]
+    fn test_hoist_is_stable_and_majority_range_wins() {
+        let root = tempfile::tempdir().unwrap();
+        let mut workspace = Workspace::new(root.path());
+        workspace.add_member(member_with_dependencies(
+            root.path(),
+            "app",
+            &[("lodash", "^1.0.0"), ("react", "^18.0.0"), ("lib", "*")],
+        ));
+        workspace.add_member(member_with_dependencies(
+            root.path(),
+            "docs",
+            &[("lodash", "^1.0.0")],
+        ));
+        workspace.add_member(member_with_dependencies(
+            root.path(),
+            "lib",
+            &[("lodash", "^2.0.0")],
+        ));
+
+        let layout = workspace.hoist_layout();
+        assert_eq!(
+            layout.hoisted,
+            vec![
+                ("lodash".to_string(), "^1.0.0".to_string()),
+                ("react".to_string(), "^18.0.0".to_string()),
+            ],
+            "workspace members never hoist and the majority lodash range wins"
+        );
+        assert_eq!(
+            layout.nested,
+            vec![(
+                "lib".to_string(),
+                "lodash".to_string(),
+                "^2.0.0".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_nohoist_patterns_keep_matching_dependencies_local() {
+        let root = tempfile::tempdir().unwrap();
+        let mut workspace = Workspace::new(root.path());
+        workspace.nohoist = vec!["@native/*".to_string(), "fsevents".to_string()];
+        workspace.add_member(member_with_dependencies(
+            root.path(),
+            "app",
+            &[
+                ("fsevents", "^2.0.0"),
+                ("@native/gpu", "^1.0.0"),
+                ("lodash", "^1.0.0"),
+            ],
+        ));
+        workspace.add_member(member_with_dependencies(
+            root.path(),
+            "docs",
+            &[("fsevents", "^2.0.0"), ("lodash", "^1.0.0")],
+        ));
+
+        let layout = workspace.hoist_layout();
+        assert_eq!(
+            layout.hoisted,
+            vec![("lodash".to_string(), "^1.0.0".to_string())],
+            "a nohoist-matched package never reaches the hoisted set, even \
+             when every declared range agrees"
+        );
+        assert_eq!(
+            layout.nested,
+            vec![
+                (
+                    "app".to_string(),
+                    "@native/gpu".to_string(),
+                    "^1.0.0".to_string()
+                ),
+                (
+                    "app".to_string(),
+                    "fsevents".to_string(),
+                    "^2.0.0".to_string()
+                ),
+                (
+                    "docs".to_string(),
+                    "fsevents".to_string(),
+                    "^2.0.0".to_string()
+                ),
+            ],
+            "each declaring member keeps its own local copy"
+        );
+    }
+
+    #[test]
+    fn test_lockfile_round_trips_and_is_deterministic() {
+        let root = tempfile::tempdir().unwrap();
+        let mut workspace = Workspace::new(root.path());
+        workspace.add_member(member_with_dependencies(
+            root.path(),
+            "app",
+            &[("react", "^18.0.0"), ("lib", "*")],
+        ));
+        workspace.add_member(member_with_dependencies(
+            root.path(),
+            "lib",
+            &[("lodash", "^2.0.0")],
+        ));
+
+        let path = workspace.write_lockfile().unwrap();
+        let first = fs::read_to_string(&path).unwrap();
+        workspace.verify_lockfile().unwrap();
+
+        workspace.write_lockfile().unwrap();
+        let second = fs::read_to_string(&path).unwrap();
+        assert_eq!(first, second, "rewriting an unchanged workspace is a no-op");
+    }
+
+    #[test]
+    fn test_changed_member_dependencies_fail_verification_naming_the_member() {
+        let root = tempfile::tempdir().unwrap();
+        let mut workspace = Workspace::new(root.path());
+        workspace.add_member(member_with_dependencies(
+            root.path(),
+            "app",
+            &[("react", "^18.0.0")],
+        ));
+        workspace.add_member(member_with_dependencies(
+            root.path(),
+            "lib",
+            &[("lodash", "^2.0.0")],
+        ));
+        workspace.write_lockfile().unwrap();
+
+        if let Some(member) = workspace
+            .members
+            .iter_mut()
+            .find(|member| member.name == "lib")
+        {
+            member
+                .dependencies
+                .insert("chalk".to_string(), "^5.0.0".to_string());
+        }
+        match workspace.verify_lockfile() {
+            Err(PkgError::LockfileDrift { package, .. }) => assert_eq!(package, "lib"),
+            other => panic!("expected drift pointing at lib, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_added_member_is_detected_as_drift() {
+        let root = tempfile::tempdir().unwrap();
+        let mut workspace = Workspace::new(root.path());
+        workspace.add_member(member_with_dependencies(root.path(), "app", &[]));
+        workspace.write_lockfile().unwrap();
+
+        workspace.add_member(member_with_dependencies(root.path(), "docs", &[]));
+        match workspace.verify_lockfile() {
+            Err(PkgError::LockfileDrift { package, reason }) => {
+                assert_eq!(package, "docs");
+                assert!(reason.contains("added"));
+            }
+            other => panic!("expected drift pointing at docs, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_missing_script_is_an_error() {
+        let root = tempfile::tempdir().unwrap();
+        let workspace = workspace_with_member(root.path(), &[]);
+        assert!(matches!(
+            workspace.run_script_in_package("app", "nope"),
+            Err(PkgError::ScriptNotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,1191 @@
+use crate::{
+    BinaryMessage, MessagePriority, MessageType, MessageVerifier, SignedEnvelope, SyncError,
+    SyncMetrics,
+};
+use collections::HashMap;
+use futures::Stream;
+use parking_lot::RwLock;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::task::{Context, Poll};
+
+pub const DEFAULT_HISTORY_LIMIT: usize = 256;
+
+/// How many control-priority messages (presence, acks, control frames) may
+/// bypass an exhausted credit window before they too start buffering.
+/// Replenished on every grant.
+pub const PRIORITY_ALLOWANCE: u64 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriberId(pub u64);
+
+pub struct ChannelManager {
+    channels: RwLock<HashMap<String, Channel>>,
+    /// Channels that require signed payloads; see
+    /// [`require_signatures`](Self::require_signatures). Kept apart from
+    /// `channels` so a verifier survives the channel entry being removed
+    /// when its last subscriber leaves.
+    pub(crate) verifiers: RwLock<HashMap<String, MessageVerifier>>,
+    next_subscriber_id: AtomicU64,
+    next_message_id: AtomicU64,
+    history_limit: usize,
+    /// Per-channel wire-byte counters, fed from the publish path; shared so
+    /// dashboards can hold the registry without holding the manager.
+    metrics: Arc<SyncMetrics>,
+    /// Total wire bytes of retained history and credit-buffered messages
+    /// allowed across all channels; `None` bounds channels only by the
+    /// per-channel count limit.
+    memory_budget: Option<usize>,
+}
+
+struct Channel {
+    subscribers: Vec<Subscriber>,
+    history: VecDeque<BinaryMessage>,
+    /// Id of the newest message evicted from `history`, if any. A delta base
+    /// older than this has aged out: some of the messages after it are gone.
+    evicted_through: Option<u64>,
+    /// Id of the most recent publish to this channel. Orders channels for
+    /// budget eviction: the least-recently-published channel's history is
+    /// trimmed first.
+    last_published: Option<u64>,
+}
+
+/// The bytes a channel holds onto: its retained history plus every message
+/// buffered behind an exhausted credit window, measured as wire size so the
+/// budget tracks what a snapshot or flush would actually move.
+fn channel_memory(channel: &Channel) -> usize {
+    let history: usize = channel.history.iter().map(crate::encoded_len).sum();
+    let buffered: usize = channel
+        .subscribers
+        .iter()
+        .filter_map(|subscriber| subscriber.flow_control.as_ref())
+        .flat_map(|flow_control| flow_control.iter_pending())
+        .map(crate::encoded_len)
+        .sum();
+    history + buffered
+}
+
+/// Trims histories until total usage fits in `budget`, taking from the
+/// least-recently-published channel first, oldest messages first. Credit
+/// buffers are counted against the budget but never discarded — dropping a
+/// buffered message would silently break the subscriber's ordered stream —
+/// so a budget consumed entirely by buffers ends with every history empty
+/// and usage still above it.
+fn enforce_memory_budget(channels: &mut HashMap<String, Channel>, budget: usize) {
+    let mut usage: usize = channels.values().map(channel_memory).sum();
+    while usage > budget {
+        let Some(channel) = channels
+            .values_mut()
+            .filter(|channel| !channel.history.is_empty())
+            .min_by_key(|channel| channel.last_published)
+        else {
+            break;
+        };
+        while usage > budget
+            && let Some(evicted) = channel.history.pop_front()
+        {
+            usage = usage.saturating_sub(crate::encoded_len(&evicted));
+            channel.evicted_through = Some(evicted.id);
+        }
+    }
+}
+
+/// Everything a client needs to catch up on a channel, in the cheaper of two
+/// shapes: the messages it missed, or the full retained history to rebuild
+/// from scratch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeltaOrSnapshot {
+    Delta(ChannelDelta),
+    Snapshot(ChannelSnapshot),
+}
+
+/// The messages published after `base_version`, to be appended to a client
+/// that has already seen everything up to and including that id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelDelta {
+    pub base_version: u64,
+    pub messages: Vec<BinaryMessage>,
+}
+
+/// The channel's full retained history; a client replaces its state with
+/// this rather than appending. `version` is the newest included message id,
+/// or `None` when the channel has no history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelSnapshot {
+    pub version: Option<u64>,
+    pub messages: Vec<BinaryMessage>,
+}
+
+/// A server-side delivery predicate over a message's header fields. This is
+/// deliberately a small data DSL rather than a callback: filters stay cheap,
+/// serializable, and safe to accept from remote clients.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageFilter {
+    MessageType(MessageType),
+    /// Matches when the payload starts with these bytes. By convention
+    /// payloads embed routing fields (e.g. an entity id) in a fixed prefix.
+    PayloadPrefix(Vec<u8>),
+    Not(Box<MessageFilter>),
+    All(Vec<MessageFilter>),
+    Any(Vec<MessageFilter>),
+}
+
+impl MessageFilter {
+    pub fn matches(&self, message: &BinaryMessage) -> bool {
+        match self {
+            Self::MessageType(message_type) => message.message_type == *message_type,
+            Self::PayloadPrefix(prefix) => message.data.starts_with(prefix),
+            Self::Not(filter) => !filter.matches(message),
+            Self::All(filters) => filters.iter().all(|filter| filter.matches(message)),
+            Self::Any(filters) => filters.iter().any(|filter| filter.matches(message)),
+        }
+    }
+}
+
+struct Subscriber {
+    id: SubscriberId,
+    sender: flume::Sender<BinaryMessage>,
+    /// `None` means the subscriber opted out of flow control and receives
+    /// every message immediately.
+    flow_control: Option<FlowControl>,
+    filter: Option<MessageFilter>,
+}
+
+/// Credit-window state for one flow-controlled subscriber. Each delivered
+/// message consumes a credit; at zero the subscriber's messages queue here
+/// until [`ChannelManager::grant_credits`] opens the window again.
+struct FlowControl {
+    credits: u64,
+    priority_allowance: u64,
+    /// One queue per [`MessagePriority`]; the control lane drains first so a
+    /// saturated data channel cannot delay presence or acks.
+    pending_control: VecDeque<BinaryMessage>,
+    pending: VecDeque<BinaryMessage>,
+}
+
+impl FlowControl {
+    fn enqueue(&mut self, message: BinaryMessage) {
+        match message.message_type.priority() {
+            MessagePriority::Control => self.pending_control.push_back(message),
+            MessagePriority::Normal => self.pending.push_back(message),
+        }
+    }
+
+    fn dequeue(&mut self) -> Option<BinaryMessage> {
+        self.pending_control
+            .pop_front()
+            .or_else(|| self.pending.pop_front())
+    }
+
+    fn pending_len(&self) -> usize {
+        self.pending_control.len() + self.pending.len()
+    }
+
+    fn iter_pending(&self) -> impl Iterator<Item = &BinaryMessage> {
+        self.pending_control.iter().chain(self.pending.iter())
+    }
+}
+
+impl Subscriber {
+    /// Delivers the message, returning false when the send failed because
+    /// the receiver was dropped, so the publish loop can prune the dead
+    /// subscriber instead of iterating it on every future publish. A
+    /// filtered-out or credit-buffered message attempts no send and reports
+    /// the subscriber alive; a dead one is caught on its next delivery.
+    fn deliver(&mut self, message: &BinaryMessage) -> bool {
+        // Filtered-out messages are dropped before flow control, so they
+        // never consume credits.
+        if let Some(filter) = &self.filter
+            && !filter.matches(message)
+        {
+            return true;
+        }
+        let Some(flow_control) = &mut self.flow_control else {
+            return self.sender.send(message.clone()).is_ok();
+        };
+        if flow_control.credits > 0 {
+            flow_control.credits -= 1;
+            self.sender.send(message.clone()).is_ok()
+        } else if message.message_type.priority() == MessagePriority::Control
+            && flow_control.priority_allowance > 0
+        {
+            flow_control.priority_allowance -= 1;
+            self.sender.send(message.clone()).is_ok()
+        } else {
+            flow_control.enqueue(message.clone());
+            true
+        }
+    }
+}
+
+impl ChannelManager {
+    pub fn new() -> Self {
+        Self::with_history_limit(DEFAULT_HISTORY_LIMIT)
+    }
+
+    pub fn with_history_limit(history_limit: usize) -> Self {
+        Self {
+            channels: RwLock::new(HashMap::default()),
+            verifiers: RwLock::new(HashMap::default()),
+            next_subscriber_id: AtomicU64::new(0),
+            next_message_id: AtomicU64::new(0),
+            history_limit,
+            metrics: Arc::new(SyncMetrics::new()),
+            memory_budget: None,
+        }
+    }
+
+    /// The byte-counter registry this manager feeds; see [`SyncMetrics`].
+    /// Cloning the [`Arc`] lets billing and dashboards read totals without
+    /// going through the manager.
+    pub fn metrics(&self) -> Arc<SyncMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Like [`with_history_limit`](Self::with_history_limit), but also caps
+    /// the total memory of all channel histories at `memory_budget` bytes.
+    /// When a publish pushes usage past the budget, the least-recently-
+    /// published channels' histories are trimmed (oldest messages first)
+    /// until usage fits again; trimmed messages age out of deltas exactly
+    /// as count-limit evictions do.
+    pub fn with_memory_budget(history_limit: usize, memory_budget: usize) -> Self {
+        Self {
+            memory_budget: Some(memory_budget),
+            ..Self::with_history_limit(history_limit)
+        }
+    }
+
+    /// The configured global memory budget in bytes, or `None` when only the
+    /// per-channel count limit applies.
+    pub fn memory_budget(&self) -> Option<usize> {
+        self.memory_budget
+    }
+
+    /// Total wire bytes currently held across all channel histories and
+    /// credit-window buffers.
+    pub fn memory_usage(&self) -> usize {
+        self.channels.read().values().map(channel_memory).sum()
+    }
+
+    pub fn subscribe(&self, channel: &str) -> (SubscriberId, flume::Receiver<BinaryMessage>) {
+        self.add_subscriber(channel, None, None)
+    }
+
+    /// Like [`subscribe`](Self::subscribe), but returns the subscription as a
+    /// [`futures::Stream`] for use with async runtimes and `StreamExt`
+    /// combinators. Dropping the stream unsubscribes.
+    pub fn subscribe_stream(self: &Arc<Self>, channel: &str) -> MessageStream {
+        let (subscriber_id, receiver) = self.subscribe(channel);
+        self.stream_from(channel, subscriber_id, receiver)
+    }
+
+    /// The stream counterpart of
+    /// [`subscribe_filtered`](Self::subscribe_filtered).
+    pub fn subscribe_filtered_stream(
+        self: &Arc<Self>,
+        channel: &str,
+        filter: MessageFilter,
+    ) -> MessageStream {
+        let (subscriber_id, receiver) = self.subscribe_filtered(channel, filter);
+        self.stream_from(channel, subscriber_id, receiver)
+    }
+
+    fn stream_from(
+        self: &Arc<Self>,
+        channel: &str,
+        subscriber_id: SubscriberId,
+        receiver: flume::Receiver<BinaryMessage>,
+    ) -> MessageStream {
+        MessageStream {
+            // Weak, so an outstanding stream doesn't keep the manager alive.
+            manager: Arc::downgrade(self),
+            channel: channel.to_string(),
+            subscriber_id,
+            receiver: receiver.into_stream(),
+        }
+    }
+
+    /// Subscribes with a server-side [`MessageFilter`]; only matching
+    /// messages are delivered.
+    pub fn subscribe_filtered(
+        &self,
+        channel: &str,
+        filter: MessageFilter,
+    ) -> (SubscriberId, flume::Receiver<BinaryMessage>) {
+        self.add_subscriber(channel, None, Some(filter))
+    }
+
+    /// Subscribes with credit-based flow control: only `initial_credits`
+    /// messages are delivered before the subscriber must call
+    /// [`grant_credits`](Self::grant_credits); further messages queue
+    /// server-side in order.
+    pub fn subscribe_with_credits(
+        &self,
+        channel: &str,
+        initial_credits: u64,
+    ) -> (SubscriberId, flume::Receiver<BinaryMessage>) {
+        self.add_subscriber(
+            channel,
+            Some(FlowControl {
+                credits: initial_credits,
+                priority_allowance: PRIORITY_ALLOWANCE,
+                pending_control: VecDeque::new(),
+                pending: VecDeque::new(),
+            }),
+            None,
+        )
+    }
+
+    fn add_subscriber(
+        &self,
+        channel: &str,
+        flow_control: Option<FlowControl>,
+        filter: Option<MessageFilter>,
+    ) -> (SubscriberId, flume::Receiver<BinaryMessage>) {
+        let id = SubscriberId(self.next_subscriber_id.fetch_add(1, Ordering::Relaxed));
+        let (sender, receiver) = flume::unbounded();
+        let mut channels = self.channels.write();
+        let channel = channels
+            .entry(channel.to_string())
+            .or_insert_with(|| Channel {
+                subscribers: Vec::new(),
+                history: VecDeque::new(),
+                evicted_through: None,
+                last_published: None,
+            });
+        channel.subscribers.push(Subscriber {
+            id,
+            sender,
+            flow_control,
+            filter,
+        });
+        (id, receiver)
+    }
+
+    /// Extends a flow-controlled subscriber's window by `additional` credits,
+    /// replenishes its priority allowance, and flushes queued messages into
+    /// the new window. Returns the credits remaining after the flush.
+    pub fn grant_credits(
+        &self,
+        subscriber_id: SubscriberId,
+        additional: u64,
+    ) -> Result<u64, SyncError> {
+        let mut channels = self.channels.write();
+        let Subscriber {
+            sender,
+            flow_control,
+            ..
+        } = channels
+            .values_mut()
+            .flat_map(|channel| channel.subscribers.iter_mut())
+            .find(|subscriber| subscriber.id == subscriber_id)
+            .ok_or(SyncError::SubscriberNotFound(subscriber_id.0))?;
+        let flow_control = flow_control
+            .as_mut()
+            .ok_or(SyncError::SubscriberNotFound(subscriber_id.0))?;
+        flow_control.credits = flow_control.credits.saturating_add(additional);
+        flow_control.priority_allowance = PRIORITY_ALLOWANCE;
+        while flow_control.credits > 0
+            && let Some(message) = flow_control.dequeue()
+        {
+            flow_control.credits -= 1;
+            // A failed send means the receiver is gone; the publish loop
+            // prunes the subscriber on its next delivery.
+            sender.send(message).ok();
+        }
+        Ok(flow_control.credits)
+    }
+
+    pub fn unsubscribe(&self, channel: &str, subscriber_id: SubscriberId) -> Result<(), SyncError> {
+        let mut channels = self.channels.write();
+        let channel_state = channels
+            .get_mut(channel)
+            .ok_or_else(|| SyncError::ChannelNotFound(channel.to_string()))?;
+        channel_state
+            .subscribers
+            .retain(|subscriber| subscriber.id != subscriber_id);
+        if channel_state.subscribers.is_empty() && channel_state.history.is_empty() {
+            channels.remove(channel);
+        }
+        Ok(())
+    }
+
+    /// Publishes a payload to every current subscriber of the channel and
+    /// records it in the channel's bounded history. Returns the assigned
+    /// message id.
+    ///
+    /// A channel with no subscribers is not an error: the message is still
+    /// recorded to history, where a later subscriber can replay it from
+    /// [`history`](Self::history) or a [`snapshot`](Self::snapshot). Use
+    /// [`publish_requiring_subscribers`](Self::publish_requiring_subscribers)
+    /// when delivering to nobody should fail instead.
+    pub fn publish(
+        &self,
+        channel: &str,
+        message_type: MessageType,
+        data: Vec<u8>,
+    ) -> Result<u64, SyncError> {
+        self.publish_inner(channel, message_type, data, false)
+    }
+
+    /// Like [`publish`](Self::publish), but fails with
+    /// [`SyncError::NoSubscribers`] — recording nothing — when no one is
+    /// subscribed, for callers that treat an unheard message as a bug.
+    pub fn publish_requiring_subscribers(
+        &self,
+        channel: &str,
+        message_type: MessageType,
+        data: Vec<u8>,
+    ) -> Result<u64, SyncError> {
+        self.publish_inner(channel, message_type, data, true)
+    }
+
+    fn publish_inner(
+        &self,
+        channel: &str,
+        message_type: MessageType,
+        data: Vec<u8>,
+        require_subscribers: bool,
+    ) -> Result<u64, SyncError> {
+        let data = self.verify_if_required(channel, message_type, data)?;
+        let mut channels = self.channels.write();
+        // Checked before the entry is created so a failed publish doesn't
+        // leave an empty channel behind.
+        if require_subscribers
+            && channels
+                .get(channel)
+                .is_none_or(|state| state.subscribers.is_empty())
+        {
+            return Err(SyncError::NoSubscribers(channel.to_string()));
+        }
+        let channel_state = channels
+            .entry(channel.to_string())
+            .or_insert_with(|| Channel {
+                subscribers: Vec::new(),
+                history: VecDeque::new(),
+                evicted_through: None,
+                last_published: None,
+            });
+        let id = self.next_message_id.fetch_add(1, Ordering::Relaxed);
+        let message = BinaryMessage::new(id, channel, message_type, data);
+        let frame_len = crate::encoded_len(&message) as u64;
+        self.metrics.record_channel_received(channel, frame_len);
+        channel_state
+            .subscribers
+            .retain_mut(|subscriber| subscriber.deliver(&message));
+        // Counted after the retain so dead subscribers don't bill, and a
+        // credit-buffered delivery bills now rather than when the window
+        // reopens — the bytes are committed to that subscriber either way,
+        // which keeps the grant path free of metrics bookkeeping.
+        let recipients = channel_state
+            .subscribers
+            .iter()
+            .filter(|subscriber| {
+                subscriber
+                    .filter
+                    .as_ref()
+                    .is_none_or(|filter| filter.matches(&message))
+            })
+            .count();
+        self.metrics
+            .record_channel_sent(channel, frame_len.saturating_mul(recipients as u64));
+        channel_state.history.push_back(message);
+        channel_state.last_published = Some(id);
+        while channel_state.history.len() > self.history_limit {
+            if let Some(evicted) = channel_state.history.pop_front() {
+                channel_state.evicted_through = Some(evicted.id);
+            }
+        }
+        if let Some(budget) = self.memory_budget {
+            enforce_memory_budget(&mut channels, budget);
+        }
+        Ok(id)
+    }
+
+    /// On a channel with a registered verifier, decodes and checks the
+    /// [`SignedEnvelope`], returning the verified inner payload — subscribers
+    /// and history see the payload with the envelope stripped. A malformed
+    /// envelope counts as tampering. Unsigned channels pass `data` through
+    /// untouched.
+    fn verify_if_required(
+        &self,
+        channel: &str,
+        message_type: MessageType,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>, SyncError> {
+        let verifiers = self.verifiers.read();
+        let Some(verifier) = verifiers.get(channel) else {
+            return Ok(data);
+        };
+        let envelope = SignedEnvelope::decode(&data)
+            .map_err(|_| SyncError::SignatureRejected(channel.to_string()))?;
+        if !envelope.verify(channel, message_type, verifier) {
+            return Err(SyncError::SignatureRejected(channel.to_string()));
+        }
+        Ok(envelope.payload)
+    }
+
+    /// The messages published after `base_version` — the id of the last
+    /// message the client saw on this channel. Fails with
+    /// [`SyncError::VersionAgedOut`] when messages after the base have been
+    /// evicted from the bounded history, in which case the delta would be
+    /// missing messages and the client needs a snapshot instead.
+    pub fn generate_delta(
+        &self,
+        channel: &str,
+        base_version: u64,
+    ) -> Result<ChannelDelta, SyncError> {
+        let channels = self.channels.read();
+        let channel_state = channels
+            .get(channel)
+            .ok_or_else(|| SyncError::ChannelNotFound(channel.to_string()))?;
+        if channel_state
+            .evicted_through
+            .is_some_and(|evicted_through| base_version < evicted_through)
+        {
+            return Err(SyncError::VersionAgedOut(base_version));
+        }
+        Ok(ChannelDelta {
+            base_version,
+            messages: channel_state
+                .history
+                .iter()
+                .filter(|message| message.id > base_version)
+                .cloned()
+                .collect(),
+        })
+    }
+
+    /// The channel's full retained history as a [`ChannelSnapshot`].
+    pub fn snapshot(&self, channel: &str) -> Result<ChannelSnapshot, SyncError> {
+        let channels = self.channels.read();
+        let channel_state = channels
+            .get(channel)
+            .ok_or_else(|| SyncError::ChannelNotFound(channel.to_string()))?;
+        Ok(ChannelSnapshot {
+            version: channel_state.history.back().map(|message| message.id),
+            messages: channel_state.history.iter().cloned().collect(),
+        })
+    }
+
+    /// Catches a client up from `client_known_version` with whichever of
+    /// [`generate_delta`](Self::generate_delta) or
+    /// [`snapshot`](Self::snapshot) costs fewer bytes on the wire. A version
+    /// that has aged out of history falls back to the snapshot transparently.
+    pub fn generate_optimal_delta(
+        &self,
+        channel: &str,
+        client_known_version: u64,
+    ) -> Result<DeltaOrSnapshot, SyncError> {
+        let delta = match self.generate_delta(channel, client_known_version) {
+            Ok(delta) => delta,
+            Err(SyncError::VersionAgedOut(_)) => {
+                return Ok(DeltaOrSnapshot::Snapshot(self.snapshot(channel)?));
+            }
+            Err(error) => return Err(error),
+        };
+        let snapshot = self.snapshot(channel)?;
+        let wire_size =
+            |messages: &[BinaryMessage]| messages.iter().map(crate::encoded_len).sum::<usize>();
+        if wire_size(&delta.messages) < wire_size(&snapshot.messages) {
+            Ok(DeltaOrSnapshot::Delta(delta))
+        } else {
+            // Same bytes either way: the delta spans the whole retained
+            // history, and a snapshot lets the client rebuild rather than
+            // reconcile an append against unknown state.
+            Ok(DeltaOrSnapshot::Snapshot(snapshot))
+        }
+    }
+
+    pub fn history(&self, channel: &str) -> Vec<BinaryMessage> {
+        self.channels
+            .read()
+            .get(channel)
+            .map(|channel| channel.history.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn subscriber_count(&self, channel: &str) -> usize {
+        self.channels
+            .read()
+            .get(channel)
+            .map(|channel| channel.subscribers.len())
+            .unwrap_or(0)
+    }
+
+    /// A point-in-time view of every channel for admin dashboards. Taken
+    /// under one lock acquisition, so a publish racing the inspection can't
+    /// be counted in one channel and missed in another.
+    pub fn inspect(&self) -> ManagerSnapshot {
+        let channels = self.channels.read();
+        let mut snapshot_channels: Vec<ChannelStats> = channels
+            .iter()
+            .map(|(name, channel)| ChannelStats {
+                name: name.clone(),
+                history_len: channel.history.len(),
+                subscribers: channel
+                    .subscribers
+                    .iter()
+                    .map(|subscriber| SubscriberStats {
+                        id: subscriber.id,
+                        pending_messages: subscriber
+                            .flow_control
+                            .as_ref()
+                            .map_or(0, |flow_control| flow_control.pending_len()),
+                        remaining_credits: subscriber
+                            .flow_control
+                            .as_ref()
+                            .map(|flow_control| flow_control.credits),
+                    })
+                    .collect(),
+            })
+            .collect();
+        snapshot_channels.sort_by(|a, b| a.name.cmp(&b.name));
+        ManagerSnapshot {
+            channels: snapshot_channels,
+        }
+    }
+}
+
+/// One subscriber's delivery state in a [`ManagerSnapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubscriberStats {
+    pub id: SubscriberId,
+    /// Messages buffered behind an exhausted credit window. An ever-growing
+    /// depth means the consumer stopped granting credits.
+    pub pending_messages: usize,
+    /// `None` for subscribers without flow control.
+    pub remaining_credits: Option<u64>,
+}
+
+/// One channel's state in a [`ManagerSnapshot`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelStats {
+    pub name: String,
+    pub history_len: usize,
+    pub subscribers: Vec<SubscriberStats>,
+}
+
+/// A consistent snapshot of the manager produced by
+/// [`ChannelManager::inspect`], with channels sorted by name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManagerSnapshot {
+    pub channels: Vec<ChannelStats>,
+}
+
+impl ManagerSnapshot {
+    pub fn total_subscribers(&self) -> usize {
+        self.channels
+            .iter()
+            .map(|channel| channel.subscribers.len())
+            .sum()
+    }
+
+    pub fn total_pending_messages(&self) -> usize {
+        self.channels
+            .iter()
+            .flat_map(|channel| &channel.subscribers)
+            .map(|subscriber| subscriber.pending_messages)
+            .sum()
+    }
+}
+
+impl Default for ChannelManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A subscription exposed as a [`futures::Stream`] of messages. Created by
+/// [`ChannelManager::subscribe_stream`]; dropping it unsubscribes, so a
+/// `take(n)` or an early-returning consumer cleans up after itself.
+pub struct MessageStream {
+    manager: Weak<ChannelManager>,
+    channel: String,
+    subscriber_id: SubscriberId,
+    receiver: flume::r#async::RecvStream<'static, BinaryMessage>,
+}
+
+impl MessageStream {
+    pub fn subscriber_id(&self) -> SubscriberId {
+        self.subscriber_id
+    }
+}
+
+impl Stream for MessageStream {
+    type Item = BinaryMessage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl Drop for MessageStream {
+    fn drop(&mut self) {
+        if let Some(manager) = self.manager.upgrade() {
+            // The channel may already have been torn down; there is nothing
+            // left to clean up in that case.
+            manager.unsubscribe(&self.channel, self.subscriber_id).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn test_stream_receives_in_order_and_unsubscribes_on_drop() {
+        let manager = Arc::new(ChannelManager::new());
+        let mut stream = manager.subscribe_stream("updates");
+        for byte in 0..3u8 {
+            manager
+                .publish("updates", MessageType::Publish, vec![byte])
+                .unwrap();
+        }
+
+        futures::executor::block_on(async {
+            for byte in 0..3u8 {
+                let message = stream.next().await.unwrap();
+                assert_eq!(message.data, vec![byte]);
+            }
+        });
+
+        assert_eq!(manager.subscriber_count("updates"), 1);
+        drop(stream);
+        assert_eq!(manager.subscriber_count("updates"), 0);
+    }
+
+    #[test]
+    fn test_filtered_stream_only_yields_matching_messages() {
+        let manager = Arc::new(ChannelManager::new());
+        let mut stream = manager.subscribe_filtered_stream(
+            "updates",
+            MessageFilter::MessageType(MessageType::Presence),
+        );
+        manager
+            .publish("updates", MessageType::Publish, vec![1])
+            .unwrap();
+        manager
+            .publish("updates", MessageType::Presence, vec![2])
+            .unwrap();
+
+        let message = futures::executor::block_on(stream.next()).unwrap();
+        assert_eq!(message.data, vec![2]);
+    }
+
+    #[test]
+    fn test_publish_reaches_all_subscribers() {
+        let manager = ChannelManager::new();
+        let (_, first) = manager.subscribe("updates");
+        let (_, second) = manager.subscribe("updates");
+        manager
+            .publish("updates", MessageType::Publish, vec![42])
+            .unwrap();
+        assert_eq!(first.try_recv().unwrap().data, vec![42]);
+        assert_eq!(second.try_recv().unwrap().data, vec![42]);
+    }
+
+    #[test]
+    fn test_unsubscribed_receiver_gets_nothing() {
+        let manager = ChannelManager::new();
+        let (id, receiver) = manager.subscribe("updates");
+        let (_, _other) = manager.subscribe("updates");
+        manager.unsubscribe("updates", id).unwrap();
+        manager
+            .publish("updates", MessageType::Publish, vec![1])
+            .unwrap();
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_filtered_subscription_only_receives_matching_messages() {
+        let manager = ChannelManager::new();
+        let entity_prefix = 7u64.to_le_bytes().to_vec();
+        let (_, receiver) =
+            manager.subscribe_filtered("updates", MessageFilter::PayloadPrefix(entity_prefix));
+
+        let mut for_entity_7 = 7u64.to_le_bytes().to_vec();
+        for_entity_7.push(b'a');
+        let mut for_entity_8 = 8u64.to_le_bytes().to_vec();
+        for_entity_8.push(b'b');
+        manager
+            .publish("updates", MessageType::Publish, for_entity_8)
+            .unwrap();
+        manager
+            .publish("updates", MessageType::Publish, for_entity_7.clone())
+            .unwrap();
+
+        assert_eq!(receiver.try_recv().unwrap().data, for_entity_7);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_composed_filters() {
+        let manager = ChannelManager::new();
+        let filter = MessageFilter::All(vec![
+            MessageFilter::MessageType(MessageType::Publish),
+            MessageFilter::Not(Box::new(MessageFilter::PayloadPrefix(vec![0]))),
+        ]);
+        let (_, receiver) = manager.subscribe_filtered("updates", filter);
+
+        manager
+            .publish("updates", MessageType::Presence, vec![1, 2])
+            .unwrap();
+        manager
+            .publish("updates", MessageType::Publish, vec![0, 2])
+            .unwrap();
+        manager
+            .publish("updates", MessageType::Publish, vec![1, 2])
+            .unwrap();
+
+        assert_eq!(receiver.try_recv().unwrap().data, vec![1, 2]);
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_credits_limit_delivery_and_grant_flushes_in_order() {
+        let manager = ChannelManager::new();
+        let (id, receiver) = manager.subscribe_with_credits("updates", 2);
+        for byte in 0..5u8 {
+            manager
+                .publish("updates", MessageType::Publish, vec![byte])
+                .unwrap();
+        }
+        assert_eq!(receiver.try_recv().unwrap().data, vec![0]);
+        assert_eq!(receiver.try_recv().unwrap().data, vec![1]);
+        assert!(receiver.try_recv().is_err(), "window exhausted");
+
+        let remaining = manager.grant_credits(id, 2).unwrap();
+        assert_eq!(remaining, 0);
+        assert_eq!(receiver.try_recv().unwrap().data, vec![2]);
+        assert_eq!(receiver.try_recv().unwrap().data, vec![3]);
+        assert!(receiver.try_recv().is_err());
+
+        let remaining = manager.grant_credits(id, 5).unwrap();
+        assert_eq!(remaining, 4);
+        assert_eq!(receiver.try_recv().unwrap().data, vec![4]);
+    }
+
+    #[test]
+    fn test_presence_bypasses_exhausted_credits() {
+        let manager = ChannelManager::new();
+        let (_, receiver) = manager.subscribe_with_credits("updates", 0);
+        manager
+            .publish("updates", MessageType::Publish, vec![1])
+            .unwrap();
+        manager
+            .publish("updates", MessageType::Presence, vec![2])
+            .unwrap();
+        let received = receiver.try_recv().unwrap();
+        assert_eq!(received.message_type, MessageType::Presence);
+        assert!(receiver.try_recv().is_err(), "regular message still queued");
+    }
+
+    #[test]
+    fn test_queued_control_messages_drain_before_the_data_backlog() {
+        let manager = ChannelManager::new();
+        let (subscriber_id, receiver) = manager.subscribe_with_credits("updates", 0);
+        for index in 0..10 {
+            manager
+                .publish("updates", MessageType::Publish, vec![index])
+                .unwrap();
+        }
+        // Use up the priority allowance so the next ack has to queue instead
+        // of bypassing the window.
+        for _ in 0..PRIORITY_ALLOWANCE {
+            manager
+                .publish("updates", MessageType::Presence, Vec::new())
+                .unwrap();
+        }
+        while receiver.try_recv().is_ok() {}
+        manager
+            .publish("updates", MessageType::Ack, vec![99])
+            .unwrap();
+        assert!(
+            receiver.try_recv().is_err(),
+            "with the allowance exhausted the ack queues"
+        );
+
+        manager.grant_credits(subscriber_id, 1).unwrap();
+        let first = receiver.try_recv().unwrap();
+        assert_eq!(
+            first.message_type,
+            MessageType::Ack,
+            "the control lane drains ahead of ten queued data messages"
+        );
+        assert!(
+            receiver.try_recv().is_err(),
+            "the data backlog still awaits credits"
+        );
+    }
+
+    #[test]
+    fn test_grant_to_unknown_subscriber_is_an_error() {
+        let manager = ChannelManager::new();
+        let (id, _receiver) = manager.subscribe("updates");
+        assert!(manager.grant_credits(id, 1).is_err());
+        assert!(manager.grant_credits(SubscriberId(999), 1).is_err());
+    }
+
+    #[test]
+    fn test_dropped_receiver_is_pruned_on_publish() {
+        let manager = ChannelManager::new();
+        let (_, dead) = manager.subscribe("updates");
+        let (_, alive) = manager.subscribe("updates");
+        assert_eq!(manager.subscriber_count("updates"), 2);
+
+        drop(dead);
+        manager
+            .publish("updates", MessageType::Publish, vec![1])
+            .unwrap();
+        assert_eq!(
+            manager.subscriber_count("updates"),
+            1,
+            "the dead sender was removed, not just skipped"
+        );
+        assert_eq!(alive.try_recv().unwrap().data, vec![1]);
+    }
+
+    #[test]
+    fn test_publish_to_empty_channel_is_recorded_for_replay() {
+        let manager = ChannelManager::new();
+        let first_id = manager
+            .publish("updates", MessageType::Publish, vec![1])
+            .unwrap();
+        let second_id = manager
+            .publish("updates", MessageType::Publish, vec![2])
+            .unwrap();
+        assert!(second_id > first_id);
+
+        // A late subscriber sees nothing live, but can replay from history.
+        let (_, receiver) = manager.subscribe("updates");
+        assert!(receiver.try_recv().is_err());
+        let history = manager.history("updates");
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].data, vec![1]);
+        assert_eq!(history[1].data, vec![2]);
+
+        let snapshot = manager.snapshot("updates").unwrap();
+        assert_eq!(snapshot.version, Some(second_id));
+    }
+
+    #[test]
+    fn test_publish_requiring_subscribers_fails_on_empty_channel() {
+        let manager = ChannelManager::new();
+        assert!(matches!(
+            manager.publish_requiring_subscribers("updates", MessageType::Publish, vec![1]),
+            Err(SyncError::NoSubscribers(channel)) if channel == "updates"
+        ));
+        assert!(manager.history("updates").is_empty(), "nothing recorded");
+
+        let (_, receiver) = manager.subscribe("updates");
+        manager
+            .publish_requiring_subscribers("updates", MessageType::Publish, vec![2])
+            .unwrap();
+        assert_eq!(receiver.try_recv().unwrap().data, vec![2]);
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let manager = ChannelManager::with_history_limit(2);
+        let (_, _receiver) = manager.subscribe("updates");
+        for byte in 0..5u8 {
+            manager
+                .publish("updates", MessageType::Publish, vec![byte])
+                .unwrap();
+        }
+        let history = manager.history("updates");
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].data, vec![3]);
+        assert_eq!(history[1].data, vec![4]);
+    }
+
+    #[test]
+    fn test_memory_budget_evicts_least_recently_published_histories_first() {
+        let manager = ChannelManager::with_memory_budget(DEFAULT_HISTORY_LIMIT, 2_000);
+        assert_eq!(manager.memory_budget(), Some(2_000));
+        for channel_index in 0..20 {
+            let channel = format!("channel-{channel_index:02}");
+            for byte in 0..4u8 {
+                manager
+                    .publish(&channel, MessageType::Publish, vec![byte; 64])
+                    .unwrap();
+            }
+        }
+
+        assert!(
+            manager.memory_usage() <= 2_000,
+            "usage {} exceeds the budget",
+            manager.memory_usage()
+        );
+        assert_eq!(
+            manager.history("channel-19").len(),
+            4,
+            "the most recently published channel keeps its history"
+        );
+        assert!(
+            manager.history("channel-00").is_empty(),
+            "the least recently published channel was trimmed first"
+        );
+        // Trimmed messages age out like count-limit evictions: a delta from
+        // before them is refused rather than silently incomplete.
+        assert!(matches!(
+            manager.generate_delta("channel-00", 0),
+            Err(SyncError::VersionAgedOut(_))
+        ));
+    }
+
+    #[test]
+    fn test_optimal_delta_sends_only_missed_messages() {
+        let manager = ChannelManager::new();
+        let (_, _receiver) = manager.subscribe("updates");
+        let mut ids = Vec::new();
+        for byte in 0..5u8 {
+            ids.push(
+                manager
+                    .publish("updates", MessageType::Publish, vec![byte])
+                    .unwrap(),
+            );
+        }
+
+        match manager.generate_optimal_delta("updates", ids[2]).unwrap() {
+            DeltaOrSnapshot::Delta(delta) => {
+                assert_eq!(delta.base_version, ids[2]);
+                let data: Vec<_> = delta.messages.iter().map(|message| &message.data).collect();
+                assert_eq!(data, vec![&vec![3], &vec![4]]);
+            }
+            other => panic!("expected a delta, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_aged_out_version_falls_back_to_snapshot() {
+        let manager = ChannelManager::with_history_limit(2);
+        let (_, _receiver) = manager.subscribe("updates");
+        let mut ids = Vec::new();
+        for byte in 0..5u8 {
+            ids.push(
+                manager
+                    .publish("updates", MessageType::Publish, vec![byte])
+                    .unwrap(),
+            );
+        }
+
+        assert!(matches!(
+            manager.generate_delta("updates", ids[0]),
+            Err(SyncError::VersionAgedOut(_))
+        ));
+        match manager.generate_optimal_delta("updates", ids[0]).unwrap() {
+            DeltaOrSnapshot::Snapshot(snapshot) => {
+                assert_eq!(snapshot.version, Some(ids[4]));
+                let data: Vec<_> = snapshot
+                    .messages
+                    .iter()
+                    .map(|message| &message.data)
+                    .collect();
+                assert_eq!(data, vec![&vec![3], &vec![4]]);
+            }
+            other => panic!("expected a snapshot, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_inspect_reports_subscribers_and_buffer_depths() {
+        let manager = ChannelManager::new();
+        let (_, _plain) = manager.subscribe("updates");
+        let (stuck, _stuck_receiver) = manager.subscribe_with_credits("updates", 1);
+        let (_, _presence) = manager.subscribe("presence");
+        for byte in 0..4u8 {
+            manager
+                .publish("updates", MessageType::Publish, vec![byte])
+                .unwrap();
+        }
+
+        let snapshot = manager.inspect();
+        assert_eq!(snapshot.total_subscribers(), 3);
+        assert_eq!(snapshot.total_pending_messages(), 3);
+
+        let names: Vec<&str> = snapshot
+            .channels
+            .iter()
+            .map(|channel| channel.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["presence", "updates"]);
+
+        let updates = &snapshot.channels[1];
+        assert_eq!(updates.history_len, 4);
+        let stuck_stats = updates
+            .subscribers
+            .iter()
+            .find(|subscriber| subscriber.id == stuck)
+            .unwrap();
+        assert_eq!(stuck_stats.pending_messages, 3);
+        assert_eq!(stuck_stats.remaining_credits, Some(0));
+        assert!(
+            updates
+                .subscribers
+                .iter()
+                .any(|subscriber| subscriber.remaining_credits.is_none())
+        );
+    }
+
+    #[test]
+    fn test_channel_byte_counters_match_encoded_frame_sizes() {
+        let manager = ChannelManager::new();
+        let (_, _first) = manager.subscribe("updates");
+        let (_, _second) = manager.subscribe("updates");
+        // Never matches, so this subscriber receives no bytes.
+        let (_, _filtered) = manager
+            .subscribe_filtered("updates", MessageFilter::MessageType(MessageType::Presence));
+        for len in [1usize, 16, 200] {
+            manager
+                .publish("updates", MessageType::Publish, vec![7; len])
+                .unwrap();
+        }
+
+        let frame_bytes: u64 = manager
+            .history("updates")
+            .iter()
+            .map(|message| crate::encoded_len(message) as u64)
+            .sum();
+        let metrics = manager.metrics();
+        let totals = metrics.channel_bytes("updates");
+        assert_eq!(totals.received, frame_bytes);
+        assert_eq!(
+            totals.sent,
+            frame_bytes * 2,
+            "every frame reached the two unfiltered subscribers and no one else"
+        );
+        assert_eq!(
+            metrics.channel_bytes("elsewhere"),
+            crate::ByteTotals::default()
+        );
+    }
+
+    #[test]
+    fn test_delta_spanning_full_history_becomes_a_snapshot() {
+        let manager = ChannelManager::new();
+        let (_, _other) = manager.subscribe("other");
+        let (_, _receiver) = manager.subscribe("updates");
+        // Advance the global id counter so "updates" starts past id 0.
+        let base = manager
+            .publish("other", MessageType::Publish, vec![0])
+            .unwrap();
+        for byte in 0..3u8 {
+            manager
+                .publish("updates", MessageType::Publish, vec![byte])
+                .unwrap();
+        }
+
+        // The client missed everything but nothing was evicted: the delta
+        // would equal the whole history, so a snapshot is returned instead.
+        match manager.generate_optimal_delta("updates", base).unwrap() {
+            DeltaOrSnapshot::Snapshot(snapshot) => assert_eq!(snapshot.messages.len(), 3),
+            other => panic!("expected a snapshot, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{BinaryMessage, ChannelManager, MessageType, SubscriberId, SyncError};
+use serde::{Serialize, de::DeserializeOwned};
+use std::any::type_name;
+use std::marker::PhantomData;
+
+/// Converts typed values to and from the raw bytes carried by
+/// [`BinaryMessage::data`], so publishers and subscribers agree on one
+/// serialization format instead of each call site rolling its own.
+pub trait Codec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, SyncError>;
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, SyncError>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BincodeCodec;
+
+impl Codec for BincodeCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, SyncError> {
+        bincode::serialize(value).map_err(|error| encode_error::<T>(&error))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, SyncError> {
+        bincode::deserialize(bytes).map_err(|error| decode_error::<T>(&error))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonCodec;
+
+impl Codec for JsonCodec {
+    fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>, SyncError> {
+        serde_json::to_vec(value).map_err(|error| encode_error::<T>(&error))
+    }
+
+    fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, SyncError> {
+        serde_json::from_slice(bytes).map_err(|error| decode_error::<T>(&error))
+    }
+}
+
+fn encode_error<T>(error: &dyn std::error::Error) -> SyncError {
+    SyncError::InvalidMessage(format!("failed to encode {}: {error}", type_name::<T>()))
+}
+
+fn decode_error<T>(error: &dyn std::error::Error) -> SyncError {
+    SyncError::InvalidMessage(format!("failed to decode {}: {error}", type_name::<T>()))
+}
+
+/// A subscription whose payloads are decoded with a [`Codec`] before being
+/// handed to the caller.
+pub struct TypedReceiver<T, C> {
+    receiver: flume::Receiver<BinaryMessage>,
+    codec: C,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned, C: Codec> TypedReceiver<T, C> {
+    pub fn recv(&self) -> Result<T, SyncError> {
+        let message = self
+            .receiver
+            .recv()
+            .map_err(|_| SyncError::ConnectionClosed)?;
+        self.codec.decode(&message.data)
+    }
+
+    pub fn try_recv(&self) -> Result<Option<T>, SyncError> {
+        match self.receiver.try_recv() {
+            Ok(message) => self.codec.decode(&message.data).map(Some),
+            Err(flume::TryRecvError::Empty) => Ok(None),
+            Err(flume::TryRecvError::Disconnected) => Err(SyncError::ConnectionClosed),
+        }
+    }
+}
+
+impl ChannelManager {
+    pub fn publish_typed<T: Serialize, C: Codec>(
+        &self,
+        channel: &str,
+        codec: &C,
+        value: &T,
+    ) -> Result<u64, SyncError> {
+        let data = codec.encode(value)?;
+        self.publish(channel, MessageType::Publish, data)
+    }
+
+    pub fn subscribe_typed<T: DeserializeOwned, C: Codec>(
+        &self,
+        channel: &str,
+        codec: C,
+    ) -> (SubscriberId, TypedReceiver<T, C>) {
+        let (id, receiver) = self.subscribe(channel);
+        (
+            id,
+            TypedReceiver {
+                receiver,
+                codec,
+                _value: PhantomData,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Update {
+        revision: u64,
+        path: String,
+    }
+
+    #[test]
+    fn test_typed_round_trip_bincode() {
+        let manager = ChannelManager::new();
+        let (_, receiver) = manager.subscribe_typed::<Update, _>("updates", BincodeCodec);
+        let update = Update {
+            revision: 3,
+            path: "src/main.rs".into(),
+        };
+        manager
+            .publish_typed("updates", &BincodeCodec, &update)
+            .unwrap();
+        assert_eq!(receiver.recv().unwrap(), update);
+    }
+
+    #[test]
+    fn test_typed_round_trip_json() {
+        let manager = ChannelManager::new();
+        let (_, receiver) = manager.subscribe_typed::<Update, _>("updates", JsonCodec);
+        let update = Update {
+            revision: 9,
+            path: "Cargo.toml".into(),
+        };
+        manager
+            .publish_typed("updates", &JsonCodec, &update)
+            .unwrap();
+        assert_eq!(receiver.recv().unwrap(), update);
+    }
+
+    #[test]
+    fn test_decode_error_names_the_type() {
+        let manager = ChannelManager::new();
+        let (_, receiver) = manager.subscribe_typed::<Update, _>("updates", JsonCodec);
+        manager
+            .publish("updates", MessageType::Publish, b"not json".to_vec())
+            .unwrap();
+        match receiver.recv() {
+            Err(SyncError::InvalidMessage(message)) => {
+                assert!(message.contains("Update"), "unexpected message: {message}")
+            }
+            other => panic!("expected InvalidMessage, got {other:?}"),
+        }
+    }
+}
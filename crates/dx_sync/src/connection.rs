@@ -0,0 +1,537 @@
+use crate::{BinaryMessage, MessagePriority, SyncError, SyncMetrics};
+use collections::HashMap;
+use parking_lot::RwLock;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_BASE_RETRY_DELAY: Duration = Duration::from_millis(500);
+pub const DEFAULT_MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// How long a connection must stay up before it counts as stable and the
+/// retry backoff resets. A connection that drops sooner keeps the growing
+/// delay: resetting on connect would let a flapping server be hammered at
+/// the minimum interval forever.
+pub const DEFAULT_STABILITY_THRESHOLD: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Disconnected,
+    Connected,
+}
+
+/// Reconnect state for one websocket connection: whether it is up, how many
+/// times in a row it has failed, and the exponential delay before the next
+/// attempt.
+///
+/// Callers pass the current [`Instant`] into the transition methods rather
+/// than the connection reading the clock itself, so reconnect policies stay
+/// deterministic under test.
+#[derive(Debug)]
+pub struct WebSocketConnection {
+    state: ConnectionState,
+    retry_count: u32,
+    connected_at: Option<Instant>,
+    base_retry_delay: Duration,
+    max_retry_delay: Duration,
+    stability_threshold: Duration,
+}
+
+impl WebSocketConnection {
+    pub fn new() -> Self {
+        Self::with_backoff(
+            DEFAULT_BASE_RETRY_DELAY,
+            DEFAULT_MAX_RETRY_DELAY,
+            DEFAULT_STABILITY_THRESHOLD,
+        )
+    }
+
+    pub fn with_backoff(
+        base_retry_delay: Duration,
+        max_retry_delay: Duration,
+        stability_threshold: Duration,
+    ) -> Self {
+        Self {
+            state: ConnectionState::Disconnected,
+            retry_count: 0,
+            connected_at: None,
+            base_retry_delay,
+            max_retry_delay,
+            stability_threshold,
+        }
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.state
+    }
+
+    pub fn retry_count(&self) -> u32 {
+        self.retry_count
+    }
+
+    /// Records a successful connect. The retry count is deliberately left
+    /// alone here: whether the backoff resets is decided on the next
+    /// disconnect, once we know how long the connection actually lasted.
+    pub fn set_connected(&mut self, now: Instant) {
+        self.state = ConnectionState::Connected;
+        self.connected_at = Some(now);
+    }
+
+    /// Records a disconnect. A connection that stayed up at least the
+    /// stability threshold starts the backoff over from one failure; one
+    /// that flapped sooner keeps growing the existing backoff.
+    pub fn set_disconnected(&mut self, now: Instant) {
+        let was_stable = self.connected_at.is_some_and(|connected_at| {
+            now.saturating_duration_since(connected_at) >= self.stability_threshold
+        });
+        self.state = ConnectionState::Disconnected;
+        self.connected_at = None;
+        if was_stable {
+            self.retry_count = 1;
+        } else {
+            self.retry_count = self.retry_count.saturating_add(1);
+        }
+    }
+
+    /// The delay before the next reconnect attempt: the base delay doubled
+    /// per consecutive failure, capped at the configured maximum.
+    pub fn next_retry_delay(&self) -> Duration {
+        let exponent = self.retry_count.saturating_sub(1).min(31);
+        self.base_retry_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_retry_delay)
+    }
+}
+
+impl Default for WebSocketConnection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ConnectionId(pub u64);
+
+/// Claims of an already-verified auth token. Signature checking happens
+/// upstream; the manager only consults the subject to tie a socket to a
+/// user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserToken {
+    /// The authenticated user id (the token's `sub` claim).
+    pub sub: String,
+}
+
+/// Server-side registry of logical sessions, one per connected socket: who
+/// the user is, which channels they subscribed to, which presence channels
+/// they joined, and messages buffered while the socket catches up.
+pub struct WebSocketManager {
+    connections: RwLock<HashMap<u64, ManagedConnection>>,
+    next_connection_id: AtomicU64,
+    /// Per-connection wire-byte counters; outbound frames are counted as
+    /// they drain in [`take_buffered`](Self::take_buffered), inbound frames
+    /// by the socket read loop via
+    /// [`SyncMetrics::record_connection_received`].
+    metrics: Arc<SyncMetrics>,
+}
+
+struct ManagedConnection {
+    user_id: String,
+    subscriptions: Vec<String>,
+    presence_channels: Vec<String>,
+    /// Messages buffered while the socket catches up, one lane per
+    /// [`MessagePriority`]; the control lane drains first.
+    buffered_control: VecDeque<BinaryMessage>,
+    buffered: VecDeque<BinaryMessage>,
+}
+
+impl WebSocketManager {
+    pub fn new() -> Self {
+        Self::with_metrics(Arc::new(SyncMetrics::new()))
+    }
+
+    /// Like [`new`](Self::new), but feeding an existing registry, so one
+    /// [`SyncMetrics`] can aggregate this manager's connections alongside a
+    /// [`ChannelManager`](crate::ChannelManager)'s channels.
+    pub fn with_metrics(metrics: Arc<SyncMetrics>) -> Self {
+        Self {
+            connections: RwLock::new(HashMap::default()),
+            next_connection_id: AtomicU64::new(0),
+            metrics,
+        }
+    }
+
+    /// The byte-counter registry this manager feeds; see [`SyncMetrics`].
+    pub fn metrics(&self) -> Arc<SyncMetrics> {
+        self.metrics.clone()
+    }
+
+    pub fn connect(&self, token: &UserToken) -> ConnectionId {
+        let id = ConnectionId(self.next_connection_id.fetch_add(1, Ordering::Relaxed));
+        self.connections.write().insert(
+            id.0,
+            ManagedConnection {
+                user_id: token.sub.clone(),
+                subscriptions: Vec::new(),
+                presence_channels: Vec::new(),
+                buffered_control: VecDeque::new(),
+                buffered: VecDeque::new(),
+            },
+        );
+        id
+    }
+
+    pub fn disconnect(&self, connection_id: ConnectionId) -> Result<(), SyncError> {
+        self.connections
+            .write()
+            .remove(&connection_id.0)
+            .map(|_| ())
+            .ok_or(SyncError::ConnectionNotFound(connection_id.0))
+    }
+
+    pub fn subscribe(&self, connection_id: ConnectionId, channel: &str) -> Result<(), SyncError> {
+        self.update_connection(connection_id, |connection| {
+            if !connection.subscriptions.iter().any(|name| name == channel) {
+                connection.subscriptions.push(channel.to_string());
+            }
+        })
+    }
+
+    pub fn join_presence(
+        &self,
+        connection_id: ConnectionId,
+        channel: &str,
+    ) -> Result<(), SyncError> {
+        self.update_connection(connection_id, |connection| {
+            if !connection
+                .presence_channels
+                .iter()
+                .any(|name| name == channel)
+            {
+                connection.presence_channels.push(channel.to_string());
+            }
+        })
+    }
+
+    /// Queues a message for a connection whose socket is not ready to take
+    /// it, routed into the lane for its type's priority; order within a lane
+    /// is preserved until [`take_buffered`](Self::take_buffered).
+    pub fn buffer_message(
+        &self,
+        connection_id: ConnectionId,
+        message: BinaryMessage,
+    ) -> Result<(), SyncError> {
+        self.update_connection(connection_id, |connection| {
+            match message.message_type.priority() {
+                MessagePriority::Control => connection.buffered_control.push_back(message),
+                MessagePriority::Normal => connection.buffered.push_back(message),
+            }
+        })
+    }
+
+    /// Drains the connection's buffered messages: the control lane first, so
+    /// presence and acks are delivered ahead of any data backlog, then each
+    /// lane in arrival order.
+    pub fn take_buffered(
+        &self,
+        connection_id: ConnectionId,
+    ) -> Result<Vec<BinaryMessage>, SyncError> {
+        let mut connections = self.connections.write();
+        let connection = connections
+            .get_mut(&connection_id.0)
+            .ok_or(SyncError::ConnectionNotFound(connection_id.0))?;
+        let drained: Vec<BinaryMessage> = connection
+            .buffered_control
+            .drain(..)
+            .chain(connection.buffered.drain(..))
+            .collect();
+        let frame_bytes: u64 = drained
+            .iter()
+            .map(|message| crate::encoded_len(message) as u64)
+            .sum();
+        self.metrics
+            .record_connection_sent(connection_id, frame_bytes);
+        Ok(drained)
+    }
+
+    pub fn user_id(&self, connection_id: ConnectionId) -> Result<String, SyncError> {
+        self.read_connection(connection_id, |connection| connection.user_id.clone())
+    }
+
+    pub fn subscriptions(&self, connection_id: ConnectionId) -> Result<Vec<String>, SyncError> {
+        self.read_connection(connection_id, |connection| connection.subscriptions.clone())
+    }
+
+    pub fn presence_channels(&self, connection_id: ConnectionId) -> Result<Vec<String>, SyncError> {
+        self.read_connection(connection_id, |connection| {
+            connection.presence_channels.clone()
+        })
+    }
+
+    /// Moves a logical session onto a fresh socket: the new connection takes
+    /// over the old one's subscriptions, presence channels, and buffered
+    /// messages, so a client that changed networks resumes without a
+    /// resubscribe round-trip.
+    ///
+    /// The new socket's token must be for the same user as the old
+    /// connection; a mismatch fails with [`SyncError::SubjectMismatch`] and
+    /// leaves the old connection untouched. The swap happens under one write
+    /// lock, so there is no window where the session exists under neither
+    /// id.
+    pub fn migrate(
+        &self,
+        old_connection_id: ConnectionId,
+        new_user_token: &UserToken,
+    ) -> Result<ConnectionId, SyncError> {
+        let mut connections = self.connections.write();
+        let connection = connections
+            .get(&old_connection_id.0)
+            .ok_or(SyncError::ConnectionNotFound(old_connection_id.0))?;
+        if connection.user_id != new_user_token.sub {
+            return Err(SyncError::SubjectMismatch {
+                token_subject: new_user_token.sub.clone(),
+                connection_user: connection.user_id.clone(),
+            });
+        }
+        let connection = connections
+            .remove(&old_connection_id.0)
+            .ok_or(SyncError::ConnectionNotFound(old_connection_id.0))?;
+        let new_id = ConnectionId(self.next_connection_id.fetch_add(1, Ordering::Relaxed));
+        connections.insert(new_id.0, connection);
+        Ok(new_id)
+    }
+
+    fn read_connection<T>(
+        &self,
+        connection_id: ConnectionId,
+        read: impl FnOnce(&ManagedConnection) -> T,
+    ) -> Result<T, SyncError> {
+        let connections = self.connections.read();
+        let connection = connections
+            .get(&connection_id.0)
+            .ok_or(SyncError::ConnectionNotFound(connection_id.0))?;
+        Ok(read(connection))
+    }
+
+    fn update_connection(
+        &self,
+        connection_id: ConnectionId,
+        update: impl FnOnce(&mut ManagedConnection),
+    ) -> Result<(), SyncError> {
+        let mut connections = self.connections.write();
+        let connection = connections
+            .get_mut(&connection_id.0)
+            .ok_or(SyncError::ConnectionNotFound(connection_id.0))?;
+        update(connection);
+        Ok(())
+    }
+}
+
+impl Default for WebSocketManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MessageType;
+
+    fn connection() -> WebSocketConnection {
+        WebSocketConnection::with_backoff(
+            Duration::from_millis(100),
+            Duration::from_secs(5),
+            Duration::from_secs(10),
+        )
+    }
+
+    #[test]
+    fn test_rapid_flapping_keeps_increasing_backoff() {
+        let mut connection = connection();
+        let start = Instant::now();
+        let mut now = start;
+        let mut previous_delay = Duration::ZERO;
+        for _ in 0..5 {
+            connection.set_connected(now);
+            // Drops well inside the stability threshold.
+            now += Duration::from_millis(50);
+            connection.set_disconnected(now);
+            let delay = connection.next_retry_delay();
+            assert!(
+                delay > previous_delay,
+                "flapping must keep backing off, got {delay:?} after {previous_delay:?}"
+            );
+            previous_delay = delay;
+            now += delay;
+        }
+        assert_eq!(connection.retry_count(), 5);
+    }
+
+    #[test]
+    fn test_stable_connection_resets_backoff() {
+        let mut connection = connection();
+        let mut now = Instant::now();
+        for _ in 0..4 {
+            connection.set_connected(now);
+            now += Duration::from_millis(10);
+            connection.set_disconnected(now);
+        }
+        assert_eq!(connection.retry_count(), 4);
+
+        connection.set_connected(now);
+        now += Duration::from_secs(10);
+        connection.set_disconnected(now);
+        assert_eq!(connection.retry_count(), 1);
+        assert_eq!(connection.next_retry_delay(), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_connect_alone_does_not_reset_backoff() {
+        let mut connection = connection();
+        let mut now = Instant::now();
+        for _ in 0..3 {
+            connection.set_connected(now);
+            now += Duration::from_millis(10);
+            connection.set_disconnected(now);
+        }
+        connection.set_connected(now);
+        assert_eq!(connection.state(), ConnectionState::Connected);
+        assert_eq!(
+            connection.retry_count(),
+            3,
+            "reset is earned by staying up, not by connecting"
+        );
+    }
+
+    #[test]
+    fn test_migration_preserves_the_session_under_a_new_id() {
+        let manager = WebSocketManager::new();
+        let token = UserToken {
+            sub: "user-1".to_string(),
+        };
+        let old_id = manager.connect(&token);
+        manager.subscribe(old_id, "documents").unwrap();
+        manager.subscribe(old_id, "chat").unwrap();
+        manager.join_presence(old_id, "chat").unwrap();
+        manager
+            .buffer_message(
+                old_id,
+                BinaryMessage::new(7, "chat", MessageType::Publish, vec![1, 2]),
+            )
+            .unwrap();
+
+        let new_id = manager.migrate(old_id, &token).unwrap();
+        assert_ne!(new_id, old_id);
+        assert!(matches!(
+            manager.subscriptions(old_id),
+            Err(SyncError::ConnectionNotFound(_))
+        ));
+        assert_eq!(
+            manager.subscriptions(new_id).unwrap(),
+            vec!["documents", "chat"]
+        );
+        assert_eq!(manager.presence_channels(new_id).unwrap(), vec!["chat"]);
+        assert_eq!(manager.user_id(new_id).unwrap(), "user-1");
+        let buffered = manager.take_buffered(new_id).unwrap();
+        assert_eq!(buffered.len(), 1);
+        assert_eq!(buffered[0].data, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_migration_with_a_different_subject_is_rejected() {
+        let manager = WebSocketManager::new();
+        let old_id = manager.connect(&UserToken {
+            sub: "user-1".to_string(),
+        });
+        manager.subscribe(old_id, "documents").unwrap();
+
+        let stolen = UserToken {
+            sub: "user-2".to_string(),
+        };
+        assert!(matches!(
+            manager.migrate(old_id, &stolen),
+            Err(SyncError::SubjectMismatch { token_subject, connection_user })
+                if token_subject == "user-2" && connection_user == "user-1"
+        ));
+        // The old connection is untouched by the failed migration.
+        assert_eq!(manager.subscriptions(old_id).unwrap(), vec!["documents"]);
+    }
+
+    #[test]
+    fn test_buffered_control_messages_bypass_the_data_backlog() {
+        let manager = WebSocketManager::new();
+        let id = manager.connect(&UserToken {
+            sub: "user-1".to_string(),
+        });
+        for index in 0..50u64 {
+            manager
+                .buffer_message(
+                    id,
+                    BinaryMessage::new(index, "data", MessageType::Publish, vec![0; 32]),
+                )
+                .unwrap();
+        }
+        manager
+            .buffer_message(
+                id,
+                BinaryMessage::new(50, "presence", MessageType::Control, vec![1]),
+            )
+            .unwrap();
+
+        let drained = manager.take_buffered(id).unwrap();
+        assert_eq!(drained.len(), 51);
+        assert_eq!(
+            drained[0].message_type,
+            MessageType::Control,
+            "the control message jumps the backlog"
+        );
+        let data_ids: Vec<u64> = drained[1..].iter().map(|message| message.id).collect();
+        assert_eq!(
+            data_ids,
+            (0..50).collect::<Vec<u64>>(),
+            "the data lane keeps arrival order"
+        );
+    }
+
+    #[test]
+    fn test_take_buffered_counts_outbound_bytes_per_connection() {
+        let manager = WebSocketManager::new();
+        let id = manager.connect(&UserToken {
+            sub: "user-1".to_string(),
+        });
+        let other = manager.connect(&UserToken {
+            sub: "user-2".to_string(),
+        });
+        let messages = [
+            BinaryMessage::new(0, "chat", MessageType::Publish, vec![0; 8]),
+            BinaryMessage::new(1, "chat", MessageType::Control, vec![1; 3]),
+        ];
+        let mut frame_bytes = 0u64;
+        for message in &messages {
+            frame_bytes += crate::encoded_len(message) as u64;
+            manager.buffer_message(id, message.clone()).unwrap();
+        }
+        manager.take_buffered(id).unwrap();
+
+        let metrics = manager.metrics();
+        assert_eq!(metrics.connection_bytes(id).sent, frame_bytes);
+        assert_eq!(
+            metrics.connection_bytes(other).sent,
+            0,
+            "the idle connection billed nothing"
+        );
+    }
+
+    #[test]
+    fn test_retry_delay_is_capped() {
+        let mut connection = connection();
+        let mut now = Instant::now();
+        for _ in 0..40 {
+            connection.set_connected(now);
+            now += Duration::from_millis(1);
+            connection.set_disconnected(now);
+        }
+        assert_eq!(connection.next_retry_delay(), Duration::from_secs(5));
+    }
+}
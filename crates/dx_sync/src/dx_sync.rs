@@ -0,0 +1,52 @@
+//! Real-time synchronization of binary messages over named channels.
+//!
+//! Publishers push [`BinaryMessage`]s into a channel on the [`ChannelManager`];
+//! subscribers receive them over a [`flume`] receiver. Payloads are raw bytes
+//! on the wire, with optional typed encoding via [`Codec`].
+
+mod channel;
+mod codec;
+mod connection;
+mod message;
+mod metrics;
+mod presence;
+mod session;
+mod signing;
+
+pub use channel::*;
+pub use codec::*;
+pub use connection::*;
+pub use message::*;
+pub use metrics::*;
+pub use presence::*;
+pub use session::*;
+pub use signing::*;
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SyncError {
+    #[error("channel not found: {0}")]
+    ChannelNotFound(String),
+    #[error("invalid message: {0}")]
+    InvalidMessage(String),
+    #[error("channel {0} has no subscribers")]
+    NoSubscribers(String),
+    #[error("no flow-controlled subscriber with id {0}")]
+    SubscriberNotFound(u64),
+    #[error("version {0} has aged out of channel history")]
+    VersionAgedOut(u64),
+    #[error("message on channel {0} failed signature verification")]
+    SignatureRejected(String),
+    #[error("frame on channel {0} failed MAC verification")]
+    FrameRejected(String),
+    #[error("connection closed")]
+    ConnectionClosed,
+    #[error("connection not found: {0}")]
+    ConnectionNotFound(u64),
+    #[error("token subject {token_subject} does not match connection user {connection_user}")]
+    SubjectMismatch {
+        token_subject: String,
+        connection_user: String,
+    },
+}
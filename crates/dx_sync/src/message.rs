@@ -0,0 +1,233 @@
+use crate::SyncError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum MessageType {
+    Publish = 0,
+    Subscribe = 1,
+    Unsubscribe = 2,
+    Ack = 3,
+    Presence = 4,
+    Control = 5,
+}
+
+/// Delivery lane for a message; see [`MessageType::priority`]. Declared
+/// lowest lane first so `Control > Normal` holds under the derived ordering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MessagePriority {
+    Normal,
+    Control,
+}
+
+impl MessageType {
+    /// The lane this message type is delivered on. Everything that is not
+    /// payload data — presence, acks, subscription changes, control frames —
+    /// outranks [`Publish`](Self::Publish), so the control plane stays
+    /// responsive while a data channel is saturated.
+    pub fn priority(self) -> MessagePriority {
+        match self {
+            Self::Publish => MessagePriority::Normal,
+            Self::Subscribe | Self::Unsubscribe | Self::Ack | Self::Presence | Self::Control => {
+                MessagePriority::Control
+            }
+        }
+    }
+
+    fn from_u8(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Publish),
+            1 => Some(Self::Subscribe),
+            2 => Some(Self::Unsubscribe),
+            3 => Some(Self::Ack),
+            4 => Some(Self::Presence),
+            5 => Some(Self::Control),
+            _ => None,
+        }
+    }
+}
+
+/// A single message on the wire: a numeric id assigned by the publisher side,
+/// the channel it belongs to, and an opaque payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BinaryMessage {
+    pub id: u64,
+    pub channel: String,
+    pub message_type: MessageType,
+    pub data: Vec<u8>,
+}
+
+impl BinaryMessage {
+    pub fn new(
+        id: u64,
+        channel: impl Into<String>,
+        message_type: MessageType,
+        data: Vec<u8>,
+    ) -> Self {
+        Self {
+            id,
+            channel: channel.into(),
+            message_type,
+            data,
+        }
+    }
+}
+
+/// Wire size of [`encode_message`]'s output for this message, without
+/// encoding it.
+pub fn encoded_len(message: &BinaryMessage) -> usize {
+    8 + 1 + 2 + message.channel.len() + 4 + message.data.len()
+}
+
+/// Encodes a message as: id (u64 LE), type (u8), channel length (u16 LE),
+/// channel bytes, data length (u32 LE), data bytes.
+pub fn encode_message(message: &BinaryMessage) -> Result<Vec<u8>, SyncError> {
+    let channel_bytes = message.channel.as_bytes();
+    if channel_bytes.len() > u16::MAX as usize {
+        return Err(SyncError::InvalidMessage(format!(
+            "channel name too long: {} bytes",
+            channel_bytes.len()
+        )));
+    }
+    if message.data.len() > u32::MAX as usize {
+        return Err(SyncError::InvalidMessage(format!(
+            "payload too large: {} bytes",
+            message.data.len()
+        )));
+    }
+    let mut bytes = Vec::with_capacity(encoded_len(message));
+    bytes.extend_from_slice(&message.id.to_le_bytes());
+    bytes.push(message.message_type as u8);
+    bytes.extend_from_slice(&(channel_bytes.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(channel_bytes);
+    bytes.extend_from_slice(&(message.data.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&message.data);
+    Ok(bytes)
+}
+
+/// Ceiling applied by [`decode_message`]; use
+/// [`decode_message_with_limit`] to tighten or relax it.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+pub fn decode_message(bytes: &[u8]) -> Result<BinaryMessage, SyncError> {
+    decode_message_with_limit(bytes, DEFAULT_MAX_FRAME_SIZE)
+}
+
+/// Decodes a frame, rejecting any declared length that would push the frame
+/// past `max_frame_size` *before* allocating for the payload, so a malicious
+/// length field can't trigger a huge allocation.
+pub fn decode_message_with_limit(
+    bytes: &[u8],
+    max_frame_size: usize,
+) -> Result<BinaryMessage, SyncError> {
+    let mut cursor = Cursor { bytes, offset: 0 };
+    let id = u64::from_le_bytes(cursor.take_array()?);
+    let type_byte = cursor.take_array::<1>()?[0];
+    let message_type = MessageType::from_u8(type_byte)
+        .ok_or_else(|| SyncError::InvalidMessage(format!("unknown message type {type_byte}")))?;
+    let channel_len = u16::from_le_bytes(cursor.take_array()?) as usize;
+    check_declared_length(cursor.offset, channel_len, max_frame_size)?;
+    let channel = String::from_utf8(cursor.take_slice(channel_len)?.to_vec())
+        .map_err(|_| SyncError::InvalidMessage("channel name is not valid UTF-8".into()))?;
+    let data_len = u32::from_le_bytes(cursor.take_array()?) as usize;
+    check_declared_length(cursor.offset, data_len, max_frame_size)?;
+    let data = cursor.take_slice(data_len)?.to_vec();
+    if cursor.offset != bytes.len() {
+        return Err(SyncError::InvalidMessage(format!(
+            "{} trailing bytes after message",
+            bytes.len() - cursor.offset
+        )));
+    }
+    Ok(BinaryMessage {
+        id,
+        channel,
+        message_type,
+        data,
+    })
+}
+
+fn check_declared_length(
+    offset: usize,
+    declared: usize,
+    max_frame_size: usize,
+) -> Result<(), SyncError> {
+    let total = offset.checked_add(declared);
+    if total.is_none_or(|total| total > max_frame_size) {
+        return Err(SyncError::InvalidMessage(format!(
+            "declared length {declared} exceeds max frame size {max_frame_size}"
+        )));
+    }
+    Ok(())
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take_slice(&mut self, len: usize) -> Result<&'a [u8], SyncError> {
+        let end = self.offset.checked_add(len).ok_or_else(truncated)?;
+        let slice = self.bytes.get(self.offset..end).ok_or_else(truncated)?;
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], SyncError> {
+        let slice = self.take_slice(N)?;
+        slice.try_into().map_err(|_| truncated())
+    }
+}
+
+fn truncated() -> SyncError {
+    SyncError::InvalidMessage("truncated frame".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let message = BinaryMessage::new(7, "updates", MessageType::Publish, vec![1, 2, 3]);
+        let encoded = encode_message(&message).unwrap();
+        let decoded = decode_message(&encoded).unwrap();
+        assert_eq!(decoded, message);
+    }
+
+    #[test]
+    fn test_absurd_declared_length_is_rejected_before_allocation() {
+        // Header claiming a ~4GB payload with no payload bytes behind it.
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&1u64.to_le_bytes());
+        frame.push(MessageType::Publish as u8);
+        frame.extend_from_slice(&2u16.to_le_bytes());
+        frame.extend_from_slice(b"ch");
+        frame.extend_from_slice(&u32::MAX.to_le_bytes());
+        match decode_message(&frame) {
+            Err(SyncError::InvalidMessage(message)) => {
+                assert!(message.contains("max frame size"), "got: {message}")
+            }
+            other => panic!("expected InvalidMessage, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_frame_within_custom_limit_decodes() {
+        let message = BinaryMessage::new(1, "ch", MessageType::Publish, vec![0; 64]);
+        let encoded = encode_message(&message).unwrap();
+        assert!(decode_message_with_limit(&encoded, 32).is_err());
+        assert_eq!(decode_message_with_limit(&encoded, 1024).unwrap(), message);
+    }
+
+    #[test]
+    fn test_truncated_frame_is_rejected() {
+        let message = BinaryMessage::new(7, "updates", MessageType::Publish, vec![1, 2, 3]);
+        let encoded = encode_message(&message).unwrap();
+        for len in 0..encoded.len() {
+            assert!(
+                decode_message(&encoded[..len]).is_err(),
+                "prefix of length {len} should not decode"
+            );
+        }
+    }
+}
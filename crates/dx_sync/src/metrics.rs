@@ -0,0 +1,186 @@
+//! Wire-byte accounting for billing and capacity planning: total bytes sent
+//! and received, broken down per channel and per connection, measured as
+//! encoded frame sizes. The [`ChannelManager`](crate::ChannelManager) counts
+//! publishes and deliveries; [`WebSocketManager`](crate::WebSocketManager)
+//! counts frames drained to a socket. The socket read loop lives outside
+//! this crate, so inbound connection bytes are recorded by its owner via
+//! [`SyncMetrics::record_connection_received`].
+
+use crate::ConnectionId;
+use collections::HashMap;
+use parking_lot::RwLock;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Bytes sent and received for one channel or connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ByteTotals {
+    pub sent: u64,
+    pub received: u64,
+}
+
+#[derive(Default)]
+struct ByteCounters {
+    sent: AtomicU64,
+    received: AtomicU64,
+}
+
+impl ByteCounters {
+    fn totals(&self) -> ByteTotals {
+        ByteTotals {
+            sent: self.sent.load(Ordering::Relaxed),
+            received: self.received.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Shared byte-counter registry. Recording on the hot path is a map lookup
+/// under a read lock plus a relaxed atomic add; the write lock is taken only
+/// the first time a channel or connection is seen.
+#[derive(Default)]
+pub struct SyncMetrics {
+    channels: RwLock<HashMap<String, Arc<ByteCounters>>>,
+    connections: RwLock<HashMap<u64, Arc<ByteCounters>>>,
+}
+
+impl SyncMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_channel_sent(&self, channel: &str, bytes: u64) {
+        self.channel_counters(channel)
+            .sent
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn record_channel_received(&self, channel: &str, bytes: u64) {
+        self.channel_counters(channel)
+            .received
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn record_connection_sent(&self, connection_id: ConnectionId, bytes: u64) {
+        self.connection_counters(connection_id)
+            .sent
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn record_connection_received(&self, connection_id: ConnectionId, bytes: u64) {
+        self.connection_counters(connection_id)
+            .received
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn channel_bytes(&self, channel: &str) -> ByteTotals {
+        self.channels
+            .read()
+            .get(channel)
+            .map(|counters| counters.totals())
+            .unwrap_or_default()
+    }
+
+    pub fn connection_bytes(&self, connection_id: ConnectionId) -> ByteTotals {
+        self.connections
+            .read()
+            .get(&connection_id.0)
+            .map(|counters| counters.totals())
+            .unwrap_or_default()
+    }
+
+    /// Every channel's totals, heaviest first (by sent plus received bytes),
+    /// with the name as tie-break so the ordering is deterministic.
+    pub fn per_channel(&self) -> Vec<(String, ByteTotals)> {
+        let mut rows: Vec<(String, ByteTotals)> = self
+            .channels
+            .read()
+            .iter()
+            .map(|(name, counters)| (name.clone(), counters.totals()))
+            .collect();
+        rows.sort_by(|a, b| weight(b.1).cmp(&weight(a.1)).then_with(|| a.0.cmp(&b.0)));
+        rows
+    }
+
+    /// Every connection's totals, heaviest first, id as tie-break.
+    pub fn per_connection(&self) -> Vec<(ConnectionId, ByteTotals)> {
+        let mut rows: Vec<(ConnectionId, ByteTotals)> = self
+            .connections
+            .read()
+            .iter()
+            .map(|(id, counters)| (ConnectionId(*id), counters.totals()))
+            .collect();
+        rows.sort_by(|a, b| {
+            weight(b.1)
+                .cmp(&weight(a.1))
+                .then_with(|| a.0.0.cmp(&b.0.0))
+        });
+        rows
+    }
+
+    fn channel_counters(&self, channel: &str) -> Arc<ByteCounters> {
+        if let Some(counters) = self.channels.read().get(channel) {
+            return counters.clone();
+        }
+        self.channels
+            .write()
+            .entry(channel.to_string())
+            .or_default()
+            .clone()
+    }
+
+    fn connection_counters(&self, connection_id: ConnectionId) -> Arc<ByteCounters> {
+        if let Some(counters) = self.connections.read().get(&connection_id.0) {
+            return counters.clone();
+        }
+        self.connections
+            .write()
+            .entry(connection_id.0)
+            .or_default()
+            .clone()
+    }
+}
+
+fn weight(totals: ByteTotals) -> u64 {
+    totals.sent.saturating_add(totals.received)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_channel_and_per_connection_rank_heaviest_first() {
+        let metrics = SyncMetrics::new();
+        metrics.record_channel_sent("chat", 100);
+        metrics.record_channel_received("chat", 50);
+        metrics.record_channel_sent("presence", 20);
+        metrics.record_channel_sent("documents", 150);
+
+        let rows = metrics.per_channel();
+        let names: Vec<&str> = rows.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["chat", "documents", "presence"]);
+        assert_eq!(
+            rows[0].1,
+            ByteTotals {
+                sent: 100,
+                received: 50
+            }
+        );
+
+        metrics.record_connection_received(ConnectionId(2), 30);
+        metrics.record_connection_sent(ConnectionId(1), 30);
+        let rows = metrics.per_connection();
+        let ids: Vec<u64> = rows.iter().map(|(id, _)| id.0).collect();
+        assert_eq!(ids, vec![1, 2], "equal weights fall back to the id");
+    }
+
+    #[test]
+    fn test_unknown_keys_read_as_zero() {
+        let metrics = SyncMetrics::new();
+        assert_eq!(metrics.channel_bytes("nowhere"), ByteTotals::default());
+        assert_eq!(
+            metrics.connection_bytes(ConnectionId(9)),
+            ByteTotals::default()
+        );
+    }
+}
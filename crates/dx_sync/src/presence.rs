@@ -0,0 +1,353 @@
+use crate::{SubscriberId, SyncError};
+use collections::HashMap;
+use futures::Stream;
+use parking_lot::RwLock;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::task::{Context, Poll};
+
+/// One membership change on a presence channel, sent to diff subscribers
+/// instead of the full member list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresenceDiff {
+    /// The channel's presence version after applying this diff. Versions on
+    /// one channel are consecutive, so a subscriber that sees a gap knows it
+    /// missed a diff and must resync from
+    /// [`PresenceManager::snapshot`].
+    pub version: u64,
+    pub joined: Vec<String>,
+    pub left: Vec<String>,
+}
+
+/// A channel's full membership at `version`. Taken under one lock
+/// acquisition, so the version and the member list always agree and later
+/// diffs apply cleanly on top.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PresenceSnapshot {
+    pub version: u64,
+    /// Sorted, so equal memberships compare equal.
+    pub members: Vec<String>,
+}
+
+/// Tracks who is present on each channel and streams membership *diffs* to
+/// subscribers, so large channels don't ship their whole member list on
+/// every change. A client applies diffs in version order; on a gap (or
+/// reconnect) it fetches a [`PresenceSnapshot`] and resumes from its version.
+pub struct PresenceManager {
+    channels: RwLock<HashMap<String, PresenceChannel>>,
+    next_subscriber_id: AtomicU64,
+}
+
+#[derive(Default)]
+struct PresenceChannel {
+    members: Vec<String>,
+    version: u64,
+    subscribers: Vec<PresenceSubscriber>,
+}
+
+struct PresenceSubscriber {
+    id: SubscriberId,
+    sender: flume::Sender<PresenceDiff>,
+}
+
+impl PresenceChannel {
+    fn emit(&mut self, diff: PresenceDiff) {
+        // A failed send means the receiver was dropped; sweep it here rather
+        // than accumulating dead senders.
+        self.subscribers
+            .retain(|subscriber| subscriber.sender.send(diff.clone()).is_ok());
+    }
+
+    fn snapshot(&self) -> PresenceSnapshot {
+        let mut members = self.members.clone();
+        members.sort();
+        PresenceSnapshot {
+            version: self.version,
+            members,
+        }
+    }
+}
+
+impl PresenceManager {
+    pub fn new() -> Self {
+        Self {
+            channels: RwLock::new(HashMap::default()),
+            next_subscriber_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Marks `member` present on the channel, bumping the presence version
+    /// and emitting a joined diff. Joining while already present is a no-op
+    /// at the current version. Returns the channel's version afterwards.
+    pub fn join(&self, channel: &str, member: &str) -> u64 {
+        let mut channels = self.channels.write();
+        let channel_state = channels.entry(channel.to_string()).or_default();
+        if channel_state
+            .members
+            .iter()
+            .any(|existing| existing == member)
+        {
+            return channel_state.version;
+        }
+        channel_state.members.push(member.to_string());
+        channel_state.version += 1;
+        let diff = PresenceDiff {
+            version: channel_state.version,
+            joined: vec![member.to_string()],
+            left: Vec::new(),
+        };
+        channel_state.emit(diff);
+        channel_state.version
+    }
+
+    /// Marks `member` absent, bumping the presence version and emitting a
+    /// left diff. Leaving a channel one isn't on (or that doesn't exist) is a
+    /// no-op at the current version. Returns the channel's version afterwards.
+    pub fn leave(&self, channel: &str, member: &str) -> u64 {
+        let mut channels = self.channels.write();
+        let Some(channel_state) = channels.get_mut(channel) else {
+            return 0;
+        };
+        let Some(position) = channel_state
+            .members
+            .iter()
+            .position(|existing| existing == member)
+        else {
+            return channel_state.version;
+        };
+        channel_state.members.remove(position);
+        channel_state.version += 1;
+        let diff = PresenceDiff {
+            version: channel_state.version,
+            joined: Vec::new(),
+            left: vec![member.to_string()],
+        };
+        channel_state.emit(diff);
+        channel_state.version
+    }
+
+    /// Subscribes to the channel's membership diffs. The returned snapshot is
+    /// taken under the same lock that registers the subscriber, so every diff
+    /// the receiver yields has a version strictly greater than the
+    /// snapshot's — nothing is missed or duplicated in between.
+    pub fn subscribe(
+        &self,
+        channel: &str,
+    ) -> (
+        SubscriberId,
+        PresenceSnapshot,
+        flume::Receiver<PresenceDiff>,
+    ) {
+        let id = SubscriberId(self.next_subscriber_id.fetch_add(1, Ordering::Relaxed));
+        let (sender, receiver) = flume::unbounded();
+        let mut channels = self.channels.write();
+        let channel_state = channels.entry(channel.to_string()).or_default();
+        channel_state
+            .subscribers
+            .push(PresenceSubscriber { id, sender });
+        (id, channel_state.snapshot(), receiver)
+    }
+
+    /// Like [`subscribe`](Self::subscribe), but exposes the diffs as a
+    /// [`futures::Stream`]. Dropping the stream unsubscribes.
+    pub fn subscribe_stream(
+        self: &Arc<Self>,
+        channel: &str,
+    ) -> (PresenceSnapshot, PresenceDiffStream) {
+        let (subscriber_id, snapshot, receiver) = self.subscribe(channel);
+        let stream = PresenceDiffStream {
+            // Weak, so an outstanding stream doesn't keep the manager alive.
+            manager: Arc::downgrade(self),
+            channel: channel.to_string(),
+            subscriber_id,
+            receiver: receiver.into_stream(),
+        };
+        (snapshot, stream)
+    }
+
+    pub fn unsubscribe(&self, channel: &str, subscriber_id: SubscriberId) -> Result<(), SyncError> {
+        let mut channels = self.channels.write();
+        let channel_state = channels
+            .get_mut(channel)
+            .ok_or_else(|| SyncError::ChannelNotFound(channel.to_string()))?;
+        channel_state
+            .subscribers
+            .retain(|subscriber| subscriber.id != subscriber_id);
+        // The channel itself is kept even when empty: its version must
+        // survive so a reconnecting client can tell whether it missed diffs.
+        Ok(())
+    }
+
+    /// The channel's full membership and current version, atomically — what a
+    /// reconnecting client (or one that saw a version gap) uses to resync
+    /// before resuming diffs.
+    pub fn snapshot(&self, channel: &str) -> Result<PresenceSnapshot, SyncError> {
+        self.channels
+            .read()
+            .get(channel)
+            .map(PresenceChannel::snapshot)
+            .ok_or_else(|| SyncError::ChannelNotFound(channel.to_string()))
+    }
+
+    /// The channel's current members, sorted. Prefer
+    /// [`subscribe`](Self::subscribe) over polling this for change tracking.
+    pub fn get_presence(&self, channel: &str) -> Vec<String> {
+        self.snapshot(channel)
+            .map(|snapshot| snapshot.members)
+            .unwrap_or_default()
+    }
+}
+
+impl Default for PresenceManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A presence subscription exposed as a [`futures::Stream`] of
+/// [`PresenceDiff`]s. Created by [`PresenceManager::subscribe_stream`];
+/// dropping it unsubscribes.
+pub struct PresenceDiffStream {
+    manager: Weak<PresenceManager>,
+    channel: String,
+    subscriber_id: SubscriberId,
+    receiver: flume::r#async::RecvStream<'static, PresenceDiff>,
+}
+
+impl PresenceDiffStream {
+    pub fn subscriber_id(&self) -> SubscriberId {
+        self.subscriber_id
+    }
+}
+
+impl Stream for PresenceDiffStream {
+    type Item = PresenceDiff;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl Drop for PresenceDiffStream {
+    fn drop(&mut self) {
+        if let Some(manager) = self.manager.upgrade() {
+            // The channel may already have been torn down; there is nothing
+            // left to clean up in that case.
+            manager.unsubscribe(&self.channel, self.subscriber_id).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+
+    #[test]
+    fn test_joins_and_leaves_emit_consecutive_diffs() {
+        let manager = PresenceManager::new();
+        let (_, snapshot, receiver) = manager.subscribe("room");
+        assert_eq!(snapshot.version, 0);
+        assert!(snapshot.members.is_empty());
+
+        manager.join("room", "alice");
+        manager.join("room", "bob");
+        manager.leave("room", "alice");
+
+        let diffs: Vec<PresenceDiff> = receiver.try_iter().collect();
+        assert_eq!(
+            diffs,
+            vec![
+                PresenceDiff {
+                    version: 1,
+                    joined: vec!["alice".to_string()],
+                    left: Vec::new(),
+                },
+                PresenceDiff {
+                    version: 2,
+                    joined: vec!["bob".to_string()],
+                    left: Vec::new(),
+                },
+                PresenceDiff {
+                    version: 3,
+                    joined: Vec::new(),
+                    left: vec!["alice".to_string()],
+                },
+            ]
+        );
+        assert_eq!(manager.get_presence("room"), vec!["bob".to_string()]);
+    }
+
+    #[test]
+    fn test_duplicate_join_and_absent_leave_are_no_ops() {
+        let manager = PresenceManager::new();
+        let (_, _, receiver) = manager.subscribe("room");
+        assert_eq!(manager.join("room", "alice"), 1);
+        assert_eq!(manager.join("room", "alice"), 1, "already present");
+        assert_eq!(manager.leave("room", "ghost"), 1, "never joined");
+        assert_eq!(manager.leave("nowhere", "alice"), 0);
+        assert_eq!(receiver.try_iter().count(), 1, "only the real join");
+    }
+
+    #[test]
+    fn test_subscribe_snapshot_is_atomic_with_diff_delivery() {
+        let manager = PresenceManager::new();
+        manager.join("room", "bob");
+        manager.join("room", "alice");
+
+        let (_, snapshot, receiver) = manager.subscribe("room");
+        assert_eq!(snapshot.version, 2);
+        assert_eq!(
+            snapshot.members,
+            vec!["alice".to_string(), "bob".to_string()],
+            "sorted"
+        );
+
+        // Everything after the snapshot arrives as diffs, starting at the
+        // very next version.
+        manager.join("room", "carol");
+        let diff = receiver.try_recv().unwrap();
+        assert_eq!(diff.version, snapshot.version + 1);
+        assert_eq!(diff.joined, vec!["carol".to_string()]);
+    }
+
+    #[test]
+    fn test_version_gap_signals_a_missed_diff() {
+        let manager = PresenceManager::new();
+        let (id, snapshot, receiver) = manager.subscribe("room");
+        manager.join("room", "alice");
+        let last_seen = receiver.try_recv().unwrap().version;
+        assert_eq!(last_seen, snapshot.version + 1);
+
+        // The client disconnects and misses a change.
+        manager.unsubscribe("room", id).unwrap();
+        manager.join("room", "bob");
+
+        let (_, resync, receiver) = manager.subscribe("room");
+        assert!(resync.version > last_seen, "gap is detectable");
+        assert_eq!(resync.version, 2);
+        assert_eq!(resync.members, vec!["alice".to_string(), "bob".to_string()]);
+        manager.leave("room", "alice");
+        assert_eq!(receiver.try_recv().unwrap().version, resync.version + 1);
+    }
+
+    #[test]
+    fn test_dropping_the_stream_unsubscribes() {
+        let manager = Arc::new(PresenceManager::new());
+        let (snapshot, mut stream) = manager.subscribe_stream("room");
+        assert_eq!(snapshot.version, 0);
+        manager.join("room", "alice");
+
+        let diff = futures::executor::block_on(stream.next()).unwrap();
+        assert_eq!(diff.joined, vec!["alice".to_string()]);
+
+        drop(stream);
+        // The swept subscriber no longer receives anything; emitting after
+        // the drop must not grow the subscriber list back.
+        manager.join("room", "bob");
+        let (_, _, receiver) = manager.subscribe("room");
+        manager.leave("room", "bob");
+        assert_eq!(receiver.try_iter().count(), 1);
+    }
+}
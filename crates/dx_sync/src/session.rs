@@ -0,0 +1,229 @@
+use crate::{MessageType, SyncError, signing::signing_input};
+use std::sync::Arc;
+
+/// Derives the connection's symmetric key from the peer's public key. Key
+/// agreement is a plain callback so this crate takes no crypto dependency:
+/// wire it to an X25519 `diffie_hellman` over the connection's keypair. The
+/// callback must be symmetric — both ends derive the same key from each
+/// other's public keys — which is exactly what X25519 provides.
+pub type KeyAgreement = Arc<dyn Fn(&[u8]) -> Vec<u8> + Send + Sync>;
+
+/// Computes a MAC tag over `input` with `key`; wire it to HMAC-SHA256. Like
+/// [`KeyAgreement`], a callback rather than a dependency.
+pub type FrameMac = Arc<dyn Fn(&[u8], &[u8]) -> Vec<u8> + Send + Sync>;
+
+/// One side of the optional session handshake, for deployments without TLS
+/// at the app layer. Each connection exchanges public keys in the clear,
+/// derives a shared symmetric key via [`KeyAgreement`], and then MAC-tags
+/// every frame with [`FramedSession`] so tampering is detectable even over a
+/// plaintext transport. The handshake authenticates frames; it does not
+/// encrypt them.
+pub struct SessionHandshake {
+    /// Sent to the peer in the clear during the handshake.
+    pub public_key: Vec<u8>,
+    agree: KeyAgreement,
+}
+
+impl SessionHandshake {
+    pub fn new(public_key: Vec<u8>, agree: KeyAgreement) -> Self {
+        Self { public_key, agree }
+    }
+
+    /// Completes the handshake with the peer's public key, producing the
+    /// framing for this connection.
+    pub fn establish(&self, peer_public_key: &[u8], mac: FrameMac) -> FramedSession {
+        FramedSession {
+            key: (self.agree)(peer_public_key),
+            mac,
+        }
+    }
+}
+
+/// MAC-tagged framing over an established session key. The tag covers the
+/// channel name and message type as well as the payload — the same input as
+/// a [`SignedEnvelope`](crate::SignedEnvelope) signature — so a valid frame
+/// can't be replayed onto a different channel or reinterpreted under another
+/// type.
+pub struct FramedSession {
+    key: Vec<u8>,
+    mac: FrameMac,
+}
+
+impl FramedSession {
+    /// Encodes as: tag length (u16 LE), tag bytes, payload bytes.
+    pub fn encode(
+        &self,
+        channel: &str,
+        message_type: MessageType,
+        payload: &[u8],
+    ) -> Result<Vec<u8>, SyncError> {
+        let tag = (self.mac)(&self.key, &signing_input(channel, message_type, payload));
+        if tag.len() > u16::MAX as usize {
+            return Err(SyncError::InvalidMessage(format!(
+                "frame tag too long: {} bytes",
+                tag.len()
+            )));
+        }
+        let mut bytes = Vec::with_capacity(2 + tag.len() + payload.len());
+        bytes.extend_from_slice(&(tag.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&tag);
+        bytes.extend_from_slice(payload);
+        Ok(bytes)
+    }
+
+    /// Verifies the frame's tag and returns the payload; a truncated frame
+    /// or one whose tag doesn't match — a flipped byte anywhere in the tag
+    /// or payload — is rejected with [`SyncError::FrameRejected`] and never
+    /// reaches the caller.
+    pub fn decode(
+        &self,
+        channel: &str,
+        message_type: MessageType,
+        bytes: &[u8],
+    ) -> Result<Vec<u8>, SyncError> {
+        let truncated = || SyncError::InvalidMessage("truncated session frame".into());
+        let length_bytes = bytes.get(..2).ok_or_else(truncated)?;
+        let tag_len =
+            u16::from_le_bytes(length_bytes.try_into().map_err(|_| truncated())?) as usize;
+        let end = 2usize.checked_add(tag_len).ok_or_else(truncated)?;
+        let tag = bytes.get(2..end).ok_or_else(truncated)?;
+        let payload = bytes.get(end..).ok_or_else(truncated)?;
+        let expected = (self.mac)(&self.key, &signing_input(channel, message_type, payload));
+        if !constant_time_eq(tag, &expected) {
+            return Err(SyncError::FrameRejected(channel.to_string()));
+        }
+        Ok(payload.to_vec())
+    }
+}
+
+/// Compares tags without short-circuiting on the first mismatched byte, so
+/// verification time doesn't leak how much of a forged tag was correct. The
+/// length check short-circuits, but tag length is public anyway.
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    if left.len() != right.len() {
+        return false;
+    }
+    left.iter()
+        .zip(right)
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Stands in for X25519 in tests: the public key is the secret itself and
+    // agreement is XOR, which is symmetric like the real exchange.
+    fn handshake(secret: [u8; 4]) -> SessionHandshake {
+        SessionHandshake::new(
+            secret.to_vec(),
+            Arc::new(move |peer_public| {
+                secret
+                    .iter()
+                    .zip(peer_public)
+                    .map(|(ours, theirs)| ours ^ theirs)
+                    .collect()
+            }),
+        )
+    }
+
+    // Stands in for HMAC in tests: the tag is the key followed by the input
+    // reversed, so any single-byte tampering breaks it.
+    fn test_mac() -> FrameMac {
+        Arc::new(|key, input| key.iter().chain(input.iter().rev()).copied().collect())
+    }
+
+    #[test]
+    fn test_both_sides_derive_the_same_key_and_frames_round_trip() {
+        let alice = handshake([1, 2, 3, 4]);
+        let bob = handshake([5, 6, 7, 8]);
+        let alice_session = alice.establish(&bob.public_key, test_mac());
+        let bob_session = bob.establish(&alice.public_key, test_mac());
+
+        let frame = alice_session
+            .encode("updates", MessageType::Publish, b"payload")
+            .unwrap();
+        assert_eq!(
+            bob_session
+                .decode("updates", MessageType::Publish, &frame)
+                .unwrap(),
+            b"payload"
+        );
+    }
+
+    #[test]
+    fn test_flipped_byte_fails_mac_verification() {
+        let session = handshake([1, 2, 3, 4]).establish(&[5, 6, 7, 8], test_mac());
+        let frame = session
+            .encode("updates", MessageType::Publish, b"payload")
+            .unwrap();
+
+        // Flip one byte anywhere — in the tag or in the payload — and the
+        // frame must be dropped.
+        for position in 2..frame.len() {
+            let mut tampered = frame.clone();
+            tampered[position] ^= 0x01;
+            assert!(
+                matches!(
+                    session.decode("updates", MessageType::Publish, &tampered),
+                    Err(SyncError::FrameRejected(channel)) if channel == "updates"
+                ),
+                "flipping byte {position} must fail verification"
+            );
+        }
+        assert!(
+            session
+                .decode("updates", MessageType::Publish, &frame)
+                .is_ok(),
+            "the untampered frame still verifies"
+        );
+    }
+
+    #[test]
+    fn test_frame_is_bound_to_channel_and_type() {
+        let session = handshake([1, 2, 3, 4]).establish(&[5, 6, 7, 8], test_mac());
+        let frame = session
+            .encode("updates", MessageType::Publish, b"payload")
+            .unwrap();
+        assert!(
+            session
+                .decode("other", MessageType::Publish, &frame)
+                .is_err()
+        );
+        assert!(
+            session
+                .decode("updates", MessageType::Control, &frame)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_mismatched_session_keys_reject_frames() {
+        let alice = handshake([1, 2, 3, 4]);
+        let alice_session = alice.establish(&[5, 6, 7, 8], test_mac());
+        let eavesdropper = handshake([9, 9, 9, 9]).establish(&alice.public_key, test_mac());
+
+        let frame = alice_session
+            .encode("updates", MessageType::Publish, b"payload")
+            .unwrap();
+        assert!(
+            eavesdropper
+                .decode("updates", MessageType::Publish, &frame)
+                .is_err(),
+            "a session with the wrong key accepts nothing"
+        );
+    }
+
+    #[test]
+    fn test_truncated_frames_are_invalid_not_rejected() {
+        let session = handshake([1, 2, 3, 4]).establish(&[5, 6, 7, 8], test_mac());
+        let frame = session
+            .encode("updates", MessageType::Publish, b"payload")
+            .unwrap();
+        assert!(matches!(
+            session.decode("updates", MessageType::Publish, &frame[..1]),
+            Err(SyncError::InvalidMessage(_))
+        ));
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{ChannelManager, MessageType, SyncError};
+use std::sync::Arc;
+
+/// Checks a signature over the exact bytes that were signed, returning `true`
+/// when it matches. Signing and verification are plain callbacks so this
+/// crate takes no crypto dependency: wire them to
+/// `dx_auth::ProductionTokenGenerator::sign` and
+/// `ProductionTokenGenerator::verify` to reuse the Ed25519 keys services
+/// already hold.
+pub type MessageVerifier = Arc<dyn Fn(&[u8], &[u8]) -> bool + Send + Sync>;
+
+/// A payload together with a signature over it, for channels that cross
+/// untrusted relays. The signature covers the channel name and message type
+/// as well as the payload, so a signed message can't be replayed onto a
+/// different channel or reinterpreted under another type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedEnvelope {
+    pub signature: Vec<u8>,
+    pub payload: Vec<u8>,
+}
+
+impl SignedEnvelope {
+    /// Signs `payload` for the given channel and message type.
+    pub fn seal(
+        channel: &str,
+        message_type: MessageType,
+        payload: Vec<u8>,
+        sign: impl Fn(&[u8]) -> Vec<u8>,
+    ) -> Self {
+        let signature = sign(&signing_input(channel, message_type, &payload));
+        Self { signature, payload }
+    }
+
+    /// Whether `verifier` accepts this envelope's signature for the given
+    /// channel and message type.
+    pub fn verify(
+        &self,
+        channel: &str,
+        message_type: MessageType,
+        verifier: &MessageVerifier,
+    ) -> bool {
+        verifier(
+            &signing_input(channel, message_type, &self.payload),
+            &self.signature,
+        )
+    }
+
+    /// Encodes as: signature length (u16 LE), signature bytes, payload bytes.
+    pub fn encode(&self) -> Result<Vec<u8>, SyncError> {
+        if self.signature.len() > u16::MAX as usize {
+            return Err(SyncError::InvalidMessage(format!(
+                "signature too long: {} bytes",
+                self.signature.len()
+            )));
+        }
+        let mut bytes = Vec::with_capacity(2 + self.signature.len() + self.payload.len());
+        bytes.extend_from_slice(&(self.signature.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(&self.signature);
+        bytes.extend_from_slice(&self.payload);
+        Ok(bytes)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, SyncError> {
+        let truncated = || SyncError::InvalidMessage("truncated signed envelope".into());
+        let length_bytes = bytes.get(..2).ok_or_else(truncated)?;
+        let signature_len = u16::from_le_bytes(
+            length_bytes
+                .try_into()
+                .map_err(|_| SyncError::InvalidMessage("truncated signed envelope".into()))?,
+        ) as usize;
+        let end = 2usize.checked_add(signature_len).ok_or_else(truncated)?;
+        let signature = bytes.get(2..end).ok_or_else(truncated)?.to_vec();
+        let payload = bytes.get(end..).ok_or_else(truncated)?.to_vec();
+        Ok(Self { signature, payload })
+    }
+}
+
+/// The bytes a message signature covers: channel length (u16 LE), channel
+/// bytes, type byte, payload. Length-prefixing the channel keeps the input
+/// unambiguous — without it, channel `"ab"` with payload `"c..."` would sign
+/// the same bytes as channel `"a"` with payload `"bc..."`.
+pub(crate) fn signing_input(channel: &str, message_type: MessageType, payload: &[u8]) -> Vec<u8> {
+    let channel_bytes = channel.as_bytes();
+    let mut input = Vec::with_capacity(2 + channel_bytes.len() + 1 + payload.len());
+    input.extend_from_slice(&(channel_bytes.len().min(u16::MAX as usize) as u16).to_le_bytes());
+    input.extend_from_slice(channel_bytes);
+    input.push(message_type as u8);
+    input.extend_from_slice(payload);
+    input
+}
+
+impl ChannelManager {
+    /// Requires every subsequent publish on `channel` to carry a
+    /// [`SignedEnvelope`] that `verifier` accepts; unsigned or tampered
+    /// messages are rejected with [`SyncError::SignatureRejected`] before
+    /// they reach any subscriber or the channel's history. Channels without
+    /// a verifier are unaffected — the unsigned path stays the default.
+    pub fn require_signatures(&self, channel: &str, verifier: MessageVerifier) {
+        self.verifiers.write().insert(channel.to_string(), verifier);
+    }
+
+    /// Like [`publish`](Self::publish), but signs the payload with `sign`
+    /// and publishes it wrapped in a [`SignedEnvelope`], for channels that
+    /// [`require_signatures`](Self::require_signatures).
+    pub fn publish_signed(
+        &self,
+        channel: &str,
+        message_type: MessageType,
+        data: Vec<u8>,
+        sign: impl Fn(&[u8]) -> Vec<u8>,
+    ) -> Result<u64, SyncError> {
+        let envelope = SignedEnvelope::seal(channel, message_type, data, sign);
+        self.publish(channel, message_type, envelope.encode()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Stands in for Ed25519 in tests: the "signature" is the input reversed,
+    // which any single-byte tampering breaks.
+    fn test_sign(input: &[u8]) -> Vec<u8> {
+        input.iter().rev().copied().collect()
+    }
+
+    fn test_verifier() -> MessageVerifier {
+        Arc::new(|input, signature| test_sign(input) == signature)
+    }
+
+    #[test]
+    fn test_envelope_round_trip() {
+        let envelope =
+            SignedEnvelope::seal("updates", MessageType::Publish, vec![1, 2, 3], test_sign);
+        let decoded = SignedEnvelope::decode(&envelope.encode().unwrap()).unwrap();
+        assert_eq!(decoded, envelope);
+        assert!(decoded.verify("updates", MessageType::Publish, &test_verifier()));
+        assert!(
+            !decoded.verify("other", MessageType::Publish, &test_verifier()),
+            "signature is bound to the channel"
+        );
+    }
+
+    #[test]
+    fn test_signed_publish_reaches_verifying_subscribers() {
+        let manager = ChannelManager::new();
+        manager.require_signatures("updates", test_verifier());
+        let (_, receiver) = manager.subscribe("updates");
+        manager
+            .publish_signed("updates", MessageType::Publish, vec![1, 2, 3], test_sign)
+            .unwrap();
+        assert_eq!(receiver.try_recv().unwrap().data, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_tampered_payload_is_rejected() {
+        let manager = ChannelManager::new();
+        manager.require_signatures("updates", test_verifier());
+        let (_, receiver) = manager.subscribe("updates");
+
+        let envelope =
+            SignedEnvelope::seal("updates", MessageType::Publish, vec![1, 2, 3], test_sign);
+        let mut tampered = envelope.encode().unwrap();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 0xff;
+        assert!(matches!(
+            manager.publish("updates", MessageType::Publish, tampered),
+            Err(SyncError::SignatureRejected(channel)) if channel == "updates"
+        ));
+        assert!(receiver.try_recv().is_err(), "nothing was delivered");
+        assert!(
+            manager.history("updates").is_empty(),
+            "nothing was recorded"
+        );
+    }
+
+    #[test]
+    fn test_unsigned_publish_on_a_verifying_channel_is_rejected() {
+        let manager = ChannelManager::new();
+        manager.require_signatures("updates", test_verifier());
+        assert!(matches!(
+            manager.publish("updates", MessageType::Publish, vec![1, 2, 3]),
+            Err(SyncError::SignatureRejected(_))
+        ));
+    }
+
+    #[test]
+    fn test_channels_without_a_verifier_stay_unsigned() {
+        let manager = ChannelManager::new();
+        manager.require_signatures("secure", test_verifier());
+        let (_, receiver) = manager.subscribe("updates");
+        manager
+            .publish("updates", MessageType::Publish, vec![9])
+            .unwrap();
+        assert_eq!(receiver.try_recv().unwrap().data, vec![9]);
+    }
+}